@@ -5,33 +5,114 @@ use crate::{
 use crate::core::indexmap::indexmap;
 use crate::core::indexmap::IndexMap;
 use crate::core::serde_derive::Deserialize;
-use crate::core::serde_json;
 use crate::core::*;
-use crate::draw::*;
+use crate::game::AnimationPlaybackDirection;
 use crate::image::*;
-use crate::math::*;
 
 use rayon::prelude::*;
 use std::collections::HashSet;
 
+/// Layers with these names are not rendered but mark the pivot and attachment points of a sprite
+const SPRITE_OFFSET_LAYER_NAMES: [&str; 5] = [
+    "pivot",
+    "attachment_0",
+    "attachment_1",
+    "attachment_2",
+    "attachment_3",
+];
+
+/// A filtered variant of a 2D sprite sheet. Variants are listed in an optional
+/// `<image>.spritevariants.json` file next to the image. Each variant is baked into its own sheet
+/// named `<sheet>_<variant>` (i.e. `sprite_outlined`) with the same animations as the original.
+#[derive(Debug, Clone, Deserialize)]
+struct SpriteVariant {
+    name: String,
+    filters: Vec<SpriteVariantFilter>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+enum SpriteVariantFilter {
+    Outline {
+        thickness: i32,
+        color: PixelRGBA,
+    },
+    DropShadow {
+        offset: Vec2i,
+        color: PixelRGBA,
+        blur_radius: i32,
+    },
+    HueSaturationBrightness {
+        hue_shift_degrees: f32,
+        saturation_factor: f32,
+        brightness_factor: f32,
+    },
+}
+
+impl SpriteVariant {
+    /// The amount of pixels a frame needs to be extended on each side so that the filters do not
+    /// get clipped
+    fn frame_margin(&self) -> i32 {
+        self.filters
+            .iter()
+            .map(|filter| match filter {
+                SpriteVariantFilter::Outline { thickness, .. } => *thickness,
+                SpriteVariantFilter::DropShadow {
+                    offset,
+                    blur_radius,
+                    ..
+                } => i32::max(offset.x.abs(), offset.y.abs()) + blur_radius,
+                SpriteVariantFilter::HueSaturationBrightness { .. } => 0,
+            })
+            .sum()
+    }
+
+    fn apply_to_frame(&self, frame_image: &Bitmap) -> Bitmap {
+        let margin = self.frame_margin();
+        let mut result =
+            frame_image.extended(margin, margin, margin, margin, PixelRGBA::transparent());
+        for filter in &self.filters {
+            result = match filter {
+                SpriteVariantFilter::Outline { thickness, color } => {
+                    result.outlined(*thickness, *color)
+                }
+                SpriteVariantFilter::DropShadow {
+                    offset,
+                    color,
+                    blur_radius,
+                } => result.with_drop_shadow(*offset, *color, *blur_radius),
+                SpriteVariantFilter::HueSaturationBrightness {
+                    hue_shift_degrees,
+                    saturation_factor,
+                    brightness_factor,
+                } => result.with_adjusted_hue_saturation_brightness(
+                    *hue_shift_degrees,
+                    *saturation_factor,
+                    *brightness_factor,
+                ),
+            };
+        }
+        result
+    }
+}
+
 pub fn create_sheet(image_filepath: &str, sheet_name: &str) -> GraphicsSheet {
     std::fs::create_dir_all("target/assets_temp/sprites")
         .expect("Cannot create 'target/assets_temp/sprites");
 
-    let (images, sprites, sprites_3d, animations, animations_3d) = if image_filepath
-        .ends_with("_3d.ase")
-    {
-        create_sheet_animations_3d(image_filepath, sheet_name)
-    } else {
-        let (images, sprites, animations) = create_sheet_animations_2d(image_filepath, sheet_name);
-        (
-            images,
-            sprites,
-            IndexMap::new(),
-            animations,
-            IndexMap::new(),
-        )
-    };
+    let (images, sprites, sprites_3d, animations, animations_3d) =
+        if image_filepath.ends_with("_3d.ase") || image_filepath.ends_with("_3d.aseprite") {
+            create_sheet_animations_3d(image_filepath, sheet_name)
+        } else {
+            let (images, sprites, animations) =
+                create_sheet_animations_2d(image_filepath, sheet_name);
+            (
+                images,
+                sprites,
+                IndexMap::new(),
+                animations,
+                IndexMap::new(),
+            )
+        };
 
     GraphicsSheet {
         images,
@@ -40,6 +121,7 @@ pub fn create_sheet(image_filepath: &str, sheet_name: &str) -> GraphicsSheet {
         sprites_3d,
         animations,
         animations_3d,
+        particle_emitters: IndexMap::new(),
     }
 }
 
@@ -53,15 +135,15 @@ pub fn create_sheet_animations_3d(
     IndexMap<ResourceName, AssetAnimation>,
     IndexMap<ResourceName, AssetAnimation3D>,
 ) {
+    let image = load_image_as_aseprite_file(image_filepath);
+
     let stack_layer_count = {
         // NOTE: This block is mainly for validation
         let mut layers = Vec::new();
-        for layer_name in &aseprite_list_layers_of_file(image_filepath) {
-            if layer_name != "pivot"
-                && layer_name != "attachment_0"
-                && layer_name != "attachment_1"
-                && layer_name != "attachment_2"
-                && layer_name != "attachment_3"
+        for layer in &image.layers {
+            let layer_name = &layer.name;
+            if layer.kind != AsepriteLayerKind::Group
+                && !SPRITE_OFFSET_LAYER_NAMES.contains(&layer_name.as_str())
             {
                 let layer_index = layer_name.parse::<usize>().expect(&format!(
                     "Found layer named '{}' in 3D sprite '{}', expected layernumber.\n
@@ -95,10 +177,7 @@ pub fn create_sheet_animations_3d(
         layers.len()
     };
 
-    let output_filepath_without_extension = format!("target/assets_temp/sprites/{}", sheet_name);
-
-    // Split out each of the 3D sprites stack layers into their own files and process each
-    // separately
+    // Process each of the 3D sprites stack layers separately as if it was its own 2D sprite
     let mut result_images: IndexMap<ResourceName, Bitmap> = IndexMap::new();
     let mut result_sprites: IndexMap<ResourceName, AssetSprite> = IndexMap::new();
     let mut result_animations: IndexMap<ResourceName, AssetAnimation> = IndexMap::new();
@@ -110,31 +189,14 @@ pub fn create_sheet_animations_3d(
         .into_par_iter()
         .map(|current_stack_layer| {
             let stack_layer_sheet_name = format!("{}#{}", sheet_name, current_stack_layer);
-            let stack_layer_image_filepath = format!(
-                "{}#{}.ase",
-                output_filepath_without_extension, current_stack_layer
-            );
-
-            let mut command = String::from("aseprite") + " --batch ";
-            for ignored_stack_layer in 0..stack_layer_count {
-                if current_stack_layer != ignored_stack_layer {
-                    command += &format!(" --ignore-layer \"{}\" ", ignored_stack_layer);
-                }
-            }
-            command += &image_filepath;
-            command += " --save-as ";
-            command += &stack_layer_image_filepath;
-
-            run_systemcommand_fail_on_error(&command, false);
-
-            assert!(
-                path_exists(&stack_layer_image_filepath),
-                "Failed to generate 3D sprite stack layer for '{}' - '{}' is missing",
+            let stack_layer_name = current_stack_layer.to_string();
+            create_sheet_animations_from_layers(
+                &image,
                 image_filepath,
-                stack_layer_image_filepath
-            );
-
-            create_sheet_animations_2d(&stack_layer_image_filepath, &stack_layer_sheet_name)
+                &stack_layer_sheet_name,
+                None,
+                |layer| layer.name == stack_layer_name,
+            )
         })
         .collect();
     for (images, sprites, animations) in sprites_and_animations {
@@ -194,7 +256,7 @@ pub fn create_sheet_animations_3d(
         image_filepath
     );
     for tag_name in &tag_names {
-        let (framecount, frame_durations_ms, frame_indices) = {
+        let (framecount, frame_durations_ms, frame_indices, playback_direction, frame_events) = {
             let animation_2d = &result_animations[&format!("{}#{}:{}", sheet_name, 0, tag_name)];
             (
                 animation_2d.framecount,
@@ -206,6 +268,8 @@ pub fn create_sheet_animations_3d(
                         sprite_name_2d.rsplit(".").next().unwrap().parse().unwrap()
                     })
                     .collect::<Vec<usize>>(),
+                animation_2d.playback_direction,
+                animation_2d.frame_events.clone(),
             )
         };
 
@@ -222,6 +286,8 @@ pub fn create_sheet_animations_3d(
                 framecount,
                 sprite_names,
                 frame_durations_ms,
+                playback_direction,
+                frame_events,
             },
         );
     }
@@ -243,26 +309,81 @@ pub fn create_sheet_animations_2d(
     IndexMap<ResourceName, AssetSprite>,
     IndexMap<ResourceName, AssetAnimation>,
 ) {
-    let output_path_without_extension = format!("target/assets_temp/sprites/{}", sheet_name);
-    let output_path_image = output_path_without_extension.clone() + ".png";
-    let output_path_meta = output_path_without_extension.clone() + ".json";
-
-    aseprite_run_sheet_packer(&image_filepath, &output_path_image, &output_path_meta);
-    let result_images =
-        indexmap! { sheet_name.to_owned() => Bitmap::from_png_file_or_panic(&output_path_image), };
+    let image = load_image_as_aseprite_file(image_filepath);
+    let (mut result_images, mut result_sprites, mut result_animations) =
+        create_sheet_animations_from_layers(&image, image_filepath, sheet_name, None, |_layer| {
+            true
+        });
+
+    let variants_filepath = path_with_extension(image_filepath, ".spritevariants.json");
+    if path_exists(&variants_filepath) {
+        let variants: Vec<SpriteVariant> = deserialize_from_json_file(&variants_filepath);
+        for variant in &variants {
+            let variant_sheet_name = format!("{}_{}", sheet_name, variant.name);
+            let (images, sprites, animations) = create_sheet_animations_from_layers(
+                &image,
+                image_filepath,
+                &variant_sheet_name,
+                Some(variant),
+                |_layer| true,
+            );
+            result_images.extend(images);
+            result_sprites.extend(sprites);
+            result_animations.extend(animations);
+        }
+    }
 
-    let metadata_string = std::fs::read_to_string(&output_path_meta).unwrap();
-    let meta: AsepriteJSON = serde_json::from_str(&metadata_string).expect(&format!(
-        "Failed to generate offset information for '{}' - Cannot parse metadata '{}'",
-        image_filepath, output_path_meta
-    ));
+    (result_images, result_sprites, result_animations)
+}
 
-    let framecount = meta.frames.len();
+fn create_sheet_animations_from_layers<F: Fn(&AsepriteLayer) -> bool>(
+    image: &AsepriteFile,
+    image_filepath: &str,
+    sheet_name: &str,
+    variant: Option<&SpriteVariant>,
+    include_layer: F,
+) -> (
+    IndexMap<ResourceName, Bitmap>,
+    IndexMap<ResourceName, AssetSprite>,
+    IndexMap<ResourceName, AssetAnimation>,
+) {
+    let framecount = image.frame_count();
     assert!(framecount > 0);
 
+    let is_content_layer = |layer: &AsepriteLayer| {
+        !SPRITE_OFFSET_LAYER_NAMES.contains(&layer.name.as_str()) && include_layer(layer)
+    };
+
+    // Render and trim frames
+    let mut trimmed_rects = Vec::with_capacity(framecount);
+    let mut trimmed_images = Vec::with_capacity(framecount);
+    for frame_index in 0..framecount {
+        let mut frame_image = image.render_frame_filtered(frame_index, false, &is_content_layer);
+        if let Some(variant) = variant {
+            frame_image = variant.apply_to_frame(&frame_image);
+        }
+        if let Some(trimmed_rect) = frame_image.get_trimming_rect_for_value(
+            true,
+            true,
+            true,
+            true,
+            PixelRGBA::transparent(),
+        ) {
+            trimmed_images.push(frame_image.cropped_by_rect(trimmed_rect));
+            trimmed_rects.push(trimmed_rect);
+        } else {
+            // NOTE: The sprite is zero sized. This is useful for example if a character has an
+            //       animation where it can be invisible in one frame
+            trimmed_images.push(Bitmap::new_empty());
+            trimmed_rects.push(Recti::from_width_height(0, 0));
+        }
+    }
+
+    let (sheet_image, frame_positions) = pack_frames_into_sheet(&trimmed_images);
+    sheet_image.write_to_png_file(&format!("target/assets_temp/sprites/{}.png", sheet_name));
+
     // Check for translucent pixels
-    let output_bitmap = Bitmap::from_png_file_or_panic(&output_path_image);
-    let has_translucency = output_bitmap
+    let has_translucency = sheet_image
         .data
         .iter()
         .any(|pixel| pixel.a != 255 && pixel.a != 0);
@@ -272,82 +393,23 @@ pub fn create_sheet_animations_2d(
     }
 
     // Collect offsets
-    let mut offsets_pivot = vec![Vec2i::zero(); framecount];
-    let mut offsets_attachment_0 = vec![Vec2i::zero(); framecount];
-    let mut offsets_attachment_1 = vec![Vec2i::zero(); framecount];
-    let mut offsets_attachment_2 = vec![Vec2i::zero(); framecount];
-    let mut offsets_attachment_3 = vec![Vec2i::zero(); framecount];
-    {
-        let layers = aseprite_list_layers_of_file(&image_filepath);
-
-        let output_path_pivots_image = output_path_without_extension.to_string() + "_pivots.png";
-        let output_path_pivots_meta = output_path_without_extension.to_string() + "_pivots.json";
-
-        let output_path_attachment_0_image =
-            output_path_without_extension.to_string() + "_attachment_0.png";
-        let output_path_attachment_0_meta =
-            output_path_without_extension.to_string() + "_attachment_0.json";
-        let output_path_attachment_1_image =
-            output_path_without_extension.to_string() + "_attachment_1.png";
-        let output_path_attachment_1_meta =
-            output_path_without_extension.to_string() + "_attachment_1.json";
-        let output_path_attachment_2_image =
-            output_path_without_extension.to_string() + "_attachment_2.png";
-        let output_path_attachment_2_meta =
-            output_path_without_extension.to_string() + "_attachment_2.json";
-        let output_path_attachment_3_image =
-            output_path_without_extension.to_string() + "_attachment_3.png";
-        let output_path_attachment_3_meta =
-            output_path_without_extension.to_string() + "_attachment_3.json";
-
-        for layername in layers {
-            if layername == "pivot" {
-                aseprite_get_offsets_for_layer(
-                    &image_filepath,
-                    &output_path_pivots_image,
-                    &output_path_pivots_meta,
-                    "pivot",
-                    &mut offsets_pivot,
-                );
-            } else if layername == "attachment_0" {
-                aseprite_get_offsets_for_layer(
-                    &image_filepath,
-                    &output_path_attachment_0_image,
-                    &output_path_attachment_0_meta,
-                    "attachment_0",
-                    &mut offsets_attachment_0,
-                );
-            } else if layername == "attachment_1" {
-                aseprite_get_offsets_for_layer(
-                    &image_filepath,
-                    &output_path_attachment_1_image,
-                    &output_path_attachment_1_meta,
-                    "attachment_1",
-                    &mut offsets_attachment_1,
-                );
-            } else if layername == "attachment_2" {
-                aseprite_get_offsets_for_layer(
-                    &image_filepath,
-                    &output_path_attachment_2_image,
-                    &output_path_attachment_2_meta,
-                    "attachment_2",
-                    &mut offsets_attachment_2,
-                );
-            } else if layername == "attachment_3" {
-                aseprite_get_offsets_for_layer(
-                    &image_filepath,
-                    &output_path_attachment_3_image,
-                    &output_path_attachment_3_meta,
-                    "attachment_3",
-                    &mut offsets_attachment_3,
-                );
-            }
-        }
-    }
+    // NOTE: Variants extend their frames on each side so we need to shift the offsets accordingly
+    let frame_margin = variant.map(|variant| variant.frame_margin()).unwrap_or(0);
+    let get_offsets = |layer_name: &str| -> Vec<Vec2i> {
+        get_offsets_for_layer(image, image_filepath, layer_name)
+            .into_iter()
+            .map(|offset| offset + Vec2i::filled(frame_margin))
+            .collect()
+    };
+    let offsets_pivot = get_offsets("pivot");
+    let offsets_attachment_0 = get_offsets("attachment_0");
+    let offsets_attachment_1 = get_offsets("attachment_1");
+    let offsets_attachment_2 = get_offsets("attachment_2");
+    let offsets_attachment_3 = get_offsets("attachment_3");
 
     // Create sprites
     let mut result_sprites: IndexMap<ResourceName, AssetSprite> = IndexMap::new();
-    for (frame_index, frame) in meta.frames.iter().enumerate() {
+    for frame_index in 0..framecount {
         let sprite_name = sprite_name_for_frameindex(&sheet_name, frame_index);
 
         let attachment_points = [
@@ -356,52 +418,111 @@ pub fn create_sheet_animations_2d(
             offsets_attachment_2[frame_index],
             offsets_attachment_3[frame_index],
         ];
-        let new_sprite = sprite_create_from_frame_metadata(
-            &sprite_name,
+        let trimmed_rect = trimmed_rects[frame_index];
+        let trimmed_uvs = Recti::from_pos_dim(frame_positions[frame_index], trimmed_rect.dim);
+
+        // NOTE: The `atlas_texture_index` and the `trimmed_rect_uv` will be adjusted later when we
+        // actually pack the sprites into atlas textures
+        let new_sprite = AssetSprite {
+            name: sprite_name.clone(),
+
             has_translucency,
-            offsets_pivot[frame_index],
+            atlas_texture_index: std::u32::MAX,
+
+            pivot_offset: offsets_pivot[frame_index],
+
             attachment_points,
-            frame,
-        );
+
+            untrimmed_dimensions: Vec2i::new(
+                image.width as i32 + 2 * frame_margin,
+                image.height as i32 + 2 * frame_margin,
+            ),
+
+            trimmed_rect,
+            trimmed_uvs,
+            trimmed_uvs_rotated: false,
+        };
         result_sprites.insert(sprite_name, new_sprite);
     }
 
     // Create animation tags
-    let frametags: Vec<FrameTag> = if meta.meta.frame_tags.len() == 0 {
+    let frametags: Vec<AsepriteTag> = if image.tags.is_empty() {
         // If we have no animation tags we treat the whole frame-range as one big tagless animation
-        vec![FrameTag {
+        vec![AsepriteTag {
             name: "default".to_string(),
-            from: 0,
-            to: (framecount as i32 - 1),
-            direction: "forward".to_string(),
+            frame_index_from: 0,
+            frame_index_to: framecount - 1,
+            direction: AsepriteTagDirection::Forward,
+            repeat_count: 0,
+            user_data: AsepriteUserData::default(),
         }]
     } else {
-        meta.meta.frame_tags.clone()
+        image.tags.clone()
     };
 
+    // Collect frame events from the cel user data of all layers. Multiple events in one cel can
+    // be separated by commas or newlines
+    let mut events_per_frame: Vec<Vec<String>> = vec![Vec::new(); framecount];
+    for (frame_index, frame) in image.frames.iter().enumerate() {
+        for cel in &frame.cels {
+            if !is_content_layer(&image.layers[cel.layer_index]) {
+                continue;
+            }
+            if let Some(text) = &cel.user_data.text {
+                events_per_frame[frame_index].extend(
+                    text.split(|c| c == ',' || c == '\n')
+                        .map(|event| event.trim())
+                        .filter(|event| !event.is_empty())
+                        .map(|event| event.to_owned()),
+                );
+            }
+        }
+    }
+
     // Create animations
     let mut result_animations: IndexMap<ResourceName, AssetAnimation> = IndexMap::new();
     for frametag in frametags {
+        assert!(
+            frametag.frame_index_from <= frametag.frame_index_to
+                && frametag.frame_index_to < framecount,
+            "Animation tag '{}' in '{}' has invalid frame range {}..{}",
+            frametag.name,
+            image_filepath,
+            frametag.frame_index_from,
+            frametag.frame_index_to
+        );
         let animation_name = sheet_name.to_string() + ":" + &frametag.name;
 
         let mut sprite_names: Vec<ResourceName> = Vec::new();
         let mut frame_durations_ms: Vec<u32> = Vec::new();
-        for frame_index in frametag.from..=frametag.to {
-            let sprite_name = sprite_name_for_frameindex(&sheet_name, frame_index as usize);
+        let mut frame_events: Vec<Vec<String>> = Vec::new();
+        for frame_index in frametag.frame_index_from..=frametag.frame_index_to {
+            let sprite_name = sprite_name_for_frameindex(&sheet_name, frame_index);
             sprite_names.push(sprite_name);
-            frame_durations_ms.push(meta.frames[frame_index as usize].duration);
+            frame_durations_ms.push(image.frames[frame_index].duration_ms);
+            frame_events.push(events_per_frame[frame_index].clone());
         }
 
+        let playback_direction = match frametag.direction {
+            AsepriteTagDirection::Forward => AnimationPlaybackDirection::Forward,
+            AsepriteTagDirection::Reverse => AnimationPlaybackDirection::Reverse,
+            AsepriteTagDirection::PingPong => AnimationPlaybackDirection::PingPong,
+            AsepriteTagDirection::PingPongReverse => AnimationPlaybackDirection::PingPongReverse,
+        };
+
         let new_animation = AssetAnimation {
             name: animation_name.clone(),
             framecount: sprite_names.len() as u32,
             sprite_names,
             frame_durations_ms,
+            playback_direction,
+            frame_events,
         };
 
         result_animations.insert(animation_name, new_animation);
     }
 
+    let result_images = indexmap! { sheet_name.to_owned() => sheet_image };
     (result_images, result_sprites, result_animations)
 }
 
@@ -417,252 +538,105 @@ fn sprite_name_for_frameindex_and_stack_layer(
     format!("{}#{}.{}", sheet_name, stack_layer_index, frame_index)
 }
 
-fn aseprite_run_sheet_packer(
-    image_filepath: &str,
-    output_filepath_image: &str,
-    output_filepath_meta: &str,
-) {
-    let command = String::from("aseprite")
-        + " --batch"
-        + " --list-layers"
-        + " --list-tags"
-        + " --ignore-layer"
-        + " \"pivot\""
-        + " --ignore-layer"
-        + " \"attachment_0\""
-        + " --ignore-layer"
-        + " \"attachment_1\""
-        + " --ignore-layer"
-        + " \"attachment_2\""
-        + " --ignore-layer"
-        + " \"attachment_3\""
-        + " --format"
-        + " \"json-array\""
-        + " --sheet-pack"
-        + " --trim "
-        + image_filepath
-        + " --color-mode rgb "
-        + " --sheet "
-        + output_filepath_image
-        + " --data "
-        + output_filepath_meta;
-    run_systemcommand_fail_on_error(&command, false);
-
-    assert!(
-        path_exists(&output_filepath_image),
-        "Failed to generate sprite sheet for '{}' - '{}' is missing",
-        image_filepath,
-        output_filepath_image
-    );
-    assert!(
-        path_exists(&output_filepath_meta),
-        "Failed to generate sprite sheet for '{}' - '{}' is missing",
-        image_filepath,
-        output_filepath_meta
-    );
+fn load_image_as_aseprite_file(image_filepath: &str) -> AsepriteFile {
+    if image_filepath.ends_with(".ase") || image_filepath.ends_with(".aseprite") {
+        AsepriteFile::from_file(image_filepath)
+            .unwrap_or_else(|error| panic!("Failed to load sprite: {}", error))
+    } else if image_filepath.ends_with(".gif") {
+        // NOTE: Gif animations are treated like a tagless aseprite animation
+        let animation = GifAnimation::from_file(image_filepath)
+            .unwrap_or_else(|error| panic!("Failed to load sprite: {}", error));
+        AsepriteFile::from_frames(&animation.frames, &animation.frame_durations_ms)
+    } else {
+        AsepriteFile::from_bitmap(
+            &Bitmap::from_file(image_filepath)
+                .unwrap_or_else(|error| panic!("Failed to load sprite: {}", error)),
+        )
+    }
 }
 
-fn aseprite_get_offsets_for_layer(
-    image_filepath: &str,
-    output_filepath_image: &str,
-    output_filepath_meta: &str,
-    layer_name: &str,
-    out_offsets: &mut Vec<Vec2i>,
-) {
-    let framecount = out_offsets.len();
-    assert!(framecount > 0);
+/// Packs the given frames row by row into a roughly square sheet. Returns the sheet and the
+/// position of each frame within it. Empty frames are not packed.
+fn pack_frames_into_sheet(frame_images: &[Bitmap]) -> (Bitmap, Vec<Vec2i>) {
+    let area_total: i32 = frame_images
+        .iter()
+        .map(|image| image.width * image.height)
+        .sum();
+    let frame_width_max = frame_images
+        .iter()
+        .map(|image| image.width)
+        .max()
+        .unwrap_or(0);
+    let sheet_width = i32::max(
+        1,
+        i32::max(frame_width_max, f32::sqrt(area_total as f32).ceil() as i32),
+    );
 
-    let command = String::from("aseprite")
-        + " --batch"
-        + " --list-layers"
-        + " --list-tags"
-        + " --layer"
-        + " \""
-        + layer_name
-        + "\""
-        + " --format \"json-array\""
-        + " --trim"
-        + " --ignore-empty "
-        + image_filepath
-        + " --sheet "
-        + output_filepath_image
-        + " --data "
-        + output_filepath_meta;
-    run_systemcommand_fail_on_error(&command, false);
+    // NOTE: Packing the highest frames first results in denser rows
+    let mut packing_order: Vec<usize> = (0..frame_images.len()).collect();
+    packing_order.sort_by_key(|&frame_index| -frame_images[frame_index].height);
+
+    let mut frame_positions = vec![Vec2i::zero(); frame_images.len()];
+    let mut cursor = Vec2i::zero();
+    let mut row_height = 0;
+    for frame_index in packing_order {
+        let image = &frame_images[frame_index];
+        if image.is_empty() {
+            continue;
+        }
+        if cursor.x + image.width > sheet_width {
+            cursor.x = 0;
+            cursor.y += row_height;
+            row_height = 0;
+        }
+        frame_positions[frame_index] = cursor;
+        cursor.x += image.width;
+        row_height = i32::max(row_height, image.height);
+    }
+    let sheet_height = i32::max(1, cursor.y + row_height);
 
-    assert!(
-        path_exists(&output_filepath_image),
-        "Failed to generate offset information for '{}' - '{}' is missing",
-        image_filepath,
-        output_filepath_image
-    );
-    assert!(
-        path_exists(&output_filepath_meta),
-        "Failed to generate offset information for '{}' - '{}' is missing",
-        image_filepath,
-        output_filepath_meta
-    );
+    let mut sheet = Bitmap::new(sheet_width as u32, sheet_height as u32);
+    for (image, pos) in frame_images.iter().zip(frame_positions.iter()) {
+        if !image.is_empty() {
+            image.blit_to(&mut sheet, *pos, false);
+        }
+    }
 
-    // We don't need the actual offset image as it is just a bunch of merged pixels. We do need to
-    // rename the image though so it does not get the texture packer confused in a later stage
-    std::fs::rename(
-        &output_filepath_image,
-        &(output_filepath_image.to_owned() + ".backup"),
-    )
-    .unwrap();
+    (sheet, frame_positions)
+}
 
-    let metadata_string = std::fs::read_to_string(output_filepath_meta).unwrap();
-    if metadata_string.len() == 0 {
-        // NOTE: Sometimes we get an empty json file for images without offsets
-        return;
+/// Returns the top-left position of the content of the given marker layer for every frame
+fn get_offsets_for_layer(
+    image: &AsepriteFile,
+    image_filepath: &str,
+    layer_name: &str,
+) -> Vec<Vec2i> {
+    let framecount = image.frame_count();
+    if image.find_layer_index(layer_name).is_none() {
+        return vec![Vec2i::zero(); framecount];
     }
 
-    let meta: AsepriteJSON = serde_json::from_str(&metadata_string).expect(&format!(
-        "Failed to generate offset information for '{}' - Cannot parse metadata '{}'",
-        image_filepath, output_filepath_meta
-    ));
+    // NOTE: Marker layers are usually hidden so we ignore their visibility here
+    let offsets: Vec<Option<Vec2i>> = (0..framecount)
+        .map(|frame_index| {
+            image
+                .render_frame_filtered(frame_index, true, |layer| layer.name == layer_name)
+                .get_trimming_rect_for_value(true, true, true, true, PixelRGBA::transparent())
+                .map(|rect| rect.pos)
+        })
+        .collect();
 
+    let offset_count = offsets.iter().filter(|offset| offset.is_some()).count();
     assert!(
-        meta.frames.len() == 0 || meta.frames.len() == framecount,
+        offset_count == 0 || offset_count == framecount,
         "Failed to generate offset information for '{}' - Offset points in layer '{}' need 
             to be placed either on every frame or on none",
         image_filepath,
         layer_name
     );
 
-    for (index, frame) in meta.frames.iter().enumerate() {
-        out_offsets[index] = Vec2i::new(frame.sprite_source_size.x, frame.sprite_source_size.y);
-    }
-}
-
-fn aseprite_list_layers_of_file(file_path: &str) -> Vec<String> {
-    let command = String::from("aseprite ") + " --batch" + " --list-layers " + file_path;
-    let command_stdout = run_systemcommand_fail_on_error(&command, false).stdout;
-    command_stdout.lines().map(|line| line.to_owned()).collect()
-}
-
-fn sprite_create_from_frame_metadata(
-    sprite_name: &str,
-    has_translucency: bool,
-    pivot_offset: Vec2i,
-    attachment_points: [Vec2i; SPRITE_ATTACHMENT_POINTS_MAX_COUNT],
-    frame: &Frame,
-) -> AssetSprite {
-    let (trimmed_rect, trimmed_uvs) = if frame.sprite_source_size.w == 0
-        && frame.sprite_source_size.h == 0
-    {
-        // NOTE: The sprite is zero sized. This is useful for example if a character has an
-        //       animation where it can be invisible in one frame
-        (
-            Recti::from_width_height(0, 0),
-            Recti::from_width_height(0, 0),
-        )
-    } else {
-        (
-            Recti::from_xy_width_height(
-                frame.sprite_source_size.x,
-                frame.sprite_source_size.y,
-                frame.frame.w,
-                frame.frame.h,
-            ),
-            Recti::from_xy_width_height(frame.frame.x, frame.frame.y, frame.frame.w, frame.frame.h),
-        )
-    };
-
-    // NOTE: The `atlas_texture_index` and the `trimmed_rect_uv` will be adjusted later when we
-    // actually pack the sprites into atlas textures
-    AssetSprite {
-        name: sprite_name.to_owned(),
-
-        has_translucency,
-        atlas_texture_index: std::u32::MAX,
-
-        pivot_offset: pivot_offset,
-
-        attachment_points: attachment_points,
-
-        untrimmed_dimensions: Vec2i::new(frame.source_size.w, frame.source_size.h),
-
-        trimmed_rect,
-        trimmed_uvs,
-    }
-}
-
-////////////////////////////////////////////////////////////////////////////////////////////////////
-// Generated JSON structs (https://transform.tools/json-to-rust-serde)
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct AsepriteJSON {
-    frames: Vec<Frame>,
-    meta: Meta,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct Frame {
-    filename: String,
-    frame: Frame2,
-    rotated: bool,
-    trimmed: bool,
-    #[serde(rename = "spriteSourceSize")]
-    sprite_source_size: SpriteSourceSize,
-    #[serde(rename = "sourceSize")]
-    source_size: SourceSize,
-    duration: u32,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct Frame2 {
-    x: i32,
-    y: i32,
-    w: i32,
-    h: i32,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct SpriteSourceSize {
-    x: i32,
-    y: i32,
-    w: i32,
-    h: i32,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct SourceSize {
-    w: i32,
-    h: i32,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct Meta {
-    app: String,
-    version: String,
-    image: String,
-    format: String,
-    size: Size,
-    scale: String,
-    #[serde(rename = "frameTags")]
-    frame_tags: Vec<FrameTag>,
-    layers: Vec<Layer>,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct Size {
-    w: i32,
-    h: i32,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct FrameTag {
-    name: String,
-    from: i32,
-    to: i32,
-    direction: String,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
-struct Layer {
-    name: String,
-    opacity: i32,
-    #[serde(rename = "blendMode")]
-    blend_mode: String,
+    offsets
+        .into_iter()
+        .map(|offset| offset.unwrap_or(Vec2i::zero()))
+        .collect()
 }
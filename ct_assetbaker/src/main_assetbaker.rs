@@ -37,6 +37,9 @@ pub struct AssetSprite {
 
     pub trimmed_rect: Recti,
     pub trimmed_uvs: Recti,
+    /// The sprite is stored rotated by 90 degrees clockwise in its atlas texture. Note that
+    /// `trimmed_uvs` then has its width and height swapped compared to `trimmed_rect`
+    pub trimmed_uvs_rotated: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -51,6 +54,8 @@ pub struct AssetAnimation {
     pub framecount: u32,
     pub sprite_names: Vec<ResourceName>,
     pub frame_durations_ms: Vec<u32>,
+    pub playback_direction: AnimationPlaybackDirection,
+    pub frame_events: Vec<Vec<String>>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize)]
@@ -59,6 +64,8 @@ pub struct AssetAnimation3D {
     pub framecount: u32,
     pub sprite_names: Vec<ResourceName>,
     pub frame_durations_ms: Vec<u32>,
+    pub playback_direction: AnimationPlaybackDirection,
+    pub frame_events: Vec<Vec<String>>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize)]
@@ -82,6 +89,7 @@ pub struct AssetFont {
     pub horizontal_advance_max: i32,
     pub is_fixed_width_font: bool,
     pub font_height_in_pixels: i32,
+    pub distance_field_spread: Option<f32>,
     pub glyphcount: u32,
     pub glyphs: IndexMap<Codepoint, AssetGlyph>,
 }
@@ -108,26 +116,43 @@ fn bake_graphics_resources() {
                 "No font and/or render parameters found for font name '{}'",
                 &style.fontname
             ));
-            create_sheet_from_ttf(
-                &style.fontname,
-                &font.ttf_data_bytes,
-                font.metadata.height_in_pixels,
-                font.metadata.raster_offset,
-                style.bordered,
-                style.color_glyph,
-                style.color_border,
-            )
+            if let Some(distance_field) = &style.distance_field {
+                create_sheet_from_ttf_distance_field(
+                    &style.fontname,
+                    &font.ttf_data_bytes,
+                    distance_field.height_in_pixels,
+                    distance_field.spread_in_pixels,
+                )
+            } else {
+                create_sheet_from_ttf(
+                    &style.fontname,
+                    &font.ttf_data_bytes,
+                    font.metadata.height_in_pixels,
+                    font.metadata.raster_offset,
+                    style.bordered,
+                    style.color_glyph,
+                    style.color_border,
+                )
+            }
         })
         .collect();
     for sheet in font_sheets {
         result_sheet.extend_by(sheet);
     }
 
-    // Convert png and aseprite files to png sheets and move to them to `target/assets_temp`
+    // Convert png, gif, tga, bmp and aseprite files to png sheets and move to them to
+    // `target/assets_temp`
     let sprite_sheets: Vec<GraphicsSheet> = {
         let mut imagepaths = vec![];
         imagepaths.append(&mut collect_files_by_extension_recursive("assets", ".ase"));
+        imagepaths.append(&mut collect_files_by_extension_recursive(
+            "assets",
+            ".aseprite",
+        ));
         imagepaths.append(&mut collect_files_by_extension_recursive("assets", ".png"));
+        imagepaths.append(&mut collect_files_by_extension_recursive("assets", ".gif"));
+        imagepaths.append(&mut collect_files_by_extension_recursive("assets", ".tga"));
+        imagepaths.append(&mut collect_files_by_extension_recursive("assets", ".bmp"));
         imagepaths
             .par_iter()
             .map(|imagepath| {
@@ -140,6 +165,8 @@ fn bake_graphics_resources() {
         result_sheet.extend_by(sheet)
     }
 
+    result_sheet.particle_emitters = collect_particle_emitters();
+
     result_sheet.pack_and_serialize("graphics");
 
     // Create minimal prelude graphics sheet that starts up fast and only shows splashscreen
@@ -175,6 +202,30 @@ fn bake_graphics_resources() {
     prelude_sheet.pack_and_serialize("graphics_splash");
 }
 
+fn collect_particle_emitters() -> IndexMap<ResourceName, ParticleEmitterParams> {
+    collect_files_by_extension_recursive("assets", ".particles.json")
+        .iter()
+        .map(|filepath| {
+            let emitter_name = path_to_filename(filepath).replace(".particles.json", "");
+            let json_string = std::fs::read_to_string(filepath).unwrap_or_else(|error| {
+                panic!(
+                    "Could not read particle emitter file '{}': {}",
+                    filepath, error
+                )
+            });
+            let mut params: ParticleEmitterParams = serde_json::from_str(&json_string)
+                .unwrap_or_else(|error| {
+                    panic!(
+                        "Could not parse particle emitter file '{}': {}",
+                        filepath, error
+                    )
+                });
+            params.sort_keys_and_bursts();
+            (emitter_name, params)
+        })
+        .collect()
+}
+
 fn collect_font_drawstyles() -> Vec<BitmapFontDrawStyle> {
     // Check that we have drawstyles defined for each font in our assets directory
     collect_files_recursive("assets")
@@ -196,12 +247,14 @@ fn collect_font_drawstyles() -> Vec<BitmapFontDrawStyle> {
                         bordered: true,
                         color_glyph: PixelRGBA::white(),
                         color_border: PixelRGBA::black(),
+                        distance_field: None,
                     },
                     BitmapFontDrawStyle {
                         fontname: fontname.clone(),
                         bordered: false,
                         color_glyph: PixelRGBA::white(),
                         color_border: PixelRGBA::black(),
+                        distance_field: None,
                     },
                 ];
                 serialize_to_json_file(&default_drawstyles, &drawstyle_filepath);
@@ -263,12 +316,14 @@ fn collect_font_drawstyles() -> Vec<BitmapFontDrawStyle> {
                 bordered: false,
                 color_glyph: default_color_glyph,
                 color_border: default_color_border,
+                distance_field: None,
             });
             result.push(BitmapFontDrawStyle {
                 fontname: font::FONT_DEFAULT_TINY_NAME.to_owned(),
                 bordered: true,
                 color_glyph: default_color_glyph,
                 color_border: default_color_border,
+                distance_field: None,
             });
 
             serialize_to_json_file(&result, &default_drawstyles_path);
@@ -427,6 +482,20 @@ pub struct BitmapFontDrawStyle {
     pub bordered: bool,
     pub color_glyph: PixelRGBA,
     pub color_border: PixelRGBA,
+    /// If this is set the font is baked as a multi channel signed distance field font named
+    /// `<fontname>_distancefield` instead. Borders and colors are then ignored as they are
+    /// determined at draw time.
+    #[serde(default)]
+    pub distance_field: Option<DistanceFieldFontStyle>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub struct DistanceFieldFontStyle {
+    /// The height the glyphs are rasterized at. The font can be drawn crisp at other sizes too.
+    pub height_in_pixels: i32,
+    /// The maximum distance in pixels the distance field can represent. This limits how wide
+    /// outlines and glows can get.
+    pub spread_in_pixels: i32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -594,19 +663,33 @@ fn sprite_create_from_glyph(
     sprite_name: &str,
     glyph: &BitmapGlyph,
     position_in_font_atlas: Option<Vec2i>,
+    is_distance_field: bool,
 ) -> AssetSprite {
-    let glyph_rect = glyph.get_bitmap_rect();
-    let glyph_atlas_pos = if let Some(pos) = position_in_font_atlas {
+    let mut glyph_rect = glyph.get_bitmap_rect();
+    let mut glyph_atlas_pos = if let Some(pos) = position_in_font_atlas {
         pos
     } else {
         Vec2i::zero()
     };
 
+    // NOTE: Distance field glyphs are sampled bilinearly. To make sure that we never sample
+    //       pixels of neighbouring sprites in the atlas we shrink the glyph by one pixel on each
+    //       side. This is fine because the outermost pixels of distance field glyphs are padding.
+    if is_distance_field && glyph.bitmap.is_some() {
+        glyph_rect = Recti::from_xy_width_height(
+            glyph_rect.pos.x + 1,
+            glyph_rect.pos.y + 1,
+            glyph_rect.width() - 2,
+            glyph_rect.height() - 2,
+        );
+        glyph_atlas_pos = glyph_atlas_pos + Vec2i::ones();
+    }
+
     // NOTE: The `atlas_texture_index` and the `trimmed_rect_uv` will be adjusted later when we
     // actually pack the sprites into atlas textures
     AssetSprite {
         name: sprite_name.to_owned(),
-        has_translucency: false,
+        has_translucency: is_distance_field,
         atlas_texture_index: std::u32::MAX,
         pivot_offset: Vec2i::zero(),
         attachment_points: [Vec2i::zero(); SPRITE_ATTACHMENT_POINTS_MAX_COUNT],
@@ -618,6 +701,7 @@ fn sprite_create_from_glyph(
             glyph_rect.width(),
             glyph_rect.height(),
         ),
+        trimmed_uvs_rotated: false,
     }
 }
 
@@ -644,7 +728,28 @@ fn create_sheet_from_ttf(
         color_glyph,
         color_border,
     );
-    let (font_atlas_texture, font_atlas_glyph_positions) = font.to_bitmap_atlas(&font_name);
+    create_sheet_from_font(&font, None)
+}
+
+fn create_sheet_from_ttf_distance_field(
+    font_name: &str,
+    font_ttf_bytes: &[u8],
+    height_in_pixels: i32,
+    spread_in_pixels: i32,
+) -> GraphicsSheet {
+    let font_name = font_name.to_owned() + "_distancefield";
+    let font = BitmapFont::new_distance_field(
+        &font_name,
+        &font_ttf_bytes,
+        height_in_pixels,
+        spread_in_pixels,
+    );
+    create_sheet_from_font(&font, Some(spread_in_pixels))
+}
+
+fn create_sheet_from_font(font: &BitmapFont, distance_field_spread: Option<i32>) -> GraphicsSheet {
+    let font_name = &font.font_name;
+    let (font_atlas_texture, font_atlas_glyph_positions) = font.to_bitmap_atlas(font_name);
 
     // Human readable output
     let output_png_filepath = format!("target/assets_temp/fonts/{}.png", font_name);
@@ -655,9 +760,14 @@ fn create_sheet_from_ttf(
     let mut result_sprites: IndexMap<ResourceName, AssetSprite> = IndexMap::new();
     for glyph in font.glyphs.values() {
         let codepoint = glyph.codepoint as Codepoint;
-        let sprite_name = BitmapFont::get_glyph_name(&font_name, glyph.codepoint as Codepoint);
+        let sprite_name = BitmapFont::get_glyph_name(font_name, glyph.codepoint as Codepoint);
         let sprite_pos = font_atlas_glyph_positions.get(&sprite_name).cloned();
-        let sprite = sprite_create_from_glyph(&sprite_name, glyph, sprite_pos);
+        let sprite = sprite_create_from_glyph(
+            &sprite_name,
+            glyph,
+            sprite_pos,
+            distance_field_spread.is_some(),
+        );
 
         let asset_glyph = AssetGlyph {
             codepoint,
@@ -679,6 +789,7 @@ fn create_sheet_from_ttf(
         horizontal_advance_max: font.horizontal_advance_max,
         is_fixed_width_font: font.is_fixed_width_font,
         font_height_in_pixels: font.font_height_in_pixels,
+        distance_field_spread: distance_field_spread.map(|spread| spread as f32),
         glyphcount: result_glyphs.len() as u32,
         glyphs: result_glyphs,
     };
@@ -690,6 +801,7 @@ fn create_sheet_from_ttf(
         sprites_3d: IndexMap::new(),
         animations: IndexMap::new(),
         animations_3d: IndexMap::new(),
+        particle_emitters: IndexMap::new(),
     }
 }
 
@@ -714,9 +826,14 @@ fn convert_sprite(sprite: &AssetSprite, atlas_texture_sizes: &[u32]) -> Sprite {
 
         untrimmed_dimensions: Vec2::from(sprite.untrimmed_dimensions),
         trimmed_rect: Rect::from(sprite.trimmed_rect),
-        trimmed_uvs: AAQuad::from_rect(Rect::from(sprite.trimmed_uvs).scaled_from_origin(
-            Vec2::filled(1.0 / atlas_texture_sizes[sprite.atlas_texture_index as usize] as f32),
-        )),
+        trimmed_uvs: AAQuad {
+            rotated: sprite.trimmed_uvs_rotated,
+            ..AAQuad::from_rect(
+                Rect::from(sprite.trimmed_uvs).scaled_from_origin(Vec2::filled(
+                    1.0 / atlas_texture_sizes[sprite.atlas_texture_index as usize] as f32,
+                )),
+            )
+        },
     }
 }
 
@@ -772,6 +889,7 @@ fn convert_font(
         horizontal_advance_max: font.horizontal_advance_max,
         is_fixed_width_font: font.is_fixed_width_font,
         font_height_in_pixels: font.font_height_in_pixels,
+        distance_field_spread: font.distance_field_spread,
         ascii_glyphs,
         unicode_glyphs,
     }
@@ -782,13 +900,19 @@ fn convert_animation(
     final_sprites_by_name: &IndexMap<ResourceName, Sprite>,
 ) -> Animation<Sprite> {
     assert!(anim.sprite_names.len() == anim.frame_durations_ms.len());
+    assert!(anim.sprite_names.len() == anim.frame_events.len());
     let mut anim_result = Animation::new_empty(anim.name.clone());
-    for (&frame_duration_ms, sprite_name) in
-        anim.frame_durations_ms.iter().zip(anim.sprite_names.iter())
+    anim_result.playback_direction = anim.playback_direction;
+    for ((&frame_duration_ms, sprite_name), events) in anim
+        .frame_durations_ms
+        .iter()
+        .zip(anim.sprite_names.iter())
+        .zip(anim.frame_events.iter())
     {
-        anim_result.add_frame(
+        anim_result.add_frame_with_events(
             frame_duration_ms as f32 / 1000.0,
             final_sprites_by_name[sprite_name].clone(),
+            events.clone(),
         );
     }
     anim_result
@@ -799,15 +923,19 @@ fn convert_animation_3d(
     final_sprites_by_name_3d: &IndexMap<ResourceName, Sprite3D>,
 ) -> Animation<Sprite3D> {
     assert!(anim_3d.sprite_names.len() == anim_3d.frame_durations_ms.len());
+    assert!(anim_3d.sprite_names.len() == anim_3d.frame_events.len());
     let mut anim_result = Animation::new_empty(anim_3d.name.clone());
-    for (&frame_duration_ms, sprite_name) in anim_3d
+    anim_result.playback_direction = anim_3d.playback_direction;
+    for ((&frame_duration_ms, sprite_name), events) in anim_3d
         .frame_durations_ms
         .iter()
         .zip(anim_3d.sprite_names.iter())
+        .zip(anim_3d.frame_events.iter())
     {
-        anim_result.add_frame(
+        anim_result.add_frame_with_events(
             frame_duration_ms as f32 / 1000.0,
             final_sprites_by_name_3d[sprite_name].clone(),
+            events.clone(),
         );
     }
     anim_result
@@ -939,12 +1067,18 @@ fn main() {
             if filepath.ends_with(".wav")
                 || filepath.ends_with(".ogg")
                 || filepath.ends_with(".png")
+                || filepath.ends_with(".gif")
+                || filepath.ends_with(".tga")
+                || filepath.ends_with(".bmp")
                 || filepath.ends_with(".ase")
+                || filepath.ends_with(".aseprite")
+                || filepath.ends_with(".spritevariants.json")
                 || filepath.ends_with(".license")
                 || filepath.ends_with(".audiometa.json")
                 || filepath.ends_with(".fontmeta.json")
                 || filepath.ends_with(".ttf")
                 || filepath.ends_with("font_drawstyles.json")
+                || filepath.ends_with(".particles.json")
                 || filepath.ends_with("credits.txt")
             {
                 // We already processed the above files types by other means
@@ -979,6 +1113,34 @@ fn main() {
     );
 }
 
+/// Creates a copy of the given atlas texture with outlines and names of all packed sprites drawn on
+/// top of it
+fn create_atlas_debug_bitmap(atlas: &BitmapAtlas, font: &BitmapFont) -> Bitmap {
+    let mut result = atlas.atlas_texture.clone();
+    let color_outline = PixelRGBA::new(255, 0, 255, 255);
+    for (sprite_name, rect) in atlas.sprite_rects.iter() {
+        if rect.width() == 0 || rect.height() == 0 {
+            continue;
+        }
+        result.draw_rect(
+            rect.pos.x,
+            rect.pos.y,
+            rect.width(),
+            rect.height(),
+            color_outline,
+        );
+        result.draw_text(
+            font,
+            sprite_name,
+            1,
+            rect.pos + Vec2i::ones(),
+            Vec2i::zero(),
+            false,
+        );
+    }
+    result
+}
+
 //==================================================================================================
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
@@ -990,6 +1152,7 @@ pub struct GraphicsSheet {
     sprites_3d: IndexMap<ResourceName, AssetSprite3D>,
     animations: IndexMap<ResourceName, AssetAnimation>,
     animations_3d: IndexMap<ResourceName, AssetAnimation3D>,
+    particle_emitters: IndexMap<ResourceName, ParticleEmitterParams>,
 }
 
 impl GraphicsSheet {
@@ -1001,6 +1164,7 @@ impl GraphicsSheet {
             sprites_3d: IndexMap::new(),
             animations: IndexMap::new(),
             animations_3d: IndexMap::new(),
+            particle_emitters: IndexMap::new(),
         }
     }
 
@@ -1011,6 +1175,7 @@ impl GraphicsSheet {
         self.sprites_3d.extend(other.sprites_3d);
         self.animations.extend(other.animations);
         self.animations_3d.extend(other.animations_3d);
+        self.particle_emitters.extend(other.particle_emitters);
     }
 
     fn pack_and_serialize(mut self, pack_name: &str) {
@@ -1018,13 +1183,108 @@ impl GraphicsSheet {
         let pack_out_filepath = format!("resources/{}.data", pack_name);
         path_recreate_directory_looped(&pack_temp_out_dir);
 
+        // Determine which sheet image contains which sprite
+        let mut sprite_sheet_names: IndexMap<ResourceName, ResourceName> = IndexMap::new();
+        for image_name in self.images.keys() {
+            if self.sprites.contains_key(image_name) {
+                // Image is a regular sprite
+                sprite_sheet_names.insert(image_name.clone(), image_name.clone());
+            } else if self.fonts.contains_key(image_name) {
+                // Image is a glyph-sheet of some font
+                let font = &self.fonts[image_name];
+                for sprite_glyph_name in font.glyphs.values().map(|glyph| &glyph.sprite_name) {
+                    sprite_sheet_names.insert(sprite_glyph_name.clone(), image_name.clone());
+                }
+            } else {
+                // Image must be an animation-sheet of some animation(s)
+                let mut found_anim = false;
+                for (animation_name, animation) in &self.animations {
+                    if animation_name.starts_with(&(image_name.to_owned() + ":"))
+                        || animation_name == image_name
+                    {
+                        found_anim = true;
+                        for sprite_frame_name in &animation.sprite_names {
+                            sprite_sheet_names
+                                .insert(sprite_frame_name.clone(), image_name.clone());
+                        }
+                    }
+                }
+
+                assert!(
+                    found_anim,
+                    "Image '{}' does not belong to any known sprite, font or animation",
+                    image_name,
+                );
+            }
+        }
+
+        // Cut out every sprite from its sheet image so that we can pack them individually
+        //
+        // NOTE: Our atlas textures use premultiplied alpha. We premultiply every sprite on its own
+        //       instead of the whole atlas because distance field glyphs must be left untouched.
+        //       Their rgb channels contain distances and multiplying them by the alpha channel
+        //       would destroy the distances near the glyph edges.
+        let mut sprite_bitmaps: Vec<(ResourceName, Bitmap)> = sprite_sheet_names
+            .iter()
+            .map(|(sprite_name, image_name)| {
+                let sprite = &self.sprites[sprite_name];
+                let mut bitmap = self.images[image_name].cropped_by_rect(sprite.trimmed_uvs);
+                let is_distance_field = self
+                    .fonts
+                    .get(image_name)
+                    .is_some_and(|font| font.distance_field_spread.is_some());
+                if !is_distance_field {
+                    bitmap.premultiply_alpha();
+                }
+                (sprite_name.clone(), bitmap)
+            })
+            .collect();
+        // NOTE: Packing big bitmaps first results in denser atlas textures
+        sprite_bitmaps.sort_by_key(|(_name, bitmap)| {
+            std::cmp::Reverse((
+                i32::max(bitmap.width, bitmap.height),
+                bitmap.width * bitmap.height,
+            ))
+        });
+
         // Pack textures
-        let (mut textures, sprite_positions) = {
-            let mut packer = BitmapMultiAtlas::new(1024, Some(2048), true);
-            for (image_name, image) in self.images.iter() {
-                packer.pack_bitmap(image_name, image);
+        let (mut textures, sprite_positions, atlas_stats, atlas_debug_textures) = {
+            let packing_params = BitmapAtlasPackingParams {
+                padding: 1,
+                extrusion: 1,
+                allow_rotation: true,
+                power_of_two: true,
+            };
+            let mut packer = BitmapMultiAtlas::new(1024, Some(2048), true, packing_params);
+            for (sprite_name, bitmap) in sprite_bitmaps.iter() {
+                packer.pack_bitmap(sprite_name, bitmap);
             }
-            packer.finish()
+            packer.shrink_to_fit();
+
+            let atlas_stats = packer.stats();
+            let debug_font = BitmapFont::new(
+                FONT_DEFAULT_TINY_NAME,
+                FONT_DEFAULT_TINY_TTF,
+                FONT_DEFAULT_TINY_PIXEL_HEIGHT,
+                FONT_DEFAULT_TINY_RASTER_OFFSET,
+                1,
+                0,
+                PixelRGBA::white(),
+                PixelRGBA::black(),
+            );
+            let atlas_debug_textures: Vec<Bitmap> = packer
+                .atlas_packers
+                .iter()
+                .map(|atlas| create_atlas_debug_bitmap(atlas, &debug_font))
+                .collect();
+
+            let (textures, sprite_positions) = packer.finish();
+            (
+                textures,
+                sprite_positions,
+                atlas_stats,
+                atlas_debug_textures,
+            )
         };
 
         // NOTE: Drawstate assumes that every texture has a white pixel in its bottom-right corner
@@ -1043,56 +1303,43 @@ impl GraphicsSheet {
             .collect();
         let textures_png_data: Vec<Vec<u8>> = textures
             .into_iter()
-            .map(|texture| texture.to_premultiplied_alpha().encoded_as_png())
+            .map(|texture| texture.encoded_as_png())
             .collect();
 
-        // Create texture atlas and adjust positions of our sprites according to the final packed
-        // atlas positions
+        // Adjust positions of our sprites according to the final packed atlas positions
         for (packed_sprite_name, sprite_pos) in &sprite_positions {
-            if self.sprites.contains_key(packed_sprite_name) {
-                // Atlas-sprite is a regular sprite
-                let mut sprite = self.sprites.get_mut(packed_sprite_name).unwrap();
-                sprite.atlas_texture_index = sprite_pos.atlas_texture_index;
-                sprite.trimmed_uvs = sprite
-                    .trimmed_uvs
-                    .translated_by(sprite_pos.atlas_texture_pixel_offset);
-            } else if self.fonts.contains_key(packed_sprite_name) {
-                // Atlas-sprite is a glyph-sheet of some font
-                let font = &self.fonts[packed_sprite_name];
-                for sprite_glyph_name in font.glyphs.values().map(|glyph| &glyph.sprite_name) {
-                    let mut sprite = self.sprites.get_mut(sprite_glyph_name).unwrap();
-                    sprite.atlas_texture_index = sprite_pos.atlas_texture_index;
-                    sprite.trimmed_uvs = sprite
-                        .trimmed_uvs
-                        .translated_by(sprite_pos.atlas_texture_pixel_offset);
-                }
+            let sprite = self.sprites.get_mut(packed_sprite_name).unwrap();
+            let dim = sprite.trimmed_uvs.dim;
+            sprite.atlas_texture_index = sprite_pos.atlas_texture_index;
+            sprite.trimmed_uvs_rotated = sprite_pos.rotated;
+            sprite.trimmed_uvs = if sprite_pos.rotated {
+                Recti::from_pos_width_height(sprite_pos.atlas_texture_pixel_offset, dim.y, dim.x)
             } else {
-                // AssetSprite must be an animation-sheet of some animation(s)
-                let mut found_anim = false;
-                for (animation_name, animation) in &self.animations {
-                    if animation_name.starts_with(&(packed_sprite_name.to_owned() + ":"))
-                        || animation_name == packed_sprite_name
-                    {
-                        found_anim = true;
-                        for sprite_frame_name in &animation.sprite_names {
-                            let mut sprite = self.sprites.get_mut(sprite_frame_name).unwrap();
-                            sprite.atlas_texture_index = sprite_pos.atlas_texture_index;
-                            sprite.trimmed_uvs = sprite
-                                .trimmed_uvs
-                                .translated_by(sprite_pos.atlas_texture_pixel_offset);
-                        }
-                    }
-                }
+                Recti::from_pos_dim(sprite_pos.atlas_texture_pixel_offset, dim)
+            };
+        }
 
-                assert!(
-                    found_anim,
-                    "Packed unknown sprite name '{}' into atlas at position ({},{},{})",
-                    packed_sprite_name,
-                    sprite_pos.atlas_texture_index,
-                    sprite_pos.atlas_texture_pixel_offset.x,
-                    sprite_pos.atlas_texture_pixel_offset.y,
-                );
-            }
+        // Packing report
+        for (index, stats) in atlas_stats.iter().enumerate() {
+            log::info!(
+                "Atlas '{}' page {}: {}x{} with {} sprites - {:.1}% of the area used by sprites",
+                pack_name,
+                index,
+                stats.texture_size,
+                stats.texture_size,
+                stats.bitmap_count,
+                100.0 * stats.efficiency()
+            );
+        }
+        serialize_to_json_file(
+            &atlas_stats,
+            &path_join(&pack_temp_out_dir, "atlas_stats.json"),
+        );
+        for (index, debug_texture) in atlas_debug_textures.iter().enumerate() {
+            debug_texture.write_to_png_file(&format!(
+                "{}/atlas-debug-{}x{}-{}.png",
+                pack_temp_out_dir, debug_texture.width, debug_texture.height, index
+            ));
         }
 
         // HUMAN READABLE OUTPUT
@@ -1124,6 +1371,10 @@ impl GraphicsSheet {
             &human_readable_animations_3d,
             &path_join(&pack_temp_out_dir, "animations_3d.json"),
         );
+        serialize_to_json_file(
+            &self.particle_emitters,
+            &path_join(&pack_temp_out_dir, "particle_emitters.json"),
+        );
         for (index, (png_data, texture_dimension)) in textures_png_data
             .iter()
             .zip(textures_dimensions.iter())
@@ -1152,7 +1403,7 @@ impl GraphicsSheet {
             .iter()
             .map(|(name, sprite)| (name.clone(), convert_sprite_3d(&sprite, &sprites)))
             .collect();
-        let animations = self
+        let animations: IndexMap<ResourceName, Animation<Sprite>> = self
             .animations
             .iter()
             .map(|(name, anim)| (name.clone(), convert_animation(anim, &sprites)))
@@ -1168,6 +1419,24 @@ impl GraphicsSheet {
             .map(|(name, font)| (name.clone(), convert_font(font, &sprites)))
             .collect();
 
+        // Make sure that our particle emitters only reference existing resources
+        for (emitter_name, params) in &self.particle_emitters {
+            for resource_name in params.referenced_resource_names() {
+                let resource_exists = match params.appearance {
+                    ParticleAppearance::Animation { .. } => animations.contains_key(resource_name),
+                    _ => {
+                        sprites.contains_key(resource_name)
+                            || sprites.contains_key(&format!("{}.0", resource_name))
+                    }
+                };
+                assert!(
+                    resource_exists,
+                    "Particle emitter '{}' references unknown sprite or animation '{}'",
+                    emitter_name, resource_name
+                );
+            }
+        }
+
         // Create GraphicsResources for serialization
         let graphics_resources = GraphicResources {
             animations,
@@ -1175,6 +1444,7 @@ impl GraphicsSheet {
             fonts,
             sprites,
             sprites_3d,
+            particle_emitters: self.particle_emitters,
             textures_png_data,
         };
 
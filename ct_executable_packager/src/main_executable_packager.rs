@@ -145,10 +145,15 @@ fn create_windows_launcher_icon_images(
     let mut result = HashMap::new();
     for &size in windows_icon_sizes.iter() {
         if !existing_launcher_icons.contains_key(&size) {
-            let scaled_image = existing_launcher_icons
-                .get(&biggest_size)
-                .unwrap()
-                .scaled_sample_nearest_neighbor(size as u32, size as u32);
+            // NOTE: We prefer downscaling from the smallest icon that is bigger than the target
+            //       size and only upscale if there is none
+            let source_size = existing_launcher_icons
+                .keys()
+                .filter(|&&source_size| source_size > size)
+                .min()
+                .unwrap_or(biggest_size);
+            let source_image = existing_launcher_icons.get(source_size).unwrap();
+            let scaled_image = scale_launcher_icon_image(source_image, size);
             result.insert(size, scaled_image);
         } else {
             let image = existing_launcher_icons.get(&size).unwrap();
@@ -158,6 +163,25 @@ fn create_windows_launcher_icon_images(
     result
 }
 
+/// Upscales pixel art icons with Scale2x/Scale3x until they are at least the target size and
+/// downscales the rest of the way with area averaging
+fn scale_launcher_icon_image(image: &Bitmap, size: i32) -> Bitmap {
+    assert!(image.width == image.height, "Launcher icons must be square");
+
+    let mut result = image.clone();
+    while result.width < size {
+        result = if size % (3 * result.width) == 0 {
+            result.scaled_scale3x()
+        } else {
+            result.scaled_scale2x()
+        };
+    }
+    if result.width > size {
+        result = result.scaled_down_area_average(size as u32, size as u32);
+    }
+    result
+}
+
 fn create_windows_launcher_icon(
     windows_icon_images: &HashMap<i32, Bitmap>,
     icon_output_filepath: &str,
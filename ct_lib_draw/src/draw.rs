@@ -25,6 +25,11 @@ pub const DEFAULT_WORLD_ZFAR: Depth = -100.0;
 pub const ADDITIVITY_NONE: Additivity = 0.0;
 pub const ADDITIVITY_MAX: Additivity = 1.0;
 
+/// Texture indices starting at this value refer to pages of the dynamic atlas and not to the atlas
+/// pages assigned via `Drawstate::assign_textures`
+pub const TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST: TextureIndex = 1 << 16;
+pub const DYNAMIC_ATLAS_TEXTURE_SIZE: u32 = 1024;
+
 trait Vertex: Sized + Copy + Clone + Default {
     const FLOAT_COMPONENT_COUNT: usize = std::mem::size_of::<Self>() / std::mem::size_of::<f32>();
     fn as_floats(&self) -> &[f32] {
@@ -89,11 +94,15 @@ struct Drawable {
     pub uv_region_contains_translucency: bool,
     pub drawparams: Drawparams,
     pub geometry: Geometry,
+    /// Is `Some` if the drawable is a multi channel signed distance field that needs to be drawn
+    /// with the distancefield shader
+    pub distance_field: Option<DistanceFieldParams>,
 }
 
 impl Drawable {
     pub fn is_translucent(&self) -> bool {
         self.uv_region_contains_translucency
+            || self.distance_field.is_some()
             || (self.drawparams.color_modulate.a < 1.0)
             || (self.drawparams.additivity != ADDITIVITY_NONE)
     }
@@ -185,31 +194,33 @@ impl VertexbufferDefault {
                 self.indices.push(indices_start_offset + 1); // right bottom
                 self.indices.push(indices_start_offset + 3); // left top
 
+                let [uv_left_top, uv_right_top, uv_right_bottom, uv_left_bottom] = uvs.corners();
+
                 // right top
                 self.vertices.push(VertexDefault {
                     pos: Vec3::from_vec2(quad.vert_right_top, depth),
-                    uv: Vec2::new(uvs.right, uvs.top),
+                    uv: uv_right_top,
                     color,
                     additivity,
                 });
                 // right bottom
                 self.vertices.push(VertexDefault {
                     pos: Vec3::from_vec2(quad.vert_right_bottom, depth),
-                    uv: Vec2::new(uvs.right, uvs.bottom),
+                    uv: uv_right_bottom,
                     color,
                     additivity,
                 });
                 // left bottom
                 self.vertices.push(VertexDefault {
                     pos: Vec3::from_vec2(quad.vert_left_bottom, depth),
-                    uv: Vec2::new(uvs.left, uvs.bottom),
+                    uv: uv_left_bottom,
                     color,
                     additivity,
                 });
                 // left top
                 self.vertices.push(VertexDefault {
                     pos: Vec3::from_vec2(quad.vert_left_top, depth),
-                    uv: Vec2::new(uvs.left, uvs.top),
+                    uv: uv_left_top,
                     color,
                     additivity,
                 });
@@ -267,6 +278,20 @@ struct ShaderParamsDefault {
 }
 impl UniformBlock for ShaderParamsDefault {}
 
+/// NOTE: All distances are given in texels
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct ShaderParamsDistanceField {
+    pub transform: Mat4,
+    pub texture_size: Vec2,
+    pub spread: f32,
+    pub outline_color: Color,
+    pub outline_thickness: f32,
+    pub glow_color: Color,
+    pub glow_radius: f32,
+}
+impl UniformBlock for ShaderParamsDistanceField {}
+
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
 struct ShaderParamsBlit {
@@ -384,10 +409,66 @@ impl Drawparams {
     }
 }
 
+/// Effects for text drawn via `Drawstate::draw_text_distance_field`. The outline thickness and
+/// glow radius are given in pixels of the drawn text and are limited by the spread of the font.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DistanceFieldEffects {
+    pub outline_color: Color,
+    pub outline_thickness: f32,
+    pub glow_color: Color,
+    pub glow_radius: f32,
+}
+
+impl DistanceFieldEffects {
+    #[inline]
+    pub fn none() -> DistanceFieldEffects {
+        DistanceFieldEffects::default()
+    }
+
+    #[inline]
+    pub fn with_outline(outline_color: Color, outline_thickness: f32) -> DistanceFieldEffects {
+        DistanceFieldEffects {
+            outline_color,
+            outline_thickness,
+            ..DistanceFieldEffects::default()
+        }
+    }
+
+    #[inline]
+    pub fn with_glow(glow_color: Color, glow_radius: f32) -> DistanceFieldEffects {
+        DistanceFieldEffects {
+            glow_color,
+            glow_radius,
+            ..DistanceFieldEffects::default()
+        }
+    }
+}
+
+/// Same as `DistanceFieldEffects` but with all distances given in texels of the distance field
+#[derive(Debug, Copy, Clone)]
+struct DistanceFieldParams {
+    pub spread: f32,
+    pub outline_color: Color,
+    pub outline_thickness: f32,
+    pub glow_color: Color,
+    pub glow_radius: f32,
+}
+
+impl PartialEq for DistanceFieldParams {
+    fn eq(&self, other: &DistanceFieldParams) -> bool {
+        self.spread == other.spread
+            && self.outline_color.to_slice() == other.outline_color.to_slice()
+            && self.outline_thickness == other.outline_thickness
+            && self.glow_color.to_slice() == other.glow_color.to_slice()
+            && self.glow_radius == other.glow_radius
+    }
+}
+
 #[derive(Clone)]
 struct DrawBatch {
     pub drawspace: Drawspace,
     pub texture_index: TextureIndex,
+    pub distance_field: Option<DistanceFieldParams>,
     pub is_translucent: bool,
     pub indices_start_offset: VertexIndex,
     pub indices_count: usize,
@@ -397,8 +478,13 @@ struct DrawBatch {
 pub struct Drawstate {
     textures: Vec<Rc<RefCell<Bitmap>>>,
     textures_size: u32,
+    /// Contains the dirty flags of the assigned textures followed by those of the dynamic atlas
+    /// pages. Use `textures_dirty_index` to look up the flag for a given texture index.
     textures_dirty: Vec<bool>,
 
+    /// Holds bitmaps that were created at runtime. It is created on first use
+    dynamic_atlas: Option<BitmapMultiAtlas>,
+
     untextured_uv_center_coord: AAQuad,
     untextured_uv_center_atlas_page: TextureIndex,
 
@@ -440,6 +526,7 @@ impl Drawstate {
             top: 1.0,
             right: 1.0,
             bottom: 1.0,
+            rotated: false,
         };
         let untextured_uv_center_atlas_page = 0;
 
@@ -448,6 +535,8 @@ impl Drawstate {
             textures_size: 1,
             textures_dirty,
 
+            dynamic_atlas: None,
+
             untextured_uv_center_coord,
             untextured_uv_center_atlas_page,
 
@@ -489,18 +578,130 @@ impl Drawstate {
             );
         }
 
-        let textures_dirty = vec![true; textures.len()];
+        let textures_dirty = vec![true; textures.len() + self.dynamic_atlas_page_count()];
 
         self.textures = textures;
         self.textures_size = textures_size;
         self.textures_dirty = textures_dirty;
     }
 
+    /// Returns the position of the dirty flag for the given texture index in `textures_dirty`
+    #[inline]
+    fn textures_dirty_index(&self, texture_index: TextureIndex) -> usize {
+        if texture_index >= TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST {
+            self.textures.len() + (texture_index - TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST) as usize
+        } else {
+            texture_index as usize
+        }
+    }
+
+    fn mark_texture_dirty(&mut self, texture_index: TextureIndex) {
+        let dirty_index = self.textures_dirty_index(texture_index);
+        if dirty_index >= self.textures_dirty.len() {
+            self.textures_dirty.resize(dirty_index + 1, false);
+        }
+        self.textures_dirty[dirty_index] = true;
+    }
+
+    #[inline]
+    fn dynamic_atlas_page_count(&self) -> usize {
+        self.dynamic_atlas
+            .as_ref()
+            .map(|dynamic_atlas| dynamic_atlas.atlas_packers.len())
+            .unwrap_or(0)
+    }
+
     fn texturename_for_atlaspage(textures_size: u32, page_index: TextureIndex) -> String {
-        format!(
-            "atlas_page_{}__{}x{}",
-            page_index, textures_size, textures_size
-        )
+        if page_index >= TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST {
+            format!(
+                "dynamic_atlas_page_{}__{}x{}",
+                page_index - TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST,
+                DYNAMIC_ATLAS_TEXTURE_SIZE,
+                DYNAMIC_ATLAS_TEXTURE_SIZE
+            )
+        } else {
+            format!(
+                "atlas_page_{}__{}x{}",
+                page_index, textures_size, textures_size
+            )
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Dynamic atlas
+
+    /// Packs a bitmap that was created at runtime into the dynamic atlas and returns a sprite that
+    /// can be drawn like any other sprite. If a sprite with the same name was already added
+    /// before, it is replaced. Only the atlas page that the bitmap was packed into is re-uploaded.
+    ///
+    /// NOTE: The given bitmap is expected to not have premultiplied alpha
+    /// NOTE: Unlike the sprites created by the assetbaker the resulting sprite is not trimmed and
+    ///       has its pivot in the left top corner
+    pub fn dynamic_atlas_add_bitmap(&mut self, sprite_name: &str, bitmap: &Bitmap) -> Sprite {
+        assert!(
+            bitmap.width as u32 <= DYNAMIC_ATLAS_TEXTURE_SIZE
+                && bitmap.height as u32 <= DYNAMIC_ATLAS_TEXTURE_SIZE,
+            "Bitmap '{}' with dimensions {}x{} does not fit into the dynamic atlas with dimensions {}x{}",
+            sprite_name,
+            bitmap.width,
+            bitmap.height,
+            DYNAMIC_ATLAS_TEXTURE_SIZE,
+            DYNAMIC_ATLAS_TEXTURE_SIZE
+        );
+
+        self.dynamic_atlas_remove_bitmap(sprite_name);
+
+        let dynamic_atlas = self.dynamic_atlas.get_or_insert_with(|| {
+            BitmapMultiAtlas::new(
+                DYNAMIC_ATLAS_TEXTURE_SIZE,
+                Some(DYNAMIC_ATLAS_TEXTURE_SIZE),
+                false,
+                BitmapAtlasPackingParams::default(),
+            )
+        });
+        let atlas_position = dynamic_atlas
+            .try_pack_bitmap(sprite_name, &bitmap.to_premultiplied_alpha())
+            .unwrap();
+
+        self.mark_texture_dirty(
+            TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST + atlas_position.atlas_texture_index,
+        );
+
+        let dim = Vec2::new(bitmap.width as f32, bitmap.height as f32);
+        let uvs_rect =
+            Rect::from_pos_dim(Vec2::from(atlas_position.atlas_texture_pixel_offset), dim)
+                .scaled_from_origin(Vec2::filled(1.0 / DYNAMIC_ATLAS_TEXTURE_SIZE as f32));
+        let has_translucency = bitmap
+            .data
+            .iter()
+            .any(|pixel| pixel.a != 0 && pixel.a != 255);
+
+        Sprite {
+            name: sprite_name.to_owned(),
+            atlas_texture_index: TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST
+                + atlas_position.atlas_texture_index,
+            has_translucency,
+            pivot_offset: Vec2::zero(),
+            attachment_points: [Vec2::zero(); SPRITE_ATTACHMENT_POINTS_MAX_COUNT],
+            untrimmed_dimensions: dim,
+            trimmed_rect: Rect::from_dim(dim),
+            trimmed_uvs: AAQuad::from_rect(uvs_rect),
+        }
+    }
+
+    /// Frees the region of a bitmap previously added via `dynamic_atlas_add_bitmap` so that it
+    /// can be reused. Sprites referencing the bitmap must not be drawn anymore afterwards.
+    /// Returns false if no bitmap with the given name exists in the dynamic atlas.
+    pub fn dynamic_atlas_remove_bitmap(&mut self, sprite_name: &str) -> bool {
+        if let Some(dynamic_atlas) = self.dynamic_atlas.as_mut() {
+            if let Some(atlas_position) = dynamic_atlas.free_bitmap(sprite_name) {
+                self.mark_texture_dirty(
+                    TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST + atlas_position.atlas_texture_index,
+                );
+                return true;
+            }
+        }
+        false
     }
 
     pub fn set_shaderparams_default(
@@ -610,6 +811,7 @@ impl Drawstate {
         let mut current_batch = DrawBatch {
             drawspace: drawables[0].drawparams.drawspace,
             texture_index: drawables[0].texture_index,
+            distance_field: drawables[0].distance_field,
             indices_start_offset: vertexbuffer.current_offset(),
             indices_count: 0,
             is_translucent,
@@ -618,6 +820,7 @@ impl Drawstate {
         for drawable in drawables.drain(..) {
             if drawable.texture_index != current_batch.texture_index
                 || drawable.drawparams.drawspace != current_batch.drawspace
+                || drawable.distance_field != current_batch.distance_field
             {
                 match current_batch.drawspace {
                     Drawspace::World => batches_world.push(current_batch),
@@ -627,6 +830,7 @@ impl Drawstate {
                 current_batch = DrawBatch {
                     drawspace: drawable.drawparams.drawspace,
                     texture_index: drawable.texture_index,
+                    distance_field: drawable.distance_field,
                     indices_start_offset: vertexbuffer.current_offset(),
                     indices_count: 0,
                     is_translucent,
@@ -646,21 +850,35 @@ impl Drawstate {
 
     pub fn render_frame(&mut self, renderer: &mut Renderer) {
         // Re-upload modified atlas pages
-        for atlas_page in 0..self.textures_dirty.len() {
-            if self.textures_dirty[atlas_page] {
-                let texture_name = Drawstate::texturename_for_atlaspage(
-                    self.textures_size,
-                    atlas_page as TextureIndex,
-                );
-                let atlas_page_bitmap = &self.textures[atlas_page].borrow();
-                renderer.texture_create_or_update_whole(
-                    &texture_name,
-                    atlas_page_bitmap.width as u32,
-                    atlas_page_bitmap.height as u32,
-                    &atlas_page_bitmap.as_bytes(),
-                );
-                self.textures_dirty[atlas_page] = false;
+        let texture_indices = (0..self.textures.len() as TextureIndex).chain(
+            (0..self.dynamic_atlas_page_count() as TextureIndex)
+                .map(|page| TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST + page),
+        );
+        for texture_index in texture_indices {
+            let dirty_index = self.textures_dirty_index(texture_index);
+            if !self.textures_dirty[dirty_index] {
+                continue;
             }
+
+            let texture_name =
+                Drawstate::texturename_for_atlaspage(self.textures_size, texture_index);
+            let static_page;
+            let atlas_page_bitmap: &Bitmap = if texture_index >= TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST {
+                let dynamic_atlas = self.dynamic_atlas.as_ref().unwrap();
+                &dynamic_atlas.atlas_packers
+                    [(texture_index - TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST) as usize]
+                    .atlas_texture
+            } else {
+                static_page = self.textures[texture_index as usize].borrow();
+                &static_page
+            };
+            renderer.texture_create_or_update_whole(
+                &texture_name,
+                atlas_page_bitmap.width as u32,
+                atlas_page_bitmap.height as u32,
+                &atlas_page_bitmap.as_bytes(),
+            );
+            self.textures_dirty[dirty_index] = false;
         }
 
         // NOTE: Even if we have our own offscreen framebuffer that we want to draw to, we still
@@ -695,36 +913,40 @@ impl Drawstate {
                 &transmute_slice_to_byte_slice(&self.default_vertexbuffer.vertices),
                 &transmute_slice_to_byte_slice(&self.default_vertexbuffer.indices),
             );
+
+            // NOTE: The distancefield shader uses the same vertex format as the default shader but
+            //       has its own vertex buffer
+            let contains_distance_fields = self
+                .default_batches_world
+                .iter()
+                .chain(self.default_batches_canvas.iter())
+                .chain(self.default_batches_screen.iter())
+                .any(|batch| batch.distance_field.is_some());
+            if contains_distance_fields {
+                renderer.assign_buffers(
+                    "distancefield",
+                    &transmute_slice_to_byte_slice(&self.default_vertexbuffer.vertices),
+                    &transmute_slice_to_byte_slice(&self.default_vertexbuffer.indices),
+                );
+            }
             self.default_vertexbuffer_dirty = false;
         }
 
         // Draw world- and canvas-space batches
         for world_batch in &self.default_batches_world {
-            renderer.draw(
-                "default",
-                &self.default_shaderparams_world.as_slice(),
+            self.draw_batch(
+                renderer,
+                world_batch,
+                &self.default_shaderparams_world,
                 &draw_framebuffer_name,
-                &Drawstate::texturename_for_atlaspage(
-                    self.textures_size,
-                    world_batch.texture_index,
-                ),
-                world_batch.indices_start_offset,
-                world_batch.indices_count,
-                !world_batch.is_translucent,
             );
         }
         for canvas_batch in &self.default_batches_canvas {
-            renderer.draw(
-                "default",
-                &self.default_shaderparams_canvas.as_slice(),
+            self.draw_batch(
+                renderer,
+                canvas_batch,
+                &self.default_shaderparams_canvas,
                 &draw_framebuffer_name,
-                &Drawstate::texturename_for_atlaspage(
-                    self.textures_size,
-                    canvas_batch.texture_index,
-                ),
-                canvas_batch.indices_start_offset,
-                canvas_batch.indices_count,
-                !canvas_batch.is_translucent,
             );
         }
 
@@ -755,17 +977,58 @@ impl Drawstate {
 
         // Draw screenspace batches last so they won't get overdrawn by framebuffer blits
         for screen_batch in &self.default_batches_screen {
+            self.draw_batch(
+                renderer,
+                screen_batch,
+                &self.default_shaderparams_screen,
+                "main",
+            );
+        }
+    }
+
+    fn draw_batch(
+        &self,
+        renderer: &mut Renderer,
+        batch: &DrawBatch,
+        shaderparams: &ShaderParamsDefault,
+        framebuffer_name: &str,
+    ) {
+        let texture_name =
+            Drawstate::texturename_for_atlaspage(self.textures_size, batch.texture_index);
+        if let Some(distance_field) = batch.distance_field {
+            // NOTE: Atlas pages are not required to have the same size
+            let texture_size = if batch.texture_index >= TEXTURE_INDEX_DYNAMIC_ATLAS_FIRST {
+                DYNAMIC_ATLAS_TEXTURE_SIZE
+            } else {
+                self.textures[batch.texture_index as usize].borrow().width as u32
+            };
+            let shaderparams = ShaderParamsDistanceField {
+                transform: shaderparams.transform,
+                texture_size: Vec2::filled(texture_size as f32),
+                spread: distance_field.spread,
+                outline_color: distance_field.outline_color,
+                outline_thickness: distance_field.outline_thickness,
+                glow_color: distance_field.glow_color,
+                glow_radius: distance_field.glow_radius,
+            };
+            renderer.draw(
+                "distancefield",
+                &shaderparams.as_slice(),
+                framebuffer_name,
+                &texture_name,
+                batch.indices_start_offset,
+                batch.indices_count,
+                !batch.is_translucent,
+            );
+        } else {
             renderer.draw(
                 "default",
-                &self.default_shaderparams_screen.as_slice(),
-                "main",
-                &Drawstate::texturename_for_atlaspage(
-                    self.textures_size,
-                    screen_batch.texture_index,
-                ),
-                screen_batch.indices_start_offset,
-                screen_batch.indices_count,
-                !screen_batch.is_translucent,
+                &shaderparams.as_slice(),
+                framebuffer_name,
+                &texture_name,
+                batch.indices_start_offset,
+                batch.indices_count,
+                !batch.is_translucent,
             );
         }
     }
@@ -796,15 +1059,16 @@ impl Drawstate {
                 uv_region_contains_translucency,
                 drawparams,
                 geometry: Geometry::QuadMesh { uvs, quad: *quad },
+                distance_field: None,
             });
         } else {
-            let coords_horizontal = (uvs.left + uvs.right) / 2.0;
-            let coords_vertical = (uvs.top + uvs.bottom) / 2.0;
+            let coords_center = uvs.point_at(Vec2::filled(0.5));
             let uvs = AAQuad {
-                left: coords_horizontal,
-                top: coords_vertical,
-                right: coords_horizontal,
-                bottom: coords_vertical,
+                left: coords_center.x,
+                top: coords_center.y,
+                right: coords_center.x,
+                bottom: coords_center.y,
+                rotated: false,
             };
 
             self.push_drawable(Drawable {
@@ -812,6 +1076,7 @@ impl Drawstate {
                 uv_region_contains_translucency,
                 drawparams,
                 geometry: Geometry::QuadMesh { uvs, quad: *quad },
+                distance_field: None,
             });
         };
     }
@@ -834,10 +1099,10 @@ impl Drawstate {
 
             let mut sprite_uvs = sprite.trimmed_uvs;
             if flip_horizontally {
-                std::mem::swap(&mut sprite_uvs.left, &mut sprite_uvs.right);
+                sprite_uvs = sprite_uvs.mirrored_horizontally();
             }
             if flip_vertically {
-                std::mem::swap(&mut sprite_uvs.top, &mut sprite_uvs.bottom);
+                sprite_uvs = sprite_uvs.mirrored_vertically();
             }
 
             (
@@ -903,21 +1168,13 @@ impl Drawstate {
                 let sprite_width = rect.width();
                 let sprite_height = rect.height();
 
-                let relative_offset_left = (intersection.left() - rect.left()) / sprite_width;
-                let relative_offset_right = (intersection.right() - rect.right()) / sprite_width;
-                let relative_offset_top = (intersection.top() - rect.top()) / sprite_height;
-                let relative_offset_bottom =
-                    (intersection.bottom() - rect.bottom()) / sprite_height;
-
-                let uvs_width = uvs.right - uvs.left;
-                let uvs_height = uvs.bottom - uvs.top;
-
-                let intersection_uvs = AAQuad {
-                    left: uvs.left + relative_offset_left * uvs_width,
-                    top: uvs.top + relative_offset_top * uvs_height,
-                    right: uvs.right + relative_offset_right * uvs_width,
-                    bottom: uvs.bottom + relative_offset_bottom * uvs_height,
-                };
+                let relative_rect = Rect::from_bounds_left_top_right_bottom(
+                    (intersection.left() - rect.left()) / sprite_width,
+                    (intersection.top() - rect.top()) / sprite_height,
+                    (intersection.right() - rect.left()) / sprite_width,
+                    (intersection.bottom() - rect.top()) / sprite_height,
+                );
+                let intersection_uvs = uvs.subquad(relative_rect);
 
                 let quad = Quad::from_rect(intersection);
                 self.draw_quad(
@@ -1055,6 +1312,7 @@ impl Drawstate {
                 uvs,
                 indices,
             },
+            distance_field: None,
         });
     }
 
@@ -1114,6 +1372,7 @@ impl Drawstate {
                 uvs,
                 indices,
             },
+            distance_field: None,
         });
     }
 
@@ -1438,6 +1697,116 @@ impl Drawstate {
                 ..drawparams
             },
             geometry: Geometry::LineMesh { vertices, indices },
+            distance_field: None,
+        });
+    }
+
+    /// Draws a continuous triangle strip along the given points where each point has its own
+    /// thickness and color. If a sprite is given its texture is stretched along the whole strip,
+    /// otherwise the strip is drawn untextured.
+    /// NOTE: `points`, `thicknesses` and `colors` must have the same length
+    #[inline]
+    pub fn draw_ribbon(
+        &mut self,
+        points: &[Vec2],
+        thicknesses: &[f32],
+        colors: &[Color],
+        sprite: Option<&Sprite>,
+        drawparams: Drawparams,
+    ) {
+        assert!(points.len() == thicknesses.len() && points.len() == colors.len());
+        if points.len() < 2 {
+            return;
+        }
+
+        let (uvs, texture_index) = if let Some(sprite) = sprite {
+            (sprite.trimmed_uvs, sprite.atlas_texture_index)
+        } else {
+            (
+                self.untextured_uv_center_coord,
+                self.untextured_uv_center_atlas_page,
+            )
+        };
+
+        let length_total: f32 = points
+            .windows(2)
+            .map(|segment| Vec2::distance(segment[0], segment[1]))
+            .sum();
+
+        let depth = drawparams.depth;
+        let additivity = drawparams.additivity;
+        let mut vertices = Vec::with_capacity(2 * points.len());
+        let mut indices = Vec::with_capacity(6 * (points.len() - 1));
+
+        let mut length_so_far = 0.0;
+        let mut perp_previous = Vec2::unit_y();
+        for index in 0..points.len() {
+            let point = points[index];
+            let point_previous = points[index.saturating_sub(1)];
+            let point_next = points[usize::min(index + 1, points.len() - 1)];
+
+            // NOTE: We use the direction between the previous and the next point so that the
+            //       strip bends smoothly around corners
+            let perp = (point_next - point_previous)
+                .perpendicular()
+                .normalized_or(perp_previous);
+            perp_previous = perp;
+
+            length_so_far += Vec2::distance(point_previous, point);
+            let percent = if length_total > 0.0 {
+                length_so_far / length_total
+            } else {
+                0.0
+            };
+            let uv_left = uvs.point_at(Vec2::new(percent, 0.0));
+            let uv_right = uvs.point_at(Vec2::new(percent, 1.0));
+            let color = colors[index] * drawparams.color_modulate;
+            let half_thickness = 0.5 * thicknesses[index];
+
+            // left
+            vertices.push(VertexDefault {
+                pos: Vec3::from_vec2(point + half_thickness * perp, depth),
+                uv: uv_left,
+                color,
+                additivity,
+            });
+            // right
+            vertices.push(VertexDefault {
+                pos: Vec3::from_vec2(point - half_thickness * perp, depth),
+                uv: uv_right,
+                color,
+                additivity,
+            });
+
+            if index > 0 {
+                let left_previous = 2 * (index as VertexIndex - 1);
+                let right_previous = left_previous + 1;
+                let left = left_previous + 2;
+                let right = left_previous + 3;
+
+                // first triangle
+                indices.push(left_previous);
+                indices.push(right_previous);
+                indices.push(right);
+
+                // second triangle
+                indices.push(right);
+                indices.push(left);
+                indices.push(left_previous);
+            }
+        }
+
+        self.push_drawable(Drawable {
+            texture_index,
+            uv_region_contains_translucency: true,
+            drawparams: Drawparams {
+                // NOTE: We already set the vertex colors above, we don't need to modulate them
+                //       anymore, so we set it to white
+                color_modulate: Color::white(),
+                ..drawparams
+            },
+            geometry: Geometry::LineMesh { vertices, indices },
+            distance_field: None,
         });
     }
 
@@ -1520,6 +1889,71 @@ impl Drawstate {
         }
     }
 
+    /// Draws a given utf8 text with a font that was baked as a multi channel signed distance field.
+    /// Contrary to `draw_text` the text stays crisp at any (also fractional) `font_scale` and can
+    /// be drawn with outlines and glows.
+    /// Returns the starting_offset for the next `draw_text_distance_field`
+    #[inline]
+    pub fn draw_text_distance_field(
+        &mut self,
+        text: &str,
+        font: &SpriteFont,
+        font_scale: f32,
+        starting_origin: Vec2,
+        starting_offset: Vec2,
+        alignment: Option<TextAlignment>,
+        effects: DistanceFieldEffects,
+        drawparams: Drawparams,
+    ) -> Vec2 {
+        let spread = font.distance_field_spread.unwrap_or_else(|| {
+            panic!(
+                "Font '{}' was not baked as a distance field font",
+                font.name
+            )
+        });
+        let distance_field = DistanceFieldParams {
+            spread,
+            outline_color: effects.outline_color,
+            outline_thickness: f32::min(effects.outline_thickness / font_scale, spread),
+            glow_color: effects.glow_color,
+            glow_radius: f32::min(effects.glow_radius / font_scale, spread),
+        };
+
+        // NOTE: We lay out the text in the unscaled space of the font and scale the resulting
+        //       glyph positions ourselves because the font only supports integer scales
+        let offset = (starting_offset / font_scale).roundi();
+        let next_offset = font.iter_text_glyphs_aligned_in_point(
+            text,
+            1,
+            Vec2i::zero(),
+            offset,
+            alignment,
+            &mut |glyph, draw_pos, _codepoint| {
+                let sprite = &glyph.sprite;
+                let quad = Quad::from_rect_transformed(
+                    sprite.trimmed_rect.dim,
+                    sprite.pivot_offset - sprite.trimmed_rect.pos,
+                    Transform::from_pos_scale_uniform(
+                        starting_origin + font_scale * Vec2::from(draw_pos),
+                        font_scale,
+                    ),
+                );
+                self.push_drawable(Drawable {
+                    texture_index: sprite.atlas_texture_index,
+                    uv_region_contains_translucency: true,
+                    drawparams,
+                    geometry: Geometry::QuadMesh {
+                        uvs: sprite.trimmed_uvs,
+                        quad,
+                    },
+                    distance_field: Some(distance_field),
+                });
+            },
+        );
+
+        font_scale * Vec2::from(next_offset)
+    }
+
     /// Draws a given utf8 text in a given font using a clipping rectangle
     /// NOTE: This does not do any word wrapping - the given text should be already pre-wrapped
     ///       for a good result
@@ -1657,6 +2091,7 @@ impl Drawstate {
                 uvs,
                 indices,
             },
+            distance_field: None,
         });
     }
 }
@@ -23,6 +23,9 @@ pub struct AAQuad {
     pub top: f32,
     pub right: f32,
     pub bottom: f32,
+    /// The region contains its image rotated by 90 degrees clockwise. This happens when an atlas
+    /// packer rotates images to make them fit better.
+    pub rotated: bool,
 }
 
 impl AAQuad {
@@ -37,8 +40,89 @@ impl AAQuad {
             top: rect.top(),
             right: rect.right(),
             bottom: rect.bottom(),
+            rotated: false,
         }
     }
+
+    /// Returns the coordinates of the left-top, right-top, right-bottom and left-bottom corners of
+    /// the image contained in this quad
+    #[inline]
+    pub fn corners(self) -> [Vec2; 4] {
+        if self.rotated {
+            [
+                Vec2::new(self.right, self.top),
+                Vec2::new(self.right, self.bottom),
+                Vec2::new(self.left, self.bottom),
+                Vec2::new(self.left, self.top),
+            ]
+        } else {
+            [
+                Vec2::new(self.left, self.top),
+                Vec2::new(self.right, self.top),
+                Vec2::new(self.right, self.bottom),
+                Vec2::new(self.left, self.bottom),
+            ]
+        }
+    }
+
+    /// Returns the coordinate of the given point relative to the contained image where (0,0) is
+    /// its left-top and (1,1) its right-bottom corner
+    #[inline]
+    pub fn point_at(self, relative_point: Vec2) -> Vec2 {
+        let [left_top, right_top, _right_bottom, left_bottom] = self.corners();
+        left_top
+            + relative_point.x * (right_top - left_top)
+            + relative_point.y * (left_bottom - left_top)
+    }
+
+    /// Returns the part of the quad that corresponds to the given region relative to the contained
+    /// image where (0,0) is its left-top and (1,1) its right-bottom corner
+    #[inline]
+    pub fn subquad(self, relative_rect: Rect) -> AAQuad {
+        let left_top = self.point_at(Vec2::new(relative_rect.left(), relative_rect.top()));
+        let right_bottom = self.point_at(Vec2::new(relative_rect.right(), relative_rect.bottom()));
+        if self.rotated {
+            AAQuad {
+                left: right_bottom.x,
+                top: left_top.y,
+                right: left_top.x,
+                bottom: right_bottom.y,
+                rotated: true,
+            }
+        } else {
+            AAQuad {
+                left: left_top.x,
+                top: left_top.y,
+                right: right_bottom.x,
+                bottom: right_bottom.y,
+                rotated: false,
+            }
+        }
+    }
+
+    #[must_use = "This does not change the original quad"]
+    #[inline]
+    pub fn mirrored_horizontally(self) -> AAQuad {
+        let mut result = self;
+        if self.rotated {
+            std::mem::swap(&mut result.top, &mut result.bottom);
+        } else {
+            std::mem::swap(&mut result.left, &mut result.right);
+        }
+        result
+    }
+
+    #[must_use = "This does not change the original quad"]
+    #[inline]
+    pub fn mirrored_vertically(self) -> AAQuad {
+        let mut result = self;
+        if self.rotated {
+            std::mem::swap(&mut result.left, &mut result.right);
+        } else {
+            std::mem::swap(&mut result.top, &mut result.bottom);
+        }
+        result
+    }
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
@@ -144,6 +228,9 @@ pub struct SpriteFont {
     pub horizontal_advance_max: i32,
     pub is_fixed_width_font: bool,
     pub font_height_in_pixels: i32,
+    /// Is `Some` if the glyphs are multi channel signed distance fields with the given spread in
+    /// pixels. These fonts need to be drawn via `Drawstate::draw_text_distance_field`.
+    pub distance_field_spread: Option<f32>,
 
     /// Fastpath glyphs for quick access (mainly latin glyphs)
     pub ascii_glyphs: Vec<SpriteGlyph>,
@@ -3,11 +3,30 @@ use super::*;
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Animations
 
+/// Corresponds to the tag directions of Aseprite
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationPlaybackDirection {
+    Forward,
+    Reverse,
+    /// Plays forward and then backwards without repeating the first and last frame
+    PingPong,
+    /// Plays backwards and then forward without repeating the first and last frame
+    PingPongReverse,
+}
+
+impl Default for AnimationPlaybackDirection {
+    fn default() -> Self {
+        AnimationPlaybackDirection::Forward
+    }
+}
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct AnimationFrame<FrameType: Clone> {
     pub duration_seconds: f32,
     #[serde(bound(deserialize = "FrameType: serde::de::DeserializeOwned"))]
     pub value: FrameType,
+    /// Named events that are fired when this frame is entered (i.e. "footstep", "hitbox_on")
+    pub events: Vec<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -16,6 +35,7 @@ pub struct Animation<FrameType: Clone> {
     #[serde(bound(deserialize = "FrameType: serde::de::DeserializeOwned"))]
     pub frames: Vec<AnimationFrame<FrameType>>,
     pub length: f32,
+    pub playback_direction: AnimationPlaybackDirection,
 }
 
 impl<FrameType: Clone> Animation<FrameType> {
@@ -24,68 +44,147 @@ impl<FrameType: Clone> Animation<FrameType> {
             name,
             frames: Vec::with_capacity(32),
             length: 0.0,
+            playback_direction: AnimationPlaybackDirection::Forward,
         }
     }
 
     pub fn add_frame(&mut self, duration_seconds: f32, value: FrameType) {
+        self.add_frame_with_events(duration_seconds, value, Vec::new());
+    }
+
+    pub fn add_frame_with_events(
+        &mut self,
+        duration_seconds: f32,
+        value: FrameType,
+        events: Vec<String>,
+    ) {
         assert!(duration_seconds > 0.0);
 
         self.length += duration_seconds;
         self.frames.push(AnimationFrame {
             duration_seconds,
             value,
+            events,
         });
     }
 
-    fn frame_index_and_percent_at_time(&self, time: f32, wrap_around: bool) -> (usize, f32) {
+    /// Returns the order in which the frames are visited for one full playback cycle according to
+    /// the animations playback direction
+    pub fn frame_sequence(&self) -> Vec<usize> {
+        self.frame_sequence_for_playback(true)
+    }
+
+    /// Same as `frame_sequence` but if `looping` is false, ping-pong animations end on the frame
+    /// they started with
+    pub fn frame_sequence_for_playback(&self, looping: bool) -> Vec<usize> {
+        (0..self.frame_sequence_len(looping))
+            .map(|sequence_index| self.frame_sequence_entry(sequence_index, looping))
+            .collect()
+    }
+
+    /// Returns the duration of one playback cycle which can be longer than `length` for ping-pong
+    /// animations
+    pub fn playback_length(&self, looping: bool) -> f32 {
+        (0..self.frame_sequence_len(looping))
+            .map(|sequence_index| {
+                self.frames[self.frame_sequence_entry(sequence_index, looping)].duration_seconds
+            })
+            .sum()
+    }
+
+    fn frame_sequence_len(&self, looping: bool) -> usize {
+        let framecount = self.frames.len();
+        match self.playback_direction {
+            AnimationPlaybackDirection::Forward | AnimationPlaybackDirection::Reverse => framecount,
+            AnimationPlaybackDirection::PingPong | AnimationPlaybackDirection::PingPongReverse => {
+                let cycle_len = if framecount > 2 {
+                    2 * framecount - 2
+                } else {
+                    framecount
+                };
+                // NOTE: Without looping we append the first frame so that we return to it
+                if looping || framecount < 2 {
+                    cycle_len
+                } else {
+                    cycle_len + 1
+                }
+            }
+        }
+    }
+
+    /// Returns the frame index at the given position of the frame sequence
+    fn frame_sequence_entry(&self, sequence_index: usize, looping: bool) -> usize {
+        debug_assert!(sequence_index < self.frame_sequence_len(looping));
+        let index_last = self.frames.len() - 1;
+        // NOTE: For ping-pong this yields 0 for the appended first frame of non-looping playback
+        let ping_pong = if sequence_index <= index_last {
+            sequence_index
+        } else {
+            2 * index_last - sequence_index
+        };
+        match self.playback_direction {
+            AnimationPlaybackDirection::Forward => sequence_index,
+            AnimationPlaybackDirection::Reverse => index_last - sequence_index,
+            AnimationPlaybackDirection::PingPong => ping_pong,
+            AnimationPlaybackDirection::PingPongReverse => index_last - ping_pong,
+        }
+    }
+
+    /// Returns the position in the frame sequence at the given time and how far we progressed
+    /// into its frame
+    fn sequence_index_and_percent_at_time(&self, time: f32, wrap_around: bool) -> (usize, f32) {
         assert!(!self.frames.is_empty());
 
+        let playback_length = self.playback_length(wrap_around);
         let time = if wrap_around {
-            wrap_value_in_range(time, self.length)
+            wrap_value_in_range(time, playback_length)
         } else {
-            f32::clamp(time, 0.0, self.length)
+            f32::clamp(time, 0.0, playback_length)
         };
 
+        let sequence_len = self.frame_sequence_len(wrap_around);
         let mut frame_start = 0.0;
-
-        for (index, frame) in self.frames.iter().enumerate() {
+        for sequence_index in 0..sequence_len {
+            let frame = &self.frames[self.frame_sequence_entry(sequence_index, wrap_around)];
             let frame_end = frame_start + frame.duration_seconds;
 
             if time < frame_end {
                 let percent = (time - frame_start) / frame.duration_seconds;
-                return (index, percent);
+                return (sequence_index, percent);
             }
 
             frame_start = frame_end;
         }
 
-        (self.frames.len() - 1, 1.0)
+        (sequence_len - 1, 1.0)
     }
 
     pub fn frame_at_time(&self, time: f32, wrap_around: bool) -> &FrameType {
-        let (index, _percent) = self.frame_index_and_percent_at_time(time, wrap_around);
-        &self.frames[index].value
+        let (sequence_index, _percent) = self.sequence_index_and_percent_at_time(time, wrap_around);
+        &self.frames[self.frame_sequence_entry(sequence_index, wrap_around)].value
     }
 
     pub fn frame_at_percentage(&self, percentage: f32) -> &FrameType {
         debug_assert!(0.0 <= percentage && percentage <= 1.0);
-        let time = percentage * self.length;
+        let time = percentage * self.playback_length(false);
         self.frame_at_time(time, false)
     }
 }
 
 impl Animation<f32> {
     pub fn value_at_time_interpolated_linear(&self, time: f32, wrap_around: bool) -> f32 {
-        let (frame_index, frametime_percent) =
-            self.frame_index_and_percent_at_time(time, wrap_around);
-        let next_frame_index = if wrap_around {
-            (frame_index + 1) % self.frames.len()
+        let (sequence_index, frametime_percent) =
+            self.sequence_index_and_percent_at_time(time, wrap_around);
+        let sequence_len = self.frame_sequence_len(wrap_around);
+        let next_sequence_index = if wrap_around {
+            (sequence_index + 1) % sequence_len
         } else {
-            usize::min(frame_index + 1, self.frames.len() - 1)
+            usize::min(sequence_index + 1, sequence_len - 1)
         };
 
-        let value_start = self.frames[frame_index].value;
-        let value_end = self.frames[next_frame_index].value;
+        let value_start = self.frames[self.frame_sequence_entry(sequence_index, wrap_around)].value;
+        let value_end =
+            self.frames[self.frame_sequence_entry(next_sequence_index, wrap_around)].value;
 
         lerp(value_start, value_end, frametime_percent)
     }
@@ -133,7 +232,7 @@ impl<FrameType: Clone> AnimationPlayer<FrameType> {
     }
 
     pub fn restart_from_end(&mut self) {
-        self.current_frametime = self.animation.length;
+        self.current_frametime = self.animation.playback_length(self.looping);
         self.has_finished = false;
     }
 
@@ -142,12 +241,13 @@ impl<FrameType: Clone> AnimationPlayer<FrameType> {
             return;
         }
 
+        let playback_length = self.animation.playback_length(self.looping);
         let new_frametime = self.current_frametime + self.playback_speed * deltatime;
         if self.looping {
-            self.current_frametime = wrap_value_in_range(new_frametime, self.animation.length);
+            self.current_frametime = wrap_value_in_range(new_frametime, playback_length);
         } else {
-            self.current_frametime = f32::clamp(new_frametime, 0.0, self.animation.length);
-            if self.current_frametime == self.animation.length && self.playback_speed > 0.0 {
+            self.current_frametime = f32::clamp(new_frametime, 0.0, playback_length);
+            if self.current_frametime == playback_length && self.playback_speed > 0.0 {
                 self.has_finished = true;
             }
             if self.current_frametime == 0.0 && self.playback_speed < 0.0 {
@@ -445,3 +545,244 @@ impl Afterimage {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Trail
+
+/// Records the history of a moving point and draws it as a continuous ribbon that tapers off and
+/// fades out over time. Useful for sword swipes, projectiles and motion streaks.
+#[derive(Clone)]
+pub struct Trail {
+    pub count_max: usize,
+    pub segment_length_min: f32,
+
+    pub lifetime: f32,
+    pub thickness_start: f32,
+    pub thickness_end: f32,
+    pub color_modulate_start: Color,
+    pub color_modulate_end: Color,
+    pub sprite: Option<Sprite>,
+
+    /// Ordered from oldest to newest
+    pos: Vec<Vec2>,
+    age: Vec<f32>,
+
+    points_scratchbuffer: Vec<Vec2>,
+    thicknesses_scratchbuffer: Vec<f32>,
+    colors_scratchbuffer: Vec<Color>,
+}
+
+impl Trail {
+    pub fn new(
+        lifetime: f32,
+        thickness_start: f32,
+        thickness_end: f32,
+        color_modulate_start: Color,
+        color_modulate_end: Color,
+        segment_length_min: f32,
+        count_max: usize,
+    ) -> Trail {
+        assert!(lifetime > 0.0, "Trail lifetime must be positive");
+        Trail {
+            count_max,
+            segment_length_min,
+
+            lifetime,
+            thickness_start,
+            thickness_end,
+            color_modulate_start,
+            color_modulate_end,
+            sprite: None,
+
+            pos: Vec::with_capacity(count_max),
+            age: Vec::with_capacity(count_max),
+
+            points_scratchbuffer: Vec::with_capacity(count_max + 1),
+            thicknesses_scratchbuffer: Vec::with_capacity(count_max + 1),
+            colors_scratchbuffer: Vec::with_capacity(count_max + 1),
+        }
+    }
+
+    /// Uses the given sprite as texture for the ribbon. Its texture is stretched along the whole
+    /// length of the trail.
+    pub fn with_sprite(mut self, sprite: Sprite) -> Trail {
+        self.sprite = Some(sprite);
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.pos.clear();
+        self.age.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos.is_empty()
+    }
+
+    pub fn update_and_draw(
+        &mut self,
+        deltatime: f32,
+        head_pos: Vec2,
+        draw_depth: f32,
+        additivity: f32,
+        drawspace: Drawspace,
+    ) {
+        self.update(deltatime, head_pos);
+        self.draw(head_pos, draw_depth, additivity, drawspace);
+    }
+
+    /// Ages all recorded points and records the new head position if it moved far enough
+    pub fn update(&mut self, deltatime: f32, head_pos: Vec2) {
+        for age in self.age.iter_mut() {
+            *age += deltatime;
+        }
+
+        // NOTE: Our points are ordered by age so we only need to remove from the front
+        let expired_count = self
+            .age
+            .iter()
+            .take_while(|&&age| age > self.lifetime)
+            .count();
+        self.pos.drain(0..expired_count);
+        self.age.drain(0..expired_count);
+
+        let needs_new_point = match self.pos.last() {
+            Some(&last_pos) => Vec2::distance(last_pos, head_pos) >= self.segment_length_min,
+            None => true,
+        };
+        if needs_new_point && self.count_max > 0 {
+            if self.pos.len() >= self.count_max {
+                self.pos.remove(0);
+                self.age.remove(0);
+            }
+            self.pos.push(head_pos);
+            self.age.push(0.0);
+        }
+    }
+
+    /// Draws the trail from its oldest recorded point up to the given current head position
+    pub fn draw(&mut self, head_pos: Vec2, draw_depth: f32, additivity: f32, drawspace: Drawspace) {
+        self.points_scratchbuffer.clear();
+        self.thicknesses_scratchbuffer.clear();
+        self.colors_scratchbuffer.clear();
+
+        for (&pos, &age) in self.pos.iter().zip(self.age.iter()) {
+            // NOTE: `lifetime` is public and could have been set to zero after construction which
+            //       would give us NaNs here, so we treat such points as fully aged
+            let age_percentage = if self.lifetime > 0.0 {
+                f32::clamp(age / self.lifetime, 0.0, 1.0)
+            } else {
+                1.0
+            };
+            self.points_scratchbuffer.push(pos);
+            self.thicknesses_scratchbuffer.push(lerp(
+                self.thickness_start,
+                self.thickness_end,
+                age_percentage,
+            ));
+            self.colors_scratchbuffer.push(Color::mix(
+                self.color_modulate_start,
+                self.color_modulate_end,
+                age_percentage,
+            ));
+        }
+
+        // NOTE: The head always follows the current position even if it did not move far enough
+        //       to be recorded yet, otherwise the ribbon would visibly lag behind
+        if self
+            .pos
+            .last()
+            .map_or(false, |&last_pos| last_pos != head_pos)
+        {
+            self.points_scratchbuffer.push(head_pos);
+            self.thicknesses_scratchbuffer.push(self.thickness_start);
+            self.colors_scratchbuffer.push(self.color_modulate_start);
+        }
+
+        draw_ribbon(
+            &self.points_scratchbuffer,
+            &self.thicknesses_scratchbuffer,
+            &self.colors_scratchbuffer,
+            self.sprite.as_ref(),
+            Drawparams::new(draw_depth, Color::white(), additivity, drawspace),
+        );
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation_with_direction(direction: AnimationPlaybackDirection) -> Animation<usize> {
+        let mut animation = Animation::new_empty("test".to_owned());
+        animation.add_frame(1.0, 0);
+        animation.add_frame(2.0, 1);
+        animation.add_frame(1.0, 2);
+        animation.playback_direction = direction;
+        animation
+    }
+
+    fn frames_at_half_seconds(animation: &Animation<usize>, looping: bool) -> Vec<usize> {
+        let playback_length = animation.playback_length(looping);
+        (0..(2.0 * playback_length) as usize)
+            .map(|step| *animation.frame_at_time(0.5 * step as f32 + 0.25, looping))
+            .collect()
+    }
+
+    #[test]
+    fn frame_at_time_follows_playback_direction() {
+        use AnimationPlaybackDirection::*;
+
+        let forward = animation_with_direction(Forward);
+        assert_eq!(forward.playback_length(true), 4.0);
+        assert_eq!(
+            frames_at_half_seconds(&forward, true),
+            [0, 0, 1, 1, 1, 1, 2, 2]
+        );
+
+        let reverse = animation_with_direction(Reverse);
+        assert_eq!(
+            frames_at_half_seconds(&reverse, true),
+            [2, 2, 1, 1, 1, 1, 0, 0]
+        );
+        assert_eq!(*reverse.frame_at_time(4.25, true), 2);
+
+        let ping_pong = animation_with_direction(PingPong);
+        assert_eq!(ping_pong.frame_sequence(), [0, 1, 2, 1]);
+        assert_eq!(ping_pong.playback_length(true), 6.0);
+        assert_eq!(
+            frames_at_half_seconds(&ping_pong, true),
+            [0, 0, 1, 1, 1, 1, 2, 2, 1, 1, 1, 1]
+        );
+
+        let ping_pong_reverse = animation_with_direction(PingPongReverse);
+        assert_eq!(ping_pong_reverse.frame_sequence(), [2, 1, 0, 1]);
+        assert_eq!(*ping_pong_reverse.frame_at_percentage(1.0), 2);
+    }
+
+    #[test]
+    fn non_looping_ping_pong_ends_on_first_frame() {
+        let animation = animation_with_direction(AnimationPlaybackDirection::PingPong);
+        assert_eq!(
+            animation.frame_sequence_for_playback(false),
+            [0, 1, 2, 1, 0]
+        );
+        assert_eq!(animation.playback_length(false), 7.0);
+
+        let mut player = AnimationPlayer::new_from_beginning(animation, 1.0, false);
+        player.update(100.0);
+        assert!(player.has_finished);
+        assert_eq!(*player.current_frame(), 0);
+
+        let mut player = AnimationPlayer::new_from_beginning(
+            animation_with_direction(AnimationPlaybackDirection::Reverse),
+            1.0,
+            false,
+        );
+        player.update(100.0);
+        assert_eq!(*player.current_frame(), 0);
+    }
+}
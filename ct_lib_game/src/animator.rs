@@ -0,0 +1,315 @@
+use super::*;
+
+use indexmap::IndexMap;
+use std::collections::HashSet;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Animator conditions and transitions
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimatorCondition {
+    BoolIs(String, bool),
+    FloatGreater(String, f32),
+    FloatLess(String, f32),
+    /// Is true if the trigger was set since the last update. Triggers are reset after each update.
+    Trigger(String),
+    /// Is true if the current non-looping state played its animation to the end
+    AnimationFinished,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimatorTransition {
+    /// If `None` this transition can be taken from any state
+    pub state_from: Option<String>,
+    pub state_to: String,
+    /// All conditions need to be true for the transition to be taken
+    pub conditions: Vec<AnimatorCondition>,
+}
+
+#[derive(Clone)]
+pub struct AnimatorState<FrameType: Clone> {
+    pub animation: Animation<FrameType>,
+    pub playback_speed: f32,
+    pub looping: bool,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Animator
+
+/// A state machine that maps named states to animations and switches between them via
+/// transitions. Plays the animations according to their `playback_direction` and collects the
+/// frame events of all frames entered during the last update.
+#[derive(Clone)]
+pub struct Animator<FrameType: Clone> {
+    states: IndexMap<String, AnimatorState<FrameType>>,
+    transitions: Vec<AnimatorTransition>,
+
+    params_bool: HashMap<String, bool>,
+    params_float: HashMap<String, f32>,
+    triggers: HashSet<String>,
+
+    current_state_name: String,
+    current_frame_sequence: Vec<usize>,
+    current_sequence_index: usize,
+    current_frametime: f32,
+    has_finished: bool,
+
+    events: Vec<String>,
+    /// Events fired outside of `update` (i.e. by `play`) that get reported on the next update
+    events_pending: Vec<String>,
+}
+
+impl<FrameType: Clone> Animator<FrameType> {
+    pub fn new(
+        initial_state_name: &str,
+        initial_animation: Animation<FrameType>,
+        playback_speed: f32,
+        looping: bool,
+    ) -> Animator<FrameType> {
+        let mut result = Animator {
+            states: IndexMap::new(),
+            transitions: Vec::new(),
+
+            params_bool: HashMap::new(),
+            params_float: HashMap::new(),
+            triggers: HashSet::new(),
+
+            current_state_name: initial_state_name.to_owned(),
+            current_frame_sequence: Vec::new(),
+            current_sequence_index: 0,
+            current_frametime: 0.0,
+            has_finished: false,
+
+            events: Vec::new(),
+            events_pending: Vec::new(),
+        };
+        result.add_state(
+            initial_state_name,
+            initial_animation,
+            playback_speed,
+            looping,
+        );
+        result.enter_state(initial_state_name);
+        let initial_events = result.current_frame_events().to_vec();
+        result.events_pending.extend(initial_events);
+        result
+    }
+
+    pub fn add_state(
+        &mut self,
+        state_name: &str,
+        animation: Animation<FrameType>,
+        playback_speed: f32,
+        looping: bool,
+    ) {
+        assert!(
+            !animation.frames.is_empty(),
+            "Animation '{}' for animator state '{}' has no frames",
+            animation.name,
+            state_name
+        );
+        assert!(
+            playback_speed >= 0.0,
+            "Animator state '{}' cannot have a negative playback speed - use a reversed animation instead",
+            state_name
+        );
+        self.states.insert(
+            state_name.to_owned(),
+            AnimatorState {
+                animation,
+                playback_speed,
+                looping,
+            },
+        );
+    }
+
+    pub fn add_transition(
+        &mut self,
+        state_from: &str,
+        state_to: &str,
+        conditions: Vec<AnimatorCondition>,
+    ) {
+        self.transitions.push(AnimatorTransition {
+            state_from: Some(state_from.to_owned()),
+            state_to: state_to.to_owned(),
+            conditions,
+        });
+    }
+
+    pub fn add_transition_from_any_state(
+        &mut self,
+        state_to: &str,
+        conditions: Vec<AnimatorCondition>,
+    ) {
+        self.transitions.push(AnimatorTransition {
+            state_from: None,
+            state_to: state_to.to_owned(),
+            conditions,
+        });
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Parameters
+
+    pub fn set_bool(&mut self, param_name: &str, value: bool) {
+        self.params_bool.insert(param_name.to_owned(), value);
+    }
+
+    pub fn set_float(&mut self, param_name: &str, value: f32) {
+        self.params_float.insert(param_name.to_owned(), value);
+    }
+
+    pub fn set_trigger(&mut self, trigger_name: &str) {
+        self.triggers.insert(trigger_name.to_owned());
+    }
+
+    pub fn get_bool(&self, param_name: &str) -> bool {
+        self.params_bool.get(param_name).cloned().unwrap_or(false)
+    }
+
+    pub fn get_float(&self, param_name: &str) -> f32 {
+        self.params_float.get(param_name).cloned().unwrap_or(0.0)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // State
+
+    pub fn current_state_name(&self) -> &str {
+        &self.current_state_name
+    }
+
+    pub fn current_state(&self) -> &AnimatorState<FrameType> {
+        &self.states[&self.current_state_name]
+    }
+
+    pub fn current_frame_index(&self) -> usize {
+        self.current_frame_sequence[self.current_sequence_index]
+    }
+
+    pub fn current_frame(&self) -> &FrameType {
+        let frame_index = self.current_frame_index();
+        &self.current_state().animation.frames[frame_index].value
+    }
+
+    pub fn has_finished(&self) -> bool {
+        self.has_finished
+    }
+
+    /// Returns all frame events that were fired during the last update
+    pub fn events(&self) -> &[String] {
+        &self.events
+    }
+
+    pub fn has_event(&self, event_name: &str) -> bool {
+        self.events.iter().any(|event| event == event_name)
+    }
+
+    /// Forces the animator into the given state regardless of any transitions. Does nothing if
+    /// we are already in that state unless `restart_if_current` is set.
+    /// NOTE: The events of the entered frame are reported after the next update
+    pub fn play(&mut self, state_name: &str, restart_if_current: bool) {
+        if state_name != self.current_state_name || restart_if_current {
+            self.enter_state(state_name);
+            let entered_events = self.current_frame_events().to_vec();
+            self.events_pending.extend(entered_events);
+        }
+    }
+
+    fn enter_state(&mut self, state_name: &str) {
+        let state = self
+            .states
+            .get(state_name)
+            .unwrap_or_else(|| panic!("Animator has no state named '{}'", state_name));
+
+        self.current_frame_sequence = state.animation.frame_sequence_for_playback(state.looping);
+        self.current_state_name = state_name.to_owned();
+        self.current_sequence_index = 0;
+        self.current_frametime = 0.0;
+        self.has_finished = false;
+    }
+
+    fn current_frame_events(&self) -> &[String] {
+        let frame_index = self.current_frame_index();
+        &self.current_state().animation.frames[frame_index].events
+    }
+
+    fn fire_events_of_current_frame(&mut self) {
+        let frame_index = self.current_frame_index();
+        let frame = &self.states[&self.current_state_name].animation.frames[frame_index];
+        self.events.extend(frame.events.iter().cloned());
+    }
+
+    fn condition_is_met(&self, condition: &AnimatorCondition) -> bool {
+        match condition {
+            AnimatorCondition::BoolIs(param_name, value) => self.get_bool(param_name) == *value,
+            AnimatorCondition::FloatGreater(param_name, value) => {
+                self.get_float(param_name) > *value
+            }
+            AnimatorCondition::FloatLess(param_name, value) => self.get_float(param_name) < *value,
+            AnimatorCondition::Trigger(trigger_name) => self.triggers.contains(trigger_name),
+            AnimatorCondition::AnimationFinished => self.has_finished,
+        }
+    }
+
+    fn find_transition_target(&self) -> Option<String> {
+        for transition in &self.transitions {
+            let matches_current_state = match &transition.state_from {
+                Some(state_from) => *state_from == self.current_state_name,
+                // NOTE: We don't want any-state transitions to restart our current state every
+                //       update
+                None => transition.state_to != self.current_state_name,
+            };
+            if matches_current_state
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| self.condition_is_met(condition))
+            {
+                return Some(transition.state_to.clone());
+            }
+        }
+        None
+    }
+
+    pub fn update(&mut self, deltatime: f32) {
+        self.events.clear();
+        self.events.extend(self.events_pending.drain(..));
+
+        // NOTE: We only take at most one transition per update to not get stuck in cycles
+        if let Some(state_to) = self.find_transition_target() {
+            self.enter_state(&state_to);
+            self.fire_events_of_current_frame();
+        }
+        self.triggers.clear();
+
+        let (playback_speed, looping) = {
+            let state = self.current_state();
+            (state.playback_speed, state.looping)
+        };
+        if self.has_finished || playback_speed == 0.0 {
+            return;
+        }
+
+        self.current_frametime += playback_speed * deltatime;
+        loop {
+            let frame_index = self.current_frame_index();
+            let frame_duration =
+                self.current_state().animation.frames[frame_index].duration_seconds;
+            if self.current_frametime < frame_duration {
+                break;
+            }
+
+            if self.current_sequence_index + 1 < self.current_frame_sequence.len() {
+                self.current_sequence_index += 1;
+            } else if looping {
+                self.current_sequence_index = 0;
+            } else {
+                self.current_frametime = frame_duration;
+                self.has_finished = true;
+                break;
+            }
+            self.current_frametime -= frame_duration;
+            self.fire_events_of_current_frame();
+        }
+    }
+}
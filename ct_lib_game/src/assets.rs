@@ -108,6 +108,7 @@ pub struct GraphicResources {
     pub sprites: IndexMap<ResourceName, Sprite>,
     pub sprites_3d: IndexMap<ResourceName, Sprite3D>,
     pub fonts: IndexMap<ResourceName, SpriteFont>,
+    pub particle_emitters: IndexMap<ResourceName, ParticleEmitterParams>,
 
     pub textures_png_data: Vec<Vec<u8>>,
 }
@@ -125,6 +126,10 @@ pub struct GameAssets {
     decoded_atlas_textures: Vec<Rc<RefCell<Bitmap>>>,
     decoded_atlas_textures_splash: Vec<Rc<RefCell<Bitmap>>>,
 
+    /// Increases every time the graphics resources are hotreloaded so that users holding copies
+    /// of sprites or other graphics resources know when to refresh them
+    graphics_generation: usize,
+
     files_loading_stage: AssetLoadingStage,
     files_loaders: HashMap<String, Fileloader>,
 
@@ -146,6 +151,7 @@ impl Clone for GameAssets {
         result.audio_resources = self.audio_resources.clone();
         result.graphic_resources = self.graphic_resources.clone();
         result.content = self.content.clone();
+        result.graphics_generation = self.graphics_generation;
 
         result
     }
@@ -168,6 +174,8 @@ impl GameAssets {
             decoded_atlas_textures: Vec::new(),
             decoded_atlas_textures_splash: Vec::new(),
 
+            graphics_generation: 0,
+
             files_loading_stage: AssetLoadingStage::default(),
             files_loaders: HashMap::new(),
 
@@ -543,6 +551,18 @@ impl GameAssets {
         }
     }
 
+    pub fn get_particle_emitter_params(&self, emitter_name: &str) -> &ParticleEmitterParams {
+        assert!(self.files_loading_stage >= AssetLoadingStage::DecodingFinish);
+        self.graphic_resources
+            .particle_emitters
+            .get(emitter_name)
+            .unwrap_or_else(|| panic!("Could not find particle emitter '{}'", emitter_name))
+    }
+
+    pub fn get_graphics_generation(&self) -> usize {
+        self.graphics_generation
+    }
+
     pub fn get_sprite_3d(&self, sprite_name: &str) -> &Sprite3D {
         assert!(self.files_loading_stage >= AssetLoadingStage::DecodingFinish);
         if let Some(result) = self.graphic_resources.sprites_3d.get(sprite_name) {
@@ -621,6 +641,7 @@ impl GameAssets {
             if LAST_WRITE_TIME_GRAPHICS != last_write_time_graphics {
                 self.graphic_resources = deserialize_from_binary_file(&path_graphics);
                 self.decode_atlas_textures();
+                self.graphics_generation += 1;
                 LAST_WRITE_TIME_GRAPHICS = last_write_time_graphics;
                 reload_happened = true;
             }
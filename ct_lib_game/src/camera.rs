@@ -367,14 +367,16 @@ impl GameCamera {
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Camera shake
 //
-// Based on https://jonny.morrill.me/en/blog/gamedev-how-to-implement-a-camera-shake-effect/
+// Inspired by https://jonny.morrill.me/en/blog/gamedev-how-to-implement-a-camera-shake-effect/
+// but uses smooth noise instead of interpolated random samples
 //
 
 #[derive(Clone)]
 pub struct ModulatorScreenShake {
     pub amplitude: f32,
+    /// Roughly the number of direction changes per second
     pub frequency: f32,
-    pub samples: Vec<Vec2>,
+    pub noise: Noise,
     pub timer: TimerSimple,
 }
 
@@ -385,32 +387,40 @@ impl ModulatorScreenShake {
         duration: f32,
         frequency: f32,
     ) -> ModulatorScreenShake {
-        let samplecount = ceili(duration * frequency) as usize;
-        let samples: Vec<Vec2> = (0..samplecount)
-            .map(|_sample_index| amplitude * random.vec2_in_unit_rect())
-            .collect();
-
         ModulatorScreenShake {
             amplitude,
             frequency,
-            samples,
+            noise: Noise::new(random),
             timer: TimerSimple::new_started(duration),
         }
     }
+}
 
-    pub fn update_and_get_value(&mut self, deltatime: f32) -> Vec2 {
-        self.timer.update(deltatime);
-        let percentage = self.timer.completion_ratio();
+impl Modulator for ModulatorScreenShake {
+    type Value = Vec2;
 
-        let last_sample_index = self.samples.len() - 1;
-        let sample_index = floori(last_sample_index as f32 * percentage) as usize;
-        let sample_index_next = std::cmp::min(last_sample_index, sample_index + 1);
+    fn update(&mut self, deltatime: f32) {
+        self.timer.update(deltatime);
+    }
 
-        let sample = self.samples[sample_index];
-        let sample_next = self.samples[sample_index_next];
+    fn value(&self) -> Vec2 {
+        let percentage = self.timer.completion_ratio();
+        let time = self.frequency * self.timer.time_cur;
+
+        // NOTE: We use two different rows of the noise for the two axes. The rows must not lie on
+        //       integer coordinates because there the noise only depends on the x-components of
+        //       the lattice gradients which would leave flat stretches without any shake. We
+        //       subtract the value at the start of each row so that the shake starts at zero
+        //       offset.
+        const ROW_X: f32 = 0.5;
+        const ROW_Y: f32 = 17.3;
+        let offset = Vec2::new(
+            self.noise.perlin_2d(time, ROW_X) - self.noise.perlin_2d(0.0, ROW_X),
+            self.noise.perlin_2d(time, ROW_Y) - self.noise.perlin_2d(0.0, ROW_Y),
+        );
 
         let decay = 1.0 - percentage;
 
-        decay * Vec2::lerp(sample, sample_next, percentage)
+        decay * self.amplitude * offset
     }
 }
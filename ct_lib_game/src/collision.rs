@@ -0,0 +1,1034 @@
+use super::*;
+
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+/// Penetration depth that is tolerated between bodies. This prevents resting or sliding bodies
+/// from getting stuck due to floating point inaccuracies.
+const COLLISION_EPSILON: f32 = 0.001;
+
+/// Maximum number of times a movement is redirected along obstacles per `move_and_slide` call
+const MOVE_AND_SLIDE_ITERATION_COUNT_MAX: usize = 4;
+
+/// Maximum number of passes to push a body out of obstacles it overlaps with
+const DEPENETRATION_ITERATION_COUNT_MAX: usize = 4;
+
+/// Contact normals with an upward (downward) component larger than this are considered floors
+/// (ceilings). This corresponds to slopes of up to 45 degrees.
+const FLOOR_NORMAL_THRESHOLD: f32 = 0.7;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Shapes
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileCollision {
+    Empty,
+    Solid,
+    /// Only blocks bodies that land on it from above
+    OneWay,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollisionTileGrid {
+    pub tile_size: f32,
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<TileCollision>,
+}
+
+impl CollisionTileGrid {
+    pub fn new(tile_size: f32, width: i32, height: i32) -> CollisionTileGrid {
+        assert!(tile_size > 0.0);
+        assert!(width > 0 && height > 0);
+        CollisionTileGrid {
+            tile_size,
+            width,
+            height,
+            tiles: vec![TileCollision::Empty; (width * height) as usize],
+        }
+    }
+
+    /// Returns `TileCollision::Empty` for coordinates outside of the grid
+    #[inline]
+    pub fn get(&self, x: i32, y: i32) -> TileCollision {
+        if 0 <= x && x < self.width && 0 <= y && y < self.height {
+            self.tiles[(x + y * self.width) as usize]
+        } else {
+            TileCollision::Empty
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: i32, y: i32, tile: TileCollision) {
+        assert!(0 <= x && x < self.width && 0 <= y && y < self.height);
+        self.tiles[(x + y * self.width) as usize] = tile;
+    }
+
+    #[inline]
+    fn tile_rect(&self, x: i32, y: i32) -> Rect {
+        Rect::from_xy_width_height(
+            x as f32 * self.tile_size,
+            y as f32 * self.tile_size,
+            self.tile_size,
+            self.tile_size,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CollisionShape {
+    /// Axis aligned rect relative to the body position
+    Rect(Rect),
+    /// Circle with its center relative to the body position
+    Circle(Circle),
+    /// Tile grid with its top-left corner at the body position.
+    /// NOTE: Tile grids can only be used for static and trigger bodies.
+    TileGrid(CollisionTileGrid),
+}
+
+impl CollisionShape {
+    pub fn bounds(&self, pos: Vec2) -> Rect {
+        match self {
+            CollisionShape::Rect(rect) => rect.translated_by(pos),
+            CollisionShape::Circle(circle) => Rect::from_bounds_left_top_right_bottom(
+                pos.x + circle.center.x - circle.radius,
+                pos.y + circle.center.y - circle.radius,
+                pos.x + circle.center.x + circle.radius,
+                pos.y + circle.center.y + circle.radius,
+            ),
+            CollisionShape::TileGrid(grid) => Rect::from_xy_width_height(
+                pos.x,
+                pos.y,
+                grid.width as f32 * grid.tile_size,
+                grid.height as f32 * grid.tile_size,
+            ),
+        }
+    }
+
+    fn primitive(&self, pos: Vec2) -> CollisionPrimitive {
+        match self {
+            CollisionShape::Rect(rect) => CollisionPrimitive::Rect(rect.translated_by(pos)),
+            CollisionShape::Circle(circle) => {
+                CollisionPrimitive::Circle(Circle::new(circle.center + pos, circle.radius))
+            }
+            CollisionShape::TileGrid(_) => panic!("Tile grids cannot be moved or casted"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CollisionPrimitive {
+    Rect(Rect),
+    Circle(Circle),
+}
+
+impl CollisionPrimitive {
+    #[inline]
+    fn center(self) -> Vec2 {
+        match self {
+            CollisionPrimitive::Rect(rect) => rect.center(),
+            CollisionPrimitive::Circle(circle) => circle.center,
+        }
+    }
+
+    #[inline]
+    fn bounds(self) -> Rect {
+        match self {
+            CollisionPrimitive::Rect(rect) => rect,
+            CollisionPrimitive::Circle(circle) => Rect::from_bounds_left_top_right_bottom(
+                circle.center.x - circle.radius,
+                circle.center.y - circle.radius,
+                circle.center.x + circle.radius,
+                circle.center.y + circle.radius,
+            ),
+        }
+    }
+
+    #[inline]
+    fn closest_point(self, point: Vec2) -> Vec2 {
+        match self {
+            CollisionPrimitive::Rect(rect) => Vec2::new(
+                f32::clamp(point.x, rect.left(), rect.right()),
+                f32::clamp(point.y, rect.top(), rect.bottom()),
+            ),
+            CollisionPrimitive::Circle(circle) => {
+                circle.center
+                    + circle.radius * (point - circle.center).normalized_or(Vec2::unit_x())
+            }
+        }
+    }
+
+    /// Returns the contact manifold with a normal pointing from `self` to `other`
+    fn collide(self, other: CollisionPrimitive) -> Option<ContactManifold> {
+        match (self, other) {
+            (CollisionPrimitive::Rect(a), CollisionPrimitive::Rect(b)) => {
+                collide_polygon_polygon(&ConvexPolygon::from_rect(a), &ConvexPolygon::from_rect(b))
+            }
+            (CollisionPrimitive::Rect(a), CollisionPrimitive::Circle(b)) => {
+                collide_polygon_circle(&ConvexPolygon::from_rect(a), b)
+            }
+            (CollisionPrimitive::Circle(a), CollisionPrimitive::Rect(b)) => {
+                collide_circle_polygon(a, &ConvexPolygon::from_rect(b))
+            }
+            (CollisionPrimitive::Circle(a), CollisionPrimitive::Circle(b)) => {
+                collide_circle_circle(a, b)
+            }
+        }
+    }
+
+    /// Returns the time of impact in [0, 1] of `self` moving by `motion` towards `obstacle` and
+    /// the outward facing normal of the obstacle at the point of impact
+    fn sweep(self, motion: Vec2, obstacle: CollisionPrimitive) -> Option<(f32, Vec2)> {
+        // NOTE: We reduce every shape pair to casting the center of the moving shape against the
+        //       Minkowski sum of both shapes which is a rect with rounded corners
+        match (self, obstacle) {
+            (CollisionPrimitive::Rect(moving), CollisionPrimitive::Rect(obstacle)) => {
+                raycast_rounded_rect(
+                    moving.center(),
+                    motion,
+                    rect_grown(obstacle, 0.5 * moving.dim),
+                    0.0,
+                )
+            }
+            (CollisionPrimitive::Rect(moving), CollisionPrimitive::Circle(obstacle)) => {
+                raycast_rounded_rect(
+                    moving.center(),
+                    motion,
+                    rect_grown(
+                        Rect::from_pos_dim(obstacle.center, Vec2::zero()),
+                        0.5 * moving.dim,
+                    ),
+                    obstacle.radius,
+                )
+            }
+            (CollisionPrimitive::Circle(moving), CollisionPrimitive::Rect(obstacle)) => {
+                raycast_rounded_rect(moving.center, motion, obstacle, moving.radius)
+            }
+            (CollisionPrimitive::Circle(moving), CollisionPrimitive::Circle(obstacle)) => {
+                raycast_rounded_rect(
+                    moving.center,
+                    motion,
+                    Rect::from_pos_dim(obstacle.center, Vec2::zero()),
+                    moving.radius + obstacle.radius,
+                )
+            }
+        }
+    }
+}
+
+#[inline]
+fn rect_grown(rect: Rect, extension: Vec2) -> Rect {
+    Rect::from_bounds_left_top_right_bottom(
+        rect.left() - extension.x,
+        rect.top() - extension.y,
+        rect.right() + extension.x,
+        rect.bottom() + extension.y,
+    )
+}
+
+/// Casts a ray from `start` to `start + motion` against a rect. Returns the entry time and the
+/// rect normal at the entry point.
+/// NOTE: Rays that start slightly inside the rect (up to `COLLISION_EPSILON`) count as hits at
+///       time zero. Rays that move exactly along one of the rect's faces are not considered hits.
+fn raycast_rect(start: Vec2, motion: Vec2, rect: Rect) -> Option<(f32, Vec2)> {
+    let mut time_enter = -f32::MAX;
+    let mut time_exit = f32::MAX;
+    let mut normal = Vec2::zero();
+
+    let slabs = [
+        (
+            start.x,
+            motion.x,
+            rect.left(),
+            rect.right(),
+            Vec2::new(-1.0, 0.0),
+        ),
+        (
+            start.y,
+            motion.y,
+            rect.top(),
+            rect.bottom(),
+            Vec2::new(0.0, -1.0),
+        ),
+    ];
+    for &(pos, dir, min, max, normal_min) in &slabs {
+        if is_effectively_zero(dir) {
+            if pos <= min + COLLISION_EPSILON || pos >= max - COLLISION_EPSILON {
+                return None;
+            }
+            continue;
+        }
+
+        let time_min = (min - pos) / dir;
+        let time_max = (max - pos) / dir;
+        let (slab_enter, slab_exit, slab_normal) = if dir > 0.0 {
+            (time_min, time_max, normal_min)
+        } else {
+            (time_max, time_min, -normal_min)
+        };
+        if slab_enter > time_enter {
+            time_enter = slab_enter;
+            normal = slab_normal;
+        }
+        time_exit = f32::min(time_exit, slab_exit);
+    }
+
+    if time_enter >= time_exit || time_enter > 1.0 || time_exit <= 0.0 {
+        return None;
+    }
+    let penetration_at_start = -time_enter * Vec2::dot(motion, normal).abs();
+    if penetration_at_start > COLLISION_EPSILON {
+        return None;
+    }
+    Some((f32::max(time_enter, 0.0), normal))
+}
+
+/// Same as `raycast_rect` but for circles
+fn raycast_circle(start: Vec2, motion: Vec2, circle: Circle) -> Option<(f32, Vec2)> {
+    let center_to_start = start - circle.center;
+    let motion_length = motion.magnitude();
+
+    // NOTE: Rays that only graze the circle must not stop movement. Otherwise bodies would get
+    //       stuck when sliding over the seam between two rounded shapes.
+    let distance_to_ray = Vec2::cross_z(center_to_start, motion).abs() / motion_length;
+    if circle.radius - distance_to_ray < COLLISION_EPSILON {
+        return None;
+    }
+
+    let a = Vec2::dot(motion, motion);
+    let b = 2.0 * Vec2::dot(center_to_start, motion);
+    let c = Vec2::dot(center_to_start, center_to_start) - circle.radius * circle.radius;
+    let discriminant = f32::sqrt(f32::max(b * b - 4.0 * a * c, 0.0));
+    let time_enter = (-b - discriminant) / (2.0 * a);
+    let time_exit = (-b + discriminant) / (2.0 * a);
+    if time_enter > 1.0 || time_exit <= 0.0 {
+        return None;
+    }
+    if time_enter < 0.0 && circle.radius - center_to_start.magnitude() > COLLISION_EPSILON {
+        return None;
+    }
+
+    let time = f32::max(time_enter, 0.0);
+    let normal = (start + time * motion - circle.center).normalized_or(-motion / motion_length);
+    if Vec2::dot(motion, normal) >= 0.0 {
+        return None;
+    }
+    Some((time, normal))
+}
+
+/// Casts a ray against a rect which corners are rounded by the given radius
+fn raycast_rounded_rect(start: Vec2, motion: Vec2, rect: Rect, radius: f32) -> Option<(f32, Vec2)> {
+    if radius <= 0.0 {
+        return raycast_rect(start, motion, rect);
+    }
+
+    // NOTE: The rounded rect is the union of two crossing rects and four corner circles
+    let hits = [
+        raycast_rect(start, motion, rect_grown(rect, Vec2::new(radius, 0.0))),
+        raycast_rect(start, motion, rect_grown(rect, Vec2::new(0.0, radius))),
+        raycast_circle(
+            start,
+            motion,
+            Circle::new(Vec2::new(rect.left(), rect.top()), radius),
+        ),
+        raycast_circle(
+            start,
+            motion,
+            Circle::new(Vec2::new(rect.right(), rect.top()), radius),
+        ),
+        raycast_circle(
+            start,
+            motion,
+            Circle::new(Vec2::new(rect.left(), rect.bottom()), radius),
+        ),
+        raycast_circle(
+            start,
+            motion,
+            Circle::new(Vec2::new(rect.right(), rect.bottom()), radius),
+        ),
+    ];
+    hits.iter()
+        .flatten()
+        .copied()
+        .min_by(|a, b| compare_floats(a.0, b.0))
+}
+
+/// A single solid piece of a body (i.e. one tile of a tile grid) that other bodies collide with
+#[derive(Debug, Clone, Copy)]
+struct CollisionObstacle {
+    primitive: CollisionPrimitive,
+    is_one_way: bool,
+    /// Faces that are covered by neighboring solid tiles in the order left, right, top, bottom
+    is_face_covered: [bool; 4],
+}
+
+impl CollisionObstacle {
+    #[inline]
+    fn new(primitive: CollisionPrimitive, is_one_way: bool) -> CollisionObstacle {
+        CollisionObstacle {
+            primitive,
+            is_one_way,
+            is_face_covered: [false; 4],
+        }
+    }
+
+    /// Checks whether a body hitting the obstacle with the given normal should be stopped
+    fn blocks_hit_with_normal(&self, normal: Vec2) -> bool {
+        if self.is_one_way && normal.y > -FLOOR_NORMAL_THRESHOLD {
+            return false;
+        }
+
+        // NOTE: Faces between two neighboring tiles are never hit from the outside. Ignoring them
+        //       prevents bodies from snagging on tile seams when sliding along floors or walls.
+        let face_index = if normal.x <= -FLOOR_NORMAL_THRESHOLD {
+            0
+        } else if normal.x >= FLOOR_NORMAL_THRESHOLD {
+            1
+        } else if normal.y <= -FLOOR_NORMAL_THRESHOLD {
+            2
+        } else if normal.y >= FLOOR_NORMAL_THRESHOLD {
+            3
+        } else {
+            return true;
+        };
+        !self.is_face_covered[face_index]
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Bodies
+
+pub type CollisionBodyId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionBodyKind {
+    /// Never moves by itself and blocks dynamic bodies
+    Static,
+    /// Moved via `CollisionWorld::move_and_slide` and blocks other dynamic bodies
+    Dynamic,
+    /// Does not block anything but reports dynamic bodies entering and exiting it
+    Trigger,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollisionBody {
+    pub kind: CollisionBodyKind,
+    pub shape: CollisionShape,
+    pub pos: Vec2,
+    /// Only blocks bodies that land on its top face from above
+    pub is_one_way: bool,
+}
+
+impl CollisionBody {
+    pub fn new(kind: CollisionBodyKind, shape: CollisionShape, pos: Vec2) -> CollisionBody {
+        if let CollisionShape::TileGrid(_) = shape {
+            assert!(
+                kind != CollisionBodyKind::Dynamic,
+                "Tile grids cannot be used for dynamic bodies"
+            );
+        }
+        CollisionBody {
+            kind,
+            shape,
+            pos,
+            is_one_way: false,
+        }
+    }
+
+    #[inline]
+    pub fn with_one_way(mut self, is_one_way: bool) -> CollisionBody {
+        self.is_one_way = is_one_way;
+        self
+    }
+
+    #[inline]
+    pub fn bounds(&self) -> Rect {
+        self.shape.bounds(self.pos)
+    }
+
+    /// Appends all solid parts of the body that touch the given region
+    fn obstacles_in_region(&self, region: Rect, out_obstacles: &mut Vec<CollisionObstacle>) {
+        match &self.shape {
+            CollisionShape::Rect(_) | CollisionShape::Circle(_) => out_obstacles.push(
+                CollisionObstacle::new(self.shape.primitive(self.pos), self.is_one_way),
+            ),
+            CollisionShape::TileGrid(grid) => {
+                let region_local = region.translated_by(-self.pos);
+                let x_min = i32::max(0, floori(region_local.left() / grid.tile_size));
+                let y_min = i32::max(0, floori(region_local.top() / grid.tile_size));
+                let x_max = i32::min(
+                    grid.width - 1,
+                    floori(region_local.right() / grid.tile_size),
+                );
+                let y_max = i32::min(
+                    grid.height - 1,
+                    floori(region_local.bottom() / grid.tile_size),
+                );
+                for y in y_min..=y_max {
+                    for x in x_min..=x_max {
+                        let tile = grid.get(x, y);
+                        if tile == TileCollision::Empty {
+                            continue;
+                        }
+                        let is_covered = |x, y| grid.get(x, y) == TileCollision::Solid;
+                        out_obstacles.push(CollisionObstacle {
+                            primitive: CollisionPrimitive::Rect(
+                                grid.tile_rect(x, y).translated_by(self.pos),
+                            ),
+                            is_one_way: self.is_one_way || tile == TileCollision::OneWay,
+                            is_face_covered: [
+                                is_covered(x - 1, y),
+                                is_covered(x + 1, y),
+                                is_covered(x, y - 1),
+                                is_covered(x, y + 1),
+                            ],
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Queries and events
+
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionHit {
+    pub body: CollisionBodyId,
+    /// Point on the surface of the hit body
+    pub point: Vec2,
+    /// Surface normal of the hit body pointing towards the moving shape
+    pub normal: Vec2,
+    /// Fraction in [0, 1] of the casted motion at which the hit happened
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MoveAndSlideResult {
+    pub pos: Vec2,
+    pub collisions: Vec<CollisionHit>,
+    pub is_on_floor: bool,
+    pub is_on_ceiling: bool,
+    pub is_on_wall: bool,
+}
+
+impl MoveAndSlideResult {
+    /// Removes the parts of the velocity that point into the surfaces the body collided with.
+    /// Useful to i.e. reset the vertical velocity of a jumping character when hitting the ceiling.
+    pub fn velocity_slid(&self, vel: Vec2) -> Vec2 {
+        self.collisions.iter().fold(vel, |vel, collision| {
+            let into_surface = Vec2::dot(vel, collision.normal);
+            if into_surface < 0.0 {
+                vel - into_surface * collision.normal
+            } else {
+                vel
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEventKind {
+    Enter,
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerEvent {
+    pub kind: TriggerEventKind,
+    pub trigger: CollisionBodyId,
+    pub body: CollisionBodyId,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Collision world
+
+/// Stores bodies in a spatial hash for fast lookups. Dynamic bodies are moved via swept collision
+/// checks so that they do not tunnel through thin obstacles even at high speeds.
+///
+/// NOTE: Positions use the same y-down convention as the rest of the engine. Floors therefore have
+///       normals pointing in negative y direction.
+pub struct CollisionWorld {
+    bodies: IndexMap<CollisionBodyId, CollisionBody>,
+    next_body_id: CollisionBodyId,
+
+    broadphase_cell_size: f32,
+    broadphase_cells: HashMap<(i32, i32), Vec<CollisionBodyId>>,
+
+    /// Sorted pairs of (trigger, body) that overlapped at the last `update_triggers` call
+    trigger_overlaps: Vec<(CollisionBodyId, CollisionBodyId)>,
+}
+
+impl CollisionWorld {
+    /// The cell size should roughly match the size of typical dynamic bodies
+    pub fn new(broadphase_cell_size: f32) -> CollisionWorld {
+        assert!(broadphase_cell_size > 0.0);
+        CollisionWorld {
+            bodies: IndexMap::new(),
+            next_body_id: 0,
+            broadphase_cell_size,
+            broadphase_cells: HashMap::new(),
+            trigger_overlaps: Vec::new(),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Bodies
+
+    pub fn add_body(&mut self, body: CollisionBody) -> CollisionBodyId {
+        let id = self.next_body_id;
+        self.next_body_id += 1;
+        self.broadphase_insert(id, body.bounds());
+        self.bodies.insert(id, body);
+        id
+    }
+
+    pub fn remove_body(&mut self, id: CollisionBodyId) -> Option<CollisionBody> {
+        let body = self.bodies.shift_remove(&id)?;
+        self.broadphase_remove(id, body.bounds());
+        Some(body)
+    }
+
+    #[inline]
+    pub fn body(&self, id: CollisionBodyId) -> Option<&CollisionBody> {
+        self.bodies.get(&id)
+    }
+
+    #[inline]
+    pub fn bodies(&self) -> impl Iterator<Item = (CollisionBodyId, &CollisionBody)> {
+        self.bodies.iter().map(|(&id, body)| (id, body))
+    }
+
+    /// Teleports the body without checking for collisions
+    pub fn set_body_pos(&mut self, id: CollisionBodyId, pos: Vec2) {
+        let body = self
+            .bodies
+            .get_mut(&id)
+            .expect("Collision body does not exist");
+        let bounds_previous = body.bounds();
+        body.pos = pos;
+        let bounds_new = body.bounds();
+        self.broadphase_remove(id, bounds_previous);
+        self.broadphase_insert(id, bounds_new);
+    }
+
+    pub fn set_body_shape(&mut self, id: CollisionBodyId, shape: CollisionShape) {
+        let body = self
+            .bodies
+            .get_mut(&id)
+            .expect("Collision body does not exist");
+        let bounds_previous = body.bounds();
+        body.shape = shape;
+        let bounds_new = body.bounds();
+        self.broadphase_remove(id, bounds_previous);
+        self.broadphase_insert(id, bounds_new);
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Movement
+
+    /// Moves a dynamic body by the given motion. When hitting obstacles the remaining motion is
+    /// redirected along their surfaces. Bodies that already overlap obstacles are pushed out first.
+    pub fn move_and_slide(&mut self, id: CollisionBodyId, motion: Vec2) -> MoveAndSlideResult {
+        let body = self.bodies.get(&id).expect("Collision body does not exist");
+        assert!(
+            body.kind == CollisionBodyKind::Dynamic,
+            "Only dynamic bodies can be moved"
+        );
+        let shape = body.shape.clone();
+        let mut pos = self.depenetrated_pos(id, &shape, body.pos);
+
+        let mut result = MoveAndSlideResult::default();
+        let mut motion_remaining = motion;
+        for _ in 0..MOVE_AND_SLIDE_ITERATION_COUNT_MAX {
+            if motion_remaining.is_effectively_zero() {
+                break;
+            }
+            match self.sweep(shape.primitive(pos), motion_remaining, Some(id)) {
+                None => {
+                    pos += motion_remaining;
+                    break;
+                }
+                Some(hit) => {
+                    pos += hit.time * motion_remaining;
+                    motion_remaining = (1.0 - hit.time) * motion_remaining;
+                    motion_remaining -= Vec2::dot(motion_remaining, hit.normal) * hit.normal;
+
+                    if hit.normal.y <= -FLOOR_NORMAL_THRESHOLD {
+                        result.is_on_floor = true;
+                    } else if hit.normal.y >= FLOOR_NORMAL_THRESHOLD {
+                        result.is_on_ceiling = true;
+                    } else {
+                        result.is_on_wall = true;
+                    }
+                    result.collisions.push(hit);
+                }
+            }
+        }
+
+        self.set_body_pos(id, pos);
+        result.pos = pos;
+        result
+    }
+
+    fn depenetrated_pos(&self, id: CollisionBodyId, shape: &CollisionShape, pos: Vec2) -> Vec2 {
+        let mut pos = pos;
+        let mut obstacles = Vec::new();
+        for _ in 0..DEPENETRATION_ITERATION_COUNT_MAX {
+            let bounds = shape.bounds(pos);
+            obstacles.clear();
+            for other_id in self.bodies_in_region(bounds) {
+                let other = &self.bodies[&other_id];
+                if other_id != id && other.kind != CollisionBodyKind::Trigger {
+                    other.obstacles_in_region(bounds, &mut obstacles);
+                }
+            }
+
+            let mut was_pushed = false;
+            for obstacle in obstacles.iter().filter(|obstacle| !obstacle.is_one_way) {
+                if let Some(manifold) = shape.primitive(pos).collide(obstacle.primitive) {
+                    if manifold.depth > COLLISION_EPSILON {
+                        pos -= manifold.depth * manifold.normal;
+                        was_pushed = true;
+                    }
+                }
+            }
+            if !was_pushed {
+                break;
+            }
+        }
+        pos
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Queries
+
+    /// Returns the first non-trigger body hit by the line segment. Bodies that contain the start of
+    /// the line are ignored.
+    pub fn raycast(
+        &self,
+        line: Line,
+        ignored_body: Option<CollisionBodyId>,
+    ) -> Option<CollisionHit> {
+        let point = CollisionPrimitive::Rect(Rect::from_pos_dim(line.start, Vec2::zero()));
+        self.sweep(point, line.end - line.start, ignored_body)
+    }
+
+    /// Returns the first non-trigger body hit by the given rect or circle shape when moving it from
+    /// `pos` by `motion`
+    pub fn shapecast(
+        &self,
+        shape: &CollisionShape,
+        pos: Vec2,
+        motion: Vec2,
+        ignored_body: Option<CollisionBodyId>,
+    ) -> Option<CollisionHit> {
+        self.sweep(shape.primitive(pos), motion, ignored_body)
+    }
+
+    /// Returns all bodies that overlap the given region including triggers
+    pub fn bodies_overlapping_rect(&self, rect: Rect) -> Vec<CollisionBodyId> {
+        let region = CollisionPrimitive::Rect(rect);
+        self.bodies_in_region(rect)
+            .into_iter()
+            .filter(|&id| self.body_overlaps_primitive(id, region))
+            .collect()
+    }
+
+    fn sweep(
+        &self,
+        moving: CollisionPrimitive,
+        motion: Vec2,
+        ignored_body: Option<CollisionBodyId>,
+    ) -> Option<CollisionHit> {
+        if motion.is_effectively_zero() {
+            return None;
+        }
+
+        let bounds = moving.bounds();
+        let region = Rect::smallest_that_contains_both_rects(bounds, bounds.translated_by(motion))
+            .extended_uniformly_by(COLLISION_EPSILON);
+
+        let mut result: Option<CollisionHit> = None;
+        let mut obstacles = Vec::new();
+        for id in self.bodies_in_region(region) {
+            let body = &self.bodies[&id];
+            if Some(id) == ignored_body || body.kind == CollisionBodyKind::Trigger {
+                continue;
+            }
+
+            obstacles.clear();
+            body.obstacles_in_region(region, &mut obstacles);
+            for obstacle in &obstacles {
+                if let Some((time, normal)) = moving.sweep(motion, obstacle.primitive) {
+                    let is_earlier = match result {
+                        Some(hit) => time < hit.time,
+                        None => true,
+                    };
+                    if is_earlier && obstacle.blocks_hit_with_normal(normal) {
+                        let center_at_impact = moving.center() + time * motion;
+                        result = Some(CollisionHit {
+                            body: id,
+                            point: obstacle.primitive.closest_point(center_at_impact),
+                            normal,
+                            time,
+                        });
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn body_overlaps_primitive(&self, id: CollisionBodyId, primitive: CollisionPrimitive) -> bool {
+        let body = &self.bodies[&id];
+        let mut obstacles = Vec::new();
+        body.obstacles_in_region(primitive.bounds(), &mut obstacles);
+        obstacles
+            .iter()
+            .any(|obstacle| primitive.collide(obstacle.primitive).is_some())
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Triggers
+
+    /// Checks which dynamic bodies entered or exited trigger bodies since the last call. This is
+    /// meant to be called once per frame after all bodies were moved.
+    pub fn update_triggers(&mut self) -> Vec<TriggerEvent> {
+        let mut overlaps = Vec::new();
+        for (&trigger_id, trigger) in self.bodies.iter() {
+            if trigger.kind != CollisionBodyKind::Trigger {
+                continue;
+            }
+            for body_id in self.bodies_in_region(trigger.bounds()) {
+                let body = &self.bodies[&body_id];
+                if body.kind == CollisionBodyKind::Dynamic
+                    && self.body_overlaps_primitive(trigger_id, body.shape.primitive(body.pos))
+                {
+                    overlaps.push((trigger_id, body_id));
+                }
+            }
+        }
+        overlaps.sort_unstable();
+
+        let mut events = Vec::new();
+        for &(trigger, body) in &self.trigger_overlaps {
+            if overlaps.binary_search(&(trigger, body)).is_err() {
+                events.push(TriggerEvent {
+                    kind: TriggerEventKind::Exit,
+                    trigger,
+                    body,
+                });
+            }
+        }
+        for &(trigger, body) in &overlaps {
+            if self
+                .trigger_overlaps
+                .binary_search(&(trigger, body))
+                .is_err()
+            {
+                events.push(TriggerEvent {
+                    kind: TriggerEventKind::Enter,
+                    trigger,
+                    body,
+                });
+            }
+        }
+
+        self.trigger_overlaps = overlaps;
+        events
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Broadphase
+
+    #[inline]
+    fn broadphase_cell_range(&self, bounds: Rect) -> (i32, i32, i32, i32) {
+        (
+            floori(bounds.left() / self.broadphase_cell_size),
+            floori(bounds.top() / self.broadphase_cell_size),
+            floori(bounds.right() / self.broadphase_cell_size),
+            floori(bounds.bottom() / self.broadphase_cell_size),
+        )
+    }
+
+    fn broadphase_insert(&mut self, id: CollisionBodyId, bounds: Rect) {
+        let (x_min, y_min, x_max, y_max) = self.broadphase_cell_range(bounds);
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                self.broadphase_cells.entry((x, y)).or_default().push(id);
+            }
+        }
+    }
+
+    fn broadphase_remove(&mut self, id: CollisionBodyId, bounds: Rect) {
+        let (x_min, y_min, x_max, y_max) = self.broadphase_cell_range(bounds);
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                if let Some(cell) = self.broadphase_cells.get_mut(&(x, y)) {
+                    cell.retain(|&other| other != id);
+                    if cell.is_empty() {
+                        self.broadphase_cells.remove(&(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the ids of all bodies which broadphase cells touch the given region in ascending
+    /// order
+    fn bodies_in_region(&self, region: Rect) -> Vec<CollisionBodyId> {
+        let (x_min, y_min, x_max, y_max) = self.broadphase_cell_range(region);
+        let mut result = Vec::new();
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                if let Some(cell) = self.broadphase_cells.get(&(x, y)) {
+                    result.extend_from_slice(cell);
+                }
+            }
+        }
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE_SIZE: f32 = 16.0;
+    const FLOOR_TOP: f32 = 9.0 * TILE_SIZE;
+    const PLAYER_HALF_HEIGHT: f32 = 8.0;
+
+    /// Creates a world with a 20x10 tile grid whose bottom row is a one-tile-thick solid floor
+    fn world_with_floor() -> CollisionWorld {
+        let mut grid = CollisionTileGrid::new(TILE_SIZE, 20, 10);
+        for x in 0..20 {
+            grid.set(x, 9, TileCollision::Solid);
+        }
+        let mut world = CollisionWorld::new(64.0);
+        world.add_body(CollisionBody::new(
+            CollisionBodyKind::Static,
+            CollisionShape::TileGrid(grid),
+            Vec2::zero(),
+        ));
+        world
+    }
+
+    fn add_player(world: &mut CollisionWorld, pos: Vec2) -> CollisionBodyId {
+        world.add_body(CollisionBody::new(
+            CollisionBodyKind::Dynamic,
+            CollisionShape::Rect(Rect::from_xy_width_height(
+                -4.0,
+                -PLAYER_HALF_HEIGHT,
+                8.0,
+                2.0 * PLAYER_HALF_HEIGHT,
+            )),
+            pos,
+        ))
+    }
+
+    #[test]
+    fn fast_body_lands_on_thin_tile_floor() {
+        let mut world = world_with_floor();
+        let player = add_player(&mut world, Vec2::new(40.0, 100.0));
+
+        let result = world.move_and_slide(player, Vec2::new(0.0, 500.0));
+        assert!(result.is_on_floor);
+        assert!(!result.is_on_wall);
+        assert!((result.pos.y - (FLOOR_TOP - PLAYER_HALF_HEIGHT)).abs() < 0.01);
+        assert_eq!(world.body(player).unwrap().pos, result.pos);
+    }
+
+    #[test]
+    fn walking_along_tiles_does_not_snag_on_seams() {
+        let mut world = world_with_floor();
+        let player = add_player(&mut world, Vec2::new(40.0, FLOOR_TOP - PLAYER_HALF_HEIGHT));
+
+        for _ in 0..50 {
+            let result = world.move_and_slide(player, Vec2::new(3.0, 1.0));
+            assert!(result.is_on_floor);
+            assert!(!result.is_on_wall);
+        }
+        let pos = world.body(player).unwrap().pos;
+        assert!((pos.x - 190.0).abs() < 0.01);
+        assert!((pos.y - (FLOOR_TOP - PLAYER_HALF_HEIGHT)).abs() < 0.01);
+    }
+
+    #[test]
+    fn one_way_platform_only_blocks_from_above() {
+        let mut world = CollisionWorld::new(64.0);
+        world.add_body(
+            CollisionBody::new(
+                CollisionBodyKind::Static,
+                CollisionShape::Rect(Rect::from_xy_width_height(0.0, 0.0, 32.0, 8.0)),
+                Vec2::new(80.0, 96.0),
+            )
+            .with_one_way(true),
+        );
+        let player = add_player(&mut world, Vec2::new(90.0, 136.0));
+
+        let result = world.move_and_slide(player, Vec2::new(0.0, -60.0));
+        assert!(result.collisions.is_empty());
+        assert!((result.pos.y - 76.0).abs() < 0.01);
+
+        let result = world.move_and_slide(player, Vec2::new(0.0, 30.0));
+        assert!(result.is_on_floor);
+        assert!((result.pos.y - (96.0 - PLAYER_HALF_HEIGHT)).abs() < 0.01);
+    }
+
+    #[test]
+    fn trigger_reports_enter_and_exit_once() {
+        let mut world = CollisionWorld::new(64.0);
+        let trigger = world.add_body(CollisionBody::new(
+            CollisionBodyKind::Trigger,
+            CollisionShape::Rect(Rect::from_xy_width_height(0.0, 0.0, 20.0, 20.0)),
+            Vec2::new(20.0, 120.0),
+        ));
+        let player = add_player(&mut world, Vec2::new(0.0, 0.0));
+        assert!(world.update_triggers().is_empty());
+
+        world.set_body_pos(player, Vec2::new(30.0, 130.0));
+        let events = world.update_triggers();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, TriggerEventKind::Enter);
+        assert_eq!(events[0].trigger, trigger);
+        assert_eq!(events[0].body, player);
+
+        world.set_body_pos(player, Vec2::new(32.0, 128.0));
+        assert!(world.update_triggers().is_empty());
+
+        world.set_body_pos(player, Vec2::new(100.0, 0.0));
+        let events = world.update_triggers();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, TriggerEventKind::Exit);
+        assert!(world.update_triggers().is_empty());
+    }
+
+    #[test]
+    fn raycast_hits_floor() {
+        let world = world_with_floor();
+
+        let hit = world
+            .raycast(
+                Line::new(Vec2::new(10.0, 10.0), Vec2::new(10.0, 500.0)),
+                None,
+            )
+            .unwrap();
+        assert!((hit.time - (FLOOR_TOP - 10.0) / 490.0).abs() < 0.001);
+        assert!((hit.point.y - FLOOR_TOP).abs() < 0.01);
+        assert!((hit.normal.x).abs() < 0.001);
+        assert!((hit.normal.y + 1.0).abs() < 0.001);
+
+        let along_face = world.raycast(
+            Line::new(Vec2::new(0.0, FLOOR_TOP), Vec2::new(300.0, FLOOR_TOP)),
+            None,
+        );
+        assert!(along_face.is_none());
+    }
+}
@@ -19,6 +19,14 @@ pub fn debug_get_bitmap_for_sprite(assets: &GameAssets, sprite_name: &str) -> Bi
 
     let source_rect = Recti::from_rect_rounded(texture_coordinates.to_rect());
 
+    if sprite.trimmed_uvs.rotated {
+        // NOTE: The sprite is stored rotated by 90 degrees clockwise in the atlas so we need to
+        //       rotate it back
+        return source_bitmap
+            .cropped_by_rect(source_rect)
+            .rotated_90_counterclockwise();
+    }
+
     let mut result_bitmap = Bitmap::new(dim.x as u32, dim.y as u32);
     let result_rect = result_bitmap.rect();
 
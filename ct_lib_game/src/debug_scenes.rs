@@ -1572,7 +1572,7 @@ impl SceneSprites3dSpatialSound {
             Drawparams::without_additivity(DEPTH_DRAW, Color::white(), Drawspace::Canvas),
         );
         if key_recently_pressed(Scancode::B) {
-            let screen_shake = ModulatorScreenShake::new(get_random_generator(), 4.0, 1.0, 60.0);
+            let screen_shake = ModulatorScreenShake::new(get_random_generator(), 4.0, 1.0, 15.0);
             get_camera().add_shake(screen_shake);
         }
 
@@ -18,6 +18,19 @@ pub fn get_random_generator() -> &'static mut Random {
     &mut get_globals().random
 }
 
+/// Returns the exact state of the global random generator i.e. for recording replays
+#[inline]
+pub fn random_generator_state() -> RandomState {
+    get_globals().random.state()
+}
+
+/// Restores a state previously returned by `random_generator_state`. The generator then continues
+/// with exactly the same sequence of numbers as back then.
+#[inline]
+pub fn random_generator_restore_state(state: RandomState) {
+    get_globals().random = Random::new_from_state(state);
+}
+
 //--------------------------------------------------------------------------------------------------
 // COORDINATES
 
@@ -734,6 +747,21 @@ pub fn draw_sprite_3d(sprite: &Sprite3D, xform: Transform, drawparams: Drawparam
     get_draw().draw_sprite_3d(sprite, xform, drawparams)
 }
 
+//----------------------------------------------------------------------------------------------
+// Dynamic atlas
+
+/// Creates a drawable sprite from a bitmap that was generated at runtime.
+/// NOTE: The given bitmap is expected to not have premultiplied alpha
+#[inline]
+pub fn draw_dynamic_atlas_add_bitmap(sprite_name: &str, bitmap: &Bitmap) -> Sprite {
+    get_draw().dynamic_atlas_add_bitmap(sprite_name, bitmap)
+}
+
+#[inline]
+pub fn draw_dynamic_atlas_remove_bitmap(sprite_name: &str) -> bool {
+    get_draw().dynamic_atlas_remove_bitmap(sprite_name)
+}
+
 //----------------------------------------------------------------------------------------------
 // Primitive drawing
 
@@ -815,6 +843,19 @@ pub fn draw_line_with_thickness(
     get_draw().draw_line_with_thickness(start, end, thickness, smooth_edges, drawparams)
 }
 
+/// Draws a continuous triangle strip along the given points where each point has its own
+/// thickness and color. If a sprite is given its texture is stretched along the whole strip.
+#[inline]
+pub fn draw_ribbon(
+    points: &[Vec2],
+    thicknesses: &[f32],
+    colors: &[Color],
+    sprite: Option<&Sprite>,
+    drawparams: Drawparams,
+) {
+    get_draw().draw_ribbon(points, thicknesses, colors, sprite, drawparams)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Text drawing
 
@@ -843,6 +884,33 @@ pub fn draw_text(
     )
 }
 
+/// Draws a given utf8 text with a font that was baked as a multi channel signed distance field.
+/// Contrary to `draw_text` the text stays crisp at any (also fractional) `font_scale` and can
+/// be drawn with outlines and glows.
+/// Returns the starting_offset for the next `draw_text_distance_field`
+#[inline]
+pub fn draw_text_distance_field(
+    text: &str,
+    font: &SpriteFont,
+    font_scale: f32,
+    starting_origin: Vec2,
+    starting_offset: Vec2,
+    alignment: Option<TextAlignment>,
+    effects: DistanceFieldEffects,
+    drawparams: Drawparams,
+) -> Vec2 {
+    get_draw().draw_text_distance_field(
+        text,
+        font,
+        font_scale,
+        starting_origin,
+        starting_offset,
+        alignment,
+        effects,
+        drawparams,
+    )
+}
+
 /// Draws a given utf8 text in a given font using a clipping rectangle
 /// NOTE: This does not do any word wrapping - the given text should be already pre-wrapped
 ///       for a good result
@@ -1145,3 +1213,8 @@ pub fn assets_get_sprite(sprite_name: &str) -> &Sprite {
 pub fn assets_get_sprite_3d(sprite_name: &str) -> &Sprite3D {
     get_assets().get_sprite_3d(sprite_name)
 }
+
+#[inline]
+pub fn assets_get_particle_emitter_params(emitter_name: &str) -> &ParticleEmitterParams {
+    get_assets().get_particle_emitter_params(emitter_name)
+}
@@ -4,6 +4,9 @@ pub use easyapi::*;
 pub mod animations_fx;
 pub use animations_fx::*;
 
+pub mod animator;
+pub use animator::*;
+
 pub mod assets;
 pub use assets::*;
 
@@ -13,6 +16,9 @@ pub use camera::*;
 pub mod choreographer;
 pub use choreographer::*;
 
+pub mod collision;
+pub use collision::*;
+
 pub mod debug;
 pub use debug::*;
 pub mod debug_scenes;
@@ -21,6 +27,15 @@ pub use debug_scenes::*;
 pub mod gui;
 pub use gui::*;
 
+pub mod modulators;
+pub use modulators::*;
+
+pub mod parallax;
+pub use parallax::*;
+
+pub mod particles;
+pub use particles::*;
+
 mod input;
 use input::{InputState, MouseState, TouchState};
 
@@ -0,0 +1,695 @@
+use super::*;
+
+use std::ops::{Add, Mul, Sub};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Modulator trait
+
+/// A value that changes over time. All modulators are driven by the frame deltatime so they can be
+/// used interchangeably to i.e. animate camera offsets, audio volumes or ui scales.
+pub trait Modulator {
+    type Value;
+
+    fn update(&mut self, deltatime: f32);
+    fn value(&self) -> Self::Value;
+
+    #[inline]
+    fn update_and_get_value(&mut self, deltatime: f32) -> Self::Value {
+        self.update(deltatime);
+        self.value()
+    }
+}
+
+/// Values that can be driven by springs and followers
+pub trait ModulatorValue:
+    Copy + Default + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+    fn magnitude(self) -> f32;
+}
+
+impl ModulatorValue for f32 {
+    #[inline]
+    fn magnitude(self) -> f32 {
+        f32::abs(self)
+    }
+}
+
+impl ModulatorValue for Vec2 {
+    #[inline]
+    fn magnitude(self) -> f32 {
+        Vec2::magnitude(self)
+    }
+}
+
+impl ModulatorValue for Color {
+    #[inline]
+    fn magnitude(self) -> f32 {
+        f32::sqrt(self.r * self.r + self.g * self.g + self.b * self.b + self.a * self.a)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Damped spring
+//
+// Inspired by https://github.com/a327ex/blog/issues/60 but uses the closed form solution of the
+// damped harmonic oscillator from https://www.ryanjuckett.com/damped-springs/ which stays stable
+// for arbitrary deltatimes.
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModulatorSpring<ValueType: ModulatorValue> {
+    pub value: ValueType,
+    pub velocity: ValueType,
+    pub target: ValueType,
+    /// Number of oscillations per second when undamped
+    pub frequency: f32,
+    /// 0.0 -> oscillates forever
+    /// 1.0 -> critically damped, reaches the target as fast as possible without overshooting
+    /// >1.0 -> overdamped, reaches the target slowly without overshooting
+    pub damping_ratio: f32,
+}
+
+impl<ValueType: ModulatorValue> ModulatorSpring<ValueType> {
+    pub fn new(value: ValueType, frequency: f32, damping_ratio: f32) -> ModulatorSpring<ValueType> {
+        ModulatorSpring {
+            value,
+            velocity: ValueType::default(),
+            target: value,
+            frequency,
+            damping_ratio,
+        }
+    }
+
+    /// Displaces the value from its target. Useful for bouncy effects, i.e. pulling the scale of
+    /// a button when it is pressed.
+    #[inline]
+    pub fn pull(&mut self, displacement: ValueType) {
+        self.value = self.value + displacement;
+    }
+
+    /// Adds velocity to the value as if it was hit
+    #[inline]
+    pub fn kick(&mut self, velocity: ValueType) {
+        self.velocity = self.velocity + velocity;
+    }
+}
+
+impl<ValueType: ModulatorValue> Modulator for ModulatorSpring<ValueType> {
+    type Value = ValueType;
+
+    fn update(&mut self, deltatime: f32) {
+        let (pos_pos, pos_vel, vel_pos, vel_vel) =
+            spring_motion_coefficients(deltatime, 2.0 * PI * self.frequency, self.damping_ratio);
+        let offset = self.value - self.target;
+        self.value = self.target + offset * pos_pos + self.velocity * pos_vel;
+        self.velocity = offset * vel_pos + self.velocity * vel_vel;
+    }
+
+    #[inline]
+    fn value(&self) -> ValueType {
+        self.value
+    }
+}
+
+/// Returns the coefficients `(pos_pos, pos_vel, vel_pos, vel_vel)` such that
+/// `offset_new = pos_pos * offset + pos_vel * velocity` and
+/// `velocity_new = vel_pos * offset + vel_vel * velocity`
+/// where `offset` is the displacement of the spring from its target
+fn spring_motion_coefficients(
+    deltatime: f32,
+    angular_frequency: f32,
+    damping_ratio: f32,
+) -> (f32, f32, f32, f32) {
+    const EPSILON: f32 = 0.0001;
+    let damping_ratio = f32::max(damping_ratio, 0.0);
+    if angular_frequency < EPSILON {
+        return (1.0, 0.0, 0.0, 1.0);
+    }
+
+    if damping_ratio > 1.0 + EPSILON {
+        // Overdamped
+        let za = -angular_frequency * damping_ratio;
+        let zb = angular_frequency * f32::sqrt(damping_ratio * damping_ratio - 1.0);
+        let z1 = za - zb;
+        let z2 = za + zb;
+
+        let e1 = f32::exp(z1 * deltatime);
+        let e2 = f32::exp(z2 * deltatime);
+        let e1_over_two_zb = e1 / (2.0 * zb);
+        let e2_over_two_zb = e2 / (2.0 * zb);
+        let z1e1_over_two_zb = z1 * e1_over_two_zb;
+        let z2e2_over_two_zb = z2 * e2_over_two_zb;
+
+        (
+            e1_over_two_zb * z2 - z2e2_over_two_zb + e2,
+            -e1_over_two_zb + e2_over_two_zb,
+            (z1e1_over_two_zb - z2e2_over_two_zb + e2) * z2,
+            -z1e1_over_two_zb + z2e2_over_two_zb,
+        )
+    } else if damping_ratio < 1.0 - EPSILON {
+        // Underdamped
+        let omega_zeta = angular_frequency * damping_ratio;
+        let alpha = angular_frequency * f32::sqrt(1.0 - damping_ratio * damping_ratio);
+
+        let exp_term = f32::exp(-omega_zeta * deltatime);
+        let cos_term = f32::cos(alpha * deltatime);
+        let sin_term = f32::sin(alpha * deltatime);
+        let exp_sin = exp_term * sin_term;
+        let exp_cos = exp_term * cos_term;
+        let exp_omega_zeta_sin_over_alpha = exp_term * omega_zeta * sin_term / alpha;
+
+        (
+            exp_cos + exp_omega_zeta_sin_over_alpha,
+            exp_sin / alpha,
+            -exp_sin * alpha - omega_zeta * exp_omega_zeta_sin_over_alpha,
+            exp_cos - exp_omega_zeta_sin_over_alpha,
+        )
+    } else {
+        // Critically damped
+        let exp_term = f32::exp(-angular_frequency * deltatime);
+        let time_exp = deltatime * exp_term;
+        let time_exp_freq = time_exp * angular_frequency;
+
+        (
+            time_exp_freq + exp_term,
+            time_exp,
+            -angular_frequency * time_exp_freq,
+            -time_exp_freq + exp_term,
+        )
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Critically damped smoothing
+//
+// Based on "Critically Damped Ease-In/Ease-Out Smoothing" from Game Programming Gems 4
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModulatorSmoothDamp<ValueType: ModulatorValue> {
+    pub value: ValueType,
+    pub velocity: ValueType,
+    pub target: ValueType,
+    /// Roughly the time in seconds it takes to reach the target
+    pub smooth_time: f32,
+}
+
+impl<ValueType: ModulatorValue> ModulatorSmoothDamp<ValueType> {
+    pub fn new(value: ValueType, smooth_time: f32) -> ModulatorSmoothDamp<ValueType> {
+        ModulatorSmoothDamp {
+            value,
+            velocity: ValueType::default(),
+            target: value,
+            smooth_time,
+        }
+    }
+}
+
+impl<ValueType: ModulatorValue> Modulator for ModulatorSmoothDamp<ValueType> {
+    type Value = ValueType;
+
+    fn update(&mut self, deltatime: f32) {
+        if self.smooth_time <= 0.0 {
+            self.value = self.target;
+            self.velocity = ValueType::default();
+            return;
+        }
+
+        // NOTE: This is a taylor approximation of `exp(-omega * deltatime)`
+        let omega = 2.0 / self.smooth_time;
+        let x = omega * deltatime;
+        let exp = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+
+        let offset = self.value - self.target;
+        let temp = (self.velocity + offset * omega) * deltatime;
+        self.velocity = (self.velocity - temp * omega) * exp;
+        self.value = self.target + (offset + temp) * exp;
+    }
+
+    #[inline]
+    fn value(&self) -> ValueType {
+        self.value
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Newtonian follower
+//
+// Inspired by the modulators in https://www.youtube.com/watch?v=n-txrCMvdms
+//
+// Accelerates towards its target with a constant force like a physical object. It therefore
+// overshoots and swings around the target until its friction calms it down.
+//
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModulatorNewtonianFollower<ValueType: ModulatorValue> {
+    pub value: ValueType,
+    pub velocity: ValueType,
+    pub target: ValueType,
+    /// Units per second squared
+    pub acceleration: f32,
+    /// Units per second
+    pub speed_max: f32,
+    /// Fraction of the velocity that is lost per second
+    pub friction: f32,
+}
+
+impl<ValueType: ModulatorValue> ModulatorNewtonianFollower<ValueType> {
+    pub fn new(
+        value: ValueType,
+        acceleration: f32,
+        speed_max: f32,
+        friction: f32,
+    ) -> ModulatorNewtonianFollower<ValueType> {
+        ModulatorNewtonianFollower {
+            value,
+            velocity: ValueType::default(),
+            target: value,
+            acceleration,
+            speed_max,
+            friction,
+        }
+    }
+}
+
+impl<ValueType: ModulatorValue> Modulator for ModulatorNewtonianFollower<ValueType> {
+    type Value = ValueType;
+
+    fn update(&mut self, deltatime: f32) {
+        let offset = self.target - self.value;
+        let distance = offset.magnitude();
+        if distance > EPSILON {
+            self.velocity = self.velocity + offset * (self.acceleration * deltatime / distance);
+        }
+
+        let friction = f32::clamp(self.friction, 0.0, 1.0);
+        self.velocity = self.velocity * f32::powf(1.0 - friction, deltatime);
+
+        let speed = self.velocity.magnitude();
+        if speed > self.speed_max {
+            self.velocity = self.velocity * (self.speed_max / speed);
+        }
+
+        self.value = self.value + self.velocity * deltatime;
+    }
+
+    #[inline]
+    fn value(&self) -> ValueType {
+        self.value
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Shift register modulator
+//
+// Inspired by the modulators in https://www.youtube.com/watch?v=n-txrCMvdms
+//
+// Cycles through a ring of random values. Whenever a value leaves the ring it is replaced with a
+// new random value by a given chance. This produces random patterns that repeat for a while and
+// change slowly over time.
+//
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ModulatorShiftRegister {
+    pub value_min: f32,
+    pub value_max: f32,
+    /// Number of register values that are visited per second
+    pub frequency: f32,
+    /// 0.0 -> repeats the current pattern forever
+    /// 1.0 -> produces a completely new random value on every step
+    pub change_probability: f32,
+    /// Determines how the value transitions from one register value to the next. Use
+    /// `EasingType::StepEnd` to get hard steps.
+    pub interpolation: EasingType,
+
+    /// Values in [0, 1]
+    register: Vec<f32>,
+    step_percent: f32,
+    random: Random,
+}
+
+impl ModulatorShiftRegister {
+    pub fn new(
+        random: &mut Random,
+        register_length: usize,
+        frequency: f32,
+        change_probability: f32,
+        value_min: f32,
+        value_max: f32,
+    ) -> ModulatorShiftRegister {
+        assert!(
+            register_length >= 2,
+            "Shift register needs at least two values"
+        );
+        let mut random = Random::new_from_seed(random.u32() as u64);
+        let register = (0..register_length).map(|_| random.f32()).collect();
+        ModulatorShiftRegister {
+            value_min,
+            value_max,
+            frequency,
+            change_probability,
+            interpolation: EasingType::SineInOut,
+            register,
+            step_percent: 0.0,
+            random,
+        }
+    }
+
+    fn step(&mut self) {
+        self.register.rotate_left(1);
+        if self.random.f32() < self.change_probability {
+            *self.register.last_mut().unwrap() = self.random.f32();
+        }
+    }
+}
+
+impl Modulator for ModulatorShiftRegister {
+    type Value = f32;
+
+    fn update(&mut self, deltatime: f32) {
+        self.step_percent += self.frequency * deltatime;
+        while self.step_percent >= 1.0 {
+            self.step_percent -= 1.0;
+            self.step();
+        }
+    }
+
+    fn value(&self) -> f32 {
+        let percent = lerp(
+            self.register[0],
+            self.register[1],
+            ease(self.step_percent, self.interpolation),
+        );
+        lerp(self.value_min, self.value_max, percent)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Low frequency oscillator
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    Square,
+    Sawtooth,
+}
+
+impl Waveform {
+    /// Returns a value in [-1, 1] for the given phase in [0, 1]. All waveforms except `Square`
+    /// start at zero and rise like a sine wave. `Square` is 1 in the first half of the period and
+    /// -1 in the second half.
+    #[inline]
+    pub fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Sine => f32::sin(2.0 * PI * phase),
+            Waveform::Triangle => 4.0 * f32::abs((phase + 0.75).fract() - 0.5) - 1.0,
+            Waveform::Square => {
+                if phase.fract() < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sawtooth => 2.0 * (phase + 0.5).fract() - 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModulatorLfo {
+    pub waveform: Waveform,
+    /// Oscillations per second
+    pub frequency: f32,
+    pub amplitude: f32,
+    /// The value the oscillation is centered around
+    pub offset: f32,
+    /// In [0, 1]
+    pub phase: f32,
+}
+
+impl ModulatorLfo {
+    pub fn new(waveform: Waveform, frequency: f32, amplitude: f32, offset: f32) -> ModulatorLfo {
+        ModulatorLfo {
+            waveform,
+            frequency,
+            amplitude,
+            offset,
+            phase: 0.0,
+        }
+    }
+}
+
+impl Modulator for ModulatorLfo {
+    type Value = f32;
+
+    #[inline]
+    fn update(&mut self, deltatime: f32) {
+        self.phase = (self.phase + self.frequency * deltatime).rem_euclid(1.0);
+    }
+
+    #[inline]
+    fn value(&self) -> f32 {
+        self.offset + self.amplitude * self.waveform.sample(self.phase)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Envelope
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvelopeStage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Attack-decay-sustain-release envelope with values in [0, 1]. After `trigger` it rises to 1 in
+/// `attack` seconds, falls to `sustain_level` in `decay` seconds and stays there until `release`
+/// is called. Then it falls to 0 in `release` seconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModulatorEnvelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain_level: f32,
+    pub release: f32,
+
+    stage: EnvelopeStage,
+    stage_time: f32,
+    stage_start_value: f32,
+    value: f32,
+}
+
+impl ModulatorEnvelope {
+    pub fn new(attack: f32, decay: f32, sustain_level: f32, release: f32) -> ModulatorEnvelope {
+        ModulatorEnvelope {
+            attack,
+            decay,
+            sustain_level,
+            release,
+            stage: EnvelopeStage::Idle,
+            stage_time: 0.0,
+            stage_start_value: 0.0,
+            value: 0.0,
+        }
+    }
+
+    /// Starts the attack from the current value so that retriggering does not jump
+    pub fn trigger(&mut self) {
+        self.enter_stage(EnvelopeStage::Attack);
+    }
+
+    pub fn release(&mut self) {
+        if self.stage != EnvelopeStage::Idle {
+            self.enter_stage(EnvelopeStage::Release);
+        }
+    }
+
+    #[inline]
+    pub fn stage(&self) -> EnvelopeStage {
+        self.stage
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.stage == EnvelopeStage::Idle
+    }
+
+    fn enter_stage(&mut self, stage: EnvelopeStage) {
+        self.stage = stage;
+        self.stage_time = 0.0;
+        self.stage_start_value = self.value;
+    }
+}
+
+impl Modulator for ModulatorEnvelope {
+    type Value = f32;
+
+    fn update(&mut self, deltatime: f32) {
+        self.stage_time += deltatime;
+
+        // NOTE: We loop here because a single update can pass multiple short stages
+        loop {
+            let (duration, value_end, stage_next) = match self.stage {
+                EnvelopeStage::Idle | EnvelopeStage::Sustain => break,
+                EnvelopeStage::Attack => (self.attack, 1.0, EnvelopeStage::Decay),
+                EnvelopeStage::Decay => (self.decay, self.sustain_level, EnvelopeStage::Sustain),
+                EnvelopeStage::Release => (self.release, 0.0, EnvelopeStage::Idle),
+            };
+
+            if self.stage_time < duration {
+                let percent = self.stage_time / duration;
+                self.value = lerp(self.stage_start_value, value_end, percent);
+                break;
+            }
+
+            let time_remaining = self.stage_time - duration;
+            self.value = value_end;
+            self.enter_stage(stage_next);
+            self.stage_time = time_remaining;
+        }
+
+        if self.stage == EnvelopeStage::Sustain {
+            self.value = self.sustain_level;
+        }
+    }
+
+    #[inline]
+    fn value(&self) -> f32 {
+        self.value
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTATIME: f32 = 1.0 / 60.0;
+
+    #[test]
+    fn critically_damped_spring_reaches_target_without_overshoot() {
+        let mut spring = ModulatorSpring::new(0.0, 2.0, 1.0);
+        spring.target = 10.0;
+        for _ in 0..600 {
+            let value = spring.update_and_get_value(DELTATIME);
+            assert!(value <= spring.target + EPSILON);
+        }
+        assert!((spring.value() - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn followers_converge_to_target() {
+        let mut smooth_damp = ModulatorSmoothDamp::new(0.0, 0.3);
+        smooth_damp.target = 5.0;
+        let mut follower = ModulatorNewtonianFollower::new(0.0, 50.0, 20.0, 0.99);
+        follower.target = 5.0;
+        for _ in 0..600 {
+            smooth_damp.update(DELTATIME);
+            follower.update(DELTATIME);
+        }
+        assert!((smooth_damp.value() - 5.0).abs() < 0.01);
+        assert!((follower.value() - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn envelope_crosses_stages_in_one_update() {
+        let mut envelope = ModulatorEnvelope::new(0.1, 0.2, 0.5, 0.4);
+        assert_eq!(envelope.stage(), EnvelopeStage::Idle);
+
+        envelope.trigger();
+        envelope.update(0.05);
+        assert_eq!(envelope.stage(), EnvelopeStage::Attack);
+        assert!((envelope.value() - 0.5).abs() < EPSILON);
+
+        // Finishes the attack and lands halfway into the decay
+        envelope.update(0.15);
+        assert_eq!(envelope.stage(), EnvelopeStage::Decay);
+        assert!((envelope.value() - 0.75).abs() < EPSILON);
+
+        envelope.update(1.0);
+        assert_eq!(envelope.stage(), EnvelopeStage::Sustain);
+        assert!((envelope.value() - 0.5).abs() < EPSILON);
+
+        envelope.release();
+        envelope.update(1.0);
+        assert!(envelope.is_finished());
+        assert_eq!(envelope.value(), 0.0);
+    }
+
+    #[test]
+    fn envelope_releases_from_attack() {
+        let mut envelope = ModulatorEnvelope::new(0.1, 0.2, 0.5, 0.4);
+        envelope.trigger();
+        envelope.update(0.05);
+        envelope.release();
+        assert_eq!(envelope.stage(), EnvelopeStage::Release);
+
+        let value_before = envelope.value();
+        envelope.update(0.1);
+        assert!(envelope.value() < value_before);
+        envelope.update(1.0);
+        assert!(envelope.is_finished());
+        assert_eq!(envelope.value(), 0.0);
+    }
+
+    #[test]
+    fn waveform_samples() {
+        let phases = [0.0, 0.25, 0.5, 0.75];
+        let expected = [
+            (Waveform::Sine, [0.0, 1.0, 0.0, -1.0]),
+            (Waveform::Triangle, [0.0, 1.0, 0.0, -1.0]),
+            (Waveform::Square, [1.0, 1.0, -1.0, -1.0]),
+            (Waveform::Sawtooth, [0.0, 0.5, -1.0, -0.5]),
+        ];
+        for (waveform, values) in expected.iter() {
+            for (phase, value) in phases.iter().zip(values.iter()) {
+                let sample = waveform.sample(*phase);
+                assert!(
+                    (sample - value).abs() < 0.001,
+                    "{:?} at phase {} is {} but should be {}",
+                    waveform,
+                    phase,
+                    sample,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn waveforms_at_phase_zero() {
+        for &waveform in &[Waveform::Sine, Waveform::Triangle, Waveform::Sawtooth] {
+            assert_eq!(waveform.sample(0.0), 0.0, "{:?}", waveform);
+            assert!(waveform.sample(0.01) > 0.0, "{:?}", waveform);
+            assert!((waveform.sample(1.0) - waveform.sample(0.0)).abs() < 0.001);
+        }
+        assert_eq!(Waveform::Square.sample(0.0), 1.0);
+        assert_eq!(Waveform::Square.sample(1.0), 1.0);
+    }
+
+    #[test]
+    fn shift_register_is_deterministic_and_serializable() {
+        let create = || {
+            let mut random = Random::new_from_seed(1);
+            ModulatorShiftRegister::new(&mut random, 8, 4.0, 0.5, -1.0, 1.0)
+        };
+        let mut a = create();
+        let mut b = create();
+        for _ in 0..100 {
+            assert_eq!(a.update_and_get_value(0.1), b.update_and_get_value(0.1));
+        }
+
+        let mut restored: ModulatorShiftRegister =
+            deserialize_from_binary(&serialize_to_binary(&a));
+        for _ in 0..100 {
+            let value = a.update_and_get_value(0.1);
+            assert!(-1.0 <= value && value <= 1.0);
+            assert_eq!(value, restored.update_and_get_value(0.1));
+        }
+    }
+}
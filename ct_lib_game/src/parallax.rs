@@ -0,0 +1,187 @@
+use super::*;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Parallax layers
+
+#[derive(Clone)]
+pub struct ParallaxLayer {
+    pub sprite: Sprite,
+
+    /// Determines how much the layer moves relative to the camera per axis.
+    /// 1.0 -> moves together with the world (i.e. foreground)
+    /// 0.5 -> moves at half the speed of the world (i.e. distant mountains)
+    /// 0.0 -> stays fixed on the screen (i.e. sky at infinite distance)
+    pub scroll_factor: Vec2,
+
+    /// The position of the layers top-left corner when the camera is at the world origin
+    pub origin: Worldpoint,
+
+    /// Repeats the sprite infinitely in the given directions to fill the camera view
+    pub repeat_horizontally: bool,
+    pub repeat_vertically: bool,
+
+    /// Additional movement of the layer in world units per second (i.e. for drifting clouds)
+    pub autoscroll_vel: Vec2,
+
+    pub depth: Depth,
+    pub color_modulate: Color,
+    pub additivity: Additivity,
+
+    autoscroll_offset: Vec2,
+}
+
+impl ParallaxLayer {
+    pub fn new(sprite: Sprite, scroll_factor: Vec2, depth: Depth) -> ParallaxLayer {
+        ParallaxLayer {
+            sprite,
+            scroll_factor,
+            origin: Worldpoint::zero(),
+            repeat_horizontally: false,
+            repeat_vertically: false,
+            autoscroll_vel: Vec2::zero(),
+            depth,
+            color_modulate: Color::white(),
+            additivity: ADDITIVITY_NONE,
+            autoscroll_offset: Vec2::zero(),
+        }
+    }
+
+    #[inline]
+    pub fn with_origin(mut self, origin: Worldpoint) -> ParallaxLayer {
+        self.origin = origin;
+        self
+    }
+
+    #[inline]
+    pub fn with_repeat(
+        mut self,
+        repeat_horizontally: bool,
+        repeat_vertically: bool,
+    ) -> ParallaxLayer {
+        self.repeat_horizontally = repeat_horizontally;
+        self.repeat_vertically = repeat_vertically;
+        self
+    }
+
+    #[inline]
+    pub fn with_autoscroll(mut self, autoscroll_vel: Vec2) -> ParallaxLayer {
+        self.autoscroll_vel = autoscroll_vel;
+        self
+    }
+
+    #[inline]
+    pub fn with_color_modulate(mut self, color_modulate: Color) -> ParallaxLayer {
+        self.color_modulate = color_modulate;
+        self
+    }
+
+    /// Returns the pixel snapped world position of the layers top-left corner for a given camera
+    /// position
+    #[inline]
+    pub fn pos_for_camera(&self, camera_pos: Worldpoint) -> Worldpoint {
+        let parallax_offset = camera_pos * (Vec2::ones() - self.scroll_factor);
+        (self.origin + self.autoscroll_offset + parallax_offset).pixel_snapped()
+    }
+
+    pub fn update(&mut self, deltatime: f32) {
+        self.autoscroll_offset += self.autoscroll_vel * deltatime;
+
+        // NOTE: We wrap the autoscroll offset to prevent it from growing indefinitely which would
+        //       cost us floating point precision over time
+        let tile_dim = self.sprite.untrimmed_dimensions;
+        if self.repeat_horizontally && tile_dim.x > 0.0 {
+            self.autoscroll_offset.x = wrap_value_in_range(self.autoscroll_offset.x, tile_dim.x);
+        }
+        if self.repeat_vertically && tile_dim.y > 0.0 {
+            self.autoscroll_offset.y = wrap_value_in_range(self.autoscroll_offset.y, tile_dim.y);
+        }
+    }
+
+    pub fn draw(&self, camera: &mut GameCamera) {
+        let tile_dim = self.sprite.untrimmed_dimensions;
+        if tile_dim.x <= 0.0 || tile_dim.y <= 0.0 {
+            return;
+        }
+
+        let camera_bounds = camera.bounds_pixelsnapped();
+        let layer_pos = self.pos_for_camera(camera.cam.pos_pixelsnapped);
+
+        // NOTE: Centered cameras have a flipped y-axis in their bounds so we need to normalize here
+        let view_min = Vec2::new(
+            f32::min(camera_bounds.left(), camera_bounds.right()),
+            f32::min(camera_bounds.top(), camera_bounds.bottom()),
+        );
+        let view_max = Vec2::new(
+            f32::max(camera_bounds.left(), camera_bounds.right()),
+            f32::max(camera_bounds.top(), camera_bounds.bottom()),
+        );
+
+        let (start_x, end_x) = if self.repeat_horizontally {
+            let start =
+                layer_pos.x + f32::floor((view_min.x - layer_pos.x) / tile_dim.x) * tile_dim.x;
+            (start, view_max.x)
+        } else {
+            (layer_pos.x, layer_pos.x + 1.0)
+        };
+        let (start_y, end_y) = if self.repeat_vertically {
+            let start =
+                layer_pos.y + f32::floor((view_min.y - layer_pos.y) / tile_dim.y) * tile_dim.y;
+            (start, view_max.y)
+        } else {
+            (layer_pos.y, layer_pos.y + 1.0)
+        };
+
+        let drawparams = Drawparams::new(
+            self.depth,
+            self.color_modulate,
+            self.additivity,
+            Drawspace::World,
+        );
+
+        let mut tile_y = start_y;
+        while tile_y < end_y {
+            let mut tile_x = start_x;
+            while tile_x < end_x {
+                let tile_pos = Vec2::new(tile_x, tile_y);
+                draw_sprite(
+                    &self.sprite,
+                    Transform::from_pos(tile_pos + self.sprite.pivot_offset),
+                    false,
+                    false,
+                    drawparams,
+                );
+                tile_x += tile_dim.x;
+            }
+            tile_y += tile_dim.y;
+        }
+    }
+
+    pub fn update_and_draw(&mut self, deltatime: f32, camera: &mut GameCamera) {
+        self.update(deltatime);
+        self.draw(camera);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Parallax background
+
+#[derive(Clone, Default)]
+pub struct ParallaxBackground {
+    pub layers: Vec<ParallaxLayer>,
+}
+
+impl ParallaxBackground {
+    pub fn new() -> ParallaxBackground {
+        ParallaxBackground { layers: Vec::new() }
+    }
+
+    pub fn add_layer(&mut self, layer: ParallaxLayer) {
+        self.layers.push(layer);
+    }
+
+    pub fn update_and_draw(&mut self, deltatime: f32, camera: &mut GameCamera) {
+        for layer in self.layers.iter_mut() {
+            layer.update_and_draw(deltatime, camera);
+        }
+    }
+}
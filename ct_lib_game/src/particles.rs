@@ -0,0 +1,638 @@
+use super::*;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Particle curves
+
+/// A piecewise curve that maps the normalized age of a particle in [0, 1] to a value.
+/// The keys are given as `(percent, value)` pairs. They are sorted by percent when an emitter is
+/// created (see `sort_keys`) so hand-written asset files may list them in any order. Values between
+/// two keys are interpolated using the given easing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticleCurve<ValueType: Lerp + Default> {
+    pub keys: Vec<(f32, ValueType)>,
+    #[serde(default)]
+    pub easing: EasingType,
+}
+
+impl<ValueType: Lerp + Default> Default for ParticleCurve<ValueType> {
+    fn default() -> Self {
+        ParticleCurve {
+            keys: vec![(0.0, ValueType::default())],
+            easing: EasingType::Linear,
+        }
+    }
+}
+
+impl<ValueType: Lerp + Default> ParticleCurve<ValueType> {
+    pub fn constant(value: ValueType) -> ParticleCurve<ValueType> {
+        ParticleCurve {
+            keys: vec![(0.0, value)],
+            easing: EasingType::Linear,
+        }
+    }
+
+    pub fn linear(start: ValueType, end: ValueType) -> ParticleCurve<ValueType> {
+        ParticleCurve {
+            keys: vec![(0.0, start), (1.0, end)],
+            easing: EasingType::Linear,
+        }
+    }
+
+    /// Sorts the keys by percent which `value_at` relies on. Keys with equal percent keep their
+    /// relative order.
+    pub fn sort_keys(&mut self) {
+        self.keys.sort_by(|a, b| compare_floats(a.0, b.0));
+    }
+
+    pub fn value_at(&self, percent: f32) -> ValueType {
+        debug_assert!(
+            self.keys
+                .windows(2)
+                .all(|segment| segment[0].0 <= segment[1].0),
+            "Particle curve keys must be sorted by percent"
+        );
+        match self.keys.len() {
+            0 => ValueType::default(),
+            1 => self.keys[0].1,
+            _ => {
+                let (first_percent, first_value) = self.keys[0];
+                if percent <= first_percent {
+                    return first_value;
+                }
+
+                for segment in self.keys.windows(2) {
+                    let (start_percent, start_value) = segment[0];
+                    let (end_percent, end_value) = segment[1];
+                    if percent < end_percent {
+                        let segment_percent = if end_percent > start_percent {
+                            (percent - start_percent) / (end_percent - start_percent)
+                        } else {
+                            1.0
+                        };
+                        return ValueType::lerp_value(
+                            start_value,
+                            end_value,
+                            ease(segment_percent, self.easing),
+                        );
+                    }
+                }
+
+                self.keys.last().unwrap().1
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Particle emitter params
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ParticleAppearance {
+    /// Untextured square pixel that grows into a rect when scaled
+    Pixel,
+    Sprite {
+        sprite_name: ResourceName,
+    },
+    Animation {
+        animation_name: ResourceName,
+        /// If true the whole animation is played exactly once over the lifetime of the particle,
+        /// otherwise it loops with its own frame timings
+        stretch_over_lifetime: bool,
+    },
+}
+
+impl Default for ParticleAppearance {
+    fn default() -> Self {
+        ParticleAppearance::Pixel
+    }
+}
+
+/// The area in which new particles are spawned relative to the emitter position
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ParticleEmissionShape {
+    Point,
+    Circle { radius: f32, edge_only: bool },
+    Rect { dim: Vec2, edge_only: bool },
+    Line { start: Vec2, end: Vec2 },
+}
+
+impl Default for ParticleEmissionShape {
+    fn default() -> Self {
+        ParticleEmissionShape::Point
+    }
+}
+
+impl ParticleEmissionShape {
+    pub fn sample_offset(&self, random: &mut Random) -> Vec2 {
+        match *self {
+            ParticleEmissionShape::Point => Vec2::zero(),
+            ParticleEmissionShape::Circle { radius, edge_only } => {
+                if edge_only {
+                    radius * Vec2::from_angle_flipped_y(random.f32_in_range(0.0, 360.0))
+                } else {
+                    radius * random.vec2_in_unit_disk()
+                }
+            }
+            ParticleEmissionShape::Rect { dim, edge_only } => {
+                let offset = 0.5 * dim * random.vec2_in_unit_rect();
+                if edge_only {
+                    // NOTE: We pick a random side weighted by its length and push the point onto it
+                    let perimeter_percent = random.f32() * (dim.x + dim.y);
+                    let push_to_positive_side = random.f32() < 0.5;
+                    let sign = if push_to_positive_side { 1.0 } else { -1.0 };
+                    if perimeter_percent < dim.x {
+                        Vec2::new(offset.x, sign * 0.5 * dim.y)
+                    } else {
+                        Vec2::new(sign * 0.5 * dim.x, offset.y)
+                    }
+                } else {
+                    offset
+                }
+            }
+            ParticleEmissionShape::Line { start, end } => Vec2::lerp(start, end, random.f32()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticleSimulationSpace {
+    /// Particles stay where they were spawned when the emitter moves
+    World,
+    /// Particles move and rotate together with the emitter
+    Local,
+}
+
+impl Default for ParticleSimulationSpace {
+    fn default() -> Self {
+        ParticleSimulationSpace::World
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ParticleBurst {
+    /// Time since the start of the emission cycle
+    pub time: f32,
+    pub count: usize,
+}
+
+/// Describes the behaviour of a particle emitter. These are usually authored as
+/// `*.particles.json` files in the assets folder which are baked into the graphics resources by
+/// the assetbaker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleEmitterParams {
+    pub appearance: ParticleAppearance,
+    pub emission_shape: ParticleEmissionShape,
+    pub simulation_space: ParticleSimulationSpace,
+
+    /// Length of one emission cycle in seconds
+    pub duration: f32,
+    pub looping: bool,
+    /// Number of particles spawned per second
+    pub spawn_rate: f32,
+    /// Bursts are sorted by time when an emitter is created (see `sort_keys_and_bursts`) so
+    /// hand-written asset files may list them in any order
+    pub bursts: Vec<ParticleBurst>,
+    pub count_max: usize,
+
+    pub lifetime_min: f32,
+    pub lifetime_max: f32,
+
+    /// Direction angle in degrees (counterclockwise) and the total spread around it
+    pub direction_angle: f32,
+    pub direction_spread: f32,
+    /// If true particles fly away from the center of the emission shape instead
+    pub direction_outward: bool,
+    pub speed_min: f32,
+    pub speed_max: f32,
+    pub vel_max: f32,
+    pub gravity: Vec2,
+    pub drag: f32,
+
+    pub rotation_min: f32,
+    pub rotation_max: f32,
+    pub angular_vel_min: f32,
+    pub angular_vel_max: f32,
+    pub align_to_velocity: bool,
+
+    pub scale_min: f32,
+    pub scale_max: f32,
+
+    pub scale_over_lifetime: ParticleCurve<f32>,
+    pub speed_over_lifetime: ParticleCurve<f32>,
+    pub color_over_lifetime: ParticleCurve<Color>,
+    pub additivity_over_lifetime: ParticleCurve<f32>,
+}
+
+impl Default for ParticleEmitterParams {
+    fn default() -> Self {
+        ParticleEmitterParams {
+            appearance: ParticleAppearance::Pixel,
+            emission_shape: ParticleEmissionShape::Point,
+            simulation_space: ParticleSimulationSpace::World,
+
+            duration: 1.0,
+            looping: true,
+            spawn_rate: 10.0,
+            bursts: Vec::new(),
+            count_max: 100,
+
+            lifetime_min: 1.0,
+            lifetime_max: 1.0,
+
+            direction_angle: 90.0,
+            direction_spread: 0.0,
+            direction_outward: false,
+            speed_min: 0.0,
+            speed_max: 0.0,
+            vel_max: 10_000.0,
+            gravity: Vec2::zero(),
+            drag: 0.0,
+
+            rotation_min: 0.0,
+            rotation_max: 0.0,
+            angular_vel_min: 0.0,
+            angular_vel_max: 0.0,
+            align_to_velocity: false,
+
+            scale_min: 1.0,
+            scale_max: 1.0,
+
+            scale_over_lifetime: ParticleCurve::constant(1.0),
+            speed_over_lifetime: ParticleCurve::constant(1.0),
+            color_over_lifetime: ParticleCurve::constant(Color::white()),
+            additivity_over_lifetime: ParticleCurve::constant(ADDITIVITY_NONE),
+        }
+    }
+}
+
+impl ParticleEmitterParams {
+    /// Sorts the bursts by time and the keys of all curves by percent. The emitter relies on this
+    /// ordering when firing bursts and evaluating curves.
+    pub fn sort_keys_and_bursts(&mut self) {
+        self.bursts.sort_by(|a, b| compare_floats(a.time, b.time));
+        self.scale_over_lifetime.sort_keys();
+        self.speed_over_lifetime.sort_keys();
+        self.color_over_lifetime.sort_keys();
+        self.additivity_over_lifetime.sort_keys();
+    }
+
+    /// Returns the sprite- and animation-names referenced by these params
+    pub fn referenced_resource_names(&self) -> Vec<&str> {
+        match &self.appearance {
+            ParticleAppearance::Pixel => Vec::new(),
+            ParticleAppearance::Sprite { sprite_name } => vec![sprite_name],
+            ParticleAppearance::Animation { animation_name, .. } => vec![animation_name],
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Particle emitter
+
+#[derive(Clone)]
+pub struct ParticleEmitter {
+    pub params: ParticleEmitterParams,
+    pub pos: Vec2,
+    /// Angle given in degrees counterclockwise
+    pub dir_angle: f32,
+
+    /// Is set if the emitter was created from a baked particle asset. We use it to pick up
+    /// changed params after asset hotreloading.
+    params_resource_name: Option<ResourceName>,
+    assets_generation: usize,
+
+    sprite: Option<Sprite>,
+    animation: Option<Animation<Sprite>>,
+
+    is_emitting: bool,
+    emission_time: f32,
+    time_since_last_spawn: f32,
+    next_burst_index: usize,
+
+    particle_pos: Vec<Vec2>,
+    particle_vel: Vec<Vec2>,
+    particle_age: Vec<f32>,
+    particle_lifetime: Vec<f32>,
+    particle_scale: Vec<f32>,
+    particle_rotation: Vec<f32>,
+    particle_angular_vel: Vec<f32>,
+}
+
+impl ParticleEmitter {
+    pub fn new(mut params: ParticleEmitterParams, pos: Vec2) -> ParticleEmitter {
+        params.sort_keys_and_bursts();
+        let count_max = params.count_max;
+        let mut result = ParticleEmitter {
+            params,
+            pos,
+            dir_angle: 0.0,
+
+            params_resource_name: None,
+            assets_generation: get_assets().get_graphics_generation(),
+
+            sprite: None,
+            animation: None,
+
+            is_emitting: true,
+            emission_time: 0.0,
+            time_since_last_spawn: 0.0,
+            next_burst_index: 0,
+
+            particle_pos: Vec::with_capacity(count_max),
+            particle_vel: Vec::with_capacity(count_max),
+            particle_age: Vec::with_capacity(count_max),
+            particle_lifetime: Vec::with_capacity(count_max),
+            particle_scale: Vec::with_capacity(count_max),
+            particle_rotation: Vec::with_capacity(count_max),
+            particle_angular_vel: Vec::with_capacity(count_max),
+        };
+        result.resolve_appearance();
+        result
+    }
+
+    /// Creates an emitter from params baked from a `<emitter_name>.particles.json` asset file
+    pub fn new_from_asset(emitter_name: &str, pos: Vec2) -> ParticleEmitter {
+        let params = get_assets()
+            .get_particle_emitter_params(emitter_name)
+            .clone();
+        let mut result = ParticleEmitter::new(params, pos);
+        result.params_resource_name = Some(emitter_name.to_owned());
+        result
+    }
+
+    fn resolve_appearance(&mut self) {
+        self.sprite = None;
+        self.animation = None;
+        match &self.params.appearance {
+            ParticleAppearance::Pixel => {}
+            ParticleAppearance::Sprite { sprite_name } => {
+                self.sprite = Some(get_assets().get_sprite(sprite_name).clone());
+            }
+            ParticleAppearance::Animation { animation_name, .. } => {
+                self.animation = Some(get_assets().get_anim(animation_name).clone());
+            }
+        }
+    }
+
+    fn reload_params_if_assets_changed(&mut self) {
+        let assets_generation = get_assets().get_graphics_generation();
+        if self.assets_generation == assets_generation {
+            return;
+        }
+        self.assets_generation = assets_generation;
+
+        if let Some(emitter_name) = &self.params_resource_name {
+            self.params = get_assets()
+                .get_particle_emitter_params(emitter_name)
+                .clone();
+            self.params.sort_keys_and_bursts();
+        }
+        // NOTE: We also need to do this for non-asset emitters as the sprites may have moved
+        //       inside the atlas textures
+        self.resolve_appearance();
+    }
+
+    pub fn count(&self) -> usize {
+        self.particle_pos.len()
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.is_emitting
+    }
+
+    /// Returns true if the emitter stopped emitting and all of its particles died
+    pub fn is_finished(&self) -> bool {
+        !self.is_emitting && self.particle_pos.is_empty()
+    }
+
+    pub fn move_to(&mut self, pos: Vec2) {
+        self.pos = pos;
+    }
+
+    pub fn start(&mut self) {
+        self.is_emitting = true;
+        self.emission_time = 0.0;
+        self.time_since_last_spawn = 0.0;
+        self.next_burst_index = 0;
+    }
+
+    pub fn stop(&mut self) {
+        self.is_emitting = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.particle_pos.clear();
+        self.particle_vel.clear();
+        self.particle_age.clear();
+        self.particle_lifetime.clear();
+        self.particle_scale.clear();
+        self.particle_rotation.clear();
+        self.particle_angular_vel.clear();
+    }
+
+    /// Spawns the given number of particles immediately, respecting `count_max`
+    pub fn burst(&mut self, random: &mut Random, count: usize) {
+        for _ in 0..count {
+            self.spawn_particle(random);
+        }
+    }
+
+    fn spawn_particle(&mut self, random: &mut Random) {
+        if self.particle_pos.len() >= self.params.count_max {
+            return;
+        }
+
+        let params = &self.params;
+        let spawn_offset = params.emission_shape.sample_offset(random);
+        let dir = if params.direction_outward && !spawn_offset.is_effectively_zero() {
+            spawn_offset.normalized()
+        } else {
+            let spread_half = 0.5 * params.direction_spread;
+            let angle = params.direction_angle + random.f32_in_range(-spread_half, spread_half);
+            Vec2::from_angle_flipped_y(angle)
+        };
+        let speed = random.f32_in_range(params.speed_min, params.speed_max);
+        let (pos, vel) = match params.simulation_space {
+            ParticleSimulationSpace::World => (
+                self.pos + spawn_offset.rotated_flipped_y(self.dir_angle),
+                speed * dir.rotated_flipped_y(self.dir_angle),
+            ),
+            ParticleSimulationSpace::Local => (spawn_offset, speed * dir),
+        };
+
+        self.particle_pos.push(pos);
+        self.particle_vel.push(vel);
+        self.particle_age.push(0.0);
+        self.particle_lifetime
+            .push(random.f32_in_range(params.lifetime_min, params.lifetime_max));
+        self.particle_scale
+            .push(random.f32_in_range(params.scale_min, params.scale_max));
+        self.particle_rotation
+            .push(random.f32_in_range(params.rotation_min, params.rotation_max));
+        self.particle_angular_vel
+            .push(random.f32_in_range(params.angular_vel_min, params.angular_vel_max));
+    }
+
+    pub fn update_and_draw(
+        &mut self,
+        random: &mut Random,
+        deltatime: f32,
+        depth: f32,
+        drawspace: Drawspace,
+    ) {
+        self.update(random, deltatime);
+        self.draw(depth, drawspace);
+    }
+
+    pub fn update(&mut self, random: &mut Random, deltatime: f32) {
+        self.reload_params_if_assets_changed();
+
+        // Simulate
+        for index in 0..self.particle_pos.len() {
+            let age_percentage = self.particle_age[index] / self.particle_lifetime[index];
+            let speed_factor = self.params.speed_over_lifetime.value_at(age_percentage);
+
+            let mut vel = self.particle_vel[index] + self.params.gravity * deltatime;
+            vel *= f32::max(0.0, 1.0 - self.params.drag * deltatime);
+            vel = vel.clamped_abs(self.params.vel_max);
+
+            self.particle_vel[index] = vel;
+            self.particle_pos[index] += speed_factor * vel * deltatime;
+            self.particle_rotation[index] += self.particle_angular_vel[index] * deltatime;
+        }
+
+        // Remove old
+        for index in (0..self.particle_pos.len()).rev() {
+            self.particle_age[index] += deltatime;
+            if self.particle_age[index] > self.particle_lifetime[index] {
+                self.particle_pos.swap_remove(index);
+                self.particle_vel.swap_remove(index);
+                self.particle_age.swap_remove(index);
+                self.particle_lifetime.swap_remove(index);
+                self.particle_scale.swap_remove(index);
+                self.particle_rotation.swap_remove(index);
+                self.particle_angular_vel.swap_remove(index);
+            }
+        }
+
+        if !self.is_emitting {
+            return;
+        }
+
+        // Spawn new
+        self.emission_time += deltatime;
+        self.time_since_last_spawn += deltatime;
+
+        if self.params.spawn_rate > 0.0 {
+            let time_between_spawns = 1.0 / self.params.spawn_rate;
+            while self.time_since_last_spawn >= time_between_spawns {
+                self.time_since_last_spawn -= time_between_spawns;
+                self.spawn_particle(random);
+            }
+        }
+
+        // NOTE: A long frame can span multiple emission cycles. In this case we fire the bursts of
+        //       every cycle that we passed.
+        loop {
+            while let Some(burst) = self.params.bursts.get(self.next_burst_index).cloned() {
+                if burst.time > self.emission_time {
+                    break;
+                }
+                self.next_burst_index += 1;
+                self.burst(random, burst.count);
+            }
+
+            if self.emission_time < self.params.duration {
+                break;
+            }
+            if !self.params.looping {
+                self.is_emitting = false;
+                break;
+            }
+
+            self.next_burst_index = 0;
+            if self.params.duration <= 0.0 {
+                self.emission_time = 0.0;
+                break;
+            }
+            self.emission_time -= self.params.duration;
+        }
+    }
+
+    pub fn draw(&self, depth: f32, drawspace: Drawspace) {
+        for index in 0..self.particle_pos.len() {
+            let age = self.particle_age[index];
+            let age_percentage = age / self.particle_lifetime[index];
+            let scale = self.particle_scale[index]
+                * self.params.scale_over_lifetime.value_at(age_percentage);
+            let color = self.params.color_over_lifetime.value_at(age_percentage);
+            let additivity = self
+                .params
+                .additivity_over_lifetime
+                .value_at(age_percentage);
+            let drawparams = Drawparams::new(depth, color, additivity, drawspace);
+
+            let (pos, vel, rotation) = match self.params.simulation_space {
+                ParticleSimulationSpace::World => (
+                    self.particle_pos[index],
+                    self.particle_vel[index],
+                    self.particle_rotation[index],
+                ),
+                ParticleSimulationSpace::Local => (
+                    self.pos + self.particle_pos[index].rotated_flipped_y(self.dir_angle),
+                    self.particle_vel[index].rotated_flipped_y(self.dir_angle),
+                    self.particle_rotation[index] + self.dir_angle,
+                ),
+            };
+            let rotation = if self.params.align_to_velocity && !vel.is_effectively_zero() {
+                vel.to_angle_flipped_y()
+            } else {
+                rotation
+            };
+
+            let sprite = if let Some(sprite) = &self.sprite {
+                Some(sprite)
+            } else if let Some(animation) = &self.animation {
+                let stretch_over_lifetime = match self.params.appearance {
+                    ParticleAppearance::Animation {
+                        stretch_over_lifetime,
+                        ..
+                    } => stretch_over_lifetime,
+                    _ => false,
+                };
+                if stretch_over_lifetime {
+                    Some(animation.frame_at_percentage(age_percentage))
+                } else {
+                    Some(animation.frame_at_time(age, true))
+                }
+            } else {
+                None
+            };
+
+            if let Some(sprite) = sprite {
+                draw_sprite(
+                    sprite,
+                    Transform::from_pos_scale_angle(pos, Vec2::filled(scale), rotation),
+                    false,
+                    false,
+                    drawparams,
+                );
+            } else {
+                let pos = pos.pixel_snapped();
+                if scale > 1.0 {
+                    draw_rect_transformed(
+                        Vec2::ones(),
+                        true,
+                        true,
+                        Vec2::zero(),
+                        Transform::from_pos_scale_angle(pos, Vec2::filled(scale), rotation),
+                        drawparams,
+                    );
+                } else {
+                    draw_pixel(pos, drawparams);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1120 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Aseprite file parser
+//
+// Reads `.ase`/`.aseprite` files directly without needing an Aseprite installation.
+// Format specification: https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+
+use super::bitmap::*;
+use super::color::*;
+use super::math::*;
+
+use super::core::log;
+use super::core::*;
+
+const ASEPRITE_HEADER_MAGIC: u16 = 0xA5E0;
+const ASEPRITE_FRAME_MAGIC: u16 = 0xF1FA;
+
+const ASEPRITE_CHUNK_TYPE_PALETTE_OLD: u16 = 0x0004;
+const ASEPRITE_CHUNK_TYPE_LAYER: u16 = 0x2004;
+const ASEPRITE_CHUNK_TYPE_CEL: u16 = 0x2005;
+const ASEPRITE_CHUNK_TYPE_TAGS: u16 = 0x2018;
+const ASEPRITE_CHUNK_TYPE_PALETTE: u16 = 0x2019;
+const ASEPRITE_CHUNK_TYPE_USER_DATA: u16 = 0x2020;
+const ASEPRITE_CHUNK_TYPE_SLICE: u16 = 0x2022;
+
+const ASEPRITE_HEADER_FLAG_LAYER_OPACITY_VALID: u32 = 1;
+
+const ASEPRITE_LAYER_FLAG_VISIBLE: u16 = 1;
+const ASEPRITE_LAYER_FLAG_BACKGROUND: u16 = 8;
+const ASEPRITE_LAYER_FLAG_REFERENCE: u16 = 64;
+
+const ASEPRITE_CEL_TYPE_RAW: u16 = 0;
+const ASEPRITE_CEL_TYPE_LINKED: u16 = 1;
+const ASEPRITE_CEL_TYPE_COMPRESSED_IMAGE: u16 = 2;
+const ASEPRITE_CEL_TYPE_COMPRESSED_TILEMAP: u16 = 3;
+
+const ASEPRITE_USER_DATA_FLAG_HAS_TEXT: u32 = 1;
+const ASEPRITE_USER_DATA_FLAG_HAS_COLOR: u32 = 2;
+
+const ASEPRITE_SLICE_FLAG_NINE_PATCH: u32 = 1;
+const ASEPRITE_SLICE_FLAG_HAS_PIVOT: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsepriteColorMode {
+    Rgba,
+    Grayscale,
+    Indexed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsepriteLayerKind {
+    Normal,
+    Group,
+    Tilemap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsepriteTagDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsepriteUserData {
+    pub text: Option<String>,
+    pub color: Option<PixelRGBA>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteLayer {
+    pub name: String,
+    pub kind: AsepriteLayerKind,
+    pub is_visible: bool,
+    pub is_background: bool,
+    /// Reference layers are never part of the rendered output
+    pub is_reference: bool,
+    pub child_level: u16,
+    /// Index of the group layer that contains this layer
+    pub parent_index: Option<usize>,
+    pub blend_mode: ColorBlendMode,
+    pub opacity: u8,
+    pub user_data: AsepriteUserData,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteCel {
+    pub layer_index: usize,
+    pub pos: Vec2i,
+    pub opacity: u8,
+    pub z_index: i16,
+    /// NOTE: This is not premultiplied
+    pub image: Bitmap,
+    pub user_data: AsepriteUserData,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteFrame {
+    pub duration_ms: u32,
+    pub cels: Vec<AsepriteCel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub frame_index_from: usize,
+    /// NOTE: This is inclusive
+    pub frame_index_to: usize,
+    pub direction: AsepriteTagDirection,
+    /// Zero means infinite repetitions
+    pub repeat_count: u16,
+    pub user_data: AsepriteUserData,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteSliceKey {
+    /// The key is valid from this frame onwards until the next key
+    pub frame_index: usize,
+    pub rect: Recti,
+    /// Nine-patch center rect relative to `rect`
+    pub center: Option<Recti>,
+    /// Pivot relative to `rect`
+    pub pivot: Option<Vec2i>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteSlice {
+    pub name: String,
+    pub keys: Vec<AsepriteSliceKey>,
+    pub user_data: AsepriteUserData,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteFile {
+    pub width: u32,
+    pub height: u32,
+    pub color_mode: AsepriteColorMode,
+    /// Palette index that is treated as transparent in non-background layers of indexed images
+    pub transparent_index: u8,
+    pub palette: Vec<PixelRGBA>,
+    pub layers: Vec<AsepriteLayer>,
+    pub frames: Vec<AsepriteFrame>,
+    pub tags: Vec<AsepriteTag>,
+    pub slices: Vec<AsepriteSlice>,
+    pub user_data: AsepriteUserData,
+}
+
+/// Determines which object the next user data chunk belongs to
+#[derive(Clone, Copy)]
+enum UserDataTarget {
+    None,
+    Sprite,
+    Layer(usize),
+    Cel(usize),
+    Tag(usize),
+    Slice(usize),
+}
+
+impl AsepriteFile {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(filepath: &str) -> Result<AsepriteFile, String> {
+        let file_content = read_file_whole(filepath)
+            .map_err(|error| format!("Could not open aseprite file '{}': {}", filepath, error))?;
+        AsepriteFile::from_bytes(&file_content)
+            .map_err(|error| format!("Could not parse aseprite file '{}': {}", filepath, error))
+    }
+
+    /// Creates a single layer and single frame image from a given (non-premultiplied) bitmap.
+    /// This is useful to treat plain images the same way as aseprite files.
+    pub fn from_bitmap(bitmap: &Bitmap) -> AsepriteFile {
+        AsepriteFile::from_frames(std::slice::from_ref(bitmap), &[100])
+    }
+
+    /// Creates a single layer image from given (non-premultiplied) equally sized frames. This is
+    /// useful to treat animated images like gifs the same way as aseprite files.
+    pub fn from_frames(frames: &[Bitmap], frame_durations_ms: &[u32]) -> AsepriteFile {
+        assert!(!frames.is_empty());
+        assert_eq!(frames.len(), frame_durations_ms.len());
+        let width = frames[0].width;
+        let height = frames[0].height;
+        assert!(frames
+            .iter()
+            .all(|frame| frame.width == width && frame.height == height));
+
+        AsepriteFile {
+            width: width as u32,
+            height: height as u32,
+            color_mode: AsepriteColorMode::Rgba,
+            transparent_index: 0,
+            palette: Vec::new(),
+            layers: vec![AsepriteLayer {
+                name: "Layer 1".to_owned(),
+                kind: AsepriteLayerKind::Normal,
+                is_visible: true,
+                is_background: false,
+                is_reference: false,
+                child_level: 0,
+                parent_index: None,
+                blend_mode: ColorBlendMode::Normal,
+                opacity: 255,
+                user_data: AsepriteUserData::default(),
+            }],
+            frames: frames
+                .iter()
+                .zip(frame_durations_ms.iter())
+                .map(|(frame, &duration_ms)| AsepriteFrame {
+                    duration_ms,
+                    cels: vec![AsepriteCel {
+                        layer_index: 0,
+                        pos: Vec2i::zero(),
+                        opacity: 255,
+                        z_index: 0,
+                        image: frame.clone(),
+                        user_data: AsepriteUserData::default(),
+                    }],
+                })
+                .collect(),
+            tags: Vec::new(),
+            slices: Vec::new(),
+            user_data: AsepriteUserData::default(),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<AsepriteFile, String> {
+        let mut reader = ByteReader::new(bytes);
+
+        // Header
+        let _file_size = reader.read_u32()?;
+        let magic = reader.read_u16()?;
+        if magic != ASEPRITE_HEADER_MAGIC {
+            return Err(format!("Invalid header magic number 0x{:X}", magic));
+        }
+        let frame_count = reader.read_u16()? as usize;
+        let width = reader.read_u16()? as u32;
+        let height = reader.read_u16()? as u32;
+        let color_depth = reader.read_u16()?;
+        let color_mode = match color_depth {
+            32 => AsepriteColorMode::Rgba,
+            16 => AsepriteColorMode::Grayscale,
+            8 => AsepriteColorMode::Indexed,
+            _ => return Err(format!("Unsupported color depth {}", color_depth)),
+        };
+        let header_flags = reader.read_u32()?;
+        let _speed_deprecated = reader.read_u16()?;
+        reader.skip(4 + 4)?;
+        let transparent_index = reader.read_u8()?;
+        reader.skip(3)?;
+        let _color_count = reader.read_u16()?;
+        // NOTE: We skip pixel ratio, grid and reserved bytes
+        reader.skip(1 + 1 + 2 + 2 + 2 + 2 + 84)?;
+
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid image dimensions {}x{}", width, height));
+        }
+
+        let mut result = AsepriteFile {
+            width,
+            height,
+            color_mode,
+            transparent_index,
+            palette: Vec::new(),
+            layers: Vec::new(),
+            frames: Vec::with_capacity(frame_count),
+            tags: Vec::new(),
+            slices: Vec::new(),
+            user_data: AsepriteUserData::default(),
+        };
+        let layer_opacity_valid = header_flags & ASEPRITE_HEADER_FLAG_LAYER_OPACITY_VALID != 0;
+        let mut has_new_palette = false;
+
+        for frame_index in 0..frame_count {
+            let frame_start = reader.pos;
+            let frame_size = reader.read_u32()? as usize;
+            let magic = reader.read_u16()?;
+            if magic != ASEPRITE_FRAME_MAGIC {
+                return Err(format!(
+                    "Invalid frame magic number 0x{:X} in frame {}",
+                    magic, frame_index
+                ));
+            }
+            let chunk_count_old = reader.read_u16()? as usize;
+            let duration_ms = reader.read_u16()? as u32;
+            reader.skip(2)?;
+            let chunk_count_new = reader.read_u32()? as usize;
+            let chunk_count = if chunk_count_new == 0 {
+                chunk_count_old
+            } else {
+                chunk_count_new
+            };
+
+            result.frames.push(AsepriteFrame {
+                duration_ms,
+                cels: Vec::new(),
+            });
+
+            // NOTE: The first user data chunk in the first frame that does not follow a layer,
+            //       cel, tags or slice chunk belongs to the sprite itself
+            let mut user_data_target = if frame_index == 0 {
+                UserDataTarget::Sprite
+            } else {
+                UserDataTarget::None
+            };
+
+            for _ in 0..chunk_count {
+                let chunk_start = reader.pos;
+                let chunk_size = reader.read_u32()? as usize;
+                let chunk_type = reader.read_u16()?;
+                if chunk_size < 6 {
+                    return Err(format!(
+                        "Invalid chunk size {} in frame {}",
+                        chunk_size, frame_index
+                    ));
+                }
+                let chunk_data = reader.read_bytes(chunk_size - 6)?;
+                let mut chunk = ByteReader::new(chunk_data);
+
+                match chunk_type {
+                    ASEPRITE_CHUNK_TYPE_PALETTE_OLD => {
+                        if !has_new_palette {
+                            result.read_chunk_palette_old(&mut chunk)?;
+                        }
+                        user_data_target = UserDataTarget::Sprite;
+                    }
+                    ASEPRITE_CHUNK_TYPE_PALETTE => {
+                        has_new_palette = true;
+                        result.read_chunk_palette(&mut chunk)?;
+                        user_data_target = UserDataTarget::Sprite;
+                    }
+                    ASEPRITE_CHUNK_TYPE_LAYER => {
+                        result.read_chunk_layer(&mut chunk, layer_opacity_valid)?;
+                        user_data_target = UserDataTarget::Layer(result.layers.len() - 1);
+                    }
+                    ASEPRITE_CHUNK_TYPE_CEL => {
+                        result.read_chunk_cel(&mut chunk, frame_index)?;
+                        let cel_count = result.frames[frame_index].cels.len();
+                        user_data_target = UserDataTarget::Cel(cel_count - 1);
+                    }
+                    ASEPRITE_CHUNK_TYPE_TAGS => {
+                        let first_new_tag_index = result.tags.len();
+                        result.read_chunk_tags(&mut chunk)?;
+                        user_data_target = UserDataTarget::Tag(first_new_tag_index);
+                    }
+                    ASEPRITE_CHUNK_TYPE_SLICE => {
+                        result.read_chunk_slice(&mut chunk)?;
+                        user_data_target = UserDataTarget::Slice(result.slices.len() - 1);
+                    }
+                    ASEPRITE_CHUNK_TYPE_USER_DATA => {
+                        let user_data = read_chunk_user_data(&mut chunk)?;
+                        match user_data_target {
+                            UserDataTarget::None => {}
+                            UserDataTarget::Sprite => {
+                                result.user_data = user_data;
+                                user_data_target = UserDataTarget::None;
+                            }
+                            UserDataTarget::Layer(layer_index) => {
+                                result.layers[layer_index].user_data = user_data;
+                            }
+                            UserDataTarget::Cel(cel_index) => {
+                                result.frames[frame_index].cels[cel_index].user_data = user_data;
+                            }
+                            UserDataTarget::Tag(tag_index) => {
+                                // NOTE: The user data chunks following a tags chunk belong to
+                                //       the tags in the order they were listed
+                                if let Some(tag) = result.tags.get_mut(tag_index) {
+                                    tag.user_data = user_data;
+                                }
+                                user_data_target = UserDataTarget::Tag(tag_index + 1);
+                            }
+                            UserDataTarget::Slice(slice_index) => {
+                                result.slices[slice_index].user_data = user_data;
+                            }
+                        }
+                    }
+                    _ => {
+                        // NOTE: We ignore all other chunk types like color profiles, cel extras,
+                        //       external files and tilesets
+                    }
+                }
+
+                reader.pos = chunk_start + chunk_size;
+            }
+
+            reader.pos = frame_start + frame_size;
+        }
+
+        if result.frames.is_empty() {
+            return Err("File does not contain any frames".to_owned());
+        }
+
+        Ok(result)
+    }
+
+    fn read_chunk_palette_old(&mut self, chunk: &mut ByteReader) -> Result<(), String> {
+        let packet_count = chunk.read_u16()?;
+        let mut palette_index = 0;
+        for _ in 0..packet_count {
+            palette_index += chunk.read_u8()? as usize;
+            let color_count = match chunk.read_u8()? {
+                0 => 256,
+                count => count as usize,
+            };
+            for _ in 0..color_count {
+                let r = chunk.read_u8()?;
+                let g = chunk.read_u8()?;
+                let b = chunk.read_u8()?;
+                if self.palette.len() <= palette_index {
+                    self.palette
+                        .resize(palette_index + 1, PixelRGBA::transparent());
+                }
+                self.palette[palette_index] = PixelRGBA::new(r, g, b, 255);
+                palette_index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_chunk_palette(&mut self, chunk: &mut ByteReader) -> Result<(), String> {
+        let palette_size = chunk.read_u32()? as usize;
+        let index_first = chunk.read_u32()? as usize;
+        let index_last = chunk.read_u32()? as usize;
+        chunk.skip(8)?;
+
+        if index_first > index_last || index_last >= palette_size {
+            return Err(format!(
+                "Invalid palette range {}..{} for palette of size {}",
+                index_first, index_last, palette_size
+            ));
+        }
+
+        self.palette.resize(palette_size, PixelRGBA::transparent());
+        for palette_index in index_first..=index_last {
+            let entry_flags = chunk.read_u16()?;
+            let r = chunk.read_u8()?;
+            let g = chunk.read_u8()?;
+            let b = chunk.read_u8()?;
+            let a = chunk.read_u8()?;
+            if entry_flags & 1 != 0 {
+                let _color_name = chunk.read_string()?;
+            }
+            self.palette[palette_index] = PixelRGBA::new(r, g, b, a);
+        }
+        Ok(())
+    }
+
+    fn read_chunk_layer(
+        &mut self,
+        chunk: &mut ByteReader,
+        layer_opacity_valid: bool,
+    ) -> Result<(), String> {
+        let flags = chunk.read_u16()?;
+        let kind = match chunk.read_u16()? {
+            0 => AsepriteLayerKind::Normal,
+            1 => AsepriteLayerKind::Group,
+            2 => AsepriteLayerKind::Tilemap,
+            kind => return Err(format!("Unknown layer type {}", kind)),
+        };
+        let child_level = chunk.read_u16()?;
+        let _default_width = chunk.read_u16()?;
+        let _default_height = chunk.read_u16()?;
+        let blend_mode_raw = chunk.read_u16()?;
+        let opacity = chunk.read_u8()?;
+        chunk.skip(3)?;
+        let name = chunk.read_string()?;
+
+        let blend_mode = blend_mode_from_aseprite(blend_mode_raw, &name);
+        let parent_index = if child_level == 0 {
+            None
+        } else {
+            self.layers
+                .iter()
+                .rposition(|layer| layer.child_level == child_level - 1)
+        };
+
+        self.layers.push(AsepriteLayer {
+            name,
+            kind,
+            is_visible: flags & ASEPRITE_LAYER_FLAG_VISIBLE != 0,
+            is_background: flags & ASEPRITE_LAYER_FLAG_BACKGROUND != 0,
+            is_reference: flags & ASEPRITE_LAYER_FLAG_REFERENCE != 0,
+            child_level,
+            parent_index,
+            blend_mode,
+            opacity: if layer_opacity_valid { opacity } else { 255 },
+            user_data: AsepriteUserData::default(),
+        });
+        Ok(())
+    }
+
+    fn read_chunk_cel(&mut self, chunk: &mut ByteReader, frame_index: usize) -> Result<(), String> {
+        let layer_index = chunk.read_u16()? as usize;
+        let pos_x = chunk.read_i16()? as i32;
+        let pos_y = chunk.read_i16()? as i32;
+        let opacity = chunk.read_u8()?;
+        let cel_type = chunk.read_u16()?;
+        let z_index = chunk.read_i16()?;
+        chunk.skip(5)?;
+
+        if layer_index >= self.layers.len() {
+            return Err(format!(
+                "Cel in frame {} references unknown layer {}",
+                frame_index, layer_index
+            ));
+        }
+
+        let image = match cel_type {
+            ASEPRITE_CEL_TYPE_RAW | ASEPRITE_CEL_TYPE_COMPRESSED_IMAGE => {
+                let width = chunk.read_u16()? as u32;
+                let height = chunk.read_u16()? as u32;
+                let pixel_data_raw = chunk.read_remaining();
+                let pixel_data = if cel_type == ASEPRITE_CEL_TYPE_COMPRESSED_IMAGE {
+                    miniz_oxide::inflate::decompress_to_vec_zlib(pixel_data_raw).map_err(
+                        |error| {
+                            format!(
+                                "Could not decompress cel of layer {} in frame {}: {:?}",
+                                layer_index, frame_index, error
+                            )
+                        },
+                    )?
+                } else {
+                    pixel_data_raw.to_vec()
+                };
+                self.decode_cel_pixels(&pixel_data, width, height, layer_index)?
+            }
+            ASEPRITE_CEL_TYPE_LINKED => {
+                let linked_frame_index = chunk.read_u16()? as usize;
+                self.frames
+                    .get(linked_frame_index)
+                    .and_then(|frame| frame.cels.iter().find(|cel| cel.layer_index == layer_index))
+                    .map(|cel| cel.image.clone())
+                    .ok_or_else(|| {
+                        format!(
+                            "Cel of layer {} in frame {} is linked to missing cel in frame {}",
+                            layer_index, frame_index, linked_frame_index
+                        )
+                    })?
+            }
+            ASEPRITE_CEL_TYPE_COMPRESSED_TILEMAP => {
+                return Err(format!(
+                    "Tilemap cels are not supported (layer '{}' in frame {})",
+                    self.layers[layer_index].name, frame_index
+                ))
+            }
+            _ => return Err(format!("Unknown cel type {}", cel_type)),
+        };
+
+        self.frames[frame_index].cels.push(AsepriteCel {
+            layer_index,
+            pos: Vec2i::new(pos_x, pos_y),
+            opacity,
+            z_index,
+            image,
+            user_data: AsepriteUserData::default(),
+        });
+        Ok(())
+    }
+
+    fn decode_cel_pixels(
+        &self,
+        pixel_data: &[u8],
+        width: u32,
+        height: u32,
+        layer_index: usize,
+    ) -> Result<Bitmap, String> {
+        if width == 0 || height == 0 {
+            return Ok(Bitmap::new_empty());
+        }
+
+        let pixel_size_bytes = match self.color_mode {
+            AsepriteColorMode::Rgba => 4,
+            AsepriteColorMode::Grayscale => 2,
+            AsepriteColorMode::Indexed => 1,
+        };
+        let size_bytes = pixel_size_bytes * (width * height) as usize;
+        if pixel_data.len() < size_bytes {
+            return Err(format!(
+                "Cel of layer {} has {} bytes of pixel data - expected {}",
+                layer_index,
+                pixel_data.len(),
+                size_bytes
+            ));
+        }
+
+        let pixels: Vec<PixelRGBA> = match self.color_mode {
+            AsepriteColorMode::Rgba => pixel_data[..size_bytes]
+                .chunks_exact(4)
+                .map(|rgba| PixelRGBA::new(rgba[0], rgba[1], rgba[2], rgba[3]))
+                .collect(),
+            AsepriteColorMode::Grayscale => pixel_data[..size_bytes]
+                .chunks_exact(2)
+                .map(|va| PixelRGBA::new(va[0], va[0], va[0], va[1]))
+                .collect(),
+            AsepriteColorMode::Indexed => {
+                // NOTE: Background layers are opaque so they ignore the transparent index
+                let is_background = self.layers[layer_index].is_background;
+                pixel_data[..size_bytes]
+                    .iter()
+                    .map(|&palette_index| {
+                        if palette_index == self.transparent_index && !is_background {
+                            PixelRGBA::transparent()
+                        } else {
+                            self.palette
+                                .get(palette_index as usize)
+                                .cloned()
+                                .unwrap_or(PixelRGBA::transparent())
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(Bitmap::new_from_buffer(width, height, pixels))
+    }
+
+    fn read_chunk_tags(&mut self, chunk: &mut ByteReader) -> Result<(), String> {
+        let tag_count = chunk.read_u16()?;
+        chunk.skip(8)?;
+        for _ in 0..tag_count {
+            let frame_index_from = chunk.read_u16()? as usize;
+            let frame_index_to = chunk.read_u16()? as usize;
+            let direction = match chunk.read_u8()? {
+                0 => AsepriteTagDirection::Forward,
+                1 => AsepriteTagDirection::Reverse,
+                2 => AsepriteTagDirection::PingPong,
+                3 => AsepriteTagDirection::PingPongReverse,
+                direction => return Err(format!("Unknown tag direction {}", direction)),
+            };
+            let repeat_count = chunk.read_u16()?;
+            // NOTE: We skip reserved bytes and the deprecated tag color
+            chunk.skip(6 + 3 + 1)?;
+            let name = chunk.read_string()?;
+
+            self.tags.push(AsepriteTag {
+                name,
+                frame_index_from,
+                frame_index_to,
+                direction,
+                repeat_count,
+                user_data: AsepriteUserData::default(),
+            });
+        }
+        Ok(())
+    }
+
+    fn read_chunk_slice(&mut self, chunk: &mut ByteReader) -> Result<(), String> {
+        let key_count = chunk.read_u32()?;
+        let flags = chunk.read_u32()?;
+        chunk.skip(4)?;
+        let name = chunk.read_string()?;
+
+        let mut keys = Vec::new();
+        for _ in 0..key_count {
+            let frame_index = chunk.read_u32()? as usize;
+            let rect = Recti::from_xy_width_height(
+                chunk.read_i32()?,
+                chunk.read_i32()?,
+                chunk.read_u32()? as i32,
+                chunk.read_u32()? as i32,
+            );
+            let center = if flags & ASEPRITE_SLICE_FLAG_NINE_PATCH != 0 {
+                Some(Recti::from_xy_width_height(
+                    chunk.read_i32()?,
+                    chunk.read_i32()?,
+                    chunk.read_u32()? as i32,
+                    chunk.read_u32()? as i32,
+                ))
+            } else {
+                None
+            };
+            let pivot = if flags & ASEPRITE_SLICE_FLAG_HAS_PIVOT != 0 {
+                Some(Vec2i::new(chunk.read_i32()?, chunk.read_i32()?))
+            } else {
+                None
+            };
+            keys.push(AsepriteSliceKey {
+                frame_index,
+                rect,
+                center,
+                pivot,
+            });
+        }
+
+        self.slices.push(AsepriteSlice {
+            name,
+            keys,
+            user_data: AsepriteUserData::default(),
+        });
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn find_layer_index(&self, layer_name: &str) -> Option<usize> {
+        self.layers
+            .iter()
+            .position(|layer| layer.name == layer_name)
+    }
+
+    /// Returns true if the layer and all of its parent groups are visible
+    pub fn layer_is_visible(&self, layer_index: usize) -> bool {
+        let layer = &self.layers[layer_index];
+        if !layer.is_visible {
+            return false;
+        }
+        if let Some(parent_index) = layer.parent_index {
+            self.layer_is_visible(parent_index)
+        } else {
+            true
+        }
+    }
+
+    /// Composites all visible layers of the given frame. Returns a non-premultiplied bitmap with
+    /// the dimensions of the whole sprite.
+    pub fn render_frame(&self, frame_index: usize) -> Bitmap {
+        self.render_frame_filtered(frame_index, false, |_layer| true)
+    }
+
+    /// Composites all layers of the given frame for which `include_layer` returns true. If
+    /// `ignore_visibility` is set, hidden layers are rendered too. Returns a non-premultiplied
+    /// bitmap with the dimensions of the whole sprite.
+    pub fn render_frame_filtered<F: Fn(&AsepriteLayer) -> bool>(
+        &self,
+        frame_index: usize,
+        ignore_visibility: bool,
+        include_layer: F,
+    ) -> Bitmap {
+        let mut cels: Vec<&AsepriteCel> = self.frames[frame_index]
+            .cels
+            .iter()
+            .filter(|cel| {
+                let layer = &self.layers[cel.layer_index];
+                layer.kind == AsepriteLayerKind::Normal
+                    && !layer.is_reference
+                    && !cel.image.is_empty()
+                    && (ignore_visibility || self.layer_is_visible(cel.layer_index))
+                    && include_layer(layer)
+            })
+            .collect();
+
+        // NOTE: Aseprite orders cels by their layer index offset by their z-index. Ties are
+        //       resolved by the z-index itself.
+        cels.sort_by_key(|cel| (cel.layer_index as i32 + cel.z_index as i32, cel.z_index));
+
+        let mut result = Bitmap::new(self.width, self.height);
+        for cel in cels {
+            let layer = &self.layers[cel.layer_index];
+            let opacity = (cel.opacity as u32 * layer.opacity as u32) / 255;
+            if opacity == 0 {
+                continue;
+            }
+
+            let mut cel_image = cel.image.to_premultiplied_alpha();
+            if opacity < 255 {
+                for pixel in cel_image.data.iter_mut() {
+                    pixel.r = ((pixel.r as u32 * opacity) / 255) as u8;
+                    pixel.g = ((pixel.g as u32 * opacity) / 255) as u8;
+                    pixel.b = ((pixel.b as u32 * opacity) / 255) as u8;
+                    pixel.a = ((pixel.a as u32 * opacity) / 255) as u8;
+                }
+            }
+            cel_image.blit_to_alpha_blended_premultiplied(
+                &mut result,
+                cel.pos,
+                true,
+                layer.blend_mode,
+            );
+        }
+
+        result.unpremultiply_alpha();
+        for pixel in result.data.iter_mut() {
+            if pixel.a == 0 {
+                *pixel = PixelRGBA::transparent();
+            }
+        }
+        result
+    }
+}
+
+fn blend_mode_from_aseprite(blend_mode_raw: u16, layer_name: &str) -> ColorBlendMode {
+    match blend_mode_raw {
+        0 => ColorBlendMode::Normal,
+        1 => ColorBlendMode::Multiply,
+        2 => ColorBlendMode::Screen,
+        3 => ColorBlendMode::Overlay,
+        4 => ColorBlendMode::Darken,
+        5 => ColorBlendMode::Lighten,
+        6 => ColorBlendMode::ColorDodge,
+        7 => ColorBlendMode::ColorBurn,
+        8 => ColorBlendMode::HardLight,
+        9 => ColorBlendMode::SoftLight,
+        12 => ColorBlendMode::Hue,
+        13 => ColorBlendMode::Saturation,
+        14 => ColorBlendMode::Color,
+        15 => ColorBlendMode::Luminosity,
+        _ => {
+            // NOTE: Difference, Exclusion, Addition, Subtract and Divide have no equivalent in
+            //       `ColorBlendMode` yet
+            log::warn!(
+                "Layer '{}' uses unsupported blend mode {} - falling back to normal blending",
+                layer_name,
+                blend_mode_raw
+            );
+            ColorBlendMode::Normal
+        }
+    }
+}
+
+fn read_chunk_user_data(chunk: &mut ByteReader) -> Result<AsepriteUserData, String> {
+    let flags = chunk.read_u32()?;
+    let text = if flags & ASEPRITE_USER_DATA_FLAG_HAS_TEXT != 0 {
+        Some(chunk.read_string()?)
+    } else {
+        None
+    };
+    let color = if flags & ASEPRITE_USER_DATA_FLAG_HAS_COLOR != 0 {
+        let r = chunk.read_u8()?;
+        let g = chunk.read_u8()?;
+        let b = chunk.read_u8()?;
+        let a = chunk.read_u8()?;
+        Some(PixelRGBA::new(r, g, b, a))
+    } else {
+        None
+    };
+    // NOTE: We ignore user data properties
+    Ok(AsepriteUserData { text, color })
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Little endian byte reader
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { data, pos: 0 }
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.pos + count > self.data.len() {
+            return Err(format!(
+                "Unexpected end of data - tried to read {} bytes at offset {} of {}",
+                count,
+                self.pos,
+                self.data.len()
+            ));
+        }
+        let result = &self.data[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(result)
+    }
+
+    fn read_remaining(&mut self) -> &'a [u8] {
+        let result = &self.data[usize::min(self.pos, self.data.len())..];
+        self.pos = self.data.len();
+        result
+    }
+
+    #[inline]
+    fn skip(&mut self, count: usize) -> Result<(), String> {
+        self.read_bytes(count).map(|_| ())
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    #[inline]
+    fn read_u16(&mut self) -> Result<u16, String> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    #[inline]
+    fn read_i16(&mut self) -> Result<i16, String> {
+        let bytes = self.read_bytes(2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    #[inline]
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    #[inline]
+    fn read_i32(&mut self) -> Result<i32, String> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_string(&mut self) -> Result<String, String> {
+        let length = self.read_u16()? as usize;
+        let bytes = self.read_bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|error| format!("Invalid string: {}", error))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn push_string(buffer: &mut Vec<u8>, value: &str) {
+        push_u16(buffer, value.len() as u16);
+        buffer.extend_from_slice(value.as_bytes());
+    }
+
+    fn push_chunk(frame_chunks: &mut Vec<Vec<u8>>, chunk_type: u16, data: Vec<u8>) {
+        let mut chunk = Vec::new();
+        push_u32(&mut chunk, data.len() as u32 + 6);
+        push_u16(&mut chunk, chunk_type);
+        chunk.extend(data);
+        frame_chunks.push(chunk);
+    }
+
+    fn layer_chunk(name: &str, flags: u16, blend_mode: u16, opacity: u8) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_u16(&mut data, flags);
+        push_u16(&mut data, 0);
+        push_u16(&mut data, 0);
+        push_u16(&mut data, 0);
+        push_u16(&mut data, 0);
+        push_u16(&mut data, blend_mode);
+        data.push(opacity);
+        data.extend_from_slice(&[0; 3]);
+        push_string(&mut data, name);
+        data
+    }
+
+    fn cel_chunk(layer_index: u16, x: i16, y: i16, w: u16, h: u16, pixels: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_u16(&mut data, layer_index);
+        data.extend_from_slice(&x.to_le_bytes());
+        data.extend_from_slice(&y.to_le_bytes());
+        data.push(255);
+        push_u16(&mut data, ASEPRITE_CEL_TYPE_COMPRESSED_IMAGE);
+        data.extend_from_slice(&0i16.to_le_bytes());
+        data.extend_from_slice(&[0; 5]);
+        push_u16(&mut data, w);
+        push_u16(&mut data, h);
+        data.extend(miniz_oxide::deflate::compress_to_vec_zlib(pixels, 6));
+        data
+    }
+
+    fn linked_cel_chunk(layer_index: u16, linked_frame: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_u16(&mut data, layer_index);
+        data.extend_from_slice(&[0; 4]);
+        data.push(255);
+        push_u16(&mut data, ASEPRITE_CEL_TYPE_LINKED);
+        data.extend_from_slice(&[0; 7]);
+        push_u16(&mut data, linked_frame);
+        data
+    }
+
+    fn user_data_chunk(text: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_u32(&mut data, ASEPRITE_USER_DATA_FLAG_HAS_TEXT);
+        push_string(&mut data, text);
+        data
+    }
+
+    fn build_file(width: u16, height: u16, frames: Vec<(u16, Vec<Vec<u8>>)>) -> Vec<u8> {
+        let mut result = Vec::new();
+        push_u32(&mut result, 0);
+        push_u16(&mut result, ASEPRITE_HEADER_MAGIC);
+        push_u16(&mut result, frames.len() as u16);
+        push_u16(&mut result, width);
+        push_u16(&mut result, height);
+        push_u16(&mut result, 32);
+        push_u32(&mut result, ASEPRITE_HEADER_FLAG_LAYER_OPACITY_VALID);
+        result.resize(128, 0);
+
+        for (duration_ms, chunks) in frames {
+            let chunks_size: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+            push_u32(&mut result, 16 + chunks_size as u32);
+            push_u16(&mut result, ASEPRITE_FRAME_MAGIC);
+            push_u16(&mut result, chunks.len() as u16);
+            push_u16(&mut result, duration_ms);
+            push_u16(&mut result, 0);
+            push_u32(&mut result, chunks.len() as u32);
+            for chunk in chunks {
+                result.extend(chunk);
+            }
+        }
+        let file_size = result.len() as u32;
+        result[0..4].copy_from_slice(&file_size.to_le_bytes());
+        result
+    }
+
+    fn test_file() -> Vec<u8> {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+
+        let mut frame_0 = Vec::new();
+        push_chunk(
+            &mut frame_0,
+            ASEPRITE_CHUNK_TYPE_LAYER,
+            layer_chunk("body", ASEPRITE_LAYER_FLAG_VISIBLE, 0, 255),
+        );
+        push_chunk(
+            &mut frame_0,
+            ASEPRITE_CHUNK_TYPE_LAYER,
+            layer_chunk("pivot", 0, 0, 255),
+        );
+        {
+            let mut tags = Vec::new();
+            push_u16(&mut tags, 1);
+            tags.extend_from_slice(&[0; 8]);
+            push_u16(&mut tags, 0);
+            push_u16(&mut tags, 1);
+            tags.push(2);
+            push_u16(&mut tags, 0);
+            tags.extend_from_slice(&[0; 10]);
+            push_string(&mut tags, "walk");
+            push_chunk(&mut frame_0, ASEPRITE_CHUNK_TYPE_TAGS, tags);
+            push_chunk(
+                &mut frame_0,
+                ASEPRITE_CHUNK_TYPE_USER_DATA,
+                user_data_chunk("tagdata"),
+            );
+        }
+        let body_pixels: Vec<u8> = [red, red, red, blue].concat();
+        push_chunk(
+            &mut frame_0,
+            ASEPRITE_CHUNK_TYPE_CEL,
+            cel_chunk(0, 1, 1, 2, 2, &body_pixels),
+        );
+        push_chunk(
+            &mut frame_0,
+            ASEPRITE_CHUNK_TYPE_USER_DATA,
+            user_data_chunk("step"),
+        );
+        push_chunk(
+            &mut frame_0,
+            ASEPRITE_CHUNK_TYPE_CEL,
+            cel_chunk(1, 3, 0, 1, 1, &red),
+        );
+
+        let mut frame_1 = Vec::new();
+        push_chunk(
+            &mut frame_1,
+            ASEPRITE_CHUNK_TYPE_CEL,
+            linked_cel_chunk(0, 0),
+        );
+
+        build_file(4, 4, vec![(100, frame_0), (50, frame_1)])
+    }
+
+    #[test]
+    fn parse_layers_frames_and_tags() {
+        let file = AsepriteFile::from_bytes(&test_file()).unwrap();
+        assert_eq!(file.width, 4);
+        assert_eq!(file.height, 4);
+        assert_eq!(file.layers.len(), 2);
+        assert_eq!(file.layers[0].name, "body");
+        assert!(file.layers[0].is_visible);
+        assert!(!file.layers[1].is_visible);
+        assert_eq!(file.frames.len(), 2);
+        assert_eq!(file.frames[0].duration_ms, 100);
+        assert_eq!(file.frames[1].duration_ms, 50);
+        assert_eq!(
+            file.frames[0].cels[0].user_data.text.as_deref(),
+            Some("step")
+        );
+        assert_eq!(file.frames[1].cels[0].image, file.frames[0].cels[0].image);
+
+        assert_eq!(file.tags.len(), 1);
+        assert_eq!(file.tags[0].name, "walk");
+        assert_eq!(file.tags[0].frame_index_to, 1);
+        assert_eq!(file.tags[0].direction, AsepriteTagDirection::PingPong);
+        assert_eq!(file.tags[0].user_data.text.as_deref(), Some("tagdata"));
+    }
+
+    #[test]
+    fn render_frame_skips_hidden_layers() {
+        let file = AsepriteFile::from_bytes(&test_file()).unwrap();
+
+        let frame = file.render_frame(0);
+        assert_eq!(frame.get(0, 0), PixelRGBA::transparent());
+        assert_eq!(frame.get(1, 1), PixelRGBA::new(255, 0, 0, 255));
+        assert_eq!(frame.get(2, 2), PixelRGBA::new(0, 0, 255, 255));
+        assert_eq!(frame.get(3, 0), PixelRGBA::transparent());
+        assert_eq!(
+            frame.get_trimming_rect_for_value(true, true, true, true, PixelRGBA::transparent()),
+            Some(Recti::from_xy_width_height(1, 1, 2, 2))
+        );
+
+        let pivot = file.render_frame_filtered(0, true, |layer| layer.name == "pivot");
+        assert_eq!(
+            pivot.get_trimming_rect_for_value(true, true, true, true, PixelRGBA::transparent()),
+            Some(Recti::from_xy_width_height(3, 0, 1, 1))
+        );
+    }
+
+    #[test]
+    fn reject_invalid_data() {
+        assert!(AsepriteFile::from_bytes(&[]).is_err());
+        let mut data = test_file();
+        data[4] = 0;
+        assert!(AsepriteFile::from_bytes(&data).is_err());
+        let data = test_file();
+        assert!(AsepriteFile::from_bytes(&data[..200]).is_err());
+    }
+}
@@ -1,19 +1,37 @@
+use crate::AsepriteFile;
 use crate::ColorBlendMode;
+use crate::MaxRectsPacker;
 
 pub use super::color::{Color, PixelRGBA};
 pub use super::font::{BitmapFont, Font, TextAlignment};
 pub use super::grid::GluePosition;
-pub use super::math::{AlignmentHorizontal, AlignmentVertical, Vec2i};
+pub use super::math::{AlignmentHorizontal, AlignmentVertical, Recti, Vec2i};
 
-use super::core::indexmap::IndexMap;
+use super::core::indexmap::{IndexMap, IndexSet};
 use super::core::serde_derive::Serialize;
 use super::core::*;
 use super::math;
 
-use rect_packer;
+use std::collections::HashMap;
 
 pub type Bitmap = super::grid::Grid<PixelRGBA>;
 
+/// Rounds a 16-bit color sample to the nearest 8-bit value
+#[inline]
+fn png_sample_16_bit_to_8_bit(sample: u16) -> u8 {
+    ((sample as u32 * 255 + 32767) / 65535) as u8
+}
+
+/// Returns the lowercase extension of an image filepath
+#[cfg(not(target_arch = "wasm32"))]
+fn bitmap_file_extension(filepath: &str) -> Result<String, String> {
+    std::path::Path::new(filepath)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .ok_or_else(|| format!("Image file '{}' has no extension", filepath))
+}
+
 impl Bitmap {
     pub fn as_bytes(&self) -> &[u8] {
         transmute_slice_to_byte_slice(&self.data)
@@ -61,22 +79,6 @@ impl Bitmap {
         result
     }
 
-    pub fn scale_sample_nearest_neighbor(&mut self, new_width: u32, new_height: u32) {
-        *self = self.scaled_sample_nearest_neighbor(new_width, new_height);
-    }
-
-    #[must_use]
-    pub fn scaled_sample_nearest_neighbor(&self, new_width: u32, new_height: u32) -> Bitmap {
-        assert!(new_width > 0);
-        assert!(new_height > 0);
-
-        let mut result = Bitmap::new(new_width, new_height);
-        let result_rect = result.rect();
-        Bitmap::copy_region_sample_nearest_neighbor(self, self.rect(), &mut result, result_rect);
-
-        result
-    }
-
     // This assumes `self` and `other` to be premultiplied
     #[must_use]
     pub fn masked_by_premultiplied_alpha(&self, mask: &Bitmap) -> Bitmap {
@@ -103,57 +105,18 @@ impl Bitmap {
         allow_partial_blit: bool,
         blend_mode: ColorBlendMode,
     ) {
-        match blend_mode {
-            ColorBlendMode::Normal => self.blit_to_with_function(
-                other,
-                pos,
-                allow_partial_blit,
-                |pixel_source, pixel_dest| {
-                    let color_source = pixel_source.to_color();
-                    let color_dest = pixel_dest.to_color();
-                    let color_result =
-                        Color::premultiplied_alpha_blend_normal(color_source, color_dest);
-                    *pixel_dest = color_result.to_pixelrgba();
-                },
-            ),
-            ColorBlendMode::Multiply => self.blit_to_with_function(
-                other,
-                pos,
-                allow_partial_blit,
-                |pixel_source, pixel_dest| {
-                    let color_source = pixel_source.to_color();
-                    let color_dest = pixel_dest.to_color();
-                    let color_result =
-                        Color::premultiplied_alpha_blend_multiply(color_source, color_dest);
-                    *pixel_dest = color_result.to_pixelrgba();
-                },
-            ),
-            ColorBlendMode::Screen => self.blit_to_with_function(
-                other,
-                pos,
-                allow_partial_blit,
-                |pixel_source, pixel_dest| {
-                    let color_source = pixel_source.to_color();
-                    let color_dest = pixel_dest.to_color();
-                    let color_result =
-                        Color::premultiplied_alpha_blend_screen(color_source, color_dest);
-                    *pixel_dest = color_result.to_pixelrgba();
-                },
-            ),
-            ColorBlendMode::Luminosity => self.blit_to_with_function(
-                other,
-                pos,
-                allow_partial_blit,
-                |pixel_source, pixel_dest| {
-                    let color_source = pixel_source.to_color();
-                    let color_dest = pixel_dest.to_color();
-                    let color_result =
-                        Color::premultiplied_alpha_blend_luminosity(color_source, color_dest);
-                    *pixel_dest = color_result.to_pixelrgba();
-                },
-            ),
-            _ => todo!(),
-        }
+        self.blit_to_with_function(
+            other,
+            pos,
+            allow_partial_blit,
+            |pixel_source, pixel_dest| {
+                let color_source = pixel_source.to_color();
+                let color_dest = pixel_dest.to_color();
+                let color_result =
+                    Color::premultiplied_alpha_blend(color_source, color_dest, blend_mode);
+                *pixel_dest = color_result.to_pixelrgba();
+            },
+        )
     }
 
     pub fn from_greyscale_bytes_premultiplied(bytes: &[u8], width: u32, height: u32) -> Bitmap {
@@ -172,33 +135,36 @@ impl Bitmap {
 
     pub fn from_png_data(png_data: &[u8]) -> Result<Bitmap, String> {
         let mut decoder = png::Decoder::new(std::io::Cursor::new(png_data));
+        // NOTE: This expands indexed images, bit depths below 8 and tRNS transparency for us so
+        //       that we only need to handle 8-bit and 16-bit Grayscale/GrayscaleAlpha/RGB/RGBA
         decoder.set_transformations(png::Transformations::EXPAND);
         let (png_info, mut png_reader) = decoder
             .read_info()
             .map_err(|error| format!("Could not read png data info: {}", error))?;
 
-        let pixel_size_bytes = match png_info.color_type {
+        let (color_type, bit_depth) = png_reader.output_color_type();
+        let channel_count = match color_type {
             png::ColorType::Grayscale => 1,
             png::ColorType::RGB => 3,
-            png::ColorType::Indexed => 1,
             png::ColorType::GrayscaleAlpha => 2,
             png::ColorType::RGBA => 4,
+            png::ColorType::Indexed => {
+                return Err("Could not expand indexed png data".to_owned());
+            }
         };
-
-        match png_info.bit_depth {
-            png::BitDepth::Eight => {}
+        match bit_depth {
+            png::BitDepth::Eight | png::BitDepth::Sixteen => {}
             _ => {
                 return Err(format!(
-                    "Currently only 8-Bit-per-pixel png data is supported - got {:?}",
-                    png_info.bit_depth
+                    "Could not expand png data with bit depth {:?} to 8 bits",
+                    bit_depth
                 ))
             }
         }
 
-        let size_bytes = pixel_size_bytes * png_info.width as usize * png_info.height as usize;
         let mut buffer =
             vec![PixelRGBA::transparent(); (png_info.width * png_info.height) as usize];
-        if png_info.color_type == png::ColorType::RGBA {
+        if color_type == png::ColorType::RGBA && bit_depth == png::BitDepth::Eight {
             // Fast path - we can directly cast our buffer into our target type
             let buffer_raw = super::core::transmute_slice_to_byte_slice_mut(&mut buffer);
             png_reader
@@ -206,39 +172,31 @@ impl Bitmap {
                 .map_err(|error| format!("Could not decode png data: {}", error))?;
         } else {
             // Slow path - needs conversion
-            let mut buffer_raw = vec![0u8; size_bytes];
+            let mut buffer_raw = vec![0u8; png_reader.output_buffer_size()];
             png_reader
                 .next_frame(&mut buffer_raw)
                 .map_err(|error| format!("Could not decode png data: {}", error))?;
-            match png_info.color_type {
-                png::ColorType::RGBA => unreachable!(),
-                png::ColorType::RGB => {
-                    for (rgb, pixel) in buffer_raw.chunks_exact(3).zip(buffer.iter_mut()) {
-                        pixel.r = rgb[0];
-                        pixel.g = rgb[1];
-                        pixel.b = rgb[2];
-                        pixel.a = 255;
-                    }
-                }
-                png::ColorType::Grayscale => {
-                    for (grey, pixel) in buffer_raw.into_iter().zip(buffer.iter_mut()) {
-                        pixel.r = grey;
-                        pixel.g = grey;
-                        pixel.b = grey;
-                        pixel.a = 255;
-                    }
-                }
-                png::ColorType::GrayscaleAlpha => {
-                    for (ga, pixel) in buffer_raw.chunks_exact(2).zip(buffer.iter_mut()) {
-                        pixel.r = ga[0];
-                        pixel.g = ga[0];
-                        pixel.b = ga[0];
-                        pixel.a = ga[1];
-                    }
-                }
-                png::ColorType::Indexed => {
-                    return Err("Currently indexed png data is not supported".to_owned());
-                }
+
+            let samples = if bit_depth == png::BitDepth::Sixteen {
+                // NOTE: 16-bit samples are stored in big endian order
+                buffer_raw
+                    .chunks_exact(2)
+                    .map(|sample| {
+                        png_sample_16_bit_to_8_bit(u16::from_be_bytes([sample[0], sample[1]]))
+                    })
+                    .collect()
+            } else {
+                buffer_raw
+            };
+
+            for (channels, pixel) in samples.chunks_exact(channel_count).zip(buffer.iter_mut()) {
+                *pixel = match channel_count {
+                    1 => PixelRGBA::new(channels[0], channels[0], channels[0], 255),
+                    2 => PixelRGBA::new(channels[0], channels[0], channels[0], channels[1]),
+                    3 => PixelRGBA::new(channels[0], channels[1], channels[2], 255),
+                    4 => PixelRGBA::new(channels[0], channels[1], channels[2], channels[3]),
+                    _ => unreachable!(),
+                };
             }
         };
 
@@ -261,6 +219,26 @@ impl Bitmap {
         Bitmap::from_png_file(png_filepath).expect("Failed to read png file")
     }
 
+    /// Loads an image file by dispatching on its extension. Supports png, qoi, tga, bmp, gif and
+    /// aseprite files. For animated formats only the first frame is returned.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(filepath: &str) -> Result<Bitmap, String> {
+        let extension = bitmap_file_extension(filepath)?;
+        let file_content = read_file_whole(filepath)
+            .map_err(|error| format!("Could not open image file '{}': {}", filepath, error))?;
+        let result = match extension.as_str() {
+            "png" => Bitmap::from_png_data(&file_content),
+            "qoi" => Bitmap::from_qoi_data(&file_content),
+            "tga" => Bitmap::from_tga_data(&file_content),
+            "bmp" => Bitmap::from_bmp_data(&file_content),
+            "gif" => Bitmap::from_gif_data(&file_content),
+            "ase" | "aseprite" => AsepriteFile::from_bytes(&file_content)
+                .map(|aseprite_file| aseprite_file.render_frame(0)),
+            _ => Err(format!("Image format '{}' is not supported", extension)),
+        };
+        result.map_err(|error| format!("Could not decode image file '{}': {}", filepath, error))
+    }
+
     pub fn create_from_text(
         font: &BitmapFont,
         text: &str,
@@ -318,6 +296,131 @@ impl Bitmap {
             .expect(&format!("Could not write png file to '{}'", png_filepath));
     }
 
+    /// Encodes the bitmap as indexed png with the given palette of up to 256 colors. The
+    /// smallest bit depth that fits the palette is used. Palette colors with alpha are written
+    /// into the tRNS chunk. Returns an error if the bitmap contains colors that are not part of
+    /// the palette.
+    pub fn encoded_as_png_indexed(&self, palette: &[PixelRGBA]) -> Result<Vec<u8>, String> {
+        if palette.is_empty() || palette.len() > 256 {
+            return Err(format!(
+                "Indexed png palettes need between 1 and 256 colors - got {}",
+                palette.len()
+            ));
+        }
+
+        let bit_depth = match palette.len() {
+            1..=2 => png::BitDepth::One,
+            3..=4 => png::BitDepth::Two,
+            5..=16 => png::BitDepth::Four,
+            _ => png::BitDepth::Eight,
+        };
+        let bits_per_pixel = bit_depth as usize;
+
+        // NOTE: If a color is contained multiple times in the palette we use its first index
+        let mut palette_indices: HashMap<PixelRGBA, u8> = HashMap::new();
+        for (index, &color) in palette.iter().enumerate() {
+            palette_indices.entry(color).or_insert(index as u8);
+        }
+
+        // NOTE: Each row starts on a new byte and pixels are packed starting from the most
+        //       significant bit
+        let row_size_bytes = (self.width as usize * bits_per_pixel + 7) / 8;
+        let mut index_data = vec![0u8; row_size_bytes * self.height as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = self.get(x, y);
+                let palette_index = *palette_indices.get(&color).ok_or_else(|| {
+                    format!(
+                        "Color {:?} at ({},{}) is not part of the given palette",
+                        color, x, y
+                    )
+                })?;
+                let bit_offset = x as usize * bits_per_pixel;
+                let byte_index = y as usize * row_size_bytes + bit_offset / 8;
+                let shift = 8 - bits_per_pixel - bit_offset % 8;
+                index_data[byte_index] |= palette_index << shift;
+            }
+        }
+
+        let palette_rgb: Vec<u8> = palette
+            .iter()
+            .flat_map(|color| vec![color.r, color.g, color.b])
+            .collect();
+        // NOTE: The tRNS chunk can omit all opaque entries at the end of the palette
+        let palette_alpha: Vec<u8> = {
+            let alpha_count = palette
+                .iter()
+                .rposition(|color| color.a != 255)
+                .map(|index| index + 1)
+                .unwrap_or(0);
+            palette[..alpha_count].iter().map(|color| color.a).collect()
+        };
+
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(
+                std::io::Cursor::new(&mut png_data),
+                self.width as u32,
+                self.height as u32,
+            );
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(bit_depth);
+            encoder.set_palette(palette_rgb);
+            if !palette_alpha.is_empty() {
+                encoder.set_trns(palette_alpha);
+            }
+            let mut writer = encoder
+                .write_header()
+                .map_err(|error| format!("Could not write png header: {}", error))?;
+            writer
+                .write_image_data(&index_data)
+                .map_err(|error| format!("Could not encode indexed png data: {}", error))?;
+        }
+        Ok(png_data)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_to_png_file_indexed(
+        &self,
+        png_filepath: &str,
+        palette: &[PixelRGBA],
+    ) -> Result<(), String> {
+        let png_data = self.encoded_as_png_indexed(palette)?;
+        std::fs::create_dir_all(path_without_filename(png_filepath)).map_err(|error| {
+            format!(
+                "Could not create necessary directories to write to '{}': {}",
+                png_filepath, error
+            )
+        })?;
+        std::fs::write(png_filepath, png_data)
+            .map_err(|error| format!("Could not write png file to '{}': {}", png_filepath, error))
+    }
+
+    /// Writes the bitmap to an image file by dispatching on its extension. Supports png, qoi,
+    /// tga, bmp and gif files.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_to_file(&self, filepath: &str) -> Result<(), String> {
+        let extension = bitmap_file_extension(filepath)?;
+        let file_content = match extension.as_str() {
+            "png" => self.encoded_as_png(),
+            "qoi" => self.encoded_as_qoi(),
+            "tga" => self.encoded_as_tga(),
+            "bmp" => self.encoded_as_bmp(),
+            "gif" => self.encoded_as_gif().map_err(|error| {
+                format!("Could not encode image file '{}': {}", filepath, error)
+            })?,
+            _ => return Err(format!("Image format '{}' is not supported", extension)),
+        };
+        std::fs::create_dir_all(path_without_filename(filepath)).map_err(|error| {
+            format!(
+                "Could not create necessary directories to write to '{}': {}",
+                filepath, error
+            )
+        })?;
+        std::fs::write(filepath, file_content)
+            .map_err(|error| format!("Could not write image file to '{}': {}", filepath, error))
+    }
+
     /// Draws a given utf8 text to a given bitmap
     /// Returns the starting_offset for the next `draw_text` call
     pub fn draw_text(
@@ -384,14 +487,62 @@ impl Bitmap {
 pub struct BitmapAtlasPosition {
     pub atlas_texture_index: u32,
     pub atlas_texture_pixel_offset: Vec2i,
+    /// The bitmap was packed rotated by 90 degrees clockwise
+    pub rotated: bool,
+}
+
+/// Determines how bitmaps are laid out in a `BitmapAtlas`
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapAtlasPackingParams {
+    /// Number of transparent pixels between packed bitmaps
+    pub padding: i32,
+    /// Number of times the border pixels of a packed bitmap are repeated around it. This prevents
+    /// neighbouring bitmaps from bleeding into each other when drawn at fractional positions or
+    /// with linear filtering.
+    pub extrusion: i32,
+    /// Allows bitmaps to be packed rotated by 90 degrees clockwise if they fit better that way
+    pub allow_rotation: bool,
+    /// Atlas textures always have power of two dimensions. This also applies when shrinking the
+    /// atlas textures via `shrink_to_fit`.
+    pub power_of_two: bool,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BitmapAtlasStats {
+    pub texture_size: u32,
+    pub bitmap_count: usize,
+    /// The pixel area of all packed bitmaps
+    pub area_bitmaps: i64,
+    /// Same as `area_bitmaps` but including padding and extrusion
+    pub area_packed: i64,
+    pub area_total: i64,
+}
+
+impl BitmapAtlasStats {
+    /// Returns the percentage of the atlas texture that is covered by packed bitmaps
+    #[inline]
+    pub fn efficiency(&self) -> f32 {
+        if self.area_total == 0 {
+            0.0
+        } else {
+            self.area_bitmaps as f32 / self.area_total as f32
+        }
+    }
 }
 
 /// An atlaspacker that can grow in size
+#[derive(Clone)]
 pub struct BitmapAtlas {
     pub atlas_texture: Bitmap,
     pub atlas_texture_size_max: Option<u32>,
-    pub rect_packer: rect_packer::DensePacker,
+    pub rect_packer: MaxRectsPacker,
+    pub packing_params: BitmapAtlasPackingParams,
     pub sprite_positions: IndexMap<String, Vec2i>,
+    /// Same as `sprite_positions` but with the full packed region including padding and extrusion.
+    /// Needed for freeing packed bitmaps
+    pub sprite_rects: IndexMap<String, Recti>,
+    /// Names of the bitmaps that were packed rotated by 90 degrees clockwise
+    pub sprites_rotated: IndexSet<String>,
     pub reserve_last_row: bool,
 }
 
@@ -400,40 +551,115 @@ impl BitmapAtlas {
         atlas_texture_size_initial: u32,
         atlas_texture_size_max: Option<u32>,
         reserve_last_row: bool,
+        packing_params: BitmapAtlasPackingParams,
     ) -> BitmapAtlas {
-        let rect_packer = if reserve_last_row {
-            rect_packer::DensePacker::new(
-                atlas_texture_size_initial as i32,
-                atlas_texture_size_initial as i32 - 1,
-            )
+        let atlas_texture_size_initial = if packing_params.power_of_two {
+            atlas_texture_size_initial.next_power_of_two()
         } else {
-            rect_packer::DensePacker::new(
-                atlas_texture_size_initial as i32,
-                atlas_texture_size_initial as i32,
-            )
+            atlas_texture_size_initial
         };
         BitmapAtlas {
             atlas_texture: Bitmap::new(atlas_texture_size_initial, atlas_texture_size_initial),
-            rect_packer,
+            rect_packer: BitmapAtlas::create_rect_packer(
+                atlas_texture_size_initial as i32,
+                reserve_last_row,
+            ),
+            packing_params,
             sprite_positions: IndexMap::new(),
+            sprite_rects: IndexMap::new(),
+            sprites_rotated: IndexSet::new(),
             atlas_texture_size_max,
             reserve_last_row,
         }
     }
 
+    fn create_rect_packer(texture_size: i32, reserve_last_row: bool) -> MaxRectsPacker {
+        if reserve_last_row {
+            MaxRectsPacker::new(texture_size, texture_size - 1)
+        } else {
+            MaxRectsPacker::new(texture_size, texture_size)
+        }
+    }
+
     pub fn finish(self) -> (Bitmap, IndexMap<String, Vec2i>) {
         (self.atlas_texture, self.sprite_positions)
     }
 
+    /// Returns the dimensions a bitmap occupies in the atlas including padding and extrusion
+    #[inline]
+    fn packed_dim(&self, image_dim: Vec2i) -> Vec2i {
+        image_dim + Vec2i::filled(2 * self.packing_params.extrusion + self.packing_params.padding)
+    }
+
     pub fn pack_bitmap(&mut self, name: &str, image: &Bitmap) -> Option<Vec2i> {
-        if let Some(rect) = self.rect_packer.pack(image.width, image.height, false) {
-            let position = Vec2i::new(rect.x, rect.y);
+        if image.width == 0 || image.height == 0 {
+            // NOTE: Empty bitmaps don't need any space in the atlas
+            self.sprite_positions.insert(name.to_owned(), Vec2i::zero());
+            self.sprite_rects.insert(name.to_owned(), Recti::zero());
+            return Some(Vec2i::zero());
+        }
+
+        let packed_dim = self.packed_dim(image.dim());
+        let packed = self.rect_packer.pack(
+            packed_dim.x,
+            packed_dim.y,
+            self.packing_params.allow_rotation,
+        )?;
+
+        let position = packed.rect.pos + Vec2i::filled(self.packing_params.extrusion);
+        let image_rect = if packed.rotated {
+            image
+                .rotated_90_clockwise()
+                .blit_to(&mut self.atlas_texture, position, false);
+            self.sprites_rotated.insert(name.to_owned());
+            Recti::from_pos_width_height(position, image.height, image.width)
+        } else {
             image.blit_to(&mut self.atlas_texture, position, false);
-            self.sprite_positions.insert(name.to_owned(), position);
-            Some(position)
+            Recti::from_pos_dim(position, image.dim())
+        };
+        extrude_region(
+            &mut self.atlas_texture,
+            image_rect,
+            self.packing_params.extrusion,
+        );
+
+        self.sprite_positions.insert(name.to_owned(), position);
+        self.sprite_rects.insert(name.to_owned(), packed.rect);
+        Some(position)
+    }
+
+    /// Removes a previously packed bitmap from the atlas texture and makes its region available
+    /// for packing again. Returns false if no bitmap with the given name was found.
+    pub fn free_bitmap(&mut self, name: &str) -> bool {
+        let rect = if let Some(rect) = self.sprite_rects.swap_remove(name) {
+            rect
+        } else {
+            return false;
+        };
+        self.sprite_positions.swap_remove(name);
+        self.sprites_rotated.swap_remove(name);
+
+        if rect.width() == 0 || rect.height() == 0 {
+            return true;
+        }
+
+        self.atlas_texture.draw_rect_filled(
+            rect.pos.x,
+            rect.pos.y,
+            rect.width(),
+            rect.height(),
+            PixelRGBA::transparent(),
+        );
+
+        if self.sprite_rects.is_empty() {
+            // NOTE: The atlas is empty again so we can just start over with a fresh packer which
+            //       prevents fragmentation
+            self.rect_packer =
+                BitmapAtlas::create_rect_packer(self.atlas_texture.width, self.reserve_last_row);
         } else {
-            None
+            self.rect_packer.free(rect);
         }
+        true
     }
 
     /// NOTE: Resizing is done by doubling current texture size
@@ -453,10 +679,10 @@ impl BitmapAtlas {
             self.atlas_texture
                 .extend(0, 0, texture_size, texture_size, PixelRGBA::transparent());
             if self.reserve_last_row {
-                self.rect_packer.resize(2 * texture_size, 2 * texture_size);
-            } else {
                 self.rect_packer
                     .resize(2 * texture_size, 2 * texture_size - 1);
+            } else {
+                self.rect_packer.resize(2 * texture_size, 2 * texture_size);
             }
 
             if let Some(pos) = self.pack_bitmap(name, image) {
@@ -464,13 +690,92 @@ impl BitmapAtlas {
             }
         }
     }
+
+    /// Shrinks the atlas texture to the smallest square size that still contains all packed
+    /// bitmaps (and the reserved last row if any). Packing more bitmaps afterwards is still
+    /// possible.
+    pub fn shrink_to_fit(&mut self) {
+        let content_size = self
+            .sprite_rects
+            .values()
+            .map(|rect| {
+                let bottom = rect.bottom() + if self.reserve_last_row { 1 } else { 0 };
+                i32::max(rect.right(), bottom)
+            })
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let size_new = if self.packing_params.power_of_two {
+            (content_size as u32).next_power_of_two() as i32
+        } else {
+            content_size
+        };
+        if size_new >= self.atlas_texture.width {
+            return;
+        }
+
+        self.atlas_texture = self
+            .atlas_texture
+            .cropped_by_rect(Recti::from_square(size_new));
+
+        // NOTE: Our packer cannot shrink so we create a new one and mark all packed regions as
+        //       occupied
+        self.rect_packer = BitmapAtlas::create_rect_packer(size_new, self.reserve_last_row);
+        for rect in self.sprite_rects.values() {
+            if rect.width() != 0 && rect.height() != 0 {
+                self.rect_packer.mark_occupied(*rect);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> BitmapAtlasStats {
+        let border = 2 * self.packing_params.extrusion + self.packing_params.padding;
+        let rects = self
+            .sprite_rects
+            .values()
+            .filter(|rect| rect.width() != 0 && rect.height() != 0);
+        BitmapAtlasStats {
+            texture_size: self.atlas_texture.width as u32,
+            bitmap_count: self.sprite_rects.len(),
+            area_bitmaps: rects
+                .clone()
+                .map(|rect| (rect.width() - border) as i64 * (rect.height() - border) as i64)
+                .sum(),
+            area_packed: rects
+                .map(|rect| rect.width() as i64 * rect.height() as i64)
+                .sum(),
+            area_total: self.atlas_texture.width as i64 * self.atlas_texture.height as i64,
+        }
+    }
+}
+
+/// Repeats the border pixels of the given region `extrusion` times outwards
+fn extrude_region(bitmap: &mut Bitmap, region: Recti, extrusion: i32) {
+    for y in region.top()..region.bottom() {
+        let color_left = bitmap.get(region.left(), y);
+        let color_right = bitmap.get(region.right() - 1, y);
+        for offset in 1..=extrusion {
+            bitmap.set(region.left() - offset, y, color_left);
+            bitmap.set(region.right() - 1 + offset, y, color_right);
+        }
+    }
+    for x in (region.left() - extrusion)..(region.right() + extrusion) {
+        let color_top = bitmap.get(x, region.top());
+        let color_bottom = bitmap.get(x, region.bottom() - 1);
+        for offset in 1..=extrusion {
+            bitmap.set(x, region.top() - offset, color_top);
+            bitmap.set(x, region.bottom() - 1 + offset, color_bottom);
+        }
+    }
 }
 
 /// An atlaspacker that can have multiple atlas textures
+#[derive(Clone)]
 pub struct BitmapMultiAtlas {
     pub atlas_texture_size_initial: u32,
     pub atlas_texture_size_max: Option<u32>,
     pub atlas_packers: Vec<BitmapAtlas>,
+    pub packing_params: BitmapAtlasPackingParams,
     pub sprite_positions: IndexMap<String, BitmapAtlasPosition>,
     pub reserve_last_row: bool,
 }
@@ -480,6 +785,7 @@ impl BitmapMultiAtlas {
         atlas_texture_size_initial: u32,
         atlas_texture_size_max: Option<u32>,
         reserve_last_row: bool,
+        packing_params: BitmapAtlasPackingParams,
     ) -> BitmapMultiAtlas {
         BitmapMultiAtlas {
             atlas_texture_size_initial,
@@ -488,15 +794,52 @@ impl BitmapMultiAtlas {
                 atlas_texture_size_initial,
                 atlas_texture_size_max,
                 reserve_last_row,
+                packing_params,
             )],
+            packing_params,
             sprite_positions: IndexMap::new(),
             reserve_last_row,
         }
     }
 
     pub fn pack_bitmap(&mut self, sprite_name: &str, image: &Bitmap) -> BitmapAtlasPosition {
+        if let Some(atlas_position) = self.try_pack_bitmap(sprite_name, image) {
+            atlas_position
+        } else {
+            let texture_size_max = self.atlas_texture_size_max.unwrap_or(std::u32::MAX);
+            panic!(
+                "Could not pack image with dimensions {}x{} into atlas with dimensions with maxium dimensions {}x{}",
+                image.width, image.height, texture_size_max, texture_size_max
+            )
+        }
+    }
+
+    /// Same as `pack_bitmap` but returns `None` if the image is too big to fit into an empty atlas
+    /// texture
+    pub fn try_pack_bitmap(
+        &mut self,
+        sprite_name: &str,
+        image: &Bitmap,
+    ) -> Option<BitmapAtlasPosition> {
         if let Some(atlas_position) = self.pack_bitmap_internal(sprite_name, image) {
-            return atlas_position;
+            return Some(atlas_position);
+        }
+
+        let texture_size_max = self.atlas_texture_size_max.unwrap_or(std::u32::MAX) as i64;
+        let texture_height_max = if self.reserve_last_row {
+            texture_size_max - 1
+        } else {
+            texture_size_max
+        };
+        let border = (2 * self.packing_params.extrusion + self.packing_params.padding) as i64;
+        let packed_width = image.width as i64 + border;
+        let packed_height = image.height as i64 + border;
+        let fits = packed_width <= texture_size_max && packed_height <= texture_height_max;
+        let fits_rotated = self.packing_params.allow_rotation
+            && packed_height <= texture_size_max
+            && packed_width <= texture_height_max;
+        if !fits && !fits_rotated {
+            return None;
         }
 
         // NOTE: At this point our image did not fit in any of the existing atlas textures, so we
@@ -505,18 +848,35 @@ impl BitmapMultiAtlas {
             self.atlas_texture_size_initial,
             self.atlas_texture_size_max,
             self.reserve_last_row,
+            self.packing_params,
         ));
-        if let Some(atlas_position) = self.pack_bitmap_internal(sprite_name, image) {
-            atlas_position
-        } else {
-            let texture_size_max = self.atlas_texture_size_max.unwrap_or(std::u32::MAX);
-            panic!(
-                "Could not pack image with dimensions {}x{} into atlas with dimensions with maxium dimensions {}x{}",
-                image.width, image.height, texture_size_max, texture_size_max
-            )
+        self.pack_bitmap_internal(sprite_name, image)
+    }
+
+    /// Removes a previously packed bitmap from its atlas texture so that its region can be reused.
+    /// Returns the position the bitmap was packed at or `None` if it was not found.
+    pub fn free_bitmap(&mut self, sprite_name: &str) -> Option<BitmapAtlasPosition> {
+        let atlas_position = self.sprite_positions.swap_remove(sprite_name)?;
+        let packer = &mut self.atlas_packers[atlas_position.atlas_texture_index as usize];
+        let was_freed = packer.free_bitmap(sprite_name);
+        assert!(was_freed);
+        Some(atlas_position)
+    }
+
+    /// Shrinks all atlas textures to the smallest size that still contains their packed bitmaps
+    pub fn shrink_to_fit(&mut self) {
+        for packer in self.atlas_packers.iter_mut() {
+            packer.shrink_to_fit();
         }
     }
 
+    pub fn stats(&self) -> Vec<BitmapAtlasStats> {
+        self.atlas_packers
+            .iter()
+            .map(|packer| packer.stats())
+            .collect()
+    }
+
     pub fn finish(self) -> (Vec<Bitmap>, IndexMap<String, BitmapAtlasPosition>) {
         let atlas_textures = self
             .atlas_packers
@@ -533,6 +893,7 @@ impl BitmapMultiAtlas {
                 let atlas_position = BitmapAtlasPosition {
                     atlas_texture_index: atlas_index as u32,
                     atlas_texture_pixel_offset: position,
+                    rotated: packer.sprites_rotated.contains(name),
                 };
                 self.sprite_positions
                     .insert(name.to_owned(), atlas_position);
@@ -542,3 +903,168 @@ impl BitmapMultiAtlas {
         None
     }
 }
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png_raw(
+        width: u32,
+        height: u32,
+        color_type: png::ColorType,
+        bit_depth: png::BitDepth,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(std::io::Cursor::new(&mut png_data), width, height);
+            encoder.set_color(color_type);
+            encoder.set_depth(bit_depth);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        png_data
+    }
+
+    fn create_test_bitmap(palette: &[PixelRGBA], width: u32, height: u32) -> Bitmap {
+        let mut result = Bitmap::new(width, height);
+        for (index, pixel) in result.data.iter_mut().enumerate() {
+            *pixel = palette[(index * 7) % palette.len()];
+        }
+        result
+    }
+
+    #[test]
+    fn png_indexed_roundtrip() {
+        let palette_full: Vec<PixelRGBA> = (0..256)
+            .map(|index| PixelRGBA::new(index as u8, 255 - index as u8, 17, 255))
+            .collect();
+
+        // NOTE: The odd width makes sure that rows of lower bit depths are padded correctly
+        for &palette_size in &[1, 2, 3, 4, 5, 16, 17, 256] {
+            let mut palette = palette_full[..palette_size].to_vec();
+            palette[0] = PixelRGBA::transparent();
+
+            let bitmap = create_test_bitmap(&palette, 13, 5);
+            let png_data = bitmap.encoded_as_png_indexed(&palette).unwrap();
+            let decoded = Bitmap::from_png_data(&png_data).unwrap();
+            assert_eq!(decoded, bitmap, "Palette size {}", palette_size);
+        }
+    }
+
+    #[test]
+    fn png_indexed_invalid_palette() {
+        let palette = [PixelRGBA::black(), PixelRGBA::white()];
+        let bitmap = create_test_bitmap(&[PixelRGBA::black(), PixelRGBA::red()], 4, 4);
+        assert!(bitmap.encoded_as_png_indexed(&palette).is_err());
+        assert!(bitmap.encoded_as_png_indexed(&[]).is_err());
+        assert!(bitmap
+            .encoded_as_png_indexed(&vec![PixelRGBA::black(); 257])
+            .is_err());
+    }
+
+    #[test]
+    fn png_decode_low_bit_depth_grayscale() {
+        // Two 2-bit pixels per row: 0b00, 0b11 -> black, white
+        let png_data = encode_png_raw(
+            2,
+            2,
+            png::ColorType::Grayscale,
+            png::BitDepth::Two,
+            &[0b0011_0000, 0b1100_0000],
+        );
+        let decoded = Bitmap::from_png_data(&png_data).unwrap();
+        assert_eq!(decoded.get(0, 0), PixelRGBA::black());
+        assert_eq!(decoded.get(1, 0), PixelRGBA::white());
+        assert_eq!(decoded.get(0, 1), PixelRGBA::white());
+        assert_eq!(decoded.get(1, 1), PixelRGBA::black());
+    }
+
+    #[test]
+    fn png_decode_16_bit() {
+        let png_data = encode_png_raw(
+            2,
+            1,
+            png::ColorType::RGBA,
+            png::BitDepth::Sixteen,
+            &[
+                0xFF, 0xFF, 0x00, 0x00, 0x80, 0x80, 0xFF, 0xFF, //
+                0x00, 0x00, 0x00, 0x7F, 0xFF, 0xFF, 0x80, 0x00,
+            ],
+        );
+        let decoded = Bitmap::from_png_data(&png_data).unwrap();
+        assert_eq!(decoded.get(0, 0), PixelRGBA::new(255, 0, 128, 255));
+        assert_eq!(decoded.get(1, 0), PixelRGBA::new(0, 0, 255, 128));
+
+        let png_data = encode_png_raw(
+            1,
+            1,
+            png::ColorType::GrayscaleAlpha,
+            png::BitDepth::Sixteen,
+            &[0x40, 0x00, 0xFF, 0xFF],
+        );
+        let decoded = Bitmap::from_png_data(&png_data).unwrap();
+        assert_eq!(decoded.get(0, 0), PixelRGBA::new(64, 64, 64, 255));
+    }
+
+    #[test]
+    fn atlas_packing_with_padding_extrusion_and_rotation() {
+        let params = BitmapAtlasPackingParams {
+            padding: 1,
+            extrusion: 1,
+            allow_rotation: true,
+            power_of_two: true,
+        };
+        let mut atlas = BitmapMultiAtlas::new(10, Some(16), false, params);
+
+        // NOTE: A 13x2 bitmap exactly fills the width of a 16x16 texture with its border of 3 pixels
+        let mut image_wide = Bitmap::new_filled(13, 2, PixelRGBA::new(255, 0, 0, 255));
+        image_wide.set(0, 0, PixelRGBA::new(0, 255, 0, 255));
+        let position = atlas.pack_bitmap("wide", &image_wide);
+        assert_eq!(atlas.atlas_packers[0].atlas_texture.width, 16);
+        assert_eq!(position.atlas_texture_pixel_offset, Vec2i::new(1, 1));
+        assert!(!position.rotated);
+
+        // NOTE: The extruded border repeats the edge pixels including the corners
+        let texture = &atlas.atlas_packers[0].atlas_texture;
+        assert_eq!(texture.get(0, 0), PixelRGBA::new(0, 255, 0, 255));
+        assert_eq!(texture.get(1, 0), PixelRGBA::new(0, 255, 0, 255));
+        assert_eq!(texture.get(14, 3), PixelRGBA::new(255, 0, 0, 255));
+        assert_eq!(texture.get(15, 3), PixelRGBA::transparent());
+        assert_eq!(texture.get(14, 4), PixelRGBA::transparent());
+
+        // NOTE: A 2x13 bitmap can only be placed rotated below the first one
+        let mut image_tall = Bitmap::new_filled(2, 13, PixelRGBA::new(0, 0, 255, 255));
+        image_tall.set(0, 0, PixelRGBA::new(0, 255, 0, 255));
+        let position = atlas.pack_bitmap("tall", &image_tall);
+        assert_eq!(position.atlas_texture_index, 0);
+        assert!(position.rotated);
+        let offset = position.atlas_texture_pixel_offset;
+        assert_eq!(
+            atlas.atlas_packers[0]
+                .atlas_texture
+                .get(offset.x + 12, offset.y),
+            PixelRGBA::new(0, 255, 0, 255)
+        );
+
+        let stats = atlas.stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].area_bitmaps, 2 * 13 * 2);
+        assert_eq!(stats[0].area_packed, 2 * 16 * 5);
+
+        // NOTE: Bitmaps that are too big even with rotation are rejected
+        let image_huge = Bitmap::new(14, 15);
+        assert!(atlas.try_pack_bitmap("huge", &image_huge).is_none());
+
+        // NOTE: Without the power of two restriction we can shrink to the exact content size
+        atlas.free_bitmap("wide");
+        atlas.atlas_packers[0].packing_params.power_of_two = false;
+        atlas.shrink_to_fit();
+        assert_eq!(atlas.atlas_packers[0].atlas_texture.width, 16);
+        atlas.free_bitmap("tall");
+        atlas.shrink_to_fit();
+        assert_eq!(atlas.atlas_packers[0].atlas_texture.width, 1);
+    }
+}
@@ -0,0 +1,418 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// BMP image format
+//
+// Specification: https://docs.microsoft.com/en-us/windows/win32/gdi/bitmap-storage
+
+use super::bitmap::*;
+
+use super::core::Deserializer;
+
+const BMP_FILE_HEADER_SIZE: usize = 14;
+const BMP_CORE_HEADER_SIZE: u32 = 12;
+const BMP_INFO_HEADER_SIZE: u32 = 40;
+const BMP_V4_HEADER_SIZE: u32 = 108;
+
+const BMP_COMPRESSION_RGB: u32 = 0;
+const BMP_COMPRESSION_RLE8: u32 = 1;
+const BMP_COMPRESSION_RLE4: u32 = 2;
+const BMP_COMPRESSION_BITFIELDS: u32 = 3;
+const BMP_COMPRESSION_ALPHABITFIELDS: u32 = 6;
+
+const BMP_COLORSPACE_SRGB: u32 = 0x7352_4742;
+
+/// Guards against allocating absurd amounts of memory for malformed headers
+const BMP_PIXEL_COUNT_MAX: u64 = 1 << 28;
+
+/// Extracts the channel given by `mask` from `value` and scales it to 8 bits
+fn bmp_channel_from_mask(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let bit_count = (mask >> shift).count_ones();
+    let channel = (value & mask) >> shift;
+    let channel_max = (1u64 << bit_count) - 1;
+    ((channel as u64 * 255 + channel_max / 2) / channel_max) as u8
+}
+
+impl Bitmap {
+    pub fn from_bmp_data(bmp_data: &[u8]) -> Result<Bitmap, String> {
+        let mut deserializer = Deserializer::new(bmp_data);
+        let magic: [u8; 2] = deserializer.deserialize()?;
+        if &magic != b"BM" {
+            return Err("Bmp data has invalid magic number".to_owned());
+        }
+        let _file_size: u32 = deserializer.deserialize()?;
+        let _reserved: u32 = deserializer.deserialize()?;
+        let pixel_data_offset: u32 = deserializer.deserialize()?;
+
+        let header_size: u32 = deserializer.deserialize()?;
+        let (width, height, bits_per_pixel, compression, palette_size) =
+            if header_size == BMP_CORE_HEADER_SIZE {
+                let width: u16 = deserializer.deserialize()?;
+                let height: i16 = deserializer.deserialize()?;
+                let _planes: u16 = deserializer.deserialize()?;
+                let bits_per_pixel: u16 = deserializer.deserialize()?;
+                (
+                    width as i32,
+                    height as i32,
+                    bits_per_pixel,
+                    BMP_COMPRESSION_RGB,
+                    0,
+                )
+            } else if header_size >= BMP_INFO_HEADER_SIZE {
+                let width: i32 = deserializer.deserialize()?;
+                let height: i32 = deserializer.deserialize()?;
+                let _planes: u16 = deserializer.deserialize()?;
+                let bits_per_pixel: u16 = deserializer.deserialize()?;
+                let compression: u32 = deserializer.deserialize()?;
+                let _image_size: u32 = deserializer.deserialize()?;
+                let _pixels_per_meter_x: i32 = deserializer.deserialize()?;
+                let _pixels_per_meter_y: i32 = deserializer.deserialize()?;
+                let palette_size: u32 = deserializer.deserialize()?;
+                let _colors_important: u32 = deserializer.deserialize()?;
+                (width, height, bits_per_pixel, compression, palette_size)
+            } else {
+                return Err(format!("Bmp header size {} is not supported", header_size));
+            };
+
+        // NOTE: Negative heights denote top-down images
+        let is_top_down = height < 0;
+        let height = height.unsigned_abs();
+        if width <= 0 || height == 0 || width as u64 * height as u64 > BMP_PIXEL_COUNT_MAX {
+            return Err(format!(
+                "Bmp data has invalid dimensions {}x{}",
+                width, height
+            ));
+        }
+
+        // Channel masks
+        let (mut mask_r, mut mask_g, mut mask_b, mut mask_a) = match bits_per_pixel {
+            16 => (0x7C00, 0x03E0, 0x001F, 0),
+            _ => (0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0),
+        };
+        if compression == BMP_COMPRESSION_BITFIELDS || compression == BMP_COMPRESSION_ALPHABITFIELDS
+        {
+            // NOTE: For the info header the masks follow directly after the header, for later
+            //       header versions they are part of the header
+            mask_r = deserializer.deserialize()?;
+            mask_g = deserializer.deserialize()?;
+            mask_b = deserializer.deserialize()?;
+            if compression == BMP_COMPRESSION_ALPHABITFIELDS || header_size >= 56 {
+                mask_a = deserializer.deserialize()?;
+            }
+        }
+
+        // Palette
+        let header_end_offset = BMP_FILE_HEADER_SIZE + header_size as usize;
+        let palette_offset = usize::max(
+            header_end_offset,
+            bmp_data.len() - deserializer.get_remaining_data().len(),
+        );
+        let palette_entry_size = if header_size == BMP_CORE_HEADER_SIZE {
+            3
+        } else {
+            4
+        };
+        let palette_size = if bits_per_pixel <= 8 {
+            if palette_size == 0 {
+                1 << bits_per_pixel
+            } else {
+                usize::min(palette_size as usize, 1 << bits_per_pixel)
+            }
+        } else {
+            0
+        };
+        let palette: Vec<PixelRGBA> = bmp_data
+            .get(
+                palette_offset
+                    ..usize::min(
+                        palette_offset + palette_size * palette_entry_size,
+                        pixel_data_offset as usize,
+                    ),
+            )
+            .ok_or_else(|| "Bmp palette is truncated".to_owned())?
+            .chunks_exact(palette_entry_size)
+            .map(|bgr| PixelRGBA::new(bgr[2], bgr[1], bgr[0], 255))
+            .collect();
+        if bits_per_pixel <= 8 && palette.is_empty() {
+            return Err("Paletted bmp data has no palette".to_owned());
+        }
+        let palette_color = |index: u8| {
+            palette
+                .get(index as usize)
+                .cloned()
+                .unwrap_or(PixelRGBA::black())
+        };
+
+        let pixel_data = bmp_data
+            .get(pixel_data_offset as usize..)
+            .ok_or_else(|| "Bmp pixel data is missing".to_owned())?;
+
+        let width = width as usize;
+        let height = height as usize;
+        let mut buffer = vec![PixelRGBA::transparent(); width * height];
+        match compression {
+            BMP_COMPRESSION_RLE8 | BMP_COMPRESSION_RLE4 => {
+                // NOTE: Pixels not covered by the run-length data stay transparent
+                let is_rle4 = compression == BMP_COMPRESSION_RLE4;
+                let mut x = 0;
+                let mut y = 0;
+                let mut pos = 0;
+                let mut next_byte = || -> Result<u8, String> {
+                    let byte = *pixel_data
+                        .get(pos)
+                        .ok_or_else(|| "Bmp pixel data is truncated".to_owned())?;
+                    pos += 1;
+                    Ok(byte)
+                };
+                let mut set_pixel = |x: usize, y: usize, index: u8| {
+                    if x < width && y < height {
+                        buffer[y * width + x] = palette_color(index);
+                    }
+                };
+                loop {
+                    let count = next_byte()?;
+                    let value = next_byte()?;
+                    if count > 0 {
+                        // Encoded run
+                        for i in 0..count as usize {
+                            let index = if is_rle4 {
+                                if i % 2 == 0 {
+                                    value >> 4
+                                } else {
+                                    value & 0x0F
+                                }
+                            } else {
+                                value
+                            };
+                            set_pixel(x, y, index);
+                            x += 1;
+                        }
+                    } else {
+                        match value {
+                            0 => {
+                                // End of line
+                                x = 0;
+                                y += 1;
+                            }
+                            1 => {
+                                // End of bitmap
+                                break;
+                            }
+                            2 => {
+                                // Delta
+                                x += next_byte()? as usize;
+                                y += next_byte()? as usize;
+                            }
+                            _ => {
+                                // Absolute mode
+                                let pixel_count = value as usize;
+                                let byte_count = if is_rle4 {
+                                    pixel_count.div_ceil(2)
+                                } else {
+                                    pixel_count
+                                };
+                                let mut byte = 0;
+                                for i in 0..pixel_count {
+                                    let index = if is_rle4 {
+                                        if i % 2 == 0 {
+                                            byte = next_byte()?;
+                                            byte >> 4
+                                        } else {
+                                            byte & 0x0F
+                                        }
+                                    } else {
+                                        next_byte()?
+                                    };
+                                    set_pixel(x, y, index);
+                                    x += 1;
+                                }
+                                // NOTE: Absolute runs are padded to 16 bit boundaries
+                                if byte_count % 2 == 1 {
+                                    next_byte()?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            BMP_COMPRESSION_RGB | BMP_COMPRESSION_BITFIELDS | BMP_COMPRESSION_ALPHABITFIELDS => {
+                let bits_per_pixel = bits_per_pixel as usize;
+                match bits_per_pixel {
+                    1 | 2 | 4 | 8 | 16 | 24 | 32 => {}
+                    _ => {
+                        return Err(format!(
+                            "Bmp data with {} bits per pixel is not supported",
+                            bits_per_pixel
+                        ))
+                    }
+                }
+
+                // NOTE: Rows are padded to 4 byte boundaries
+                let row_size_bytes = (width * bits_per_pixel).div_ceil(32) * 4;
+                if pixel_data.len() < row_size_bytes * height {
+                    return Err("Bmp pixel data is truncated".to_owned());
+                }
+                if compression == BMP_COMPRESSION_RGB && bits_per_pixel == 32 {
+                    // NOTE: Officially the fourth byte is unused for uncompressed 32-bit data, but
+                    //       some writers store alpha in it anyway. So we only use it if it is
+                    //       actually populated.
+                    let has_alpha = pixel_data[..row_size_bytes * height]
+                        .chunks_exact(4)
+                        .any(|bgra| bgra[3] != 0);
+                    if has_alpha {
+                        mask_a = 0xFF00_0000;
+                    }
+                }
+                for (y, row) in pixel_data
+                    .chunks_exact(row_size_bytes)
+                    .take(height)
+                    .enumerate()
+                {
+                    let row_dest = &mut buffer[y * width..(y + 1) * width];
+                    for (x, pixel) in row_dest.iter_mut().enumerate() {
+                        *pixel = match bits_per_pixel {
+                            1 | 2 | 4 | 8 => {
+                                let bit_offset = x * bits_per_pixel;
+                                let shift = 8 - bits_per_pixel - bit_offset % 8;
+                                let mask = ((1u16 << bits_per_pixel) - 1) as u8;
+                                palette_color((row[bit_offset / 8] >> shift) & mask)
+                            }
+                            24 => PixelRGBA::new(row[3 * x + 2], row[3 * x + 1], row[3 * x], 255),
+                            _ => {
+                                let value = if bits_per_pixel == 16 {
+                                    u16::from_le_bytes([row[2 * x], row[2 * x + 1]]) as u32
+                                } else {
+                                    u32::from_le_bytes([
+                                        row[4 * x],
+                                        row[4 * x + 1],
+                                        row[4 * x + 2],
+                                        row[4 * x + 3],
+                                    ])
+                                };
+                                PixelRGBA::new(
+                                    bmp_channel_from_mask(value, mask_r),
+                                    bmp_channel_from_mask(value, mask_g),
+                                    bmp_channel_from_mask(value, mask_b),
+                                    if mask_a == 0 {
+                                        255
+                                    } else {
+                                        bmp_channel_from_mask(value, mask_a)
+                                    },
+                                )
+                            }
+                        };
+                    }
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Bmp compression method {} is not supported",
+                    compression
+                ))
+            }
+        }
+
+        if !is_top_down {
+            buffer = buffer
+                .chunks_exact(width)
+                .rev()
+                .flat_map(|row| row.iter().cloned())
+                .collect();
+        }
+
+        Ok(Bitmap::new_from_buffer(width as u32, height as u32, buffer))
+    }
+
+    /// Encodes the bitmap as 32-bit bmp with alpha channel
+    pub fn encoded_as_bmp(&self) -> Vec<u8> {
+        let pixel_data_offset = BMP_FILE_HEADER_SIZE as u32 + BMP_V4_HEADER_SIZE;
+        let pixel_data_size = 4 * self.data.len() as u32;
+
+        let mut result = Vec::with_capacity((pixel_data_offset + pixel_data_size) as usize);
+
+        // File header
+        result.extend_from_slice(b"BM");
+        result.extend_from_slice(&(pixel_data_offset + pixel_data_size).to_le_bytes());
+        result.extend_from_slice(&0u32.to_le_bytes());
+        result.extend_from_slice(&pixel_data_offset.to_le_bytes());
+
+        // V4 header
+        result.extend_from_slice(&BMP_V4_HEADER_SIZE.to_le_bytes());
+        result.extend_from_slice(&self.width.to_le_bytes());
+        result.extend_from_slice(&self.height.to_le_bytes());
+        result.extend_from_slice(&1u16.to_le_bytes());
+        result.extend_from_slice(&32u16.to_le_bytes());
+        result.extend_from_slice(&BMP_COMPRESSION_BITFIELDS.to_le_bytes());
+        result.extend_from_slice(&pixel_data_size.to_le_bytes());
+        result.extend_from_slice(&2835i32.to_le_bytes()); // 72 DPI
+        result.extend_from_slice(&2835i32.to_le_bytes());
+        result.extend_from_slice(&0u32.to_le_bytes());
+        result.extend_from_slice(&0u32.to_le_bytes());
+        result.extend_from_slice(&0x00FF_0000u32.to_le_bytes());
+        result.extend_from_slice(&0x0000_FF00u32.to_le_bytes());
+        result.extend_from_slice(&0x0000_00FFu32.to_le_bytes());
+        result.extend_from_slice(&0xFF00_0000u32.to_le_bytes());
+        result.extend_from_slice(&BMP_COLORSPACE_SRGB.to_le_bytes());
+        result.extend_from_slice(&[0; 36]); // Colorspace endpoints
+        result.extend_from_slice(&[0; 12]); // Gamma
+
+        // NOTE: We write the rows bottom-up as this is what most readers expect
+        for row in self.data.chunks_exact(self.width as usize).rev() {
+            for pixel in row {
+                result.extend_from_slice(&[pixel.b, pixel.g, pixel.r, pixel.a]);
+            }
+        }
+
+        result
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bmp_roundtrip() {
+        let mut bitmap = Bitmap::new(5, 3);
+        for (index, pixel) in bitmap.data.iter_mut().enumerate() {
+            *pixel = PixelRGBA::new(index as u8 * 10, 255 - index as u8, 7, index as u8 * 17);
+        }
+
+        let bmp_data = bitmap.encoded_as_bmp();
+        let decoded = Bitmap::from_bmp_data(&bmp_data).unwrap();
+        assert_eq!(decoded, bitmap);
+    }
+
+    #[test]
+    fn bmp_decode_paletted() {
+        // 3x2 4-bit paletted info header bitmap stored bottom-up
+        let mut bmp_data = Vec::new();
+        bmp_data.extend_from_slice(b"BM");
+        bmp_data.extend_from_slice(&0u32.to_le_bytes());
+        bmp_data.extend_from_slice(&0u32.to_le_bytes());
+        bmp_data.extend_from_slice(&(14u32 + 40 + 2 * 4).to_le_bytes());
+        bmp_data.extend_from_slice(&BMP_INFO_HEADER_SIZE.to_le_bytes());
+        bmp_data.extend_from_slice(&3i32.to_le_bytes());
+        bmp_data.extend_from_slice(&2i32.to_le_bytes());
+        bmp_data.extend_from_slice(&1u16.to_le_bytes());
+        bmp_data.extend_from_slice(&4u16.to_le_bytes());
+        bmp_data.extend_from_slice(&BMP_COMPRESSION_RGB.to_le_bytes());
+        bmp_data.extend_from_slice(&[0; 12]);
+        bmp_data.extend_from_slice(&2u32.to_le_bytes());
+        bmp_data.extend_from_slice(&0u32.to_le_bytes());
+        bmp_data.extend_from_slice(&[0, 0, 255, 0, 255, 0, 0, 0]); // red, blue
+        bmp_data.extend_from_slice(&[0x10, 0x00, 0, 0]); // bottom row: blue, red, red
+        bmp_data.extend_from_slice(&[0x00, 0x10, 0, 0]); // top row: red, red, blue
+
+        let decoded = Bitmap::from_bmp_data(&bmp_data).unwrap();
+        assert_eq!(decoded.get(0, 0), PixelRGBA::red());
+        assert_eq!(decoded.get(2, 0), PixelRGBA::blue());
+        assert_eq!(decoded.get(0, 1), PixelRGBA::blue());
+        assert_eq!(decoded.get(1, 1), PixelRGBA::red());
+    }
+}
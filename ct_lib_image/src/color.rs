@@ -208,7 +208,7 @@ pub enum ColorBlendMode {
 
 /// Premultiplied RGBA
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -458,7 +458,45 @@ impl Color {
     /// From https://cairographics.org/operators/
     #[inline]
     pub fn saturation(self) -> f32 {
-        f32::max(f32::max(self.r, self.g), self.b) - f32::min(f32::max(self.r, self.g), self.b)
+        f32::max(f32::max(self.r, self.g), self.b) - f32::min(f32::min(self.r, self.g), self.b)
+    }
+
+    /// From https://www.w3.org/TR/compositing-1/#blendingnonseparable
+    #[inline]
+    #[must_use = "This does not change the original color"]
+    pub fn with_replaced_saturation(self, new_saturation: f32) -> Color {
+        let mut channels = [self.r, self.g, self.b];
+        let mut index_min = 0;
+        let mut index_max = 0;
+        for index in 1..3 {
+            if channels[index] < channels[index_min] {
+                index_min = index;
+            }
+            if channels[index] >= channels[index_max] {
+                index_max = index;
+            }
+        }
+        // NOTE: If all channels are equal, `index_min` and `index_max` still differ
+        let index_mid = 3 - index_min - index_max;
+
+        let channel_min = channels[index_min];
+        let channel_max = channels[index_max];
+        if channel_max > channel_min {
+            channels[index_mid] = ((channels[index_mid] - channel_min) * new_saturation)
+                / (channel_max - channel_min);
+            channels[index_max] = new_saturation;
+        } else {
+            channels[index_mid] = 0.0;
+            channels[index_max] = 0.0;
+        }
+        channels[index_min] = 0.0;
+
+        Color {
+            r: channels[0],
+            g: channels[1],
+            b: channels[2],
+            a: self.a,
+        }
     }
 
     /// From https://cairographics.org/operators/
@@ -491,6 +529,35 @@ impl Color {
         result
     }
 
+    /// Blends a premultiplied source color onto a premultiplied destination color with the
+    /// given blend mode. The blend modes follow the semantics of Photoshop/Aseprite as specified
+    /// in https://www.w3.org/TR/compositing-1/
+    #[inline]
+    pub fn premultiplied_alpha_blend(
+        source: Color,
+        dest: Color,
+        blend_mode: ColorBlendMode,
+    ) -> Color {
+        match blend_mode {
+            ColorBlendMode::Normal => Color::premultiplied_alpha_blend_normal(source, dest),
+            ColorBlendMode::Multiply => Color::premultiplied_alpha_blend_multiply(source, dest),
+            ColorBlendMode::Screen => Color::premultiplied_alpha_blend_screen(source, dest),
+            ColorBlendMode::Overlay => Color::premultiplied_alpha_blend_overlay(source, dest),
+            ColorBlendMode::Darken => Color::premultiplied_alpha_blend_darken(source, dest),
+            ColorBlendMode::Lighten => Color::premultiplied_alpha_blend_lighten(source, dest),
+            ColorBlendMode::ColorDodge => {
+                Color::premultiplied_alpha_blend_color_dodge(source, dest)
+            }
+            ColorBlendMode::ColorBurn => Color::premultiplied_alpha_blend_color_burn(source, dest),
+            ColorBlendMode::HardLight => Color::premultiplied_alpha_blend_hard_light(source, dest),
+            ColorBlendMode::SoftLight => Color::premultiplied_alpha_blend_soft_light(source, dest),
+            ColorBlendMode::Hue => Color::premultiplied_alpha_blend_hue(source, dest),
+            ColorBlendMode::Saturation => Color::premultiplied_alpha_blend_saturation(source, dest),
+            ColorBlendMode::Color => Color::premultiplied_alpha_blend_color(source, dest),
+            ColorBlendMode::Luminosity => Color::premultiplied_alpha_blend_luminosity(source, dest),
+        }
+    }
+
     #[inline]
     pub fn premultiplied_alpha_blend_normal(source: Color, dest: Color) -> Color {
         return Color::premultiplied_alpha_blend_with_function(
@@ -520,6 +587,126 @@ impl Color {
         );
     }
 
+    #[inline]
+    pub fn premultiplied_alpha_blend_overlay(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(
+            source,
+            dest,
+            |source_x, dest_x| blend_hard_light(dest_x, source_x),
+        )
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_darken(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(source, dest, f32::min)
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_lighten(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(source, dest, f32::max)
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_color_dodge(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(
+            source,
+            dest,
+            |source_x, dest_x| {
+                if dest_x <= 0.0 {
+                    0.0
+                } else if source_x >= 1.0 {
+                    1.0
+                } else {
+                    f32::min(1.0, dest_x / (1.0 - source_x))
+                }
+            },
+        )
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_color_burn(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(
+            source,
+            dest,
+            |source_x, dest_x| {
+                if dest_x >= 1.0 {
+                    1.0
+                } else if source_x <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - f32::min(1.0, (1.0 - dest_x) / source_x)
+                }
+            },
+        )
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_hard_light(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(source, dest, blend_hard_light)
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_soft_light(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_separable_function(
+            source,
+            dest,
+            |source_x, dest_x| {
+                if source_x <= 0.5 {
+                    dest_x - (1.0 - 2.0 * source_x) * dest_x * (1.0 - dest_x)
+                } else {
+                    let d = if dest_x <= 0.25 {
+                        ((16.0 * dest_x - 12.0) * dest_x + 4.0) * dest_x
+                    } else {
+                        f32::sqrt(dest_x)
+                    };
+                    dest_x + (2.0 * source_x - 1.0) * (d - dest_x)
+                }
+            },
+        )
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_hue(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_non_separable_function(
+            source,
+            dest,
+            |source, dest| {
+                let source = source.to_unpremultipled_alpha();
+                let dest = dest.to_unpremultipled_alpha();
+                source
+                    .with_replaced_saturation(dest.saturation())
+                    .with_replaced_luminosity(dest.luminosity())
+            },
+        )
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_saturation(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_non_separable_function(
+            source,
+            dest,
+            |source, dest| {
+                let source = source.to_unpremultipled_alpha();
+                let dest = dest.to_unpremultipled_alpha();
+                dest.with_replaced_saturation(source.saturation())
+                    .with_replaced_luminosity(dest.luminosity())
+            },
+        )
+    }
+
+    #[inline]
+    pub fn premultiplied_alpha_blend_color(source: Color, dest: Color) -> Color {
+        Color::premultiplied_alpha_blend_with_non_separable_function(
+            source,
+            dest,
+            |source, dest| {
+                let source = source.to_unpremultipled_alpha();
+                let dest = dest.to_unpremultipled_alpha();
+                source.with_replaced_luminosity(dest.luminosity())
+            },
+        )
+    }
+
     #[inline]
     pub fn premultiplied_alpha_blend_luminosity(source: Color, dest: Color) -> Color {
         Color::premultiplied_alpha_blend_with_non_separable_function(
@@ -565,6 +752,27 @@ impl Color {
         }
     }
 
+    /// Same as `premultiplied_alpha_blend_with_function` but the `blend_function` operates on
+    /// non-premultiplied color channels `(source_x, dest_x)`
+    #[inline]
+    fn premultiplied_alpha_blend_with_separable_function<F: Fn(f32, f32) -> f32>(
+        source: Color,
+        dest: Color,
+        blend_function: F,
+    ) -> Color {
+        Color::premultiplied_alpha_blend_with_function(
+            source,
+            dest,
+            |source_x, source_a, dest_x, dest_a| {
+                if source_a == 0.0 || dest_a == 0.0 {
+                    0.0
+                } else {
+                    source_a * dest_a * blend_function(source_x / source_a, dest_x / dest_a)
+                }
+            },
+        )
+    }
+
     #[inline]
     fn premultiplied_alpha_blend_with_non_separable_function<F: Fn(Color, Color) -> Color>(
         source: Color,
@@ -591,6 +799,17 @@ impl Color {
     }
 }
 
+/// From https://www.w3.org/TR/compositing-1/#blendinghardlight
+#[inline]
+fn blend_hard_light(source_x: f32, dest_x: f32) -> f32 {
+    if source_x <= 0.5 {
+        dest_x * 2.0 * source_x
+    } else {
+        let source_x = 2.0 * source_x - 1.0;
+        dest_x + source_x - dest_x * source_x
+    }
+}
+
 impl Lerp for Color {
     #[inline]
     fn lerp_value(start: Color, end: Color, percent: f32) -> Color {
@@ -658,6 +877,7 @@ impl Color {
     }
 }
 
+use std::convert::TryFrom;
 use std::ops::Add;
 use std::ops::AddAssign;
 use std::ops::Div;
@@ -1008,3 +1228,917 @@ impl DivAssign<i32> for Color {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Oklab
+
+/// Perceptually uniform color space where euclidean distances roughly match perceived color
+/// differences. See https://bottosson.github.io/posts/oklab/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl Oklab {
+    #[inline]
+    pub const fn new(l: f32, a: f32, b: f32) -> Oklab {
+        Oklab { l, a, b }
+    }
+
+    /// Ignores the alpha channel
+    #[inline]
+    pub fn from_pixelrgba(pixel: PixelRGBA) -> Oklab {
+        Oklab::from_srgb(
+            pixel.r as f32 / 255.0,
+            pixel.g as f32 / 255.0,
+            pixel.b as f32 / 255.0,
+        )
+    }
+
+    /// Returns an opaque pixel with the color clamped into the sRGB gamut
+    #[inline]
+    pub fn to_pixelrgba(self) -> PixelRGBA {
+        let (r, g, b) = self.to_srgb();
+        PixelRGBA::new(
+            f32::clamp(r * 255.0 + 0.5, 0.0, 255.0) as u8,
+            f32::clamp(g * 255.0 + 0.5, 0.0, 255.0) as u8,
+            f32::clamp(b * 255.0 + 0.5, 0.0, 255.0) as u8,
+            255,
+        )
+    }
+
+    /// Expects gamma encoded sRGB components in [0, 1]
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Oklab {
+        Oklab::from_linear_rgb(
+            srgb_component_to_linear(r),
+            srgb_component_to_linear(g),
+            srgb_component_to_linear(b),
+        )
+    }
+
+    /// Returns gamma encoded sRGB components which can be out of the [0, 1] range if the color
+    /// is outside of the sRGB gamut
+    pub fn to_srgb(self) -> (f32, f32, f32) {
+        let (r, g, b) = self.to_linear_rgb();
+        (
+            linear_component_to_srgb(r),
+            linear_component_to_srgb(g),
+            linear_component_to_srgb(b),
+        )
+    }
+
+    pub fn from_linear_rgb(r: f32, g: f32, b: f32) -> Oklab {
+        let l = 0.41222146 * r + 0.53633255 * g + 0.051445995 * b;
+        let m = 0.2119035 * r + 0.6806995 * g + 0.10739696 * b;
+        let s = 0.08830246 * r + 0.28171885 * g + 0.6299787 * b;
+
+        let l = f32::cbrt(l);
+        let m = f32::cbrt(m);
+        let s = f32::cbrt(s);
+
+        Oklab {
+            l: 0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            a: 1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            b: 0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        }
+    }
+
+    pub fn to_linear_rgb(self) -> (f32, f32, f32) {
+        let l = self.l + 0.39633778 * self.a + 0.21580376 * self.b;
+        let m = self.l - 0.105561346 * self.a - 0.06385417 * self.b;
+        let s = self.l - 0.08948418 * self.a - 1.2914855 * self.b;
+
+        let l = l * l * l;
+        let m = m * m * m;
+        let s = s * s * s;
+
+        (
+            4.0767417 * l - 3.3077116 * m + 0.23096994 * s,
+            -1.268438 * l + 2.6097574 * m - 0.34131938 * s,
+            -0.0041960863 * l - 0.7034186 * m + 1.7076147 * s,
+        )
+    }
+
+    #[inline]
+    pub fn to_oklch(self) -> Oklch {
+        let chroma = f32::sqrt(self.a * self.a + self.b * self.b);
+        let hue = if chroma == 0.0 {
+            0.0
+        } else {
+            f32::atan2(self.b, self.a).to_degrees().rem_euclid(360.0)
+        };
+        Oklch::new(self.l, chroma, hue)
+    }
+
+    #[inline]
+    pub fn distance_squared(x: Oklab, y: Oklab) -> f32 {
+        let dl = x.l - y.l;
+        let da = x.a - y.a;
+        let db = x.b - y.b;
+        dl * dl + da * da + db * db
+    }
+
+    #[inline]
+    pub fn distance(x: Oklab, y: Oklab) -> f32 {
+        f32::sqrt(Oklab::distance_squared(x, y))
+    }
+}
+
+impl Lerp for Oklab {
+    #[inline]
+    fn lerp_value(start: Oklab, end: Oklab, percent: f32) -> Oklab {
+        Oklab {
+            l: lerp(start.l, end.l, percent),
+            a: lerp(start.a, end.a, percent),
+            b: lerp(start.b, end.b, percent),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Oklch
+
+/// Cylindrical form of Oklab with lightness, chroma and hue in degrees [0, 360[
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Oklch {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+impl Oklch {
+    #[inline]
+    pub const fn new(l: f32, c: f32, h: f32) -> Oklch {
+        Oklch { l, c, h }
+    }
+
+    #[inline]
+    pub fn to_oklab(self) -> Oklab {
+        let hue = self.h.to_radians();
+        Oklab::new(self.l, self.c * f32::cos(hue), self.c * f32::sin(hue))
+    }
+}
+
+impl Lerp for Oklch {
+    /// Interpolates the hue along the shorter arc. If one of the colors is achromatic its hue is
+    /// ignored so that fading to grey does not sweep through unrelated hues.
+    #[inline]
+    fn lerp_value(start: Oklch, end: Oklch, percent: f32) -> Oklch {
+        let (hue_start, hue_end) = if start.c == 0.0 {
+            (end.h, end.h)
+        } else if end.c == 0.0 {
+            (start.h, start.h)
+        } else {
+            (start.h, end.h)
+        };
+        let mut hue_difference = (hue_end - hue_start).rem_euclid(360.0);
+        if hue_difference > 180.0 {
+            hue_difference -= 360.0;
+        }
+        Oklch {
+            l: lerp(start.l, end.l, percent),
+            c: lerp(start.c, end.c, percent),
+            h: (hue_start + percent * hue_difference).rem_euclid(360.0),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// CIELAB
+
+/// CIE 1976 L*a*b* color space relative to the D65 white point. Mainly useful for the CIEDE2000
+/// color difference.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Cielab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl Cielab {
+    #[inline]
+    pub const fn new(l: f32, a: f32, b: f32) -> Cielab {
+        Cielab { l, a, b }
+    }
+
+    pub fn from_linear_rgb(r: f32, g: f32, b: f32) -> Cielab {
+        const WHITE_X: f32 = 0.95047;
+        const WHITE_Y: f32 = 1.0;
+        const WHITE_Z: f32 = 1.08883;
+
+        let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = 0.0193339 * r + 0.119192 * g + 0.9503041 * b;
+
+        fn f(t: f32) -> f32 {
+            const DELTA: f32 = 6.0 / 29.0;
+            if t > DELTA * DELTA * DELTA {
+                f32::cbrt(t)
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        }
+        let fx = f(x / WHITE_X);
+        let fy = f(y / WHITE_Y);
+        let fz = f(z / WHITE_Z);
+
+        Cielab {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+
+    /// The CIEDE2000 color difference where a value of about 1.0 is the smallest difference
+    /// a human can notice. Based on
+    /// http://www2.ece.rochester.edu/~gsharma/ciede2000/ciede2000noteCRNA.pdf
+    pub fn distance_ciede2000(x: Cielab, y: Cielab) -> f32 {
+        let (l1, a1, b1) = (x.l as f64, x.a as f64, x.b as f64);
+        let (l2, a2, b2) = (y.l as f64, y.a as f64, y.b as f64);
+        let pow_25_7 = 25.0f64.powi(7);
+
+        let c1 = f64::sqrt(a1 * a1 + b1 * b1);
+        let c2 = f64::sqrt(a2 * a2 + b2 * b2);
+        let c_mean = (c1 + c2) / 2.0;
+        let g = 0.5 * (1.0 - f64::sqrt(c_mean.powi(7) / (c_mean.powi(7) + pow_25_7)));
+
+        let a1 = (1.0 + g) * a1;
+        let a2 = (1.0 + g) * a2;
+        let c1 = f64::sqrt(a1 * a1 + b1 * b1);
+        let c2 = f64::sqrt(a2 * a2 + b2 * b2);
+        let hue = |a: f64, b: f64| {
+            if a == 0.0 && b == 0.0 {
+                0.0
+            } else {
+                f64::atan2(b, a).to_degrees().rem_euclid(360.0)
+            }
+        };
+        let h1 = hue(a1, b1);
+        let h2 = hue(a2, b2);
+
+        let delta_l = l2 - l1;
+        let delta_c = c2 - c1;
+        let delta_h = if c1 * c2 == 0.0 {
+            0.0
+        } else if (h2 - h1).abs() <= 180.0 {
+            h2 - h1
+        } else if h2 - h1 > 180.0 {
+            h2 - h1 - 360.0
+        } else {
+            h2 - h1 + 360.0
+        };
+        let delta_hue = 2.0 * f64::sqrt(c1 * c2) * f64::sin((delta_h / 2.0).to_radians());
+
+        let l_mean = (l1 + l2) / 2.0;
+        let c_mean = (c1 + c2) / 2.0;
+        let h_mean = if c1 * c2 == 0.0 {
+            h1 + h2
+        } else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        } else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        } else {
+            (h1 + h2 - 360.0) / 2.0
+        };
+
+        let t = 1.0 - 0.17 * f64::cos((h_mean - 30.0).to_radians())
+            + 0.24 * f64::cos((2.0 * h_mean).to_radians())
+            + 0.32 * f64::cos((3.0 * h_mean + 6.0).to_radians())
+            - 0.20 * f64::cos((4.0 * h_mean - 63.0).to_radians());
+        let delta_theta = 30.0 * f64::exp(-((h_mean - 275.0) / 25.0).powi(2));
+        let r_c = 2.0 * f64::sqrt(c_mean.powi(7) / (c_mean.powi(7) + pow_25_7));
+        let s_l =
+            1.0 + (0.015 * (l_mean - 50.0).powi(2)) / f64::sqrt(20.0 + (l_mean - 50.0).powi(2));
+        let s_c = 1.0 + 0.045 * c_mean;
+        let s_h = 1.0 + 0.015 * c_mean * t;
+        let r_t = -f64::sin((2.0 * delta_theta).to_radians()) * r_c;
+
+        let term_l = delta_l / s_l;
+        let term_c = delta_c / s_c;
+        let term_h = delta_hue / s_h;
+        f64::sqrt(term_l * term_l + term_c * term_c + term_h * term_h + r_t * term_c * term_h)
+            as f32
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Color space conversions
+
+impl Color {
+    /// Converts the color channels from sRGB to linear RGB
+    #[inline]
+    #[must_use = "This does not change the original color"]
+    pub fn to_linear_rgb(self) -> Color {
+        let color = self.to_unpremultipled_alpha();
+        Color::new(
+            srgb_component_to_linear(color.r),
+            srgb_component_to_linear(color.g),
+            srgb_component_to_linear(color.b),
+            color.a,
+        )
+        .to_premultiplied_alpha()
+    }
+
+    /// Converts the color channels from linear RGB to sRGB
+    #[inline]
+    pub fn from_linear_rgb(linear: Color) -> Color {
+        let linear = linear.to_unpremultipled_alpha();
+        Color::new(
+            linear_component_to_srgb(linear.r),
+            linear_component_to_srgb(linear.g),
+            linear_component_to_srgb(linear.b),
+            linear.a,
+        )
+        .to_premultiplied_alpha()
+    }
+
+    /// Ignores the alpha channel
+    #[inline]
+    pub fn to_oklab(self) -> Oklab {
+        let color = self.to_unpremultipled_alpha();
+        Oklab::from_srgb(color.r, color.g, color.b)
+    }
+
+    #[inline]
+    pub fn from_oklab(oklab: Oklab, alpha: f32) -> Color {
+        let (r, g, b) = oklab.to_srgb();
+        Color::new(r, g, b, alpha).to_premultiplied_alpha()
+    }
+
+    /// Ignores the alpha channel
+    #[inline]
+    pub fn to_oklch(self) -> Oklch {
+        self.to_oklab().to_oklch()
+    }
+
+    #[inline]
+    pub fn from_oklch(oklch: Oklch, alpha: f32) -> Color {
+        Color::from_oklab(oklch.to_oklab(), alpha)
+    }
+
+    /// Ignores the alpha channel
+    #[inline]
+    pub fn to_cielab(self) -> Cielab {
+        let linear = self.to_linear_rgb().to_unpremultipled_alpha();
+        Cielab::from_linear_rgb(linear.r, linear.g, linear.b)
+    }
+
+    /// The perceptual CIEDE2000 color difference which ignores the alpha channel. See
+    /// `Cielab::distance_ciede2000`.
+    #[inline]
+    pub fn distance_ciede2000(x: Color, y: Color) -> f32 {
+        Cielab::distance_ciede2000(x.to_cielab(), y.to_cielab())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Gradient
+
+pub const GRADIENT_STOP_COUNT_MAX: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GradientColorSpace {
+    Srgb,
+    LinearRgb,
+    Oklab,
+    /// Interpolates the hue along the shorter arc which gives vivid rainbow-like ramps
+    Oklch,
+}
+
+/// A multi-stop color gradient. The stops are `(percent, color)` pairs sorted by percent. Between
+/// two stops the colors are interpolated in the chosen color space, before the first and after
+/// the last stop the color is constant. Has a fixed capacity so that it can be `Copy` and used as
+/// a `Lerp` value, i.e. in particle curves.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "GradientState", into = "GradientState")]
+pub struct Gradient {
+    pub color_space: GradientColorSpace,
+    stop_count: usize,
+    stops: [(f32, Color); GRADIENT_STOP_COUNT_MAX],
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient::new(GradientColorSpace::Oklab, &[(0.0, Color::white())])
+    }
+}
+
+/// NOTE: Only the used stops are serialized. Deserialized data goes through the same validation
+///       as `Gradient::new` so that malformed data cannot break the invariants of `stops()`.
+#[derive(Serialize, Deserialize)]
+struct GradientState {
+    color_space: GradientColorSpace,
+    stops: Vec<(f32, Color)>,
+}
+
+impl TryFrom<GradientState> for Gradient {
+    type Error = String;
+
+    fn try_from(state: GradientState) -> Result<Gradient, String> {
+        Gradient::try_new(state.color_space, &state.stops)
+    }
+}
+
+impl From<Gradient> for GradientState {
+    fn from(gradient: Gradient) -> GradientState {
+        GradientState {
+            color_space: gradient.color_space,
+            stops: gradient.stops().to_vec(),
+        }
+    }
+}
+
+impl Gradient {
+    pub fn new(color_space: GradientColorSpace, stops: &[(f32, Color)]) -> Gradient {
+        Gradient::try_new(color_space, stops).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Same as `new` but returns an error instead of panicking if the stops are empty, exceed
+    /// `GRADIENT_STOP_COUNT_MAX` or are not sorted by percent
+    pub fn try_new(
+        color_space: GradientColorSpace,
+        stops: &[(f32, Color)],
+    ) -> Result<Gradient, String> {
+        if stops.is_empty() || stops.len() > GRADIENT_STOP_COUNT_MAX {
+            return Err(format!(
+                "Gradients need between 1 and {} stops - got {}",
+                GRADIENT_STOP_COUNT_MAX,
+                stops.len()
+            ));
+        }
+        if !stops.windows(2).all(|pair| pair[0].0 <= pair[1].0) {
+            return Err("Gradient stops must be sorted by percent".to_owned());
+        }
+
+        let mut result = Gradient {
+            color_space,
+            stop_count: stops.len(),
+            stops: [(0.0, Color::transparent()); GRADIENT_STOP_COUNT_MAX],
+        };
+        result.stops[..stops.len()].copy_from_slice(stops);
+        Ok(result)
+    }
+
+    /// Places the given colors evenly from 0 to 1
+    pub fn new_evenly_spaced(color_space: GradientColorSpace, colors: &[Color]) -> Gradient {
+        let stops: Vec<(f32, Color)> = colors
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let percent = if colors.len() > 1 {
+                    index as f32 / (colors.len() - 1) as f32
+                } else {
+                    0.0
+                };
+                (percent, *color)
+            })
+            .collect();
+        Gradient::new(color_space, &stops)
+    }
+
+    #[inline]
+    pub fn stops(&self) -> &[(f32, Color)] {
+        &self.stops[..self.stop_count]
+    }
+
+    pub fn sample(&self, percent: f32) -> Color {
+        let stops = self.stops();
+        let (first_percent, first_color) = stops[0];
+        if percent <= first_percent {
+            return first_color;
+        }
+        for pair in stops.windows(2) {
+            let (start_percent, start_color) = pair[0];
+            let (end_percent, end_color) = pair[1];
+            if percent < end_percent {
+                let segment_percent = if end_percent > start_percent {
+                    (percent - start_percent) / (end_percent - start_percent)
+                } else {
+                    1.0
+                };
+                return self.mix(start_color, end_color, segment_percent);
+            }
+        }
+        stops[stops.len() - 1].1
+    }
+
+    /// Returns `count` colors sampled evenly from 0 to 1
+    pub fn to_color_ramp(&self, count: usize) -> Vec<Color> {
+        (0..count)
+            .map(|index| {
+                let percent = if count > 1 {
+                    index as f32 / (count - 1) as f32
+                } else {
+                    0.0
+                };
+                self.sample(percent)
+            })
+            .collect()
+    }
+
+    fn mix(&self, start: Color, end: Color, percent: f32) -> Color {
+        // NOTE: We interpolate the unpremultiplied colors. Fully transparent stops take on the
+        //       color of the other stop so that fading out does not darken the color.
+        let mut start_opaque = start.to_unpremultipled_alpha().made_opaque();
+        let mut end_opaque = end.to_unpremultipled_alpha().made_opaque();
+        if start.a == 0.0 {
+            start_opaque = end_opaque;
+        } else if end.a == 0.0 {
+            end_opaque = start_opaque;
+        }
+
+        let mixed = match self.color_space {
+            GradientColorSpace::Srgb => Color::lerp(start_opaque, end_opaque, percent),
+            GradientColorSpace::LinearRgb => Color::from_linear_rgb(Color::lerp(
+                start_opaque.to_linear_rgb(),
+                end_opaque.to_linear_rgb(),
+                percent,
+            )),
+            GradientColorSpace::Oklab => Color::from_oklab(
+                Oklab::lerp_value(start_opaque.to_oklab(), end_opaque.to_oklab(), percent),
+                1.0,
+            ),
+            GradientColorSpace::Oklch => Color::from_oklch(
+                Oklch::lerp_value(start_opaque.to_oklch(), end_opaque.to_oklch(), percent),
+                1.0,
+            ),
+        };
+        Color {
+            a: lerp(start.a, end.a, percent),
+            ..mixed
+        }
+        .to_premultiplied_alpha()
+    }
+}
+
+impl Lerp for Gradient {
+    /// Blends two gradients by sampling both at the union of their stop positions. If there are
+    /// more distinct positions than a gradient can hold, evenly spaced positions are used instead.
+    /// The color space of the result is the one of `start`.
+    fn lerp_value(start: Gradient, end: Gradient, percent: f32) -> Gradient {
+        let mut positions: Vec<f32> = start
+            .stops()
+            .iter()
+            .chain(end.stops().iter())
+            .map(|(position, _)| *position)
+            .collect();
+        positions.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        positions.dedup_by(|x, y| (*x - *y).abs() < EPSILON);
+        if positions.len() > GRADIENT_STOP_COUNT_MAX {
+            let first = positions[0];
+            let last = positions[positions.len() - 1];
+            positions = (0..GRADIENT_STOP_COUNT_MAX)
+                .map(|index| {
+                    lerp(
+                        first,
+                        last,
+                        index as f32 / (GRADIENT_STOP_COUNT_MAX - 1) as f32,
+                    )
+                })
+                .collect();
+        }
+
+        let stops: Vec<(f32, Color)> = positions
+            .iter()
+            .map(|&position| {
+                (
+                    position,
+                    start.mix(start.sample(position), end.sample(position), percent),
+                )
+            })
+            .collect();
+        Gradient::new(start.color_space, &stops)
+    }
+}
+
+/// Removes the sRGB gamma encoding of a color component in [0, 1]
+#[inline]
+pub fn srgb_component_to_linear(component: f32) -> f32 {
+    if component <= 0.04045 {
+        component / 12.92
+    } else {
+        f32::powf((component + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// Applies the sRGB gamma encoding to a linear color component in [0, 1]
+#[inline]
+pub fn linear_component_to_srgb(component: f32) -> f32 {
+    if component <= 0.0031308 {
+        component * 12.92
+    } else {
+        1.055 * f32::powf(component, 1.0 / 2.4) - 0.055
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLEND_MODES_ALL: [ColorBlendMode; 14] = [
+        ColorBlendMode::Normal,
+        ColorBlendMode::Multiply,
+        ColorBlendMode::Screen,
+        ColorBlendMode::Overlay,
+        ColorBlendMode::Darken,
+        ColorBlendMode::Lighten,
+        ColorBlendMode::ColorDodge,
+        ColorBlendMode::ColorBurn,
+        ColorBlendMode::HardLight,
+        ColorBlendMode::SoftLight,
+        ColorBlendMode::Hue,
+        ColorBlendMode::Saturation,
+        ColorBlendMode::Color,
+        ColorBlendMode::Luminosity,
+    ];
+
+    fn assert_colors_equal(result: Color, expected: Color) {
+        assert!(
+            (result - expected).is_effectively_zero(),
+            "Expected {:?} but got {:?}",
+            expected,
+            result
+        );
+    }
+
+    fn blend_grey(source: f32, dest: f32, blend_mode: ColorBlendMode) -> f32 {
+        let result = Color::premultiplied_alpha_blend(
+            Color::greyscale(source),
+            Color::greyscale(dest),
+            blend_mode,
+        );
+        assert!(result.a == 1.0);
+        result.r
+    }
+
+    #[test]
+    fn blend_modes_with_transparent_colors() {
+        let color = Color::new(0.2, 0.3, 0.4, 0.5);
+        for &blend_mode in BLEND_MODES_ALL.iter() {
+            // Blending a transparent source must not change the destination
+            let result = Color::premultiplied_alpha_blend(Color::transparent(), color, blend_mode);
+            assert_colors_equal(result, color);
+
+            // Blending onto a transparent destination must yield the source
+            let result = Color::premultiplied_alpha_blend(color, Color::transparent(), blend_mode);
+            assert_colors_equal(result, color);
+        }
+    }
+
+    #[test]
+    fn blend_modes_separable() {
+        assert!(is_effectively_zero(
+            blend_grey(0.8, 0.25, ColorBlendMode::Overlay) - 0.4
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.8, 0.75, ColorBlendMode::Overlay) - 0.9
+        ));
+
+        assert!(is_effectively_zero(
+            blend_grey(0.8, 0.25, ColorBlendMode::Darken) - 0.25
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.8, 0.25, ColorBlendMode::Lighten) - 0.8
+        ));
+
+        assert!(is_effectively_zero(
+            blend_grey(0.5, 0.25, ColorBlendMode::ColorDodge) - 0.5
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(1.0, 0.25, ColorBlendMode::ColorDodge) - 1.0
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.7, 0.0, ColorBlendMode::ColorDodge) - 0.0
+        ));
+
+        assert!(is_effectively_zero(
+            blend_grey(0.5, 0.75, ColorBlendMode::ColorBurn) - 0.5
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.0, 0.75, ColorBlendMode::ColorBurn) - 0.0
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.3, 1.0, ColorBlendMode::ColorBurn) - 1.0
+        ));
+
+        assert!(is_effectively_zero(
+            blend_grey(0.25, 0.5, ColorBlendMode::HardLight) - 0.25
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.75, 0.5, ColorBlendMode::HardLight) - 0.75
+        ));
+
+        assert!(is_effectively_zero(
+            blend_grey(0.5, 0.3, ColorBlendMode::SoftLight) - 0.3
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(1.0, 0.25, ColorBlendMode::SoftLight) - 0.5
+        ));
+        assert!(is_effectively_zero(
+            blend_grey(0.0, 0.5, ColorBlendMode::SoftLight) - 0.25
+        ));
+    }
+
+    #[test]
+    fn blend_modes_separable_with_partial_alpha() {
+        // Half transparent white darkened onto opaque black stays black
+        let source = Color::white().to_premultiplied_alpha() * 0.5;
+        let result =
+            Color::premultiplied_alpha_blend(source, Color::black(), ColorBlendMode::Darken);
+        assert_colors_equal(result, Color::black());
+
+        // Half transparent white lightened onto opaque black is the same as normal blending
+        let result_lighten =
+            Color::premultiplied_alpha_blend(source, Color::black(), ColorBlendMode::Lighten);
+        let result_normal =
+            Color::premultiplied_alpha_blend(source, Color::black(), ColorBlendMode::Normal);
+        assert_colors_equal(result_lighten, result_normal);
+        assert_colors_equal(result_lighten, Color::new(0.5, 0.5, 0.5, 1.0));
+    }
+
+    #[test]
+    fn blend_modes_non_separable() {
+        let red = Color::from_rgb(1.0, 0.0, 0.0);
+        let grey = Color::greyscale(0.5);
+
+        // Hue and saturation of a grey source remove all saturation but keep the luminosity
+        let result = Color::premultiplied_alpha_blend(grey, red, ColorBlendMode::Hue);
+        assert_colors_equal(result, Color::greyscale(red.luminosity()));
+        let result = Color::premultiplied_alpha_blend(grey, red, ColorBlendMode::Saturation);
+        assert_colors_equal(result, Color::greyscale(red.luminosity()));
+
+        // Hue of a red source onto a saturated green keeps the greens saturation and luminosity
+        let green = Color::from_rgb(0.0, 0.8, 0.0);
+        let result = Color::premultiplied_alpha_blend(red, green, ColorBlendMode::Hue);
+        assert!(is_effectively_zero(
+            result.luminosity() - green.luminosity()
+        ));
+        assert!(result.r > result.g && result.g == result.b);
+
+        // Color keeps the luminosity of the destination
+        let result = Color::premultiplied_alpha_blend(red, grey, ColorBlendMode::Color);
+        assert!(is_effectively_zero(result.luminosity() - 0.5));
+        assert!(result.r > result.g && result.g == result.b);
+
+        // Color and luminosity are inverses of each other
+        let result_color = Color::premultiplied_alpha_blend(red, green, ColorBlendMode::Color);
+        let result_luminosity =
+            Color::premultiplied_alpha_blend(green, red, ColorBlendMode::Luminosity);
+        assert_colors_equal(result_color, result_luminosity);
+    }
+
+    #[test]
+    fn saturation_replacement() {
+        let color = Color::from_rgb(0.2, 0.6, 0.4);
+        assert!(is_effectively_zero(color.saturation() - 0.4));
+
+        let result = color.with_replaced_saturation(0.8);
+        assert_colors_equal(result, Color::from_rgb(0.0, 0.8, 0.4));
+        assert!(is_effectively_zero(result.saturation() - 0.8));
+
+        let result = Color::greyscale(0.7).with_replaced_saturation(0.5);
+        assert_colors_equal(result, Color::from_rgb(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn oklab_and_oklch_conversions() {
+        let red = Color::from_rgb(1.0, 0.0, 0.0).to_oklab();
+        assert!((red.l - 0.627955).abs() < 0.001);
+        assert!((red.a - 0.224863).abs() < 0.001);
+        assert!((red.b - 0.125846).abs() < 0.001);
+
+        let white = Color::white().to_oklch();
+        assert!((white.l - 1.0).abs() < 0.001);
+        assert!(white.c < 0.001);
+
+        let color = Color::new(0.1, 0.3, 0.2, 0.5);
+        assert!(Color::distance(Color::from_oklab(color.to_oklab(), 0.5), color) < 0.001);
+        assert!(Color::distance(Color::from_oklch(color.to_oklch(), 0.5), color) < 0.001);
+        assert!(Color::distance(Color::from_linear_rgb(color.to_linear_rgb()), color) < 0.001);
+        assert!((srgb_component_to_linear(0.5) - 0.214041).abs() < 0.0001);
+    }
+
+    #[test]
+    fn ciede2000_reference_values() {
+        // NOTE: Test data from http://www2.ece.rochester.edu/~gsharma/ciede2000/
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.49, -0.001), (50.0, -2.49, 0.0011), 7.2195),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            (
+                (60.2574, -34.0099, 36.2677),
+                (60.4626, -34.1751, 39.4387),
+                1.2644,
+            ),
+            (
+                (22.7233, 20.0904, -46.694),
+                (23.0331, 14.973, -42.5619),
+                2.0373,
+            ),
+        ];
+        for &((l1, a1, b1), (l2, a2, b2), expected) in &pairs {
+            let x = Cielab::new(l1, a1, b1);
+            let y = Cielab::new(l2, a2, b2);
+            assert!((Cielab::distance_ciede2000(x, y) - expected).abs() < 0.001);
+            assert!((Cielab::distance_ciede2000(y, x) - expected).abs() < 0.001);
+        }
+        assert_eq!(
+            Color::distance_ciede2000(Color::white(), Color::white()),
+            0.0
+        );
+    }
+
+    #[test]
+    fn gradient_sampling() {
+        let red = Color::from_rgb(1.0, 0.0, 0.0);
+        let blue = Color::from_rgb(0.0, 0.0, 1.0);
+        let gradient = Gradient::new(
+            GradientColorSpace::Srgb,
+            &[(0.25, red), (0.5, blue), (1.0, Color::white())],
+        );
+        assert_colors_equal(gradient.sample(0.0), red);
+        assert_colors_equal(gradient.sample(0.25), red);
+        assert_colors_equal(gradient.sample(0.375), Color::from_rgb(0.5, 0.0, 0.5));
+        assert_colors_equal(gradient.sample(0.75), Color::from_rgb(0.5, 0.5, 1.0));
+        assert_colors_equal(gradient.sample(2.0), Color::white());
+        assert_eq!(gradient.to_color_ramp(5).len(), 5);
+
+        // NOTE: Fading out must not darken the color
+        for &color_space in &[
+            GradientColorSpace::Srgb,
+            GradientColorSpace::LinearRgb,
+            GradientColorSpace::Oklab,
+            GradientColorSpace::Oklch,
+        ] {
+            let fade = Gradient::new_evenly_spaced(color_space, &[red, Color::transparent()]);
+            let middle = fade.sample(0.5).to_unpremultipled_alpha();
+            assert!((middle.a - 0.5).abs() < 0.001);
+            assert!((middle.r - 1.0).abs() < 0.001 && middle.g.abs() < 0.001);
+        }
+
+        // NOTE: The hue of an Oklch ramp between red and blue passes through purple
+        let ramp = Gradient::new_evenly_spaced(GradientColorSpace::Oklch, &[red, blue]);
+        let middle = ramp.sample(0.5);
+        assert!(middle.r > middle.g && middle.b > middle.g);
+    }
+
+    #[test]
+    fn gradient_lerp() {
+        let start = Gradient::new_evenly_spaced(
+            GradientColorSpace::Srgb,
+            &[Color::black(), Color::white()],
+        );
+        let end = Gradient::new(
+            GradientColorSpace::Srgb,
+            &[
+                (0.0, Color::white()),
+                (0.5, Color::black()),
+                (1.0, Color::white()),
+            ],
+        );
+        let result = Gradient::lerp_value(start, end, 0.5);
+        assert_eq!(result.stops().len(), 3);
+        assert_colors_equal(result.sample(0.0), Color::greyscale(0.5));
+        assert_colors_equal(result.sample(0.5), Color::greyscale(0.25));
+        assert_colors_equal(result.sample(1.0), Color::white());
+    }
+
+    #[test]
+    fn gradient_serialization_validates_stops() {
+        use super::super::core::serde_json;
+
+        let gradient = Gradient::new_evenly_spaced(
+            GradientColorSpace::Oklch,
+            &[Color::black(), Color::white(), Color::black()],
+        );
+        let json = serde_json::to_string(&gradient).unwrap();
+        let restored: Gradient = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.color_space, GradientColorSpace::Oklch);
+        assert_eq!(restored.stops().len(), gradient.stops().len());
+        for (restored_stop, stop) in restored.stops().iter().zip(gradient.stops().iter()) {
+            assert_eq!(restored_stop.0, stop.0);
+            assert_colors_equal(restored_stop.1, stop.1);
+        }
+
+        let gradient_json = |percents: &[f32]| {
+            let stops: Vec<String> = percents
+                .iter()
+                .map(|percent| format!("[{},{{\"r\":1,\"g\":1,\"b\":1,\"a\":1}}]", percent))
+                .collect();
+            format!(
+                "{{\"color_space\":\"Srgb\",\"stops\":[{}]}}",
+                stops.join(",")
+            )
+        };
+        assert!(serde_json::from_str::<Gradient>(&gradient_json(&[0.0, 1.0])).is_ok());
+        assert!(serde_json::from_str::<Gradient>(&gradient_json(&[])).is_err());
+        assert!(serde_json::from_str::<Gradient>(&gradient_json(&[1.0, 0.0])).is_err());
+        assert!(serde_json::from_str::<Gradient>(&gradient_json(&[0.0; 9])).is_err());
+    }
+}
@@ -0,0 +1,751 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Signed distance fields
+//
+// NOTE: Distances are given in pixels and are positive inside of shapes. They are encoded into
+//       pixel channels as `0.5 + distance / (2 * spread)` clamped to [0, 1] so that a channel value
+//       of 0.5 marks the edge of a shape.
+//
+//       Single channel distance fields store the same distance in all four channels. Multi channel
+//       distance fields (MSDF) store per-channel pseudo-distances in the RGB channels which allows
+//       reconstructing sharp corners via the median of the three channels. Their alpha channel
+//       contains the true distance which is better suited for rounded effects like outlines and
+//       glows. For more information see https://github.com/Chlumsky/msdfgen
+
+use super::bitmap::*;
+use super::math::*;
+
+use rusttype::OutlineBuilder;
+
+/// Edges of a contour which are shorter than this (in pixels) are ignored
+const EDGE_LENGTH_MIN: f32 = 0.0001;
+/// The maximum length (in pixels) of the line segments that curves are flattened into
+const CURVE_FLATTENING_SEGMENT_LENGTH: f32 = 0.5;
+/// Two edges meet in a corner if the sine of their angle is above this threshold (~3 degrees)
+const CORNER_ANGLE_SINE_THRESHOLD: f32 = 0.05;
+
+const EDGE_COLOR_RED: u8 = 1;
+const EDGE_COLOR_GREEN: u8 = 2;
+const EDGE_COLOR_BLUE: u8 = 4;
+const EDGE_COLOR_WHITE: u8 = EDGE_COLOR_RED | EDGE_COLOR_GREEN | EDGE_COLOR_BLUE;
+const EDGE_COLOR_CYAN: u8 = EDGE_COLOR_GREEN | EDGE_COLOR_BLUE;
+const EDGE_COLOR_MAGENTA: u8 = EDGE_COLOR_RED | EDGE_COLOR_BLUE;
+const EDGE_COLOR_YELLOW: u8 = EDGE_COLOR_RED | EDGE_COLOR_GREEN;
+
+#[inline]
+pub fn distance_field_encode(distance: f32, spread: f32) -> u8 {
+    (255.0 * f32::clamp(0.5 + distance / (2.0 * spread), 0.0, 1.0) + 0.5) as u8
+}
+
+#[inline]
+pub fn distance_field_decode(value: u8, spread: f32) -> f32 {
+    (value as f32 / 255.0 - 0.5) * 2.0 * spread
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Distance fields from bitmaps
+
+impl Bitmap {
+    /// Creates a single channel signed distance field from the alpha channel where pixels with at
+    /// least 50% opacity count as inside. The result is extended by `ceil(spread)` pixels on each
+    /// side so that the distance field does not get cut off at the bitmap borders.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn to_distance_field(&self, spread: f32) -> Bitmap {
+        assert!(spread > 0.0);
+        let padding = ceili(spread);
+        let width = self.width + 2 * padding;
+        let height = self.height + 2 * padding;
+
+        let is_inside = |x: i32, y: i32| {
+            self.get_or_default(x - padding, y - padding, PixelRGBA::transparent())
+                .a
+                >= 128
+        };
+        let distances_to_inside = euclidean_distance_transform(width, height, is_inside);
+        let distances_to_outside =
+            euclidean_distance_transform(width, height, |x, y| !is_inside(x, y));
+
+        let mut result = Bitmap::new(width as u32, height as u32);
+        for y in 0..height {
+            for x in 0..width {
+                let index = (x + y * width) as usize;
+                // NOTE: The edge of the shape lies halfway between the centers of an inside and an
+                //       outside pixel
+                let distance = if is_inside(x, y) {
+                    distances_to_outside[index] - 0.5
+                } else {
+                    0.5 - distances_to_inside[index]
+                };
+                let value = distance_field_encode(distance, spread);
+                result.set(x, y, PixelRGBA::new(value, value, value, value));
+            }
+        }
+        result
+    }
+}
+
+/// Returns the euclidean distance of every cell to the nearest cell for which `is_feature` holds.
+/// Implements "Distance Transforms of Sampled Functions" by Felzenszwalb and Huttenlocher.
+pub(crate) fn euclidean_distance_transform<F: Fn(i32, i32) -> bool>(
+    width: i32,
+    height: i32,
+    is_feature: F,
+) -> Vec<f32> {
+    // NOTE: We don't use `f32::MAX` here because we need to square and add these values
+    const DISTANCE_INFINITE: f32 = 1.0e20;
+
+    let mut distances_squared = vec![0.0; (width * height) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            distances_squared[(x + y * width) as usize] = if is_feature(x, y) {
+                0.0
+            } else {
+                DISTANCE_INFINITE
+            };
+        }
+    }
+
+    let size_max = i32::max(width, height) as usize;
+    let mut line = vec![0.0; size_max];
+    let mut line_transformed = vec![0.0; size_max];
+    let mut parabola_vertices = vec![0usize; size_max];
+    let mut parabola_boundaries = vec![0.0; size_max + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            line[y as usize] = distances_squared[(x + y * width) as usize];
+        }
+        distance_transform_1d(
+            &line[..height as usize],
+            &mut line_transformed[..height as usize],
+            &mut parabola_vertices,
+            &mut parabola_boundaries,
+        );
+        for y in 0..height {
+            distances_squared[(x + y * width) as usize] = line_transformed[y as usize];
+        }
+    }
+    for y in 0..height {
+        let row = &mut distances_squared[(y * width) as usize..((y + 1) * width) as usize];
+        line[..width as usize].copy_from_slice(row);
+        distance_transform_1d(
+            &line[..width as usize],
+            row,
+            &mut parabola_vertices,
+            &mut parabola_boundaries,
+        );
+    }
+
+    distances_squared
+        .into_iter()
+        .map(|distance_squared| distance_squared.sqrt())
+        .collect()
+}
+
+fn distance_transform_1d(
+    values: &[f32],
+    result: &mut [f32],
+    parabola_vertices: &mut [usize],
+    parabola_boundaries: &mut [f32],
+) {
+    let intersection = |q: usize, vertex: usize| -> f32 {
+        ((values[q] + (q * q) as f32) - (values[vertex] + (vertex * vertex) as f32))
+            / (2.0 * q as f32 - 2.0 * vertex as f32)
+    };
+
+    let mut parabola_index = 0;
+    parabola_vertices[0] = 0;
+    parabola_boundaries[0] = -f32::MAX;
+    parabola_boundaries[1] = f32::MAX;
+    for q in 1..values.len() {
+        let mut s = intersection(q, parabola_vertices[parabola_index]);
+        while s <= parabola_boundaries[parabola_index] {
+            parabola_index -= 1;
+            s = intersection(q, parabola_vertices[parabola_index]);
+        }
+        parabola_index += 1;
+        parabola_vertices[parabola_index] = q;
+        parabola_boundaries[parabola_index] = s;
+        parabola_boundaries[parabola_index + 1] = f32::MAX;
+    }
+
+    parabola_index = 0;
+    for (q, result_value) in result.iter_mut().enumerate().take(values.len()) {
+        while parabola_boundaries[parabola_index + 1] < q as f32 {
+            parabola_index += 1;
+        }
+        let vertex = parabola_vertices[parabola_index];
+        *result_value = squared(q as f32 - vertex as f32) + values[vertex];
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Distance fields from vector shapes
+
+/// A shape made of closed contours that can be turned into signed distance fields. It can be built
+/// from font glyph outlines or manually via the `OutlineBuilder` interface. Curves are
+/// flattened into line segments. Coordinates are given in pixels where y points down.
+#[derive(Debug, Clone, Default)]
+pub struct DistanceFieldShape {
+    /// Every contour is a list of edges and every edge is a polyline. We need to keep the edges
+    /// separate from each other to be able to detect corners for the MSDF generation.
+    contours: Vec<Vec<Vec<Vec2>>>,
+    contour_current: Vec<Vec<Vec2>>,
+    contour_start: Vec2,
+    pos_current: Vec2,
+}
+
+impl OutlineBuilder for DistanceFieldShape {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        self.contour_start = Vec2::new(x, y);
+        self.pos_current = self.contour_start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let end = Vec2::new(x, y);
+        self.push_edge(vec![self.pos_current, end]);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let start = self.pos_current;
+        let control = Vec2::new(x1, y1);
+        let end = Vec2::new(x, y);
+
+        let length_estimate = Vec2::distance(start, control) + Vec2::distance(control, end);
+        let segment_count = curve_flattening_segment_count(length_estimate);
+        let points = (0..=segment_count)
+            .map(|index| {
+                let t = index as f32 / segment_count as f32;
+                squared(1.0 - t) * start + 2.0 * (1.0 - t) * t * control + squared(t) * end
+            })
+            .collect();
+        self.push_edge(points);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let start = self.pos_current;
+        let control_a = Vec2::new(x1, y1);
+        let control_b = Vec2::new(x2, y2);
+        let end = Vec2::new(x, y);
+
+        let length_estimate = Vec2::distance(start, control_a)
+            + Vec2::distance(control_a, control_b)
+            + Vec2::distance(control_b, end);
+        let segment_count = curve_flattening_segment_count(length_estimate);
+        let points = (0..=segment_count)
+            .map(|index| {
+                let t = index as f32 / segment_count as f32;
+                cubed(1.0 - t) * start
+                    + 3.0 * squared(1.0 - t) * t * control_a
+                    + 3.0 * (1.0 - t) * squared(t) * control_b
+                    + cubed(t) * end
+            })
+            .collect();
+        self.push_edge(points);
+    }
+
+    fn close(&mut self) {
+        if Vec2::distance(self.pos_current, self.contour_start) > EDGE_LENGTH_MIN {
+            self.push_edge(vec![self.pos_current, self.contour_start]);
+        }
+        self.pos_current = self.contour_start;
+        if !self.contour_current.is_empty() {
+            let contour = std::mem::take(&mut self.contour_current);
+            self.contours.push(contour);
+        }
+    }
+}
+
+impl DistanceFieldShape {
+    pub fn new() -> DistanceFieldShape {
+        DistanceFieldShape::default()
+    }
+
+    /// Creates the shape from the outline of a glyph. The coordinates are relative to the glyphs
+    /// origin on the baseline.
+    pub fn from_glyph(glyph: &rusttype::ScaledGlyph) -> DistanceFieldShape {
+        let mut result = DistanceFieldShape::new();
+        glyph.build_outline(&mut result);
+        result.close();
+        result
+    }
+
+    /// Creates the shape from a closed polygon
+    pub fn from_polygon(points: &[Vec2]) -> DistanceFieldShape {
+        let mut result = DistanceFieldShape::new();
+        if let Some((first, rest)) = points.split_first() {
+            result.move_to(first.x, first.y);
+            for point in rest {
+                result.line_to(point.x, point.y);
+            }
+            result.close();
+        }
+        result
+    }
+
+    /// Returns the bounds of the shape or `None` if the shape is empty
+    pub fn bounds(&self) -> Option<Rect> {
+        let mut points = self.points();
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), point| {
+            (
+                Vec2::new(f32::min(min.x, point.x), f32::min(min.y, point.y)),
+                Vec2::new(f32::max(max.x, point.x), f32::max(max.y, point.y)),
+            )
+        });
+        Some(Rect::from_bounds_left_top_right_bottom(
+            min.x, min.y, max.x, max.y,
+        ))
+    }
+
+    /// Creates a single channel signed distance field of the given dimensions. The pixel at (0, 0)
+    /// covers the shape region starting at `origin`.
+    pub fn to_distance_field(&self, origin: Vec2, width: u32, height: u32, spread: f32) -> Bitmap {
+        let mut result = Bitmap::new(width, height);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let point = origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let value = distance_field_encode(self.signed_distance(point), spread);
+                result.set(x, y, PixelRGBA::new(value, value, value, value));
+            }
+        }
+        result
+    }
+
+    /// Creates a multi channel signed distance field of the given dimensions with the true signed
+    /// distance in its alpha channel. The pixel at (0, 0) covers the shape region starting at
+    /// `origin`.
+    pub fn to_multichannel_distance_field(
+        &self,
+        origin: Vec2,
+        width: u32,
+        height: u32,
+        spread: f32,
+    ) -> Bitmap {
+        let edges: Vec<(Vec<Vec2>, u8)> = self
+            .contours
+            .iter()
+            .flat_map(|contour| contour_edges_colored(contour))
+            .collect();
+        let orientation = self.orientation();
+
+        let mut result = Bitmap::new(width, height);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let point = origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+                // NOTE: Every channel takes the pseudo-distance of its closest edge
+                let mut channels = [EdgeDistance::none(); 3];
+                let mut distance_min = f32::MAX;
+                for (edge, color) in &edges {
+                    let edge_distance = EdgeDistance::new(edge, point, orientation);
+                    distance_min = f32::min(distance_min, edge_distance.distance);
+                    for (channel_index, channel) in channels.iter_mut().enumerate() {
+                        if color & (1 << channel_index) != 0
+                            && edge_distance.is_closer_than(channel)
+                        {
+                            *channel = edge_distance;
+                        }
+                    }
+                }
+
+                let distance_true = if self.contains_point(point) {
+                    distance_min
+                } else {
+                    -distance_min
+                };
+                let mut distances = [0.0; 3];
+                for (distance, channel) in distances.iter_mut().zip(channels.iter()) {
+                    *distance = if channel.distance == f32::MAX {
+                        distance_true
+                    } else {
+                        channel.pseudo_distance
+                    };
+                }
+
+                // NOTE: If the median disagrees with the true distance about whether we are inside
+                //       or outside we would get visible artifacts, so we fall back to the true
+                //       distance in this case
+                let median = median(distances[0], distances[1], distances[2]);
+                if (median > 0.0) != (distance_true > 0.0) {
+                    distances = [distance_true; 3];
+                }
+
+                result.set(
+                    x,
+                    y,
+                    PixelRGBA::new(
+                        distance_field_encode(distances[0], spread),
+                        distance_field_encode(distances[1], spread),
+                        distance_field_encode(distances[2], spread),
+                        distance_field_encode(distance_true, spread),
+                    ),
+                );
+            }
+        }
+        result
+    }
+
+    /// Returns the signed distance to the shape which is positive inside the shape
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        let distance = self
+            .segments()
+            .map(|(start, end)| {
+                let (closest, _t) = closest_point_on_segment(start, end, point);
+                Vec2::distance(point, closest)
+            })
+            .fold(f32::MAX, f32::min);
+        if self.contains_point(point) {
+            distance
+        } else {
+            -distance
+        }
+    }
+
+    /// Uses the non-zero winding rule
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        let mut winding_number = 0;
+        for (start, end) in self.segments() {
+            if start.y <= point.y {
+                if end.y > point.y && Vec2::cross_z(end - start, point - start) > 0.0 {
+                    winding_number += 1;
+                }
+            } else if end.y <= point.y && Vec2::cross_z(end - start, point - start) < 0.0 {
+                winding_number -= 1;
+            }
+        }
+        winding_number != 0
+    }
+
+    fn push_edge(&mut self, points: Vec<Vec2>) {
+        let end = *points.last().unwrap();
+        let length: f32 = points
+            .windows(2)
+            .map(|pair| Vec2::distance(pair[0], pair[1]))
+            .sum();
+        if length > EDGE_LENGTH_MIN {
+            self.contour_current.push(points);
+        }
+        self.pos_current = end;
+    }
+
+    fn points(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.contours
+            .iter()
+            .flat_map(|contour| contour.iter())
+            .flat_map(|edge| edge.iter().cloned())
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.contours
+            .iter()
+            .flat_map(|contour| contour.iter())
+            .flat_map(|edge| edge.windows(2).map(|pair| (pair[0], pair[1])))
+    }
+
+    /// Returns 1.0 if the inside of the shape lies to the left of its edges when walking along them
+    /// in the sense of positive cross products, -1.0 otherwise
+    fn orientation(&self) -> f32 {
+        let area_doubled: f32 = self
+            .segments()
+            .map(|(start, end)| Vec2::cross_z(start, end))
+            .sum();
+        if area_doubled < 0.0 {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Assigns colors to the edges of a contour such that the two edges meeting in a corner always
+/// share exactly one color channel. This is a variant of the simple edge coloring of msdfgen.
+fn contour_edges_colored(contour: &[Vec<Vec2>]) -> Vec<(Vec<Vec2>, u8)> {
+    let edge_count = contour.len();
+    let corner_indices: Vec<usize> = (0..edge_count)
+        .filter(|&index| {
+            let previous = &contour[(index + edge_count - 1) % edge_count];
+            let current = &contour[index];
+            let direction_in = edge_direction_at_end(previous);
+            let direction_out = edge_direction_at_start(current);
+            Vec2::dot(direction_in, direction_out) <= 0.0
+                || Vec2::cross_z(direction_in, direction_out).abs() > CORNER_ANGLE_SINE_THRESHOLD
+        })
+        .collect();
+
+    if corner_indices.is_empty() {
+        // NOTE: Smooth contours don't need any special treatment
+        return contour
+            .iter()
+            .map(|edge| (edge.clone(), EDGE_COLOR_WHITE))
+            .collect();
+    }
+
+    // NOTE: We rotate the edges so that the contour starts at a corner
+    let mut edges: Vec<Vec<Vec2>> = contour[corner_indices[0]..]
+        .iter()
+        .chain(contour[..corner_indices[0]].iter())
+        .cloned()
+        .collect();
+
+    if corner_indices.len() == 1 {
+        // NOTE: A contour with a single corner (i.e. a teardrop) needs at least three edges to be
+        //       colored correctly, so we split up edges until we have enough
+        while edges.len() < 3 {
+            let longest_index = (0..edges.len())
+                .max_by_key(|&index| edges[index].len())
+                .unwrap();
+            let edge = edges.remove(longest_index);
+            let (first, second) = edge_split_in_half(&edge);
+            edges.insert(longest_index, second);
+            edges.insert(longest_index, first);
+        }
+        let edge_count = edges.len();
+        let colors = [EDGE_COLOR_MAGENTA, EDGE_COLOR_WHITE, EDGE_COLOR_YELLOW];
+        return edges
+            .into_iter()
+            .enumerate()
+            .map(|(index, edge)| (edge, colors[3 * index / edge_count]))
+            .collect();
+    }
+
+    // NOTE: The edges between two corners form a spline which gets a single color. Neighboring
+    //       splines must have different colors, which includes the last and the first spline.
+    let corner_indices: Vec<usize> = corner_indices
+        .iter()
+        .map(|&index| (index + edge_count - corner_indices[0]) % edge_count)
+        .collect();
+    let spline_count = corner_indices.len();
+    let colors = [EDGE_COLOR_CYAN, EDGE_COLOR_MAGENTA, EDGE_COLOR_YELLOW];
+    let mut spline_index = 0;
+    edges
+        .into_iter()
+        .enumerate()
+        .map(|(index, edge)| {
+            if spline_index + 1 < spline_count && index == corner_indices[spline_index + 1] {
+                spline_index += 1;
+            }
+            let color = if spline_index == spline_count - 1 && spline_index % 3 == 0 {
+                colors[1]
+            } else {
+                colors[spline_index % 3]
+            };
+            (edge, color)
+        })
+        .collect()
+}
+
+fn edge_split_in_half(edge: &[Vec2]) -> (Vec<Vec2>, Vec<Vec2>) {
+    if edge.len() == 2 {
+        let center = Vec2::lerp(edge[0], edge[1], 0.5);
+        (vec![edge[0], center], vec![center, edge[1]])
+    } else {
+        let center_index = edge.len() / 2;
+        (
+            edge[..=center_index].to_vec(),
+            edge[center_index..].to_vec(),
+        )
+    }
+}
+
+#[inline]
+fn edge_direction_at_start(edge: &[Vec2]) -> Vec2 {
+    edge.windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .find(|direction| direction.magnitude() > EDGE_LENGTH_MIN)
+        .unwrap_or(Vec2::zero())
+        .normalized_or(Vec2::zero())
+}
+
+#[inline]
+fn edge_direction_at_end(edge: &[Vec2]) -> Vec2 {
+    edge.windows(2)
+        .rev()
+        .map(|pair| pair[1] - pair[0])
+        .find(|direction| direction.magnitude() > EDGE_LENGTH_MIN)
+        .unwrap_or(Vec2::zero())
+        .normalized_or(Vec2::zero())
+}
+
+#[inline]
+fn curve_flattening_segment_count(length_estimate: f32) -> usize {
+    usize::clamp(
+        (length_estimate / CURVE_FLATTENING_SEGMENT_LENGTH).ceil() as usize,
+        2,
+        64,
+    )
+}
+
+/// Returns the closest point on the segment and its unclamped parameter on the segments line
+#[inline]
+fn closest_point_on_segment(start: Vec2, end: Vec2, point: Vec2) -> (Vec2, f32) {
+    let direction = end - start;
+    let length_squared = direction.magnitude_squared();
+    if length_squared == 0.0 {
+        return (start, 0.0);
+    }
+    let t = Vec2::dot(point - start, direction) / length_squared;
+    (start + f32::clamp(t, 0.0, 1.0) * direction, t)
+}
+
+#[inline]
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    f32::max(f32::min(a, b), f32::min(f32::max(a, b), c))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EdgeDistance {
+    /// Absolute distance to the closest point on the edge
+    distance: f32,
+    /// Used to break ties between edges that share their closest point (i.e. at corners). The
+    /// edge that is more perpendicular to the direction to our point wins.
+    orthogonality: f32,
+    /// Signed distance which uses the extended edge line beyond the edges endpoints
+    pseudo_distance: f32,
+}
+
+impl EdgeDistance {
+    #[inline]
+    fn none() -> EdgeDistance {
+        EdgeDistance {
+            distance: f32::MAX,
+            orthogonality: 0.0,
+            pseudo_distance: 0.0,
+        }
+    }
+
+    fn new(edge: &[Vec2], point: Vec2, orientation: f32) -> EdgeDistance {
+        let segment_count = edge.len() - 1;
+        let mut result = EdgeDistance::none();
+        for segment_index in 0..segment_count {
+            let start = edge[segment_index];
+            let end = edge[segment_index + 1];
+            let direction = (end - start).normalized_or(Vec2::zero());
+            let (closest, t) = closest_point_on_segment(start, end, point);
+            let to_point = point - closest;
+            let distance = to_point.magnitude();
+            let side = orientation * Vec2::cross_z(direction, point - start);
+            let orthogonality = if distance > EDGE_LENGTH_MIN {
+                Vec2::cross_z(direction, to_point / distance).abs()
+            } else {
+                1.0
+            };
+
+            let mut pseudo_distance = if side < 0.0 { -distance } else { distance };
+            let is_first_segment_before_start = segment_index == 0 && t < 0.0;
+            let is_last_segment_after_end = segment_index == segment_count - 1 && t > 1.0;
+            if is_first_segment_before_start || is_last_segment_after_end {
+                // NOTE: The perpendicular distance to the extended line
+                if side.abs() <= distance {
+                    pseudo_distance = side;
+                }
+            }
+
+            let candidate = EdgeDistance {
+                distance,
+                orthogonality,
+                pseudo_distance,
+            };
+            if candidate.is_closer_than(&result) {
+                result = candidate;
+            }
+        }
+        result
+    }
+
+    #[inline]
+    fn is_closer_than(&self, other: &EdgeDistance) -> bool {
+        const TIE_TOLERANCE: f32 = 0.0001;
+        if (self.distance - other.distance).abs() <= TIE_TOLERANCE {
+            self.orthogonality > other.orthogonality
+        } else {
+            self.distance < other.distance
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_field_from_bitmap() {
+        let mut bitmap = Bitmap::new(8, 8);
+        bitmap.draw_rect_filled(2, 2, 4, 4, PixelRGBA::white());
+
+        let spread = 4.0;
+        let field = bitmap.to_distance_field(spread);
+        assert_eq!((field.width, field.height), (16, 16));
+
+        // NOTE: The square covers the pixels [6, 9] of the distance field
+        let distance_at = |x, y| distance_field_decode(field.get(x, y).a, spread);
+        assert!((distance_at(6, 7) - 0.5).abs() < 0.05);
+        assert!((distance_at(7, 7) - 1.5).abs() < 0.05);
+        assert!((distance_at(5, 7) + 0.5).abs() < 0.05);
+        assert!((distance_at(3, 7) + 2.5).abs() < 0.05);
+        assert_eq!(field.get(0, 0).a, 0);
+
+        // NOTE: Distances are euclidean and not manhattan
+        assert!((distance_at(4, 4) + (8.0f32.sqrt() - 0.5)).abs() < 0.05);
+    }
+
+    #[test]
+    fn distance_field_from_shape() {
+        let square = DistanceFieldShape::from_polygon(&[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ]);
+        let bounds = square.bounds().unwrap();
+        assert_eq!((bounds.width(), bounds.height()), (10.0, 10.0));
+
+        assert!((square.signed_distance(Vec2::new(5.0, 2.0)) - 2.0).abs() < EPSILON);
+        assert!((square.signed_distance(Vec2::new(5.0, -3.0)) + 3.0).abs() < EPSILON);
+        assert!((square.signed_distance(Vec2::new(13.0, 14.0)) + 5.0).abs() < EPSILON);
+
+        // NOTE: The result must not depend on the winding order of the contour. Only the edge
+        //       colors may differ.
+        let square_reversed = DistanceFieldShape::from_polygon(&[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 10.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(10.0, 0.0),
+        ]);
+        let spread = 4.0;
+        let origin = Vec2::new(-4.0, -4.0);
+        let field = square.to_multichannel_distance_field(origin, 18, 18, spread);
+        let field_reversed = square_reversed.to_multichannel_distance_field(origin, 18, 18, spread);
+
+        for y in 0..field.height {
+            for x in 0..field.width {
+                let point = origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let is_inside = square.contains_point(point);
+                assert_eq!(square_reversed.contains_point(point), is_inside);
+
+                let pixel = field.get(x, y);
+                let pixel_reversed = field_reversed.get(x, y);
+                let pixel_median = median(pixel.r as f32, pixel.g as f32, pixel.b as f32);
+                let pixel_median_reversed = median(
+                    pixel_reversed.r as f32,
+                    pixel_reversed.g as f32,
+                    pixel_reversed.b as f32,
+                );
+                assert_eq!(pixel_median, pixel_median_reversed);
+                assert_eq!(pixel.a, pixel_reversed.a);
+                assert_eq!(pixel_median > 127.5, is_inside);
+                assert_eq!(pixel.a > 127, is_inside);
+            }
+        }
+
+        // NOTE: Outside of a corner the true distance is rounded while the median of the
+        //       pseudo-distances keeps the corner sharp
+        let pixel = field.get(1, 1);
+        let pixel_median = median(pixel.r as f32, pixel.g as f32, pixel.b as f32);
+        assert!((distance_field_decode(pixel.a, spread) + 2.5 * 2.0f32.sqrt()).abs() < 0.05);
+        assert!((distance_field_decode(pixel_median as u8, spread) + 2.5).abs() < 0.05);
+    }
+}
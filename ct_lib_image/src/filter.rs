@@ -0,0 +1,427 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Bitmap filters
+//
+// NOTE: All filters expect and return bitmaps with non-premultiplied alpha. Filters that mix
+//       neighboring pixels do so in premultiplied space internally to prevent dark fringes around
+//       transparent areas. All filters keep the dimensions of the bitmap - extend the bitmap
+//       beforehand if effects like outlines or shadows should not get clipped at the borders.
+
+use super::bitmap::*;
+
+impl Bitmap {
+    /// Blurs the bitmap with a box filter of size `2 * radius + 1`. Pixels outside the bitmap
+    /// are treated as transparent.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn blurred_box(&self, radius: i32) -> Bitmap {
+        assert!(radius >= 0);
+        let kernel = vec![1.0 / (2 * radius + 1) as f32; (2 * radius + 1) as usize];
+        self.convolved_separable(&kernel)
+    }
+
+    /// Blurs the bitmap with a gaussian filter. The kernel is cut off at three times the standard
+    /// deviation. Pixels outside the bitmap are treated as transparent.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn blurred_gaussian(&self, standard_deviation: f32) -> Bitmap {
+        assert!(standard_deviation >= 0.0);
+        if standard_deviation == 0.0 {
+            return self.clone();
+        }
+        let radius = f32::ceil(3.0 * standard_deviation) as i32;
+        let mut kernel: Vec<f32> = (-radius..=radius)
+            .map(|offset| {
+                f32::exp(
+                    -(offset * offset) as f32 / (2.0 * standard_deviation * standard_deviation),
+                )
+            })
+            .collect();
+        let kernel_sum: f32 = kernel.iter().sum();
+        for weight in kernel.iter_mut() {
+            *weight /= kernel_sum;
+        }
+        self.convolved_separable(&kernel)
+    }
+
+    /// Grows the opaque regions of the bitmap by the given radius. Each pixel takes on the most
+    /// opaque color found within the radius.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn dilated(&self, radius: i32) -> Bitmap {
+        self.morphed(radius, |candidate, current| candidate.a > current.a)
+    }
+
+    /// Shrinks the opaque regions of the bitmap by the given radius. Each pixel takes on the least
+    /// opaque color found within the radius.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn eroded(&self, radius: i32) -> Bitmap {
+        self.morphed(radius, |candidate, current| candidate.a < current.a)
+    }
+
+    /// Draws an outline of the given thickness and color around all non-transparent pixels.
+    /// The outline is placed under the original image so it only shows in transparent areas.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn outlined(&self, thickness: i32, color: PixelRGBA) -> Bitmap {
+        assert!(thickness >= 0);
+        let outline_mask = self.dilated(thickness);
+        let mut result = Bitmap::new(self.width as u32, self.height as u32);
+        for (result_pixel, mask_pixel) in result.data.iter_mut().zip(outline_mask.data.iter()) {
+            if mask_pixel.a != 0 {
+                *result_pixel = color;
+            }
+        }
+        self.blit_to_alpha_blended(&mut result);
+        result
+    }
+
+    /// Draws a shadow of the image with the given offset, color and blur radius beneath the
+    /// original image. The alpha of the shadow is the alpha of the image multiplied by the alpha
+    /// of `color`.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn with_drop_shadow(&self, offset: Vec2i, color: PixelRGBA, blur_radius: i32) -> Bitmap {
+        let mut shadow = Bitmap::new(self.width as u32, self.height as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let source =
+                    self.get_or_default(x - offset.x, y - offset.y, PixelRGBA::transparent());
+                if source.a != 0 {
+                    let alpha = (source.a as u32 * color.a as u32 + 127) / 255;
+                    shadow.set(x, y, PixelRGBA::new(color.r, color.g, color.b, alpha as u8));
+                }
+            }
+        }
+        if blur_radius > 0 {
+            shadow = shadow.blurred_box(blur_radius);
+        }
+        self.blit_to_alpha_blended(&mut shadow);
+        shadow
+    }
+
+    /// Shifts the hue by the given amount of degrees and multiplies the saturation and
+    /// brightness (HSB/HSV color model) by the given factors
+    pub fn adjust_hue_saturation_brightness(
+        &mut self,
+        hue_shift_degrees: f32,
+        saturation_factor: f32,
+        brightness_factor: f32,
+    ) {
+        for pixel in self.data.iter_mut() {
+            let (hue, saturation, brightness) = rgb_to_hsb(*pixel);
+            let adjusted = hsb_to_rgb(
+                hue + hue_shift_degrees,
+                saturation * saturation_factor,
+                brightness * brightness_factor,
+            );
+            *pixel = PixelRGBA::new(adjusted.r, adjusted.g, adjusted.b, pixel.a);
+        }
+    }
+
+    #[must_use = "This does not change the original bitmap"]
+    pub fn with_adjusted_hue_saturation_brightness(
+        &self,
+        hue_shift_degrees: f32,
+        saturation_factor: f32,
+        brightness_factor: f32,
+    ) -> Bitmap {
+        let mut result = self.clone();
+        result.adjust_hue_saturation_brightness(
+            hue_shift_degrees,
+            saturation_factor,
+            brightness_factor,
+        );
+        result
+    }
+
+    /// Fills the color channels of fully transparent pixels with the average color of their
+    /// non-transparent neighbors, growing outwards until all transparent pixels are filled. The
+    /// alpha channel is left untouched. This prevents dark fringes when the bitmap is sampled
+    /// with bilinear filtering.
+    pub fn bleed_alpha(&mut self) {
+        let mut is_filled: Vec<bool> = self.data.iter().map(|pixel| pixel.a != 0).collect();
+        if !is_filled.iter().any(|&filled| filled) {
+            return;
+        }
+
+        let mut frontier: Vec<Vec2i> = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !is_filled[(x + y * self.width) as usize] {
+                    frontier.push(Vec2i::new(x, y));
+                }
+            }
+        }
+
+        while !frontier.is_empty() {
+            // NOTE: We compute the whole layer before writing it back so that the result does not
+            //       depend on the iteration order
+            let mut layer: Vec<(Vec2i, PixelRGBA)> = Vec::new();
+            let mut remaining: Vec<Vec2i> = Vec::new();
+            for &pos in &frontier {
+                let mut sum = [0u32; 3];
+                let mut count = 0;
+                for y in (pos.y - 1)..=(pos.y + 1) {
+                    for x in (pos.x - 1)..=(pos.x + 1) {
+                        if self.contains_coordinate(x, y)
+                            && is_filled[(x + y * self.width) as usize]
+                        {
+                            let neighbor = self.get(x, y);
+                            sum[0] += neighbor.r as u32;
+                            sum[1] += neighbor.g as u32;
+                            sum[2] += neighbor.b as u32;
+                            count += 1;
+                        }
+                    }
+                }
+                if count == 0 {
+                    remaining.push(pos);
+                } else {
+                    let pixel = self.get(pos.x, pos.y);
+                    let color = PixelRGBA::new(
+                        ((sum[0] + count / 2) / count) as u8,
+                        ((sum[1] + count / 2) / count) as u8,
+                        ((sum[2] + count / 2) / count) as u8,
+                        pixel.a,
+                    );
+                    layer.push((pos, color));
+                }
+            }
+            for (pos, color) in layer {
+                self.set(pos.x, pos.y, color);
+                is_filled[(pos.x + pos.y * self.width) as usize] = true;
+            }
+            frontier = remaining;
+        }
+    }
+
+    #[must_use = "This does not change the original bitmap"]
+    pub fn with_bled_alpha(&self) -> Bitmap {
+        let mut result = self.clone();
+        result.bleed_alpha();
+        result
+    }
+
+    /// Applies a normalized one dimensional kernel horizontally and then vertically
+    fn convolved_separable(&self, kernel: &[f32]) -> Bitmap {
+        assert!(kernel.len() % 2 == 1);
+        let radius = (kernel.len() / 2) as i32;
+        let width = self.width;
+        let height = self.height;
+
+        let premultiplied: Vec<Color> = self
+            .data
+            .iter()
+            .map(|pixel| pixel.to_color().to_premultiplied_alpha())
+            .collect();
+
+        let convolve = |source: &[Color], step: Vec2i| -> Vec<Color> {
+            let mut result = vec![Color::transparent(); source.len()];
+            for y in 0..height {
+                for x in 0..width {
+                    let mut sum = Color::transparent();
+                    for (kernel_index, weight) in kernel.iter().enumerate() {
+                        let offset = kernel_index as i32 - radius;
+                        let sample_x = x + offset * step.x;
+                        let sample_y = y + offset * step.y;
+                        if sample_x >= 0 && sample_x < width && sample_y >= 0 && sample_y < height {
+                            sum += *weight * source[(sample_x + sample_y * width) as usize];
+                        }
+                    }
+                    result[(x + y * width) as usize] = sum;
+                }
+            }
+            result
+        };
+        let blurred_horizontal = convolve(&premultiplied, Vec2i::new(1, 0));
+        let blurred = convolve(&blurred_horizontal, Vec2i::new(0, 1));
+
+        Bitmap::new_from_buffer(
+            width as u32,
+            height as u32,
+            blurred
+                .into_iter()
+                .map(|color| pixel_from_color_rounded(color.to_unpremultipled_alpha()))
+                .collect(),
+        )
+    }
+
+    /// Replaces each pixel by the pixel within a circle of the given radius that is preferred by
+    /// the `prefer` function
+    fn morphed<F: Fn(PixelRGBA, PixelRGBA) -> bool>(&self, radius: i32, prefer: F) -> Bitmap {
+        assert!(radius >= 0);
+        let offsets: Vec<Vec2i> = (-radius..=radius)
+            .flat_map(|y| (-radius..=radius).map(move |x| Vec2i::new(x, y)))
+            .filter(|offset| offset.x * offset.x + offset.y * offset.y <= radius * radius)
+            .collect();
+
+        let mut result = self.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut chosen = self.get(x, y);
+                for offset in &offsets {
+                    let candidate =
+                        self.get_or_default(x + offset.x, y + offset.y, PixelRGBA::transparent());
+                    if prefer(candidate, chosen) {
+                        chosen = candidate;
+                    }
+                }
+                result.set(x, y, chosen);
+            }
+        }
+        result
+    }
+
+    /// Blends this bitmap on top of a same sized bitmap
+    fn blit_to_alpha_blended(&self, dest: &mut Bitmap) {
+        assert!(self.width == dest.width && self.height == dest.height);
+        for (source_pixel, dest_pixel) in self.data.iter().zip(dest.data.iter_mut()) {
+            let blended = Color::premultiplied_alpha_blend_normal(
+                source_pixel.to_color().to_premultiplied_alpha(),
+                dest_pixel.to_color().to_premultiplied_alpha(),
+            );
+            *dest_pixel = pixel_from_color_rounded(blended.to_unpremultipled_alpha());
+        }
+    }
+}
+
+#[inline]
+fn pixel_from_color_rounded(color: Color) -> PixelRGBA {
+    PixelRGBA::new(
+        f32::clamp(color.r * 255.0 + 0.5, 0.0, 255.0) as u8,
+        f32::clamp(color.g * 255.0 + 0.5, 0.0, 255.0) as u8,
+        f32::clamp(color.b * 255.0 + 0.5, 0.0, 255.0) as u8,
+        f32::clamp(color.a * 255.0 + 0.5, 0.0, 255.0) as u8,
+    )
+}
+
+/// Returns hue in degrees [0, 360[ and saturation and brightness in [0, 1]
+fn rgb_to_hsb(pixel: PixelRGBA) -> (f32, f32, f32) {
+    let r = pixel.r as f32 / 255.0;
+    let g = pixel.g as f32 / 255.0;
+    let b = pixel.b as f32 / 255.0;
+    let max = f32::max(f32::max(r, g), b);
+    let min = f32::min(f32::min(r, g), b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * (((g - b) / delta) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue.rem_euclid(360.0), saturation, max)
+}
+
+/// Expects hue in degrees and clamps saturation and brightness to [0, 1]. The returned alpha is
+/// always opaque.
+fn hsb_to_rgb(hue: f32, saturation: f32, brightness: f32) -> PixelRGBA {
+    let hue = hue.rem_euclid(360.0);
+    let saturation = f32::clamp(saturation, 0.0, 1.0);
+    let brightness = f32::clamp(brightness, 0.0, 1.0);
+
+    let chroma = brightness * saturation;
+    let x = chroma * (1.0 - f32::abs((hue / 60.0) % 2.0 - 1.0));
+    let (r, g, b) = match (hue / 60.0) as i32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = brightness - chroma;
+    pixel_from_color_rounded(Color::new(r + m, g + m, b + m, 1.0))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blur_preserves_uniform_color_and_spreads_alpha() {
+        let mut bitmap = Bitmap::new(9, 9);
+        bitmap.draw_rect_filled(3, 3, 3, 3, PixelRGBA::red());
+
+        for blurred in &[bitmap.blurred_box(2), bitmap.blurred_gaussian(1.0)] {
+            // NOTE: Blurring in premultiplied space must not darken the color at the edges
+            for pixel in &blurred.data {
+                if pixel.a != 0 {
+                    assert_eq!((pixel.r, pixel.g, pixel.b), (255, 0, 0));
+                }
+            }
+            assert!(blurred.get(2, 4).a > 0);
+            assert!(blurred.get(4, 4).a < 255);
+        }
+    }
+
+    #[test]
+    fn dilate_erode_and_outline() {
+        let mut bitmap = Bitmap::new(7, 7);
+        bitmap.set(3, 3, PixelRGBA::white());
+
+        let dilated = bitmap.dilated(1);
+        assert_eq!(dilated.get(3, 2), PixelRGBA::white());
+        assert_eq!(dilated.get(2, 3), PixelRGBA::white());
+        assert_eq!(dilated.get(2, 2), PixelRGBA::transparent());
+        assert_eq!(dilated.eroded(1), bitmap);
+
+        let outlined = bitmap.outlined(1, PixelRGBA::black());
+        assert_eq!(outlined.get(3, 3), PixelRGBA::white());
+        assert_eq!(outlined.get(4, 3), PixelRGBA::black());
+        assert_eq!(outlined.get(4, 4), PixelRGBA::transparent());
+    }
+
+    #[test]
+    fn drop_shadow() {
+        let mut bitmap = Bitmap::new(4, 4);
+        bitmap.set(1, 1, PixelRGBA::white());
+
+        let shadowed = bitmap.with_drop_shadow(Vec2i::new(1, 1), PixelRGBA::new(0, 0, 0, 128), 0);
+        assert_eq!(shadowed.get(1, 1), PixelRGBA::white());
+        assert_eq!(shadowed.get(2, 2), PixelRGBA::new(0, 0, 0, 128));
+        assert_eq!(shadowed.get(2, 1), PixelRGBA::transparent());
+    }
+
+    #[test]
+    fn hue_saturation_brightness_adjustment() {
+        let bitmap = Bitmap::new_filled(2, 2, PixelRGBA::new(255, 0, 0, 100));
+        assert_eq!(
+            bitmap
+                .with_adjusted_hue_saturation_brightness(120.0, 1.0, 1.0)
+                .get(0, 0),
+            PixelRGBA::new(0, 255, 0, 100)
+        );
+        assert_eq!(
+            bitmap
+                .with_adjusted_hue_saturation_brightness(0.0, 0.0, 1.0)
+                .get(0, 0),
+            PixelRGBA::new(255, 255, 255, 100)
+        );
+        assert_eq!(
+            bitmap
+                .with_adjusted_hue_saturation_brightness(0.0, 1.0, 0.5)
+                .get(0, 0),
+            PixelRGBA::new(128, 0, 0, 100)
+        );
+        assert_eq!(
+            bitmap.with_adjusted_hue_saturation_brightness(0.0, 1.0, 1.0),
+            bitmap
+        );
+    }
+
+    #[test]
+    fn alpha_bleeding() {
+        let mut bitmap = Bitmap::new(5, 1);
+        bitmap.set(0, 0, PixelRGBA::red());
+        bitmap.set(4, 0, PixelRGBA::blue());
+        bitmap.bleed_alpha();
+
+        assert_eq!(bitmap.get(1, 0), PixelRGBA::new(255, 0, 0, 0));
+        assert_eq!(bitmap.get(3, 0), PixelRGBA::new(0, 0, 255, 0));
+        assert_eq!(bitmap.get(0, 0), PixelRGBA::red());
+        assert_eq!(bitmap.get(2, 0), PixelRGBA::new(128, 0, 128, 0));
+    }
+}
@@ -1,5 +1,6 @@
 use super::bitmap::*;
 use super::color::*;
+use super::distancefield::*;
 use super::math::*;
 
 use super::core::indexmap::IndexMap;
@@ -531,9 +532,80 @@ impl BitmapFont {
         }
     }
 
+    /// Creates a font with multi channel signed distance field glyphs that can be drawn crisp at
+    /// any scale. The glyphs RGB channels contain the MSDF and the alpha channel contains the true
+    /// signed distance (see `DistanceFieldShape::to_multichannel_distance_field`).
+    /// The `spread` determines the maximum distance in pixels (at `font_height`) that can be
+    /// represented by the distance field and therefore limits how wide outlines and glows can get.
+    pub fn new_distance_field(
+        font_name: &str,
+        font_ttf_bytes: &[u8],
+        font_height: i32,
+        spread: i32,
+    ) -> BitmapFont {
+        let font = rusttype::Font::try_from_bytes(font_ttf_bytes)
+            .unwrap_or_else(|| panic!("Could not decode font '{}' from bytes", font_name));
+
+        // Font metrics
+        // NOTE: Contrary to pixelfonts we don't need to warn about non-whole-numbered metrics here
+        //       as the glyphs are not meant to be drawn pixel-perfect anyway
+        let (vertical_advance, baseline) = {
+            let scale = rusttype::Scale::uniform(font_height as f32);
+            let v_metrics = font.v_metrics(scale);
+            let ascent_integer = roundi(v_metrics.ascent);
+            let descent_integer = roundi(v_metrics.descent);
+            let line_gap_integer = roundi(v_metrics.line_gap);
+
+            let vertical_advance = ascent_integer - descent_integer + line_gap_integer;
+            (vertical_advance, ascent_integer)
+        };
+
+        // Create glyphs
+        let mut glyphs: IndexMap<Codepoint, BitmapGlyph> = IndexMap::new();
+        for codepoint in 0..std::u16::MAX as Codepoint {
+            let character = match std::char::from_u32(codepoint as u32) {
+                Some(character) => character,
+                None => continue,
+            };
+
+            let glyph = font.glyph(character);
+            if glyph.id() == rusttype::GlyphId(0) {
+                // This glyph does not exist in the given font
+                continue;
+            }
+
+            let glyph =
+                BitmapGlyph::new_distance_field(&font, character, font_height, baseline, spread);
+            glyphs.insert(codepoint as Codepoint, glyph);
+        }
+
+        let horizontal_advance_max = glyphs
+            .values()
+            .map(|glyph| glyph.horizontal_advance)
+            .max()
+            .unwrap_or_else(|| panic!("Font '{}' does not contain any glyphs", font_name));
+        let horizontal_advance_min = glyphs
+            .values()
+            .map(|glyph| glyph.horizontal_advance)
+            .min()
+            .unwrap_or_else(|| panic!("Font '{}' does not contain any glyphs", font_name));
+
+        let is_fixed_width_font = horizontal_advance_max == horizontal_advance_min;
+
+        BitmapFont {
+            font_name: font_name.to_owned(),
+            font_height_in_pixels: font_height,
+            vertical_advance,
+            horizontal_advance_max,
+            is_fixed_width_font,
+            baseline,
+            glyphs,
+        }
+    }
+
     #[inline]
     pub fn to_bitmap_atlas(&self, fontname: &str) -> (Bitmap, IndexMap<String, Vec2i>) {
-        let mut atlas = BitmapAtlas::new(64, None, false);
+        let mut atlas = BitmapAtlas::new(64, None, false, BitmapAtlasPackingParams::default());
         for glyph in self.glyphs.values() {
             if let Some(bitmap) = &glyph.bitmap {
                 let spritename = BitmapFont::get_glyph_name(fontname, glyph.codepoint as Codepoint);
@@ -684,6 +756,57 @@ impl BitmapGlyph {
             bitmap: maybe_image,
         }
     }
+
+    /// Creates a glyph with a multi channel signed distance field image. The image is padded by
+    /// `spread + 1` pixels on each side so that the full distance range fits into the image.
+    pub fn new_distance_field(
+        font: &rusttype::Font,
+        codepoint: char,
+        font_height: i32,
+        baseline: i32,
+        spread: i32,
+    ) -> BitmapGlyph {
+        let glyph = font
+            .glyph(codepoint)
+            .scaled(rusttype::Scale::uniform(font_height as f32));
+        let horizontal_advance = roundi(glyph.h_metrics().advance_width);
+
+        let shape = DistanceFieldShape::from_glyph(&glyph);
+        let bounds = match shape.bounds() {
+            Some(bounds) => bounds,
+            None => {
+                // NOTE: This is a whitespace glyph
+                return BitmapGlyph {
+                    codepoint,
+                    horizontal_advance,
+                    offset: Vec2i::zero(),
+                    bitmap: None,
+                };
+            }
+        };
+
+        let padding = spread + 1;
+        let left = floori(bounds.left()) - padding;
+        let top = floori(bounds.top()) - padding;
+        let width = ceili(bounds.right()) + padding - left;
+        let height = ceili(bounds.bottom()) + padding - top;
+
+        let bitmap = shape.to_multichannel_distance_field(
+            Vec2::new(left as f32, top as f32),
+            width as u32,
+            height as u32,
+            spread as f32,
+        );
+
+        BitmapGlyph {
+            codepoint,
+            horizontal_advance,
+            // NOTE: The shapes coordinates are relative to the baseline but our offset is relative
+            //       to the top of the line
+            offset: Vec2i::new(left, baseline + top),
+            bitmap: Some(bitmap),
+        }
+    }
 }
 
 #[inline]
@@ -784,6 +907,36 @@ mod tests {
         )
     }
 
+    #[test]
+    fn distance_field_font_glyphs() {
+        let spread = 4;
+        let font = BitmapFont::new_distance_field(
+            FONT_DEFAULT_REGULAR_NAME,
+            FONT_DEFAULT_REGULAR_TTF,
+            4 * FONT_DEFAULT_REGULAR_PIXEL_HEIGHT,
+            spread,
+        );
+        assert!(font
+            .get_glyph_for_codepoint(' ' as Codepoint)
+            .bitmap
+            .is_none());
+
+        let glyph = font.get_glyph_for_codepoint('H' as Codepoint);
+        let bitmap = glyph.bitmap.as_ref().unwrap();
+        Bitmap::write_to_png_file(bitmap, "target/tests/distance_field_font_glyph.png");
+
+        // NOTE: The glyph must fit into its line and the borders of the padded image must be
+        //       far enough outside of the glyph
+        let rect = glyph.get_bitmap_rect();
+        assert!(rect.top() + spread + 1 >= 0);
+        assert!(rect.bottom() - spread - 1 <= font.font_height_in_pixels);
+        for x in 0..bitmap.width {
+            assert_eq!(bitmap.get(x, 0).a, 0);
+            assert_eq!(bitmap.get(x, bitmap.height - 1).a, 0);
+        }
+        assert!(bitmap.data.iter().any(|pixel| pixel.a > 128));
+    }
+
     #[test]
     fn aligned_text_drawing_in_point() {
         let font = get_default_font();
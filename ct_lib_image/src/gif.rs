@@ -0,0 +1,807 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// GIF image format
+//
+// Specification: https://www.w3.org/Graphics/GIF/spec-gif89a.txt
+
+use super::bitmap::*;
+use super::quantize::Dithering;
+
+use super::core::Deserializer;
+
+use std::collections::HashMap;
+
+const GIF_BLOCK_EXTENSION: u8 = 0x21;
+const GIF_BLOCK_IMAGE_DESCRIPTOR: u8 = 0x2C;
+const GIF_BLOCK_TRAILER: u8 = 0x3B;
+
+const GIF_EXTENSION_GRAPHIC_CONTROL: u8 = 0xF9;
+const GIF_EXTENSION_APPLICATION: u8 = 0xFF;
+
+const GIF_DISPOSAL_RESTORE_BACKGROUND: u8 = 2;
+const GIF_DISPOSAL_RESTORE_PREVIOUS: u8 = 3;
+
+const GIF_LZW_CODE_COUNT_MAX: usize = 4096;
+const GIF_LZW_CODE_SIZE_MAX: u32 = 12;
+
+/// The default frame duration used for frames that have no delay set. This is what most
+/// browsers do too.
+const GIF_FRAME_DURATION_DEFAULT_MS: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GifAnimation {
+    pub width: u32,
+    pub height: u32,
+    /// Each frame is the fully composited canvas at the time the frame is shown
+    pub frames: Vec<Bitmap>,
+    pub frame_durations_ms: Vec<u32>,
+    /// `None` means that the animation plays only once, `Some(0)` means that it loops forever
+    pub loop_count: Option<u16>,
+}
+
+impl GifAnimation {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(filepath: &str) -> Result<GifAnimation, String> {
+        let gif_data = super::core::read_file_whole(filepath)
+            .map_err(|error| format!("Could not read gif file '{}': {}", filepath, error))?;
+        GifAnimation::from_gif_data(&gif_data)
+            .map_err(|error| format!("Could not decode gif file '{}': {}", filepath, error))
+    }
+
+    pub fn from_gif_data(gif_data: &[u8]) -> Result<GifAnimation, String> {
+        let mut deserializer = Deserializer::new(gif_data);
+        let signature: [u8; 6] = deserializer.deserialize()?;
+        if &signature != b"GIF87a" && &signature != b"GIF89a" {
+            return Err("Gif data has invalid signature".to_owned());
+        }
+
+        // Logical screen descriptor
+        let width: u16 = deserializer.deserialize()?;
+        let height: u16 = deserializer.deserialize()?;
+        let flags: u8 = deserializer.deserialize()?;
+        let _background_color_index: u8 = deserializer.deserialize()?;
+        let _pixel_aspect_ratio: u8 = deserializer.deserialize()?;
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "Gif data has invalid dimensions {}x{}",
+                width, height
+            ));
+        }
+        let global_palette = if flags & 0x80 != 0 {
+            gif_read_palette(&mut deserializer, flags)?
+        } else {
+            Vec::new()
+        };
+
+        let mut result = GifAnimation {
+            width: width as u32,
+            height: height as u32,
+            frames: Vec::new(),
+            frame_durations_ms: Vec::new(),
+            loop_count: None,
+        };
+
+        // NOTE: We start out with a transparent canvas instead of the background color. This is
+        //       what most browsers do too.
+        let mut canvas = Bitmap::new(width as u32, height as u32);
+        let mut disposal_method = 0;
+        let mut transparent_color_index = None;
+        let mut frame_duration_ms = GIF_FRAME_DURATION_DEFAULT_MS;
+
+        loop {
+            let block_type: u8 = deserializer.deserialize()?;
+            match block_type {
+                GIF_BLOCK_EXTENSION => {
+                    let label: u8 = deserializer.deserialize()?;
+                    let data = gif_read_sub_blocks(&mut deserializer)?;
+                    match label {
+                        GIF_EXTENSION_GRAPHIC_CONTROL => {
+                            if data.len() < 4 {
+                                return Err("Gif graphic control extension is truncated".to_owned());
+                            }
+                            disposal_method = (data[0] >> 2) & 0b111;
+                            let delay = u16::from_le_bytes([data[1], data[2]]) as u32;
+                            frame_duration_ms = if delay == 0 {
+                                GIF_FRAME_DURATION_DEFAULT_MS
+                            } else {
+                                10 * delay
+                            };
+                            transparent_color_index = if data[0] & 1 != 0 {
+                                Some(data[3])
+                            } else {
+                                None
+                            };
+                        }
+                        GIF_EXTENSION_APPLICATION => {
+                            if data.starts_with(b"NETSCAPE2.0") && data.len() >= 14 && data[11] == 1
+                            {
+                                result.loop_count = Some(u16::from_le_bytes([data[12], data[13]]));
+                            }
+                        }
+                        _ => {
+                            // NOTE: Comments and plain text extensions are ignored
+                        }
+                    }
+                }
+                GIF_BLOCK_IMAGE_DESCRIPTOR => {
+                    let frame_left: u16 = deserializer.deserialize()?;
+                    let frame_top: u16 = deserializer.deserialize()?;
+                    let frame_width: u16 = deserializer.deserialize()?;
+                    let frame_height: u16 = deserializer.deserialize()?;
+                    let frame_flags: u8 = deserializer.deserialize()?;
+                    let local_palette = if frame_flags & 0x80 != 0 {
+                        gif_read_palette(&mut deserializer, frame_flags)?
+                    } else {
+                        Vec::new()
+                    };
+                    let is_interlaced = frame_flags & 0x40 != 0;
+                    let palette = if local_palette.is_empty() {
+                        &global_palette
+                    } else {
+                        &local_palette
+                    };
+                    if palette.is_empty() {
+                        return Err(format!(
+                            "Gif frame {} has no color table",
+                            result.frames.len()
+                        ));
+                    }
+
+                    let lzw_minimum_code_size: u8 = deserializer.deserialize()?;
+                    let lzw_data = gif_read_sub_blocks(&mut deserializer)?;
+                    let frame_pixel_count = frame_width as usize * frame_height as usize;
+                    let color_indices =
+                        gif_lzw_decode(&lzw_data, lzw_minimum_code_size, frame_pixel_count)?;
+
+                    let canvas_previous = if disposal_method == GIF_DISPOSAL_RESTORE_PREVIOUS {
+                        Some(canvas.clone())
+                    } else {
+                        None
+                    };
+
+                    let row_order = gif_row_order(frame_height as usize, is_interlaced);
+                    for (row_source, &row_dest) in row_order.iter().enumerate() {
+                        let y = frame_top as i32 + row_dest as i32;
+                        for column in 0..frame_width as usize {
+                            let x = frame_left as i32 + column as i32;
+                            let color_index =
+                                color_indices[row_source * frame_width as usize + column];
+                            if Some(color_index) == transparent_color_index
+                                || x >= canvas.width
+                                || y >= canvas.height
+                            {
+                                continue;
+                            }
+                            let color = palette
+                                .get(color_index as usize)
+                                .cloned()
+                                .unwrap_or(PixelRGBA::black());
+                            canvas.set(x, y, color);
+                        }
+                    }
+
+                    result.frames.push(canvas.clone());
+                    result.frame_durations_ms.push(frame_duration_ms);
+
+                    match disposal_method {
+                        GIF_DISPOSAL_RESTORE_BACKGROUND => {
+                            let right =
+                                i32::min(frame_left as i32 + frame_width as i32, canvas.width);
+                            let bottom =
+                                i32::min(frame_top as i32 + frame_height as i32, canvas.height);
+                            for y in frame_top as i32..bottom {
+                                for x in frame_left as i32..right {
+                                    canvas.set(x, y, PixelRGBA::transparent());
+                                }
+                            }
+                        }
+                        GIF_DISPOSAL_RESTORE_PREVIOUS => {
+                            canvas = canvas_previous.unwrap();
+                        }
+                        _ => {}
+                    }
+
+                    // NOTE: The graphic control extension only applies to the following frame
+                    disposal_method = 0;
+                    transparent_color_index = None;
+                    frame_duration_ms = GIF_FRAME_DURATION_DEFAULT_MS;
+                }
+                GIF_BLOCK_TRAILER => break,
+                _ => {
+                    return Err(format!("Gif data has unknown block type {:#X}", block_type));
+                }
+            }
+        }
+
+        if result.frames.is_empty() {
+            return Err("Gif data does not contain any frames".to_owned());
+        }
+
+        Ok(result)
+    }
+
+    /// Returns a copy of the animation that uses at most `color_count` colors in total so that it
+    /// can be encoded with a single palette. Pixels with less than half opacity become fully
+    /// transparent, all others become opaque.
+    #[must_use = "This does not change the original animation"]
+    pub fn quantized(&self, color_count: usize, dithering: Dithering) -> GifAnimation {
+        // NOTE: One palette entry is reserved for transparency
+        assert!(color_count > 0 && color_count < 256);
+        let frames_thresholded: Vec<Bitmap> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                let mut result = frame.clone();
+                for pixel in result.data.iter_mut() {
+                    *pixel = if pixel.a < 128 {
+                        PixelRGBA::transparent()
+                    } else {
+                        PixelRGBA::new(pixel.r, pixel.g, pixel.b, 255)
+                    };
+                }
+                result
+            })
+            .collect();
+
+        let frames_combined = Bitmap::new_from_buffer(
+            self.width,
+            self.height * frames_thresholded.len() as u32,
+            frames_thresholded
+                .iter()
+                .flat_map(|frame| frame.data.iter().cloned())
+                .collect(),
+        );
+        let palette = frames_combined.extract_palette_median_cut(color_count);
+        if palette.is_empty() {
+            // NOTE: All frames are fully transparent
+            return GifAnimation {
+                frames: frames_thresholded,
+                ..self.clone()
+            };
+        }
+
+        GifAnimation {
+            frames: frames_thresholded
+                .iter()
+                .map(|frame| frame.mapped_to_palette(&palette, dithering))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Encodes the animation as gif. Returns an error if a frame has more than 256 distinct
+    /// colors or has pixels that are neither fully opaque nor fully transparent.
+    pub fn encoded_as_gif(&self) -> Result<Vec<u8>, String> {
+        if self.frames.is_empty() {
+            return Err("Cannot encode gif without frames".to_owned());
+        }
+        if self.frames.len() != self.frame_durations_ms.len() {
+            return Err(format!(
+                "Gif has {} frames but {} frame durations",
+                self.frames.len(),
+                self.frame_durations_ms.len()
+            ));
+        }
+        if self.width > std::u16::MAX as u32 || self.height > std::u16::MAX as u32 {
+            return Err(format!(
+                "Gif dimensions {}x{} are too big",
+                self.width, self.height
+            ));
+        }
+
+        let mut frame_palettes = Vec::with_capacity(self.frames.len());
+        for (frame_index, frame) in self.frames.iter().enumerate() {
+            if frame.width as u32 != self.width || frame.height as u32 != self.height {
+                return Err(format!(
+                    "Gif frame {} has dimensions {}x{} but expected {}x{}",
+                    frame_index, frame.width, frame.height, self.width, self.height
+                ));
+            }
+            if frame
+                .data
+                .iter()
+                .any(|pixel| pixel.a != 0 && pixel.a != 255)
+            {
+                return Err(format!(
+                    "Gif frame {} contains semi-transparent pixels",
+                    frame_index
+                ));
+            }
+            let palette = gif_collect_palette(std::iter::once(frame));
+            if palette.len() > 256 {
+                return Err(format!(
+                    "Gif frame {} has {} colors but at most 256 are allowed",
+                    frame_index,
+                    palette.len()
+                ));
+            }
+            frame_palettes.push(palette);
+        }
+
+        // NOTE: We use a global palette if all frames together fit into one, otherwise each frame
+        //       gets its own local palette
+        let global_palette = gif_collect_palette(self.frames.iter());
+        let use_global_palette = global_palette.len() <= 256;
+
+        let mut result = Vec::new();
+        result.extend_from_slice(b"GIF89a");
+
+        // Logical screen descriptor
+        result.extend_from_slice(&(self.width as u16).to_le_bytes());
+        result.extend_from_slice(&(self.height as u16).to_le_bytes());
+        if use_global_palette {
+            result.push(0x80 | 0x70 | gif_palette_size_exponent(global_palette.len()));
+        } else {
+            result.push(0x70);
+        }
+        result.push(0); // Background color index
+        result.push(0); // Pixel aspect ratio
+        if use_global_palette {
+            gif_write_palette(&mut result, &global_palette);
+        }
+
+        if let Some(loop_count) = self.loop_count {
+            result.extend_from_slice(&[GIF_BLOCK_EXTENSION, GIF_EXTENSION_APPLICATION, 11]);
+            result.extend_from_slice(b"NETSCAPE2.0");
+            result.extend_from_slice(&[3, 1]);
+            result.extend_from_slice(&loop_count.to_le_bytes());
+            result.push(0);
+        }
+
+        for ((frame, frame_duration_ms), frame_palette) in self
+            .frames
+            .iter()
+            .zip(self.frame_durations_ms.iter())
+            .zip(frame_palettes.iter())
+        {
+            let palette = if use_global_palette {
+                &global_palette
+            } else {
+                frame_palette
+            };
+            let color_indices: HashMap<PixelRGBA, u8> = palette
+                .iter()
+                .enumerate()
+                .map(|(index, &color)| (color, index as u8))
+                .collect();
+            let transparent_color_index = color_indices.get(&PixelRGBA::transparent()).cloned();
+
+            // Graphic control extension
+            // NOTE: Each frame is a full canvas so we clear the canvas after every frame
+            let delay = u16::min(((frame_duration_ms + 5) / 10) as u16, std::u16::MAX);
+            result.extend_from_slice(&[GIF_BLOCK_EXTENSION, GIF_EXTENSION_GRAPHIC_CONTROL, 4]);
+            result.push(
+                (GIF_DISPOSAL_RESTORE_BACKGROUND << 2)
+                    | if transparent_color_index.is_some() {
+                        1
+                    } else {
+                        0
+                    },
+            );
+            result.extend_from_slice(&delay.to_le_bytes());
+            result.push(transparent_color_index.unwrap_or(0));
+            result.push(0);
+
+            // Image descriptor
+            result.push(GIF_BLOCK_IMAGE_DESCRIPTOR);
+            result.extend_from_slice(&0u16.to_le_bytes());
+            result.extend_from_slice(&0u16.to_le_bytes());
+            result.extend_from_slice(&(self.width as u16).to_le_bytes());
+            result.extend_from_slice(&(self.height as u16).to_le_bytes());
+            if use_global_palette {
+                result.push(0);
+            } else {
+                result.push(0x80 | gif_palette_size_exponent(palette.len()));
+                gif_write_palette(&mut result, palette);
+            }
+
+            let indices: Vec<u8> = frame
+                .data
+                .iter()
+                .map(|pixel| {
+                    if pixel.a == 0 {
+                        transparent_color_index.unwrap()
+                    } else {
+                        color_indices[pixel]
+                    }
+                })
+                .collect();
+            let lzw_minimum_code_size = u8::max(2, gif_palette_size_exponent(palette.len()) + 1);
+            let lzw_data = gif_lzw_encode(&indices, lzw_minimum_code_size);
+            result.push(lzw_minimum_code_size);
+            gif_write_sub_blocks(&mut result, &lzw_data);
+        }
+
+        result.push(GIF_BLOCK_TRAILER);
+        Ok(result)
+    }
+}
+
+impl Bitmap {
+    /// Decodes the first frame of the given gif
+    pub fn from_gif_data(gif_data: &[u8]) -> Result<Bitmap, String> {
+        let mut animation = GifAnimation::from_gif_data(gif_data)?;
+        Ok(animation.frames.swap_remove(0))
+    }
+
+    /// Encodes the bitmap as single frame gif. See `GifAnimation::encoded_as_gif` for
+    /// restrictions.
+    pub fn encoded_as_gif(&self) -> Result<Vec<u8>, String> {
+        GifAnimation {
+            width: self.width as u32,
+            height: self.height as u32,
+            frames: vec![self.clone()],
+            frame_durations_ms: vec![GIF_FRAME_DURATION_DEFAULT_MS],
+            loop_count: None,
+        }
+        .encoded_as_gif()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Blocks and palettes
+
+fn gif_read_palette(deserializer: &mut Deserializer, flags: u8) -> Result<Vec<PixelRGBA>, String> {
+    let color_count = 1 << ((flags & 0b111) + 1);
+    let mut palette = Vec::with_capacity(color_count);
+    for _ in 0..color_count {
+        let rgb: [u8; 3] = deserializer.deserialize()?;
+        palette.push(PixelRGBA::new(rgb[0], rgb[1], rgb[2], 255));
+    }
+    Ok(palette)
+}
+
+fn gif_read_sub_blocks(deserializer: &mut Deserializer) -> Result<Vec<u8>, String> {
+    let mut result = Vec::new();
+    loop {
+        let block_size: u8 = deserializer.deserialize()?;
+        if block_size == 0 {
+            return Ok(result);
+        }
+        let block = deserializer
+            .get_remaining_data()
+            .get(0..block_size as usize)
+            .ok_or_else(|| "Gif data block is truncated".to_owned())?;
+        result.extend_from_slice(block);
+        deserializer.skip_bytes(block_size as usize)?;
+    }
+}
+
+fn gif_write_sub_blocks(output: &mut Vec<u8>, data: &[u8]) {
+    for block in data.chunks(255) {
+        output.push(block.len() as u8);
+        output.extend_from_slice(block);
+    }
+    output.push(0);
+}
+
+/// Returns all distinct colors of the given frames in order of appearance. All fully transparent
+/// pixels share one entry.
+fn gif_collect_palette<'a>(frames: impl Iterator<Item = &'a Bitmap>) -> Vec<PixelRGBA> {
+    let mut palette = Vec::new();
+    let mut known_colors = std::collections::HashSet::new();
+    for frame in frames {
+        for &pixel in &frame.data {
+            let color = if pixel.a == 0 {
+                PixelRGBA::transparent()
+            } else {
+                pixel
+            };
+            if known_colors.insert(color) {
+                palette.push(color);
+            }
+        }
+    }
+    palette
+}
+
+/// Returns n so that the gif color table size 2^(n+1) fits the given color count
+fn gif_palette_size_exponent(color_count: usize) -> u8 {
+    let mut exponent = 0;
+    while (1 << (exponent + 1)) < color_count {
+        exponent += 1;
+    }
+    exponent
+}
+
+fn gif_write_palette(output: &mut Vec<u8>, palette: &[PixelRGBA]) {
+    let color_count = 1 << (gif_palette_size_exponent(palette.len()) + 1);
+    for index in 0..color_count {
+        let color = palette
+            .get(index)
+            .cloned()
+            .unwrap_or(PixelRGBA::transparent());
+        output.extend_from_slice(&[color.r, color.g, color.b]);
+    }
+}
+
+/// Returns the destination row for each stored row
+fn gif_row_order(height: usize, is_interlaced: bool) -> Vec<usize> {
+    if !is_interlaced {
+        return (0..height).collect();
+    }
+    let mut result = Vec::with_capacity(height);
+    for &(start, step) in &[(0, 8), (4, 8), (2, 4), (1, 2)] {
+        result.extend((start..height).step_by(step));
+    }
+    result
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// LZW compression
+
+fn gif_lzw_decode(
+    data: &[u8],
+    minimum_code_size: u8,
+    pixel_count: usize,
+) -> Result<Vec<u8>, String> {
+    if minimum_code_size < 2 || minimum_code_size > 11 {
+        return Err(format!(
+            "Gif lzw minimum code size {} is invalid",
+            minimum_code_size
+        ));
+    }
+    let code_clear = 1usize << minimum_code_size;
+    let code_end = code_clear + 1;
+
+    let mut prefixes = [0u16; GIF_LZW_CODE_COUNT_MAX];
+    let mut suffixes = [0u8; GIF_LZW_CODE_COUNT_MAX];
+    let mut lengths = [0u16; GIF_LZW_CODE_COUNT_MAX];
+    for code in 0..code_clear {
+        suffixes[code] = code as u8;
+        lengths[code] = 1;
+    }
+
+    let mut code_size = minimum_code_size as u32 + 1;
+    let mut code_next = code_clear + 2;
+    let mut code_previous: Option<usize> = None;
+
+    let mut result = Vec::with_capacity(pixel_count);
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    let mut bytes = data.iter();
+    while result.len() < pixel_count {
+        while bit_count < code_size {
+            match bytes.next() {
+                Some(&byte) => {
+                    bit_buffer |= (byte as u32) << bit_count;
+                    bit_count += 8;
+                }
+                None => break,
+            }
+        }
+        if bit_count < code_size {
+            // NOTE: Some encoders omit the end code or produce too few pixels
+            break;
+        }
+        let code = (bit_buffer & ((1 << code_size) - 1)) as usize;
+        bit_buffer >>= code_size;
+        bit_count -= code_size;
+
+        if code == code_clear {
+            code_size = minimum_code_size as u32 + 1;
+            code_next = code_clear + 2;
+            code_previous = None;
+            continue;
+        }
+        if code == code_end {
+            break;
+        }
+
+        let previous = match code_previous {
+            Some(previous) => previous,
+            None => {
+                if code >= code_clear {
+                    return Err(format!("Gif lzw data has invalid code {}", code));
+                }
+                result.push(code as u8);
+                code_previous = Some(code);
+                continue;
+            }
+        };
+
+        let start = result.len();
+        let (code_written, extra_length) = if code < code_next {
+            (code, 0)
+        } else if code == code_next {
+            (previous, 1)
+        } else {
+            return Err(format!("Gif lzw data has invalid code {}", code));
+        };
+        let length = lengths[code_written] as usize;
+        result.resize(start + length, 0);
+        let mut code_current = code_written;
+        for pos in (start..start + length).rev() {
+            result[pos] = suffixes[code_current];
+            code_current = prefixes[code_current] as usize;
+        }
+        let first_byte = result[start];
+        if extra_length > 0 {
+            result.push(first_byte);
+        }
+
+        if code_next < GIF_LZW_CODE_COUNT_MAX {
+            prefixes[code_next] = previous as u16;
+            suffixes[code_next] = first_byte;
+            lengths[code_next] = lengths[previous] + 1;
+            code_next += 1;
+            if code_next == 1 << code_size && code_size < GIF_LZW_CODE_SIZE_MAX {
+                code_size += 1;
+            }
+        }
+        code_previous = Some(code);
+    }
+
+    result.resize(pixel_count, 0);
+    Ok(result)
+}
+
+fn gif_lzw_encode(indices: &[u8], minimum_code_size: u8) -> Vec<u8> {
+    let code_clear = 1u16 << minimum_code_size;
+    let code_end = code_clear + 1;
+
+    let mut result = Vec::new();
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    let mut emit = |code: u16, code_size: u32| {
+        bit_buffer |= (code as u32) << bit_count;
+        bit_count += code_size;
+        while bit_count >= 8 {
+            result.push(bit_buffer as u8);
+            bit_buffer >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    let mut dictionary: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = minimum_code_size as u32 + 1;
+    let mut code_next = code_clear + 2;
+    emit(code_clear, code_size);
+
+    let mut code_current: Option<u16> = None;
+    for &index in indices {
+        let current = match code_current {
+            Some(current) => current,
+            None => {
+                code_current = Some(index as u16);
+                continue;
+            }
+        };
+        if let Some(&code) = dictionary.get(&(current, index)) {
+            code_current = Some(code);
+            continue;
+        }
+
+        emit(current, code_size);
+        if (code_next as usize) < GIF_LZW_CODE_COUNT_MAX {
+            dictionary.insert((current, index), code_next);
+            code_next += 1;
+            if code_next as u32 > 1 << code_size && code_size < GIF_LZW_CODE_SIZE_MAX {
+                code_size += 1;
+            }
+        } else {
+            emit(code_clear, code_size);
+            dictionary.clear();
+            code_size = minimum_code_size as u32 + 1;
+            code_next = code_clear + 2;
+        }
+        code_current = Some(index as u16);
+    }
+
+    if let Some(current) = code_current {
+        emit(current, code_size);
+        // NOTE: The decoder adds a dictionary entry after reading our last code which may increase
+        //       its code size for reading the end code
+        if (code_next as usize) < GIF_LZW_CODE_COUNT_MAX {
+            code_next += 1;
+            if code_next as u32 > 1 << code_size && code_size < GIF_LZW_CODE_SIZE_MAX {
+                code_size += 1;
+            }
+        }
+    }
+    emit(code_end, code_size);
+    if bit_count > 0 {
+        result.push(bit_buffer as u8);
+    }
+
+    result
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Random;
+
+    #[test]
+    fn gif_lzw_roundtrip() {
+        let mut random = Random::new_from_seed(4321);
+        // NOTE: This is long enough to fill the dictionary and force clear codes
+        let indices: Vec<u8> = (0..50_000)
+            .map(|index| {
+                if index % 7 < 3 {
+                    (random.u32() % 16) as u8
+                } else {
+                    (index / 100 % 16) as u8
+                }
+            })
+            .collect();
+        let encoded = gif_lzw_encode(&indices, 4);
+        let decoded = gif_lzw_decode(&encoded, 4, indices.len()).unwrap();
+        assert_eq!(decoded, indices);
+    }
+
+    #[test]
+    fn gif_animation_roundtrip() {
+        let mut random = Random::new_from_seed(1234);
+        let mut frames = Vec::new();
+        for frame_index in 0..3 {
+            let mut frame = Bitmap::new(31, 17);
+            for (index, pixel) in frame.data.iter_mut().enumerate() {
+                *pixel = if (index + frame_index) % 11 == 0 {
+                    PixelRGBA::transparent()
+                } else {
+                    PixelRGBA::new(
+                        (random.u32() % 16) as u8 * 16,
+                        (random.u32() % 8) as u8 * 32,
+                        frame_index as u8 * 100,
+                        255,
+                    )
+                };
+            }
+            frames.push(frame);
+        }
+        let animation = GifAnimation {
+            width: 31,
+            height: 17,
+            frames,
+            frame_durations_ms: vec![100, 50, 200],
+            loop_count: Some(0),
+        };
+
+        // NOTE: The three frames together have more than 256 colors so we use local palettes
+        let gif_data = animation.encoded_as_gif().unwrap();
+        let decoded = GifAnimation::from_gif_data(&gif_data).unwrap();
+        assert_eq!(decoded, animation);
+    }
+
+    #[test]
+    fn gif_quantized_animation_is_encodable() {
+        let mut random = Random::new_from_seed(1234);
+        let frames: Vec<Bitmap> = (0..2)
+            .map(|_| {
+                let mut frame = Bitmap::new(32, 32);
+                for pixel in frame.data.iter_mut() {
+                    *pixel = PixelRGBA::new_random(&mut random);
+                }
+                frame
+            })
+            .collect();
+        let animation = GifAnimation {
+            width: 32,
+            height: 32,
+            frames,
+            frame_durations_ms: vec![100, 100],
+            loop_count: Some(0),
+        };
+        assert!(animation.encoded_as_gif().is_err());
+
+        let quantized = animation.quantized(16, Dithering::FloydSteinberg);
+        let gif_data = quantized.encoded_as_gif().unwrap();
+        assert_eq!(GifAnimation::from_gif_data(&gif_data).unwrap(), quantized);
+    }
+
+    #[test]
+    fn gif_invalid_colors() {
+        let mut bitmap = Bitmap::new_filled(4, 4, PixelRGBA::red());
+        bitmap.set(1, 1, PixelRGBA::new(10, 10, 10, 128));
+        assert!(bitmap.encoded_as_gif().is_err());
+
+        let mut bitmap = Bitmap::new(32, 32);
+        for (index, pixel) in bitmap.data.iter_mut().enumerate() {
+            *pixel = PixelRGBA::new(index as u8, (index / 256) as u8, 0, 255);
+        }
+        assert!(bitmap.encoded_as_gif().is_err());
+    }
+}
@@ -582,6 +582,115 @@ where
         scaled_grid
     }
 
+    pub fn scale_sample_nearest_neighbor(&mut self, new_width: u32, new_height: u32) {
+        *self = self.scaled_sample_nearest_neighbor(new_width, new_height);
+    }
+
+    #[must_use]
+    pub fn scaled_sample_nearest_neighbor(
+        &self,
+        new_width: u32,
+        new_height: u32,
+    ) -> Grid<CellType> {
+        assert!(new_width > 0);
+        assert!(new_height > 0);
+
+        let mut result = Grid::new(new_width, new_height);
+        let result_rect = result.rect();
+        Grid::copy_region_sample_nearest_neighbor(self, self.rect(), &mut result, result_rect);
+
+        result
+    }
+
+    pub fn rotate_90_clockwise(&mut self) {
+        *self = self.rotated_90_clockwise();
+    }
+
+    #[must_use]
+    pub fn rotated_90_clockwise(&self) -> Grid<CellType> {
+        let mut result = Grid::new(self.height as u32, self.width as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                result.set(self.height - 1 - y, x, self.get(x, y));
+            }
+        }
+        result
+    }
+
+    pub fn rotate_180(&mut self) {
+        self.data.reverse();
+    }
+
+    #[must_use]
+    pub fn rotated_180(&self) -> Grid<CellType> {
+        let mut result = self.clone();
+        result.rotate_180();
+        result
+    }
+
+    pub fn rotate_90_counterclockwise(&mut self) {
+        *self = self.rotated_90_counterclockwise();
+    }
+
+    /// NOTE: This is the same as rotating by 270 degrees clockwise
+    #[must_use]
+    pub fn rotated_90_counterclockwise(&self) -> Grid<CellType> {
+        let mut result = Grid::new(self.height as u32, self.width as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                result.set(y, self.width - 1 - x, self.get(x, y));
+            }
+        }
+        result
+    }
+
+    /// Mirrors the grid on its vertical axis
+    pub fn flip_horizontally(&mut self) {
+        for row in self.data.chunks_exact_mut(self.width as usize) {
+            row.reverse();
+        }
+    }
+
+    #[must_use]
+    pub fn flipped_horizontally(&self) -> Grid<CellType> {
+        let mut result = self.clone();
+        result.flip_horizontally();
+        result
+    }
+
+    /// Mirrors the grid on its horizontal axis
+    pub fn flip_vertically(&mut self) {
+        let width = self.width as usize;
+        for y in 0..(self.height / 2) as usize {
+            let y_mirrored = self.height as usize - 1 - y;
+            let (upper, lower) = self.data.split_at_mut(y_mirrored * width);
+            upper[y * width..(y + 1) * width].swap_with_slice(&mut lower[..width]);
+        }
+    }
+
+    #[must_use]
+    pub fn flipped_vertically(&self) -> Grid<CellType> {
+        let mut result = self.clone();
+        result.flip_vertically();
+        result
+    }
+
+    /// Mirrors the grid on its main diagonal
+    pub fn transpose(&mut self) {
+        *self = self.transposed();
+    }
+
+    #[must_use]
+    pub fn transposed(&self) -> Grid<CellType> {
+        let mut result = Grid::new(self.height as u32, self.width as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                result.set(y, x, self.get(x, y));
+            }
+        }
+        result
+    }
+
     pub fn copy_region_sample_nearest_neighbor(
         source_grid: &Grid<CellType>,
         source_rect: Recti,
@@ -1023,3 +1132,63 @@ where
         (segment_images, segment_coordinates)
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_rotations_flips_and_transpose() {
+        // 0 1 2
+        // 3 4 5
+        let grid = Grid::new_from_buffer(3, 2, vec![0, 1, 2, 3, 4, 5]);
+
+        let rotated = grid.rotated_90_clockwise();
+        assert_eq!(rotated, Grid::new_from_buffer(2, 3, vec![3, 0, 4, 1, 5, 2]));
+        assert_eq!(
+            grid.rotated_90_counterclockwise(),
+            Grid::new_from_buffer(2, 3, vec![2, 5, 1, 4, 0, 3])
+        );
+        assert_eq!(
+            grid.rotated_180(),
+            Grid::new_from_buffer(3, 2, vec![5, 4, 3, 2, 1, 0])
+        );
+        assert_eq!(rotated.rotated_90_clockwise(), grid.rotated_180());
+        assert_eq!(rotated.rotated_90_counterclockwise(), grid);
+
+        assert_eq!(
+            grid.flipped_horizontally(),
+            Grid::new_from_buffer(3, 2, vec![2, 1, 0, 5, 4, 3])
+        );
+        assert_eq!(
+            grid.flipped_vertically(),
+            Grid::new_from_buffer(3, 2, vec![3, 4, 5, 0, 1, 2])
+        );
+        assert_eq!(
+            grid.flipped_horizontally().flipped_vertically(),
+            grid.rotated_180()
+        );
+        let odd_height = Grid::new_from_buffer(1, 3, vec![0, 1, 2]);
+        assert_eq!(
+            odd_height.flipped_vertically(),
+            Grid::new_from_buffer(1, 3, vec![2, 1, 0])
+        );
+
+        assert_eq!(
+            grid.transposed(),
+            Grid::new_from_buffer(2, 3, vec![0, 3, 1, 4, 2, 5])
+        );
+
+        assert_eq!(
+            grid.scaled_sample_nearest_neighbor(6, 1),
+            Grid::new_from_buffer(6, 1, vec![0, 0, 1, 1, 2, 2])
+        );
+        assert_eq!(
+            grid.scaled_sample_nearest_neighbor(2, 4),
+            Grid::new_from_buffer(2, 4, vec![0, 1, 0, 1, 3, 4, 3, 4])
+        );
+    }
+}
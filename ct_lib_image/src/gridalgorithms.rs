@@ -0,0 +1,760 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Grid algorithms
+//
+// Pathfinding, flow fields, connected component labeling, distance transforms and line of sight
+// queries on grids
+
+use super::distancefield::euclidean_distance_transform;
+use super::grid::*;
+use super::math::*;
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridNeighborhood {
+    /// Only horizontal and vertical neighbors
+    FourWay,
+    /// Horizontal, vertical and diagonal neighbors
+    EightWay,
+}
+
+impl GridNeighborhood {
+    #[inline]
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        const OFFSETS_FOUR_WAY: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        const OFFSETS_EIGHT_WAY: [(i32, i32); 8] = [
+            (1, 0),
+            (0, 1),
+            (-1, 0),
+            (0, -1),
+            (1, 1),
+            (-1, 1),
+            (-1, -1),
+            (1, -1),
+        ];
+        match self {
+            GridNeighborhood::FourWay => &OFFSETS_FOUR_WAY,
+            GridNeighborhood::EightWay => &OFFSETS_EIGHT_WAY,
+        }
+    }
+
+    /// Returns a lower bound for the cost of moving between two cells assuming that entering a
+    /// cell costs at least 1
+    #[inline]
+    fn heuristic(self, a: Vec2i, b: Vec2i) -> f32 {
+        let delta_x = (a.x - b.x).abs() as f32;
+        let delta_y = (a.y - b.y).abs() as f32;
+        match self {
+            GridNeighborhood::FourWay => delta_x + delta_y,
+            GridNeighborhood::EightWay => {
+                // NOTE: This is the octile distance
+                f32::max(delta_x, delta_y)
+                    + (std::f32::consts::SQRT_2 - 1.0) * f32::min(delta_x, delta_y)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridPath {
+    /// All cells of the path including the start and goal cell
+    pub cells: Vec<Vec2i>,
+    pub cost: f32,
+}
+
+/// The result of a Dijkstra search from one or more goal cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridDijkstraMap {
+    /// The cost of the cheapest path from each cell to its nearest goal. Unreachable cells have
+    /// a cost of `f32::INFINITY`
+    pub costs: Grid<f32>,
+    /// The direction each cell needs to move to follow the cheapest path to its nearest goal.
+    /// Goal cells and unreachable cells have a direction of zero
+    pub flow_field: Grid<Vec2i>,
+}
+
+impl GridDijkstraMap {
+    #[inline]
+    pub fn is_reachable(&self, cell: Vec2i) -> bool {
+        self.costs.get(cell.x, cell.y) != f32::INFINITY
+    }
+
+    /// Returns the cheapest path from the given cell to its nearest goal by following the flow
+    /// field or `None` if no goal is reachable from there
+    pub fn path_from(&self, start: Vec2i) -> Option<GridPath> {
+        if !self.is_reachable(start) {
+            return None;
+        }
+        let mut cells = vec![start];
+        let mut current = start;
+        loop {
+            let direction = self.flow_field.get(current.x, current.y);
+            if direction == Vec2i::zero() {
+                break;
+            }
+            current += direction;
+            cells.push(current);
+        }
+        Some(GridPath {
+            cells,
+            cost: self.costs.get(start.x, start.y),
+        })
+    }
+}
+
+/// Entry of our open lists that is ordered such that `BinaryHeap` pops the lowest priority first
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SearchNode {
+    priority: f32,
+    cell_index: usize,
+}
+
+impl Eq for SearchNode {}
+
+impl PartialOrd for SearchNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SearchNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .partial_cmp(&self.priority)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.cell_index.cmp(&self.cell_index))
+    }
+}
+
+impl<CellType> Grid<CellType>
+where
+    CellType: Default + Clone + Copy + PartialEq,
+{
+    //----------------------------------------------------------------------------------------------
+    // Pathfinding
+
+    /// Finds the cheapest path between two cells using A*. The `cost` function returns the cost of
+    /// entering a given cell or `None` if the cell is impassable. Diagonal moves cost `sqrt(2)`
+    /// times as much and are not allowed to cut corners of impassable cells.
+    ///
+    /// NOTE: The path is only guaranteed to be the cheapest if entering a cell costs at least 1.
+    ///       Use `find_path_dijkstra` otherwise
+    pub fn find_path_astar<F>(
+        &self,
+        start: Vec2i,
+        goal: Vec2i,
+        neighborhood: GridNeighborhood,
+        cost: F,
+    ) -> Option<GridPath>
+    where
+        F: Fn(CellType) -> Option<f32>,
+    {
+        self.find_path_internal(start, goal, neighborhood, &cost, true)
+    }
+
+    /// Same as `find_path_astar` but without a heuristic. This is slower but finds the cheapest
+    /// path for arbitrary non-negative cell costs
+    pub fn find_path_dijkstra<F>(
+        &self,
+        start: Vec2i,
+        goal: Vec2i,
+        neighborhood: GridNeighborhood,
+        cost: F,
+    ) -> Option<GridPath>
+    where
+        F: Fn(CellType) -> Option<f32>,
+    {
+        self.find_path_internal(start, goal, neighborhood, &cost, false)
+    }
+
+    /// Computes the cost of the cheapest path from every cell to its nearest goal cell together
+    /// with a flow field that points along these paths. See `find_path_astar` for how `cost` is
+    /// used. Note that the cost of entering the goal cells is used for movement towards them.
+    pub fn dijkstra_map<F>(
+        &self,
+        goals: &[Vec2i],
+        neighborhood: GridNeighborhood,
+        cost: F,
+    ) -> GridDijkstraMap
+    where
+        F: Fn(CellType) -> Option<f32>,
+    {
+        let mut costs = Grid::new_filled(self.width as u32, self.height as u32, f32::INFINITY);
+        let mut flow_field = Grid::new(self.width as u32, self.height as u32);
+        let mut open_list = BinaryHeap::new();
+        for goal in goals {
+            if !self.contains_point(goal.x, goal.y) || cost(self.get(goal.x, goal.y)).is_none() {
+                continue;
+            }
+            costs.set(goal.x, goal.y, 0.0);
+            open_list.push(SearchNode {
+                priority: 0.0,
+                cell_index: self.cell_index(*goal),
+            });
+        }
+
+        while let Some(node) = open_list.pop() {
+            let cell = self.cell_pos(node.cell_index);
+            let cell_cost_so_far = costs.data[node.cell_index];
+            if node.priority > cell_cost_so_far {
+                // NOTE: We already found a cheaper way to this cell
+                continue;
+            }
+
+            // NOTE: We search backwards from the goals, so we pay the cost of entering the
+            //       current cell when coming from the neighbor
+            let enter_cost = if let Some(enter_cost) = cost(self.get(cell.x, cell.y)) {
+                enter_cost
+            } else {
+                continue;
+            };
+            for &(delta_x, delta_y) in neighborhood.offsets() {
+                let neighbor = Vec2i::new(cell.x + delta_x, cell.y + delta_y);
+                if !self.is_move_allowed(cell, neighbor, &cost) {
+                    continue;
+                }
+                let step_cost = step_cost(delta_x, delta_y, enter_cost);
+                let neighbor_index = self.cell_index(neighbor);
+                let neighbor_cost = cell_cost_so_far + step_cost;
+                if neighbor_cost < costs.data[neighbor_index] {
+                    costs.data[neighbor_index] = neighbor_cost;
+                    flow_field.data[neighbor_index] = Vec2i::new(-delta_x, -delta_y);
+                    open_list.push(SearchNode {
+                        priority: neighbor_cost,
+                        cell_index: neighbor_index,
+                    });
+                }
+            }
+        }
+
+        GridDijkstraMap { costs, flow_field }
+    }
+
+    fn find_path_internal<F>(
+        &self,
+        start: Vec2i,
+        goal: Vec2i,
+        neighborhood: GridNeighborhood,
+        cost: &F,
+        use_heuristic: bool,
+    ) -> Option<GridPath>
+    where
+        F: Fn(CellType) -> Option<f32>,
+    {
+        if !self.contains_point(start.x, start.y) || !self.contains_point(goal.x, goal.y) {
+            return None;
+        }
+        cost(self.get(goal.x, goal.y))?;
+
+        let heuristic = |cell: Vec2i| -> f32 {
+            if use_heuristic {
+                neighborhood.heuristic(cell, goal)
+            } else {
+                0.0
+            }
+        };
+
+        let cell_count = (self.width * self.height) as usize;
+        let mut costs_so_far = vec![f32::INFINITY; cell_count];
+        let mut came_from = vec![usize::MAX; cell_count];
+        let mut open_list = BinaryHeap::new();
+
+        let start_index = self.cell_index(start);
+        let goal_index = self.cell_index(goal);
+        costs_so_far[start_index] = 0.0;
+        open_list.push(SearchNode {
+            priority: heuristic(start),
+            cell_index: start_index,
+        });
+
+        while let Some(node) = open_list.pop() {
+            if node.cell_index == goal_index {
+                let mut cells = vec![goal];
+                let mut current_index = goal_index;
+                while current_index != start_index {
+                    current_index = came_from[current_index];
+                    cells.push(self.cell_pos(current_index));
+                }
+                cells.reverse();
+                return Some(GridPath {
+                    cells,
+                    cost: costs_so_far[goal_index],
+                });
+            }
+
+            let cell = self.cell_pos(node.cell_index);
+            let cell_cost_so_far = costs_so_far[node.cell_index];
+            if node.priority > cell_cost_so_far + heuristic(cell) {
+                // NOTE: We already found a cheaper way to this cell
+                continue;
+            }
+
+            for &(delta_x, delta_y) in neighborhood.offsets() {
+                let neighbor = Vec2i::new(cell.x + delta_x, cell.y + delta_y);
+                if !self.is_move_allowed(cell, neighbor, cost) {
+                    continue;
+                }
+                let enter_cost = cost(self.get(neighbor.x, neighbor.y)).unwrap();
+                let neighbor_index = self.cell_index(neighbor);
+                let neighbor_cost = cell_cost_so_far + step_cost(delta_x, delta_y, enter_cost);
+                if neighbor_cost < costs_so_far[neighbor_index] {
+                    costs_so_far[neighbor_index] = neighbor_cost;
+                    came_from[neighbor_index] = node.cell_index;
+                    open_list.push(SearchNode {
+                        priority: neighbor_cost + heuristic(neighbor),
+                        cell_index: neighbor_index,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Checks if we can move between two neighboring cells. Diagonal moves must not cut corners
+    #[inline]
+    fn is_move_allowed<F>(&self, from: Vec2i, to: Vec2i, cost: &F) -> bool
+    where
+        F: Fn(CellType) -> Option<f32>,
+    {
+        if !self.contains_point(to.x, to.y) || cost(self.get(to.x, to.y)).is_none() {
+            return false;
+        }
+        if from.x != to.x && from.y != to.y {
+            return cost(self.get(to.x, from.y)).is_some()
+                && cost(self.get(from.x, to.y)).is_some();
+        }
+        true
+    }
+
+    #[inline]
+    fn cell_index(&self, cell: Vec2i) -> usize {
+        (cell.x + cell.y * self.width) as usize
+    }
+
+    #[inline]
+    fn cell_pos(&self, cell_index: usize) -> Vec2i {
+        Vec2i::new(
+            cell_index as i32 % self.width,
+            cell_index as i32 / self.width,
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Labeling
+
+    /// Assigns a label to every cell for which `is_foreground` holds such that connected cells
+    /// with equal values share the same label. Labels start at 1, background cells get the label 0.
+    /// Returns the labels and the number of components found.
+    pub fn label_connected_components<F>(
+        &self,
+        neighborhood: GridNeighborhood,
+        is_foreground: F,
+    ) -> (Grid<u32>, u32)
+    where
+        F: Fn(CellType) -> bool,
+    {
+        let mut labels = Grid::new(self.width as u32, self.height as u32);
+        let mut label_count = 0;
+        let mut stack = Vec::new();
+        for start_index in 0..self.data.len() {
+            let start_value = self.data[start_index];
+            if labels.data[start_index] != 0 || !is_foreground(start_value) {
+                continue;
+            }
+
+            label_count += 1;
+            labels.data[start_index] = label_count;
+            stack.push(start_index);
+            while let Some(cell_index) = stack.pop() {
+                let cell = self.cell_pos(cell_index);
+                for &(delta_x, delta_y) in neighborhood.offsets() {
+                    let neighbor = Vec2i::new(cell.x + delta_x, cell.y + delta_y);
+                    if !self.contains_point(neighbor.x, neighbor.y) {
+                        continue;
+                    }
+                    let neighbor_index = self.cell_index(neighbor);
+                    if labels.data[neighbor_index] == 0 && self.data[neighbor_index] == start_value
+                    {
+                        labels.data[neighbor_index] = label_count;
+                        stack.push(neighbor_index);
+                    }
+                }
+            }
+        }
+        (labels, label_count)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Distance transforms
+
+    /// Returns the euclidean distance of every cell to the nearest cell for which `is_feature`
+    /// holds. If there are no feature cells all distances are huge.
+    pub fn distance_transform_euclidean<F>(&self, is_feature: F) -> Grid<f32>
+    where
+        F: Fn(CellType) -> bool,
+    {
+        let distances = euclidean_distance_transform(self.width, self.height, |x, y| {
+            is_feature(self.get(x, y))
+        });
+        Grid::new_from_buffer(self.width as u32, self.height as u32, distances)
+    }
+
+    /// Returns the manhattan distance of every cell to the nearest cell for which `is_feature`
+    /// holds. If there are no feature cells all distances are `i32::MAX`.
+    pub fn distance_transform_manhattan<F>(&self, is_feature: F) -> Grid<i32>
+    where
+        F: Fn(CellType) -> bool,
+    {
+        let mut result = Grid::new_filled(self.width as u32, self.height as u32, i32::MAX);
+        for (distance, &cell) in result.data.iter_mut().zip(self.data.iter()) {
+            if is_feature(cell) {
+                *distance = 0;
+            }
+        }
+
+        // NOTE: The first pass propagates distances from the top-left, the second from the
+        //       bottom-right
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut distance = result.get(x, y);
+                if x > 0 {
+                    distance = i32::min(distance, result.get(x - 1, y).saturating_add(1));
+                }
+                if y > 0 {
+                    distance = i32::min(distance, result.get(x, y - 1).saturating_add(1));
+                }
+                result.set(x, y, distance);
+            }
+        }
+        for y in (0..self.height).rev() {
+            for x in (0..self.width).rev() {
+                let mut distance = result.get(x, y);
+                if x < self.width - 1 {
+                    distance = i32::min(distance, result.get(x + 1, y).saturating_add(1));
+                }
+                if y < self.height - 1 {
+                    distance = i32::min(distance, result.get(x, y + 1).saturating_add(1));
+                }
+                result.set(x, y, distance);
+            }
+        }
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Line of sight
+
+    /// Returns true if no cell strictly between the two given cells blocks the view. The cells
+    /// in between are determined via Bresenham's line algorithm.
+    pub fn has_line_of_sight<F>(&self, from: Vec2i, to: Vec2i, is_blocking: F) -> bool
+    where
+        F: Fn(CellType) -> bool,
+    {
+        let mut is_visible = true;
+        iterate_line_bresenham(from, to, true, &mut |x, y| {
+            if (x, y) == (from.x, from.y) {
+                return;
+            }
+            if !self.contains_point(x, y) || is_blocking(self.get(x, y)) {
+                is_visible = false;
+            }
+        });
+        is_visible
+    }
+
+    /// Returns all cells that are visible from the given cell within the given radius. A cell is
+    /// visible if it has a line of sight to `from`. Blocking cells can be visible themselves.
+    pub fn visible_cells<F>(&self, from: Vec2i, radius: i32, is_blocking: F) -> Grid<bool>
+    where
+        F: Fn(CellType) -> bool,
+    {
+        let mut result = Grid::new(self.width as u32, self.height as u32);
+        let radius_squared = radius * radius;
+        for y in i32::max(0, from.y - radius)..i32::min(self.height, from.y + radius + 1) {
+            for x in i32::max(0, from.x - radius)..i32::min(self.width, from.x + radius + 1) {
+                let cell = Vec2i::new(x, y);
+                if Vec2i::dot(cell - from, cell - from) > radius_squared {
+                    continue;
+                }
+                if self.has_line_of_sight(from, cell, &is_blocking) {
+                    result.set(x, y, true);
+                }
+            }
+        }
+        result
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Contours
+
+    /// Extracts the outlines of all regions for which `is_inside` holds via marching squares and
+    /// simplifies them with the Ramer-Douglas-Peucker algorithm using the given tolerance (in cells).
+    /// Cell (x,y) covers the area from (x,y) to (x+1,y+1). Outside the grid everything counts as
+    /// not inside so all returned polygons are closed.
+    ///
+    /// NOTE: Outer contours are returned in clockwise order and holes in counterclockwise order
+    ///       (with y pointing down). Diagonally touching cells are treated as not connected.
+    pub fn contours<F>(&self, is_inside: F, simplification_tolerance: f32) -> Vec<Vec<Vec2>>
+    where
+        F: Fn(CellType) -> bool,
+    {
+        let sample =
+            |x: i32, y: i32| -> bool { self.contains_point(x, y) && is_inside(self.get(x, y)) };
+
+        // NOTE: We use doubled coordinates for our segment endpoints which all lie on the
+        //       midpoints between sample points. This way we can match them exactly.
+        let mut segment_starts = Vec::new();
+        let mut segment_ends = HashMap::new();
+        for y in -1..self.height {
+            for x in -1..self.width {
+                let case = (sample(x, y) as u8) << 3
+                    | (sample(x + 1, y) as u8) << 2
+                    | (sample(x + 1, y + 1) as u8) << 1
+                    | (sample(x, y + 1) as u8);
+
+                let top = (2 * x + 2, 2 * y + 1);
+                let right = (2 * x + 3, 2 * y + 2);
+                let bottom = (2 * x + 2, 2 * y + 3);
+                let left = (2 * x + 1, 2 * y + 2);
+
+                // NOTE: Segments are oriented such that the inside is on their right hand side
+                let segments: &[((i32, i32), (i32, i32))] = match case {
+                    0b0001 => &[(left, bottom)],
+                    0b0010 => &[(bottom, right)],
+                    0b0011 => &[(left, right)],
+                    0b0100 => &[(right, top)],
+                    0b0101 => &[(left, bottom), (right, top)],
+                    0b0110 => &[(bottom, top)],
+                    0b0111 => &[(left, top)],
+                    0b1000 => &[(top, left)],
+                    0b1001 => &[(top, bottom)],
+                    0b1010 => &[(top, left), (bottom, right)],
+                    0b1011 => &[(top, right)],
+                    0b1100 => &[(right, left)],
+                    0b1101 => &[(right, bottom)],
+                    0b1110 => &[(bottom, left)],
+                    _ => &[],
+                };
+                for &(start, end) in segments {
+                    segment_starts.push(start);
+                    segment_ends.insert(start, end);
+                }
+            }
+        }
+
+        let mut result = Vec::new();
+        for contour_start in segment_starts {
+            if !segment_ends.contains_key(&contour_start) {
+                // NOTE: This segment is already part of a previous contour
+                continue;
+            }
+
+            let mut contour = Vec::new();
+            let mut current = contour_start;
+            while let Some(next) = segment_ends.remove(&current) {
+                contour.push(Vec2::new(current.0 as f32 / 2.0, current.1 as f32 / 2.0));
+                current = next;
+            }
+            debug_assert!(current == contour_start);
+            result.push(polygon_simplified(&contour, simplification_tolerance));
+        }
+        result
+    }
+}
+
+#[inline]
+fn step_cost(delta_x: i32, delta_y: i32, enter_cost: f32) -> f32 {
+    if delta_x != 0 && delta_y != 0 {
+        std::f32::consts::SQRT_2 * enter_cost
+    } else {
+        enter_cost
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a map where '#' is a wall, '~' is a swamp with cost 5 and everything else is floor
+    fn grid_from_map(map: &[&str]) -> Grid<char> {
+        let width = map[0].len() as u32;
+        let height = map.len() as u32;
+        let cells = map.iter().flat_map(|row| row.chars()).collect();
+        Grid::new_from_buffer(width, height, cells)
+    }
+
+    fn map_cost(cell: char) -> Option<f32> {
+        match cell {
+            '#' => None,
+            '~' => Some(5.0),
+            _ => Some(1.0),
+        }
+    }
+
+    #[test]
+    fn grid_pathfinding() {
+        let grid = grid_from_map(&[
+            "..........", //
+            ".########.",
+            ".#......#.",
+            ".#.####.#.",
+            "...#..#...",
+            "####..####",
+            "~~~~~~~~~~",
+        ]);
+        let start = Vec2i::new(0, 4);
+        let goal = Vec2i::new(9, 4);
+
+        let path_astar = grid
+            .find_path_astar(start, goal, GridNeighborhood::FourWay, map_cost)
+            .unwrap();
+        let path_dijkstra = grid
+            .find_path_dijkstra(start, goal, GridNeighborhood::FourWay, map_cost)
+            .unwrap();
+        assert_eq!(path_astar.cost, 13.0);
+        assert_eq!(path_dijkstra.cost, path_astar.cost);
+        assert_eq!(*path_astar.cells.first().unwrap(), start);
+        assert_eq!(*path_astar.cells.last().unwrap(), goal);
+        for step in path_astar.cells.windows(2) {
+            assert_eq!(Vec2i::manhattan_distance(step[0], step[1]), 1);
+            assert_ne!(grid.get(step[1].x, step[1].y), '#');
+        }
+
+        // NOTE: Diagonal moves cannot cut the corners of the wall
+        let path_diagonal = grid
+            .find_path_astar(start, goal, GridNeighborhood::EightWay, map_cost)
+            .unwrap();
+        assert!(path_diagonal.cost <= path_astar.cost);
+        for step in path_diagonal.cells.windows(2) {
+            assert_ne!(grid.get(step[0].x, step[1].y), '#');
+            assert_ne!(grid.get(step[1].x, step[0].y), '#');
+        }
+
+        let enclosed = Vec2i::new(4, 4);
+        assert!(grid
+            .find_path_astar(start, enclosed, GridNeighborhood::EightWay, map_cost)
+            .is_none());
+        assert!(grid
+            .find_path_astar(start, Vec2i::new(1, 1), GridNeighborhood::FourWay, map_cost)
+            .is_none());
+
+        // NOTE: The dijkstra map agrees with the pathfinding and its flow field leads to the goal
+        let dijkstra_map = grid.dijkstra_map(&[goal], GridNeighborhood::FourWay, map_cost);
+        assert_eq!(dijkstra_map.costs.get(start.x, start.y), 13.0);
+        assert!(!dijkstra_map.is_reachable(enclosed));
+        let path_flow = dijkstra_map.path_from(start).unwrap();
+        assert_eq!(path_flow.cells.len(), path_astar.cells.len());
+        assert_eq!(*path_flow.cells.last().unwrap(), goal);
+        assert!(!dijkstra_map.is_reachable(Vec2i::new(0, 6)));
+
+        // NOTE: Swamp cells are expensive to enter
+        let dijkstra_map = grid.dijkstra_map(&[enclosed], GridNeighborhood::FourWay, map_cost);
+        assert_eq!(dijkstra_map.costs.get(0, 6), 4.0 * 5.0 + 2.0 * 1.0);
+        assert_eq!(dijkstra_map.flow_field.get(0, 6), Vec2i::unit_x());
+        assert_eq!(dijkstra_map.flow_field.get(4, 4), Vec2i::zero());
+
+        // NOTE: Goals outside of the grid are ignored
+        let dijkstra_map = grid.dijkstra_map(
+            &[Vec2i::new(-1, 0), Vec2i::new(10, 4), goal],
+            GridNeighborhood::FourWay,
+            map_cost,
+        );
+        assert_eq!(dijkstra_map.costs.get(start.x, start.y), 13.0);
+    }
+
+    #[test]
+    fn grid_labeling_distance_transforms_and_line_of_sight() {
+        let grid = grid_from_map(&[
+            "##..#", //
+            "#...#", "..#..", ".#.#.",
+        ]);
+
+        let (labels, count) =
+            grid.label_connected_components(GridNeighborhood::FourWay, |cell| cell == '#');
+        assert_eq!(count, 5);
+        assert_eq!(labels.get(0, 0), labels.get(0, 1));
+        assert_eq!(labels.get(4, 0), labels.get(4, 1));
+        assert_eq!(labels.get(2, 0), 0);
+        let (_labels, count) =
+            grid.label_connected_components(GridNeighborhood::EightWay, |cell| cell == '#');
+        assert_eq!(count, 3);
+        let (_labels, count) = grid.label_connected_components(GridNeighborhood::FourWay, |_| true);
+        assert_eq!(count, 7);
+
+        let is_wall = |cell: char| cell == '#';
+        let manhattan = grid.distance_transform_manhattan(is_wall);
+        let euclidean = grid.distance_transform_euclidean(is_wall);
+        assert_eq!(manhattan.get(0, 0), 0);
+        assert_eq!(manhattan.get(2, 1), 1);
+        assert_eq!(manhattan.get(0, 3), 1);
+        assert_eq!(manhattan.get(3, 0), 1);
+        assert_eq!(euclidean.get(1, 1), 1.0);
+        assert_eq!(euclidean.get(2, 0), 1.0);
+        for (&distance_euclidean, &distance_manhattan) in
+            euclidean.data.iter().zip(manhattan.data.iter())
+        {
+            assert!(distance_euclidean <= distance_manhattan as f32);
+        }
+        assert_eq!(
+            Grid::new_filled(3, 2, '.').distance_transform_manhattan(is_wall),
+            Grid::new_filled(3, 2, i32::MAX)
+        );
+
+        assert!(grid.has_line_of_sight(Vec2i::new(1, 2), Vec2i::new(3, 1), is_wall));
+        assert!(!grid.has_line_of_sight(Vec2i::new(1, 2), Vec2i::new(3, 2), is_wall));
+        assert!(grid.has_line_of_sight(Vec2i::new(1, 2), Vec2i::new(2, 2), is_wall));
+        let visible = grid.visible_cells(Vec2i::new(0, 2), 2, is_wall);
+        assert!(visible.get(1, 2));
+        assert!(visible.get(2, 2));
+        assert!(!visible.get(3, 2));
+        assert!(visible.get(0, 3));
+        assert!(visible.get(1, 1));
+        assert!(!visible.get(0, 0));
+    }
+
+    #[test]
+    fn grid_contours() {
+        let grid = grid_from_map(&[
+            ".....", //
+            ".###.", ".#.#.", ".###.", ".....",
+        ]);
+        let contours = grid.contours(|cell| cell == '#', 0.0);
+        assert_eq!(contours.len(), 2);
+
+        let signed_area = |polygon: &[Vec2]| -> f32 {
+            let mut area = 0.0;
+            for index in 0..polygon.len() {
+                let a = polygon[index];
+                let b = polygon[(index + 1) % polygon.len()];
+                area += a.x * b.y - b.x * a.y;
+            }
+            0.5 * area
+        };
+
+        // NOTE: The outer contour is a rectangle with cut corners and the hole is a diamond
+        let outer = &contours[0];
+        let hole = &contours[1];
+        assert_eq!(outer.len(), 8);
+        assert_eq!(hole.len(), 4);
+        assert_eq!(signed_area(outer), 9.0 - 4.0 * 0.125);
+        assert_eq!(signed_area(hole), -0.5);
+        for point in outer {
+            assert!(1.0 <= point.x && point.x <= 4.0 && 1.0 <= point.y && point.y <= 4.0);
+        }
+
+        // NOTE: Regions touching the grid border are closed
+        let contours = Grid::new_filled(3, 3, true).contours(|cell| cell, 0.0);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(signed_area(&contours[0]), 9.0 - 4.0 * 0.125);
+
+        // NOTE: A larger tolerance removes the cut corners
+        let contours = grid.contours(|cell| cell == '#', 1.0);
+        assert_eq!(contours[0].len(), 4);
+    }
+}
@@ -1,12 +1,31 @@
+pub mod aseprite;
 pub mod bitmap;
+pub mod bmp;
 pub mod color;
+pub mod distancefield;
+pub mod filter;
 pub mod font;
+pub mod gif;
 pub mod grid;
+pub mod gridalgorithms;
+pub mod procedural;
+pub mod qoi;
+pub mod quantize;
+pub mod rectpacker;
+pub mod scale;
+pub mod tga;
 
+pub use aseprite::*;
 pub use bitmap::*;
 pub use color::*;
+pub use distancefield::*;
 pub use font::*;
+pub use gif::*;
 pub use grid::*;
+pub use gridalgorithms::*;
+pub use procedural::*;
+pub use quantize::*;
+pub use rectpacker::*;
 
 use ct_lib_core as core;
 use ct_lib_math as math;
@@ -0,0 +1,923 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Procedural generation
+//
+// Wave function collapse, BSP dungeons, cellular automata caves and drunkard's walk. Everything
+// here is deterministic for a given state of the passed `Random` generator.
+//
+// NOTE: The level generators return grids where `true` marks walkable floor and `false` marks
+//       walls
+
+use super::core::indexmap::IndexMap;
+use super::grid::*;
+use super::gridalgorithms::*;
+use super::math::*;
+
+use std::hash::Hash;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Wave function collapse
+
+#[derive(Debug, Clone, Copy)]
+pub struct WaveFunctionCollapseParams {
+    /// The size of the square patterns for the overlapping model or the size of the square tiles
+    /// for the tiled model
+    pub pattern_size: i32,
+    /// Patterns or tile neighborhoods wrap around the borders of the sample
+    pub periodic_input: bool,
+    /// The output wraps around its borders and can therefore be tiled seamlessly
+    pub periodic_output: bool,
+    /// Also learns from all rotated and mirrored versions of the sample
+    pub use_symmetry: bool,
+    /// The number of times generation is restarted after running into a contradiction
+    pub attempts_max: usize,
+}
+
+impl Default for WaveFunctionCollapseParams {
+    fn default() -> Self {
+        WaveFunctionCollapseParams {
+            pattern_size: 3,
+            periodic_input: true,
+            periodic_output: false,
+            use_symmetry: true,
+            attempts_max: 10,
+        }
+    }
+}
+
+/// Generates a grid of the given size that locally looks like the given sample using the
+/// overlapping model of wave function collapse. Every `pattern_size` x `pattern_size` region of
+/// the output also occurs in the sample.
+pub fn wave_function_collapse_overlapping<CellType>(
+    sample: &Grid<CellType>,
+    output_width: u32,
+    output_height: u32,
+    params: &WaveFunctionCollapseParams,
+    random: &mut Random,
+) -> Result<Grid<CellType>, String>
+where
+    CellType: Default + Clone + Copy + PartialEq + Eq + Hash,
+{
+    let size = params.pattern_size;
+    assert!(size > 0);
+    if sample.width < size || sample.height < size {
+        return Err(format!(
+            "Sample with dimensions {}x{} is smaller than the pattern size {}",
+            sample.width, sample.height, size
+        ));
+    }
+    if !params.periodic_output && ((output_width as i32) < size || (output_height as i32) < size) {
+        return Err(format!(
+            "Output with dimensions {}x{} is smaller than the pattern size {}",
+            output_width, output_height, size
+        ));
+    }
+
+    // Collect patterns and count how often they occur
+    let mut pattern_counts: IndexMap<Vec<CellType>, usize> = IndexMap::new();
+    for variant in sample_variants(sample, params.use_symmetry) {
+        let (count_x, count_y) = if params.periodic_input {
+            (variant.width, variant.height)
+        } else {
+            (variant.width - size + 1, variant.height - size + 1)
+        };
+        for y in 0..count_y {
+            for x in 0..count_x {
+                let mut pattern = Vec::with_capacity((size * size) as usize);
+                for pattern_y in 0..size {
+                    for pattern_x in 0..size {
+                        pattern.push(variant.get(
+                            (x + pattern_x) % variant.width,
+                            (y + pattern_y) % variant.height,
+                        ));
+                    }
+                }
+                *pattern_counts.entry(pattern).or_insert(0) += 1;
+            }
+        }
+    }
+    let patterns: Vec<Vec<CellType>> = pattern_counts.keys().cloned().collect();
+    let weights: Vec<f32> = pattern_counts.values().map(|&count| count as f32).collect();
+
+    // NOTE: Two patterns may be neighbors if they agree on their overlapping region
+    let agrees = |a: &[CellType], b: &[CellType], delta_x: i32, delta_y: i32| -> bool {
+        for y in i32::max(0, delta_y)..i32::min(size, size + delta_y) {
+            for x in i32::max(0, delta_x)..i32::min(size, size + delta_x) {
+                if a[(x + size * y) as usize] != b[(x - delta_x + size * (y - delta_y)) as usize] {
+                    return false;
+                }
+            }
+        }
+        true
+    };
+    let mut propagator: [Vec<Vec<usize>>; 4] = Default::default();
+    for (dir, (delta_x, delta_y)) in WFC_DIRECTIONS.iter().enumerate() {
+        propagator[dir] = patterns
+            .iter()
+            .map(|a| {
+                (0..patterns.len())
+                    .filter(|&b| agrees(a, &patterns[b], *delta_x, *delta_y))
+                    .collect()
+            })
+            .collect();
+    }
+
+    let (wave_width, wave_height) = if params.periodic_output {
+        (output_width as i32, output_height as i32)
+    } else {
+        (
+            output_width as i32 - size + 1,
+            output_height as i32 - size + 1,
+        )
+    };
+    let solver = WaveFunctionCollapseSolver {
+        weights,
+        propagator,
+    };
+    let pattern_indices = solver.solve_with_retries(wave_width, wave_height, params, random)?;
+
+    // NOTE: Every wave cell determines the output cell at its position. At the right and bottom
+    //       borders of non-periodic outputs we also need the rest of the last patterns.
+    let mut result = Grid::new(output_width, output_height);
+    for y in 0..result.height {
+        for x in 0..result.width {
+            let wave_x = i32::min(x, wave_width - 1);
+            let wave_y = i32::min(y, wave_height - 1);
+            let pattern = &patterns[pattern_indices.get(wave_x, wave_y)];
+            let offset_x = x - wave_x;
+            let offset_y = y - wave_y;
+            result.set(x, y, pattern[(offset_x + size * offset_y) as usize]);
+        }
+    }
+    Ok(result)
+}
+
+/// Generates a grid that consists of `pattern_size` x `pattern_size` tiles cut out from the given
+/// sample using the simple tiled model of wave function collapse. Which tiles may be placed next to
+/// each other is learned from their neighbors in the sample. The output has the dimensions
+/// `output_width_in_tiles * pattern_size` x `output_height_in_tiles * pattern_size`.
+pub fn wave_function_collapse_tiled<CellType>(
+    sample: &Grid<CellType>,
+    output_width_in_tiles: u32,
+    output_height_in_tiles: u32,
+    params: &WaveFunctionCollapseParams,
+    random: &mut Random,
+) -> Result<Grid<CellType>, String>
+where
+    CellType: Default + Clone + Copy + PartialEq + Eq + Hash,
+{
+    let size = params.pattern_size;
+    assert!(size > 0);
+    if sample.width % size != 0 || sample.height % size != 0 {
+        return Err(format!(
+            "Sample dimensions {}x{} are not a multiple of the tile size {}",
+            sample.width, sample.height, size
+        ));
+    }
+
+    // Collect tiles and their neighbors in the sample
+    let mut tile_counts: IndexMap<Vec<CellType>, usize> = IndexMap::new();
+    let mut adjacencies: Vec<(usize, usize, usize)> = Vec::new();
+    for variant in sample_variants(sample, params.use_symmetry) {
+        let tiles_width = variant.width / size;
+        let tiles_height = variant.height / size;
+        let mut tile_indices = Grid::new(tiles_width as u32, tiles_height as u32);
+        for tile_y in 0..tiles_height {
+            for tile_x in 0..tiles_width {
+                let tile = variant.cropped_by_rect(Recti::from_xy_width_height(
+                    tile_x * size,
+                    tile_y * size,
+                    size,
+                    size,
+                ));
+                let entry = tile_counts.entry(tile.data);
+                let tile_index = entry.index();
+                *entry.or_insert(0) += 1;
+                tile_indices.set(tile_x, tile_y, tile_index);
+            }
+        }
+        for tile_y in 0..tiles_height {
+            for tile_x in 0..tiles_width {
+                for (dir, (delta_x, delta_y)) in WFC_DIRECTIONS.iter().enumerate() {
+                    let mut neighbor_x = tile_x + delta_x;
+                    let mut neighbor_y = tile_y + delta_y;
+                    if params.periodic_input {
+                        neighbor_x = (neighbor_x + tiles_width) % tiles_width;
+                        neighbor_y = (neighbor_y + tiles_height) % tiles_height;
+                    } else if !tile_indices.contains_point(neighbor_x, neighbor_y) {
+                        continue;
+                    }
+                    adjacencies.push((
+                        dir,
+                        tile_indices.get(tile_x, tile_y),
+                        tile_indices.get(neighbor_x, neighbor_y),
+                    ));
+                }
+            }
+        }
+    }
+    let tiles: Vec<Grid<CellType>> = tile_counts
+        .keys()
+        .map(|tile| Grid::new_from_buffer(size as u32, size as u32, tile.clone()))
+        .collect();
+    let weights: Vec<f32> = tile_counts.values().map(|&count| count as f32).collect();
+
+    let mut propagator: [Vec<Vec<usize>>; 4] = Default::default();
+    for neighbors in propagator.iter_mut() {
+        *neighbors = vec![Vec::new(); tiles.len()];
+    }
+    for (dir, tile, neighbor) in adjacencies {
+        if !propagator[dir][tile].contains(&neighbor) {
+            propagator[dir][tile].push(neighbor);
+        }
+    }
+
+    let solver = WaveFunctionCollapseSolver {
+        weights,
+        propagator,
+    };
+    let tile_indices = solver.solve_with_retries(
+        output_width_in_tiles as i32,
+        output_height_in_tiles as i32,
+        params,
+        random,
+    )?;
+
+    let mut result = Grid::new(
+        output_width_in_tiles * size as u32,
+        output_height_in_tiles * size as u32,
+    );
+    for tile_y in 0..tile_indices.height {
+        for tile_x in 0..tile_indices.width {
+            tiles[tile_indices.get(tile_x, tile_y)].blit_to(
+                &mut result,
+                Vec2i::new(tile_x * size, tile_y * size),
+                false,
+            );
+        }
+    }
+    Ok(result)
+}
+
+/// Right, down, left, up
+const WFC_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+#[inline]
+fn wfc_direction_opposite(dir: usize) -> usize {
+    (dir + 2) % 4
+}
+
+/// Returns the sample and optionally all of its rotated and mirrored versions
+fn sample_variants<CellType>(sample: &Grid<CellType>, use_symmetry: bool) -> Vec<Grid<CellType>>
+where
+    CellType: Default + Clone + Copy + PartialEq,
+{
+    if !use_symmetry {
+        return vec![sample.clone()];
+    }
+    let mut result = Vec::with_capacity(8);
+    for base in &[sample.clone(), sample.flipped_horizontally()] {
+        let rotated_90 = base.rotated_90_clockwise();
+        result.push(base.rotated_180());
+        result.push(rotated_90.rotated_180());
+        result.push(rotated_90);
+        result.push(base.clone());
+    }
+    result
+}
+
+struct WaveFunctionCollapseSolver {
+    weights: Vec<f32>,
+    /// For every direction and pattern this contains all patterns that may be placed next to it in
+    /// that direction
+    propagator: [Vec<Vec<usize>>; 4],
+}
+
+impl WaveFunctionCollapseSolver {
+    fn solve_with_retries(
+        &self,
+        width: i32,
+        height: i32,
+        params: &WaveFunctionCollapseParams,
+        random: &mut Random,
+    ) -> Result<Grid<usize>, String> {
+        assert!(width > 0 && height > 0);
+        for _attempt in 0..usize::max(1, params.attempts_max) {
+            if let Some(result) = self.solve(width, height, params.periodic_output, random) {
+                return Ok(result);
+            }
+        }
+        Err(format!(
+            "Wave function collapse ran into contradictions in all {} attempts",
+            params.attempts_max
+        ))
+    }
+
+    /// Returns the chosen pattern for every cell or `None` if we ran into a contradiction
+    fn solve(
+        &self,
+        width: i32,
+        height: i32,
+        periodic: bool,
+        random: &mut Random,
+    ) -> Option<Grid<usize>> {
+        let pattern_count = self.weights.len();
+        let cell_count = (width * height) as usize;
+
+        let weight_log_weights: Vec<f32> = self.weights.iter().map(|w| w * w.ln()).collect();
+        let weights_sum: f32 = self.weights.iter().sum();
+        let weight_log_weights_sum: f32 = weight_log_weights.iter().sum();
+
+        let mut wave = vec![true; cell_count * pattern_count];
+        // NOTE: For every cell, pattern and direction this counts how many patterns of the
+        //       neighbor in the opposite direction still allow the pattern
+        let compatible_initial: Vec<[usize; 4]> = (0..pattern_count)
+            .map(|pattern| {
+                let mut counts = [0; 4];
+                for (dir, count) in counts.iter_mut().enumerate() {
+                    *count = self.propagator[wfc_direction_opposite(dir)][pattern].len();
+                }
+                counts
+            })
+            .collect();
+        let mut compatible = compatible_initial.repeat(cell_count);
+        let mut remaining_counts = vec![pattern_count; cell_count];
+        let mut remaining_weights = vec![weights_sum; cell_count];
+        let mut remaining_weight_log_weights = vec![weight_log_weights_sum; cell_count];
+        let mut bans: Vec<(usize, usize)> = Vec::new();
+
+        loop {
+            // Observe: Find the undecided cell with the lowest entropy
+            let mut entropy_min = f32::MAX;
+            let mut cell_min = None;
+            for cell in 0..cell_count {
+                let remaining = remaining_counts[cell];
+                if remaining == 0 {
+                    return None;
+                }
+                if remaining == 1 {
+                    continue;
+                }
+                let entropy = remaining_weights[cell].ln()
+                    - remaining_weight_log_weights[cell] / remaining_weights[cell];
+                // NOTE: The noise breaks ties randomly
+                let entropy_with_noise = entropy + 0.000_001 * random.f32();
+                if entropy_with_noise < entropy_min {
+                    entropy_min = entropy_with_noise;
+                    cell_min = Some(cell);
+                }
+            }
+            let cell = match cell_min {
+                Some(cell) => cell,
+                None => {
+                    // NOTE: All cells are decided
+                    let result = (0..cell_count)
+                        .map(|cell| {
+                            (0..pattern_count)
+                                .find(|&pattern| wave[cell * pattern_count + pattern])
+                                .unwrap()
+                        })
+                        .collect();
+                    return Some(Grid::new_from_buffer(width as u32, height as u32, result));
+                }
+            };
+
+            // Collapse the cell to a single pattern chosen by weight
+            let mut choice = random.f32() * remaining_weights[cell];
+            let mut pattern_chosen = None;
+            for pattern in 0..pattern_count {
+                if wave[cell * pattern_count + pattern] {
+                    pattern_chosen = Some(pattern);
+                    choice -= self.weights[pattern];
+                    if choice < 0.0 {
+                        break;
+                    }
+                }
+            }
+            let pattern_chosen = pattern_chosen.unwrap();
+            for pattern in 0..pattern_count {
+                if pattern != pattern_chosen && wave[cell * pattern_count + pattern] {
+                    bans.push((cell, pattern));
+                }
+            }
+
+            // Propagate the consequences of all bans
+            while let Some((cell, pattern)) = bans.pop() {
+                let index = cell * pattern_count + pattern;
+                if !wave[index] {
+                    continue;
+                }
+                wave[index] = false;
+                compatible[index] = [0; 4];
+                remaining_counts[cell] -= 1;
+                remaining_weights[cell] -= self.weights[pattern];
+                remaining_weight_log_weights[cell] -= weight_log_weights[pattern];
+
+                let x = cell as i32 % width;
+                let y = cell as i32 / width;
+                for (dir, (delta_x, delta_y)) in WFC_DIRECTIONS.iter().enumerate() {
+                    let mut neighbor_x = x + delta_x;
+                    let mut neighbor_y = y + delta_y;
+                    if periodic {
+                        neighbor_x = (neighbor_x + width) % width;
+                        neighbor_y = (neighbor_y + height) % height;
+                    } else if neighbor_x < 0
+                        || neighbor_y < 0
+                        || neighbor_x >= width
+                        || neighbor_y >= height
+                    {
+                        continue;
+                    }
+                    let neighbor = (neighbor_x + neighbor_y * width) as usize;
+                    for &neighbor_pattern in &self.propagator[dir][pattern] {
+                        let neighbor_index = neighbor * pattern_count + neighbor_pattern;
+                        let counter = &mut compatible[neighbor_index][dir];
+                        if *counter == 0 {
+                            continue;
+                        }
+                        *counter -= 1;
+                        if *counter == 0 {
+                            bans.push((neighbor, neighbor_pattern));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// BSP dungeons
+
+#[derive(Debug, Clone, Copy)]
+pub struct DungeonParams {
+    /// Regions are not split further if this would make them smaller than this
+    pub leaf_size_min: i32,
+    pub room_size_min: i32,
+    /// The minimum number of wall cells between a room and the border of its region
+    pub room_margin: i32,
+}
+
+impl Default for DungeonParams {
+    fn default() -> Self {
+        DungeonParams {
+            leaf_size_min: 10,
+            room_size_min: 4,
+            room_margin: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dungeon {
+    pub floor: Grid<bool>,
+    pub rooms: Vec<Recti>,
+}
+
+/// Generates rooms connected by corridors by recursively splitting the level into regions
+/// (binary space partitioning) and placing a room into every region that is not split further.
+/// Rooms of sibling regions get connected by L-shaped corridors so that all rooms are reachable.
+pub fn generate_dungeon_bsp(
+    width: u32,
+    height: u32,
+    params: &DungeonParams,
+    random: &mut Random,
+) -> Dungeon {
+    assert!(params.room_size_min > 0);
+    assert!(params.room_margin >= 0);
+    assert!(
+        params.leaf_size_min >= params.room_size_min + 2 * params.room_margin,
+        "Rooms of size {} with a margin of {} do not fit into leafs of size {}",
+        params.room_size_min,
+        params.room_margin,
+        params.leaf_size_min
+    );
+    assert!(
+        width as i32 >= params.leaf_size_min && height as i32 >= params.leaf_size_min,
+        "Level with dimensions {}x{} is smaller than the leaf size {}",
+        width,
+        height,
+        params.leaf_size_min
+    );
+
+    let mut dungeon = Dungeon {
+        floor: Grid::new(width, height),
+        rooms: Vec::new(),
+    };
+    dungeon_split_region(
+        &mut dungeon,
+        Recti::from_width_height(width as i32, height as i32),
+        params,
+        random,
+    );
+    dungeon
+}
+
+/// Returns the indices of all rooms that were placed inside the given region
+fn dungeon_split_region(
+    dungeon: &mut Dungeon,
+    region: Recti,
+    params: &DungeonParams,
+    random: &mut Random,
+) -> Vec<usize> {
+    let can_split_horizontally = region.width() >= 2 * params.leaf_size_min;
+    let can_split_vertically = region.height() >= 2 * params.leaf_size_min;
+
+    if !can_split_horizontally && !can_split_vertically {
+        let size_max_x = region.width() - 2 * params.room_margin;
+        let size_max_y = region.height() - 2 * params.room_margin;
+        let room_width = random_i32_in_range(random, params.room_size_min, size_max_x);
+        let room_height = random_i32_in_range(random, params.room_size_min, size_max_y);
+        let room = Recti::from_xy_width_height(
+            region.left()
+                + params.room_margin
+                + random_i32_in_range(random, 0, size_max_x - room_width),
+            region.top()
+                + params.room_margin
+                + random_i32_in_range(random, 0, size_max_y - room_height),
+            room_width,
+            room_height,
+        );
+        dungeon
+            .floor
+            .draw_rect_filled(room.left(), room.top(), room.width(), room.height(), true);
+        dungeon.rooms.push(room);
+        return vec![dungeon.rooms.len() - 1];
+    }
+
+    // NOTE: We prefer splitting along the longer side to avoid long thin regions
+    let split_horizontally = if can_split_horizontally && can_split_vertically {
+        if region.width() as f32 > 1.25 * region.height() as f32 {
+            true
+        } else if region.height() as f32 > 1.25 * region.width() as f32 {
+            false
+        } else {
+            random.f32() < 0.5
+        }
+    } else {
+        can_split_horizontally
+    };
+
+    let (region_a, region_b) = if split_horizontally {
+        let split = random_i32_in_range(
+            random,
+            params.leaf_size_min,
+            region.width() - params.leaf_size_min,
+        );
+        (
+            Recti::from_pos_width_height(region.pos, split, region.height()),
+            Recti::from_xy_width_height(
+                region.left() + split,
+                region.top(),
+                region.width() - split,
+                region.height(),
+            ),
+        )
+    } else {
+        let split = random_i32_in_range(
+            random,
+            params.leaf_size_min,
+            region.height() - params.leaf_size_min,
+        );
+        (
+            Recti::from_pos_width_height(region.pos, region.width(), split),
+            Recti::from_xy_width_height(
+                region.left(),
+                region.top() + split,
+                region.width(),
+                region.height() - split,
+            ),
+        )
+    };
+
+    let mut rooms_a = dungeon_split_region(dungeon, region_a, params, random);
+    let rooms_b = dungeon_split_region(dungeon, region_b, params, random);
+
+    // Connect both halves
+    let room_a = dungeon.rooms[random.pick_from_slice(&rooms_a)];
+    let room_b = dungeon.rooms[random.pick_from_slice(&rooms_b)];
+    let start = room_a.center();
+    let end = room_b.center();
+    let corner = if random.f32() < 0.5 {
+        Vec2i::new(end.x, start.y)
+    } else {
+        Vec2i::new(start.x, end.y)
+    };
+    for &(from, to) in &[(start, corner), (corner, end)] {
+        dungeon.floor.draw_rect_filled(
+            i32::min(from.x, to.x),
+            i32::min(from.y, to.y),
+            (from.x - to.x).abs() + 1,
+            (from.y - to.y).abs() + 1,
+            true,
+        );
+    }
+
+    rooms_a.extend(rooms_b);
+    rooms_a
+}
+
+/// Returns a uniformly distributed integer in [min, max]
+#[inline]
+fn random_i32_in_range(random: &mut Random, min: i32, max: i32) -> i32 {
+    assert!(min <= max);
+    min + random.u32_bounded((max - min) as u32) as i32
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Cellular automata caves
+
+#[derive(Debug, Clone, Copy)]
+pub struct CaveParams {
+    /// Probability of a cell initially being a wall
+    pub wall_probability: f32,
+    pub iterations: usize,
+    /// A cell becomes a wall if at least this many cells of its 3x3 neighborhood (including
+    /// itself) are walls. Cells outside the level count as walls.
+    pub wall_threshold: usize,
+    /// Removes all cave regions that are not connected to the largest one
+    pub keep_largest_region_only: bool,
+}
+
+impl Default for CaveParams {
+    fn default() -> Self {
+        CaveParams {
+            wall_probability: 0.45,
+            iterations: 5,
+            wall_threshold: 5,
+            keep_largest_region_only: true,
+        }
+    }
+}
+
+/// Generates organic looking caves by smoothing random noise with a cellular automaton. The
+/// border of the level is always a wall.
+pub fn generate_caves_cellular_automata(
+    width: u32,
+    height: u32,
+    params: &CaveParams,
+    random: &mut Random,
+) -> Grid<bool> {
+    let mut floor = Grid::new(width, height);
+    for y in 1..floor.height - 1 {
+        for x in 1..floor.width - 1 {
+            floor.set(x, y, random.f32() >= params.wall_probability);
+        }
+    }
+
+    for _ in 0..params.iterations {
+        let mut floor_next = Grid::new(width, height);
+        for y in 1..floor.height - 1 {
+            for x in 1..floor.width - 1 {
+                let mut wall_count = 0;
+                for neighbor_y in (y - 1)..=(y + 1) {
+                    for neighbor_x in (x - 1)..=(x + 1) {
+                        if !floor.get_or_default(neighbor_x, neighbor_y, false) {
+                            wall_count += 1;
+                        }
+                    }
+                }
+                floor_next.set(x, y, wall_count < params.wall_threshold);
+            }
+        }
+        floor = floor_next;
+    }
+
+    if params.keep_largest_region_only {
+        let (labels, label_count) =
+            floor.label_connected_components(GridNeighborhood::FourWay, |is_floor| is_floor);
+        if label_count > 1 {
+            let mut region_sizes = vec![0; label_count as usize + 1];
+            for &label in &labels.data {
+                region_sizes[label as usize] += 1;
+            }
+            let label_largest = (1..region_sizes.len())
+                .max_by_key(|&label| (region_sizes[label], std::cmp::Reverse(label)))
+                .unwrap() as u32;
+            for (is_floor, &label) in floor.data.iter_mut().zip(labels.data.iter()) {
+                *is_floor = label == label_largest;
+            }
+        }
+    }
+
+    floor
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Drunkard's walk
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrunkardsWalkParams {
+    /// The walk stops after this percentage of the level is floor
+    pub floor_percentage: f32,
+    /// The walk stops after this many steps even if `floor_percentage` was not reached
+    pub steps_max: usize,
+}
+
+impl Default for DrunkardsWalkParams {
+    fn default() -> Self {
+        DrunkardsWalkParams {
+            floor_percentage: 0.4,
+            steps_max: 100_000,
+        }
+    }
+}
+
+/// Carves a connected cave by randomly walking from the center of the level. The border of the
+/// level is always a wall.
+pub fn generate_drunkards_walk(
+    width: u32,
+    height: u32,
+    params: &DrunkardsWalkParams,
+    random: &mut Random,
+) -> Grid<bool> {
+    assert!(width >= 3 && height >= 3);
+    let mut floor = Grid::new(width, height);
+    let floor_count_target = (params.floor_percentage * (width * height) as f32).ceil() as usize;
+
+    let mut pos = Vec2i::new(width as i32 / 2, height as i32 / 2);
+    floor.set(pos.x, pos.y, true);
+    let mut floor_count = 1;
+    for _ in 0..params.steps_max {
+        if floor_count >= floor_count_target {
+            break;
+        }
+        let (delta_x, delta_y) = random.pick_from_slice(GridNeighborhood::FourWay.offsets());
+        pos = Vec2i::new(
+            i32::clamp(pos.x + delta_x, 1, floor.width - 2),
+            i32::clamp(pos.y + delta_y, 1, floor.height - 2),
+        );
+        if !floor.get(pos.x, pos.y) {
+            floor.set(pos.x, pos.y, true);
+            floor_count += 1;
+        }
+    }
+    floor
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_floor(floor: &Grid<bool>) -> usize {
+        floor.data.iter().filter(|&&is_floor| is_floor).count()
+    }
+
+    fn is_fully_connected(floor: &Grid<bool>) -> bool {
+        let (_labels, count) =
+            floor.label_connected_components(GridNeighborhood::FourWay, |is_floor| is_floor);
+        count == 1
+    }
+
+    #[test]
+    fn wave_function_collapse_overlapping_only_uses_sample_patterns() {
+        // NOTE: Diagonal stripes
+        let sample = Grid::new_from_buffer(
+            4,
+            4,
+            vec![
+                1, 0, 0, 0, //
+                0, 1, 0, 0, //
+                0, 0, 1, 0, //
+                0, 0, 0, 1,
+            ],
+        );
+        let params = WaveFunctionCollapseParams {
+            pattern_size: 2,
+            use_symmetry: false,
+            ..Default::default()
+        };
+        let output = wave_function_collapse_overlapping(
+            &sample,
+            12,
+            9,
+            &params,
+            &mut Random::new_from_seed(7),
+        )
+        .unwrap();
+        assert_eq!(output.dim(), Vec2i::new(12, 9));
+        for y in 1..output.height {
+            for x in 1..output.width {
+                if output.get(x, y) == 1 {
+                    assert_eq!(output.get(x - 1, y - 1), 1);
+                }
+            }
+        }
+
+        let output_again = wave_function_collapse_overlapping(
+            &sample,
+            12,
+            9,
+            &params,
+            &mut Random::new_from_seed(7),
+        )
+        .unwrap();
+        assert_eq!(output, output_again);
+    }
+
+    #[test]
+    fn wave_function_collapse_tiled_respects_sample_adjacencies() {
+        // NOTE: A road (2) is always surrounded by grass (1) on its left and right
+        let sample = Grid::new_from_buffer(
+            3,
+            2,
+            vec![
+                1, 2, 1, //
+                1, 2, 1,
+            ],
+        );
+        let params = WaveFunctionCollapseParams {
+            pattern_size: 1,
+            periodic_input: false,
+            use_symmetry: false,
+            ..Default::default()
+        };
+        let output =
+            wave_function_collapse_tiled(&sample, 10, 6, &params, &mut Random::new_from_seed(3))
+                .unwrap();
+        for y in 0..output.height {
+            for x in 1..output.width {
+                assert!(output.get(x - 1, y) != 2 || output.get(x, y) == 1);
+                assert!(output.get(x, y) != 2 || output.get(x - 1, y) == 1);
+            }
+        }
+
+        assert!(wave_function_collapse_tiled(
+            &sample,
+            10,
+            6,
+            &WaveFunctionCollapseParams {
+                pattern_size: 2,
+                ..params
+            },
+            &mut Random::new_from_seed(3)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn level_generators_are_deterministic_and_connected() {
+        let dungeon_params = DungeonParams::default();
+        let dungeon = generate_dungeon_bsp(64, 48, &dungeon_params, &mut Random::new_from_seed(1));
+        assert!(dungeon.rooms.len() >= 4);
+        assert!(is_fully_connected(&dungeon.floor));
+        // NOTE: Rooms are placed at different positions so the dungeon must not be all floor
+        assert!(dungeon
+            .rooms
+            .windows(2)
+            .any(|pair| pair[0].pos != pair[1].pos));
+        let dungeon_floor_ratio = count_floor(&dungeon.floor) as f32 / (64.0 * 48.0);
+        assert!(0.1 < dungeon_floor_ratio && dungeon_floor_ratio < 0.9);
+        for room in &dungeon.rooms {
+            assert!(room.width() >= dungeon_params.room_size_min);
+            assert!(room.height() >= dungeon_params.room_size_min);
+        }
+        let dungeon_again =
+            generate_dungeon_bsp(64, 48, &dungeon_params, &mut Random::new_from_seed(1));
+        assert_eq!(dungeon.floor, dungeon_again.floor);
+
+        let caves = generate_caves_cellular_automata(
+            64,
+            48,
+            &CaveParams::default(),
+            &mut Random::new_from_seed(2),
+        );
+        // NOTE: Degenerate noise would result in caves that are either completely filled or
+        //       completely empty
+        let cave_floor_ratio = count_floor(&caves) as f32 / (64.0 * 48.0);
+        assert!(
+            0.3 < cave_floor_ratio && cave_floor_ratio < 0.8,
+            "Cave floor ratio {} is out of range",
+            cave_floor_ratio
+        );
+        let interior_wall_count = (1..caves.height - 1)
+            .flat_map(|y| (1..caves.width - 1).map(move |x| (x, y)))
+            .filter(|&(x, y)| !caves.get(x, y))
+            .count();
+        assert!(interior_wall_count > 0);
+        assert!(is_fully_connected(&caves));
+        for x in 0..caves.width {
+            assert!(!caves.get(x, 0) && !caves.get(x, caves.height - 1));
+        }
+        let caves_again = generate_caves_cellular_automata(
+            64,
+            48,
+            &CaveParams::default(),
+            &mut Random::new_from_seed(2),
+        );
+        assert_eq!(caves, caves_again);
+
+        let walk_params = DrunkardsWalkParams::default();
+        let walk = generate_drunkards_walk(40, 30, &walk_params, &mut Random::new_from_seed(5));
+        assert_eq!(
+            count_floor(&walk),
+            (walk_params.floor_percentage * 40.0 * 30.0).ceil() as usize
+        );
+        assert!(is_fully_connected(&walk));
+        for y in 0..walk.height {
+            assert!(!walk.get(0, y) && !walk.get(walk.width - 1, y));
+        }
+    }
+}
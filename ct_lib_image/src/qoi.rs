@@ -0,0 +1,236 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// QOI image format
+//
+// Specification: https://qoiformat.org/qoi-specification.pdf
+
+use super::bitmap::*;
+
+const QOI_MAGIC: &[u8; 4] = b"qoif";
+const QOI_HEADER_SIZE: usize = 14;
+const QOI_END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+const QOI_OP_INDEX: u8 = 0b0000_0000;
+const QOI_OP_DIFF: u8 = 0b0100_0000;
+const QOI_OP_LUMA: u8 = 0b1000_0000;
+const QOI_OP_RUN: u8 = 0b1100_0000;
+const QOI_OP_RGB: u8 = 0b1111_1110;
+const QOI_OP_RGBA: u8 = 0b1111_1111;
+const QOI_MASK_2: u8 = 0b1100_0000;
+
+const QOI_RUN_LENGTH_MAX: u8 = 62;
+
+#[inline]
+fn qoi_color_hash(color: PixelRGBA) -> usize {
+    (color.r as usize * 3 + color.g as usize * 5 + color.b as usize * 7 + color.a as usize * 11)
+        % 64
+}
+
+impl Bitmap {
+    pub fn from_qoi_data(qoi_data: &[u8]) -> Result<Bitmap, String> {
+        if qoi_data.len() < QOI_HEADER_SIZE + QOI_END_MARKER.len() {
+            return Err(format!(
+                "Qoi data is too small with only {} bytes",
+                qoi_data.len()
+            ));
+        }
+        if &qoi_data[0..4] != QOI_MAGIC {
+            return Err("Qoi data has invalid magic number".to_owned());
+        }
+        let width = u32::from_be_bytes([qoi_data[4], qoi_data[5], qoi_data[6], qoi_data[7]]);
+        let height = u32::from_be_bytes([qoi_data[8], qoi_data[9], qoi_data[10], qoi_data[11]]);
+        let channels = qoi_data[12];
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "Qoi data has invalid dimensions {}x{}",
+                width, height
+            ));
+        }
+        if channels != 3 && channels != 4 {
+            return Err(format!("Qoi data has invalid channel count {}", channels));
+        }
+
+        let pixel_count = width as usize * height as usize;
+        let mut buffer = Vec::with_capacity(pixel_count);
+        let mut index = [PixelRGBA::transparent(); 64];
+        let mut color = PixelRGBA::new(0, 0, 0, 255);
+
+        let data = &qoi_data[QOI_HEADER_SIZE..qoi_data.len() - QOI_END_MARKER.len()];
+        let mut pos = 0;
+        let mut next_byte = || -> Result<u8, String> {
+            let byte = *data
+                .get(pos)
+                .ok_or_else(|| "Unexpected end of qoi data".to_owned())?;
+            pos += 1;
+            Ok(byte)
+        };
+
+        while buffer.len() < pixel_count {
+            let tag = next_byte()?;
+            if tag == QOI_OP_RGB {
+                color.r = next_byte()?;
+                color.g = next_byte()?;
+                color.b = next_byte()?;
+            } else if tag == QOI_OP_RGBA {
+                color.r = next_byte()?;
+                color.g = next_byte()?;
+                color.b = next_byte()?;
+                color.a = next_byte()?;
+            } else {
+                match tag & QOI_MASK_2 {
+                    QOI_OP_INDEX => {
+                        color = index[(tag & 0b0011_1111) as usize];
+                    }
+                    QOI_OP_DIFF => {
+                        color.r = color.r.wrapping_add(((tag >> 4) & 0b11).wrapping_sub(2));
+                        color.g = color.g.wrapping_add(((tag >> 2) & 0b11).wrapping_sub(2));
+                        color.b = color.b.wrapping_add((tag & 0b11).wrapping_sub(2));
+                    }
+                    QOI_OP_LUMA => {
+                        let second = next_byte()?;
+                        let diff_green = (tag & 0b0011_1111).wrapping_sub(32);
+                        color.r = color
+                            .r
+                            .wrapping_add(diff_green)
+                            .wrapping_add((second >> 4).wrapping_sub(8));
+                        color.g = color.g.wrapping_add(diff_green);
+                        color.b = color
+                            .b
+                            .wrapping_add(diff_green)
+                            .wrapping_add((second & 0b1111).wrapping_sub(8));
+                    }
+                    QOI_OP_RUN => {
+                        let run_length = (tag & 0b0011_1111) as usize + 1;
+                        if buffer.len() + run_length > pixel_count {
+                            return Err("Qoi data contains too many pixels".to_owned());
+                        }
+                        buffer.extend(std::iter::repeat(color).take(run_length));
+                        continue;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            index[qoi_color_hash(color)] = color;
+            buffer.push(color);
+        }
+
+        Ok(Bitmap::new_from_buffer(width, height, buffer))
+    }
+
+    pub fn encoded_as_qoi(&self) -> Vec<u8> {
+        let has_alpha = self.data.iter().any(|pixel| pixel.a != 255);
+
+        let mut result =
+            Vec::with_capacity(QOI_HEADER_SIZE + QOI_END_MARKER.len() + self.data.len() * 2);
+        result.extend_from_slice(QOI_MAGIC);
+        result.extend_from_slice(&(self.width as u32).to_be_bytes());
+        result.extend_from_slice(&(self.height as u32).to_be_bytes());
+        result.push(if has_alpha { 4 } else { 3 });
+        // NOTE: We always write sRGB with linear alpha as colorspace
+        result.push(0);
+
+        let mut index = [PixelRGBA::transparent(); 64];
+        let mut color_previous = PixelRGBA::new(0, 0, 0, 255);
+        let mut run_length = 0;
+        for &color in &self.data {
+            if color == color_previous {
+                run_length += 1;
+                if run_length == QOI_RUN_LENGTH_MAX {
+                    result.push(QOI_OP_RUN | (run_length - 1));
+                    run_length = 0;
+                }
+                continue;
+            }
+            if run_length > 0 {
+                result.push(QOI_OP_RUN | (run_length - 1));
+                run_length = 0;
+            }
+
+            let hash = qoi_color_hash(color);
+            if index[hash] == color {
+                result.push(QOI_OP_INDEX | hash as u8);
+            } else {
+                index[hash] = color;
+                if color.a == color_previous.a {
+                    let diff_r = color.r.wrapping_sub(color_previous.r) as i8;
+                    let diff_g = color.g.wrapping_sub(color_previous.g) as i8;
+                    let diff_b = color.b.wrapping_sub(color_previous.b) as i8;
+                    let diff_rg = diff_r.wrapping_sub(diff_g);
+                    let diff_bg = diff_b.wrapping_sub(diff_g);
+
+                    if (-2..=1).contains(&diff_r)
+                        && (-2..=1).contains(&diff_g)
+                        && (-2..=1).contains(&diff_b)
+                    {
+                        result.push(
+                            QOI_OP_DIFF
+                                | (((diff_r + 2) as u8) << 4)
+                                | (((diff_g + 2) as u8) << 2)
+                                | ((diff_b + 2) as u8),
+                        );
+                    } else if (-32..=31).contains(&diff_g)
+                        && (-8..=7).contains(&diff_rg)
+                        && (-8..=7).contains(&diff_bg)
+                    {
+                        result.push(QOI_OP_LUMA | ((diff_g + 32) as u8));
+                        result.push((((diff_rg + 8) as u8) << 4) | ((diff_bg + 8) as u8));
+                    } else {
+                        result.extend_from_slice(&[QOI_OP_RGB, color.r, color.g, color.b]);
+                    }
+                } else {
+                    result.extend_from_slice(&[QOI_OP_RGBA, color.r, color.g, color.b, color.a]);
+                }
+            }
+            color_previous = color;
+        }
+        if run_length > 0 {
+            result.push(QOI_OP_RUN | (run_length - 1));
+        }
+
+        result.extend_from_slice(&QOI_END_MARKER);
+        result
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Random;
+
+    #[test]
+    fn qoi_roundtrip() {
+        let mut random = Random::new_from_seed(1234);
+        let mut bitmap = Bitmap::new(37, 23);
+        for (index, pixel) in bitmap.data.iter_mut().enumerate() {
+            // NOTE: We want to hit all the different encoding ops here
+            *pixel = match index % 5 {
+                0 | 1 => PixelRGBA::new(10, 20, 30, 255),
+                2 => PixelRGBA::new(11, 19, 31, 255),
+                3 => PixelRGBA::new(
+                    (random.u32() % 256) as u8,
+                    (random.u32() % 256) as u8,
+                    (random.u32() % 256) as u8,
+                    255,
+                ),
+                _ => PixelRGBA::new(200, 100, 50, (random.u32() % 256) as u8),
+            };
+        }
+        for pixel in bitmap.data[100..300].iter_mut() {
+            *pixel = PixelRGBA::transparent();
+        }
+
+        let qoi_data = bitmap.encoded_as_qoi();
+        let decoded = Bitmap::from_qoi_data(&qoi_data).unwrap();
+        assert_eq!(decoded, bitmap);
+    }
+
+    #[test]
+    fn qoi_invalid_data() {
+        assert!(Bitmap::from_qoi_data(&[]).is_err());
+        let mut qoi_data = Bitmap::new_filled(4, 4, PixelRGBA::red()).encoded_as_qoi();
+        qoi_data[0] = b'x';
+        assert!(Bitmap::from_qoi_data(&qoi_data).is_err());
+    }
+}
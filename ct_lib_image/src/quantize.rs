@@ -0,0 +1,588 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Color quantization and dithering
+//
+// NOTE: Palette extraction and color matching happen in Oklab space as it gives perceptually more
+//       pleasing results than plain RGB. Fully transparent pixels are ignored by the palette
+//       extraction and are left untouched by the palette mapping.
+
+use super::bitmap::*;
+use super::color::Oklab;
+use super::math::Random;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dithering {
+    None,
+    /// Ordered dithering with a 2x2 threshold matrix
+    Bayer2x2,
+    /// Ordered dithering with a 4x4 threshold matrix
+    Bayer4x4,
+    /// Ordered dithering with a 8x8 threshold matrix
+    Bayer8x8,
+    /// Error diffusion that distributes all of the quantization error
+    FloydSteinberg,
+    /// Error diffusion that distributes only 3/4 of the quantization error which results in
+    /// higher contrast but loses detail in very dark and bright areas
+    Atkinson,
+}
+
+impl PixelRGBA {
+    /// Returns the index of the perceptually closest palette color. Ignores alpha.
+    pub fn find_closest_palette_index(self, palette: &[PixelRGBA]) -> usize {
+        assert!(!palette.is_empty());
+        let palette_oklab: Vec<Oklab> =
+            palette.iter().cloned().map(Oklab::from_pixelrgba).collect();
+        find_closest_oklab_index(Oklab::from_pixelrgba(self), &palette_oklab)
+    }
+}
+
+impl Bitmap {
+    /// Extracts a palette of at most `color_count` opaque colors by recursively splitting the
+    /// color box with the longest axis
+    pub fn extract_palette_median_cut(&self, color_count: usize) -> Vec<PixelRGBA> {
+        assert!(color_count > 0);
+        let colors = collect_weighted_colors(&self.data);
+        if colors.len() <= color_count {
+            return colors
+                .iter()
+                .map(|(color, _)| color.to_pixelrgba())
+                .collect();
+        }
+
+        let mut boxes: Vec<Vec<(Oklab, usize)>> = vec![colors];
+        while boxes.len() < color_count {
+            // Split the box with the longest axis
+            let (box_index, axis, axis_length) = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, colors)| colors.len() > 1)
+                .map(|(index, colors)| {
+                    let (axis, axis_length) = longest_axis(colors);
+                    (index, axis, axis_length)
+                })
+                .fold((0, 0, -1.0), |best, candidate| {
+                    if candidate.2 > best.2 {
+                        candidate
+                    } else {
+                        best
+                    }
+                });
+            if axis_length < 0.0 {
+                // NOTE: All boxes only contain single colors
+                break;
+            }
+
+            let mut colors = boxes.swap_remove(box_index);
+            colors.sort_by(|x, y| {
+                oklab_axis(x.0, axis)
+                    .partial_cmp(&oklab_axis(y.0, axis))
+                    .unwrap()
+            });
+            // NOTE: Instead of splitting at the plain median we split at the position that
+            //       minimizes the summed variance of both halves along the axis. This prevents
+            //       small but distinct color groups from being merged into bigger ones.
+            let mut prefix_sums = Vec::with_capacity(colors.len() + 1);
+            prefix_sums.push((0.0f64, 0.0f64, 0.0f64));
+            for (color, weight) in &colors {
+                let (weight_sum, value_sum, value_squared_sum) = *prefix_sums.last().unwrap();
+                let value = oklab_axis(*color, axis) as f64;
+                let weight = *weight as f64;
+                prefix_sums.push((
+                    weight_sum + weight,
+                    value_sum + weight * value,
+                    value_squared_sum + weight * value * value,
+                ));
+            }
+            let squared_error = |from: usize, to: usize| {
+                let weight = prefix_sums[to].0 - prefix_sums[from].0;
+                let value_sum = prefix_sums[to].1 - prefix_sums[from].1;
+                let value_squared_sum = prefix_sums[to].2 - prefix_sums[from].2;
+                value_squared_sum - value_sum * value_sum / weight
+            };
+            let split_index = (1..colors.len())
+                .map(|index| {
+                    (
+                        index,
+                        squared_error(0, index) + squared_error(index, colors.len()),
+                    )
+                })
+                .fold((1, std::f64::MAX), |best, candidate| {
+                    if candidate.1 < best.1 {
+                        candidate
+                    } else {
+                        best
+                    }
+                })
+                .0;
+            let upper_half = colors.split_off(split_index);
+            boxes.push(colors);
+            boxes.push(upper_half);
+        }
+
+        boxes
+            .iter()
+            .map(|colors| weighted_mean(colors).to_pixelrgba())
+            .collect()
+    }
+
+    /// Extracts a palette of at most `color_count` opaque colors using k-means clustering. The
+    /// initial cluster centers are chosen via k-means++ so the result depends on `random`.
+    pub fn extract_palette_kmeans(
+        &self,
+        color_count: usize,
+        iteration_count: usize,
+        random: &mut Random,
+    ) -> Vec<PixelRGBA> {
+        assert!(color_count > 0);
+        let colors = collect_weighted_colors(&self.data);
+        if colors.len() <= color_count {
+            return colors
+                .iter()
+                .map(|(color, _)| color.to_pixelrgba())
+                .collect();
+        }
+
+        // k-means++ initialization
+        let mut centers: Vec<Oklab> = Vec::with_capacity(color_count);
+        centers.push(colors[random.u32_bounded_exclusive(colors.len() as u32) as usize].0);
+        let mut distances: Vec<f32> = colors
+            .iter()
+            .map(|(color, _)| Oklab::distance_squared(*color, centers[0]))
+            .collect();
+        while centers.len() < color_count {
+            let weights: Vec<f32> = colors
+                .iter()
+                .zip(distances.iter())
+                .map(|((_, weight), distance)| *weight as f32 * distance)
+                .collect();
+            let weight_total: f32 = weights.iter().sum();
+            if weight_total <= 0.0 {
+                break;
+            }
+            let mut threshold = random.f32() * weight_total;
+            let mut chosen_index = colors.len() - 1;
+            for (index, weight) in weights.iter().enumerate() {
+                if threshold < *weight {
+                    chosen_index = index;
+                    break;
+                }
+                threshold -= weight;
+            }
+            let center = colors[chosen_index].0;
+            centers.push(center);
+            for (distance, (color, _)) in distances.iter_mut().zip(colors.iter()) {
+                *distance = f32::min(*distance, Oklab::distance_squared(*color, center));
+            }
+        }
+
+        // Lloyd iterations
+        let mut assignments = vec![0; colors.len()];
+        for _ in 0..iteration_count {
+            let mut has_changed = false;
+            for (assignment, (color, _)) in assignments.iter_mut().zip(colors.iter()) {
+                let closest = find_closest_oklab_index(*color, &centers);
+                if *assignment != closest {
+                    *assignment = closest;
+                    has_changed = true;
+                }
+            }
+
+            let mut sums = vec![(Oklab::default(), 0); centers.len()];
+            for (assignment, (color, weight)) in assignments.iter().zip(colors.iter()) {
+                let sum = &mut sums[*assignment];
+                sum.0.l += color.l * *weight as f32;
+                sum.0.a += color.a * *weight as f32;
+                sum.0.b += color.b * *weight as f32;
+                sum.1 += weight;
+            }
+            for (center, (sum, weight)) in centers.iter_mut().zip(sums.into_iter()) {
+                // NOTE: Empty clusters keep their previous center
+                if weight > 0 {
+                    *center = Oklab::new(
+                        sum.l / weight as f32,
+                        sum.a / weight as f32,
+                        sum.b / weight as f32,
+                    );
+                }
+            }
+
+            if !has_changed {
+                break;
+            }
+        }
+
+        centers.iter().map(|center| center.to_pixelrgba()).collect()
+    }
+
+    /// Replaces the color of each pixel with the perceptually closest palette color. The alpha
+    /// channel is left untouched.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn mapped_to_palette(&self, palette: &[PixelRGBA], dithering: Dithering) -> Bitmap {
+        let indices = self.to_palette_indices(palette, dithering);
+        let mut result = self.clone();
+        for (pixel, index) in result.data.iter_mut().zip(indices.into_iter()) {
+            if pixel.a != 0 {
+                let color = palette[index];
+                *pixel = PixelRGBA::new(color.r, color.g, color.b, pixel.a);
+            }
+        }
+        result
+    }
+
+    /// Returns the index of the perceptually closest palette color for each pixel. Fully
+    /// transparent pixels get the index 0.
+    pub fn to_palette_indices(&self, palette: &[PixelRGBA], dithering: Dithering) -> Vec<usize> {
+        assert!(!palette.is_empty());
+        let palette_oklab: Vec<Oklab> =
+            palette.iter().cloned().map(Oklab::from_pixelrgba).collect();
+        let mut result = vec![0; self.data.len()];
+
+        match dithering {
+            Dithering::None | Dithering::Bayer2x2 | Dithering::Bayer4x4 | Dithering::Bayer8x8 => {
+                let matrix_size = match dithering {
+                    Dithering::None => 1,
+                    Dithering::Bayer2x2 => 2,
+                    Dithering::Bayer4x4 => 4,
+                    _ => 8,
+                };
+                let threshold_matrix = bayer_threshold_matrix(matrix_size);
+                let spread = if matrix_size == 1 {
+                    0.0
+                } else {
+                    ordered_dithering_spread(palette)
+                };
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        let index = (x + y * self.width) as usize;
+                        let pixel = self.data[index];
+                        if pixel.a == 0 {
+                            continue;
+                        }
+                        let threshold = spread
+                            * threshold_matrix[(x as usize % matrix_size)
+                                + (y as usize % matrix_size) * matrix_size];
+                        let color = Oklab::from_srgb(
+                            (pixel.r as f32 + threshold) / 255.0,
+                            (pixel.g as f32 + threshold) / 255.0,
+                            (pixel.b as f32 + threshold) / 255.0,
+                        );
+                        result[index] = find_closest_oklab_index(color, &palette_oklab);
+                    }
+                }
+            }
+            Dithering::FloydSteinberg | Dithering::Atkinson => {
+                let diffusion: &[(i32, i32, f32)] = if dithering == Dithering::FloydSteinberg {
+                    &[
+                        (1, 0, 7.0 / 16.0),
+                        (-1, 1, 3.0 / 16.0),
+                        (0, 1, 5.0 / 16.0),
+                        (1, 1, 1.0 / 16.0),
+                    ]
+                } else {
+                    &[
+                        (1, 0, 1.0 / 8.0),
+                        (2, 0, 1.0 / 8.0),
+                        (-1, 1, 1.0 / 8.0),
+                        (0, 1, 1.0 / 8.0),
+                        (1, 1, 1.0 / 8.0),
+                        (0, 2, 1.0 / 8.0),
+                    ]
+                };
+
+                let mut errors = vec![[0.0f32; 3]; self.data.len()];
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        let index = (x + y * self.width) as usize;
+                        let pixel = self.data[index];
+                        if pixel.a == 0 {
+                            continue;
+                        }
+                        let error = errors[index];
+                        let wanted = [
+                            f32::clamp(pixel.r as f32 + error[0], 0.0, 255.0),
+                            f32::clamp(pixel.g as f32 + error[1], 0.0, 255.0),
+                            f32::clamp(pixel.b as f32 + error[2], 0.0, 255.0),
+                        ];
+                        let palette_index = find_closest_oklab_index(
+                            Oklab::from_srgb(
+                                wanted[0] / 255.0,
+                                wanted[1] / 255.0,
+                                wanted[2] / 255.0,
+                            ),
+                            &palette_oklab,
+                        );
+                        result[index] = palette_index;
+
+                        let chosen = palette[palette_index];
+                        let quantization_error = [
+                            wanted[0] - chosen.r as f32,
+                            wanted[1] - chosen.g as f32,
+                            wanted[2] - chosen.b as f32,
+                        ];
+                        for (offset_x, offset_y, weight) in diffusion {
+                            let neighbor_x = x + offset_x;
+                            let neighbor_y = y + offset_y;
+                            if !self.contains_coordinate(neighbor_x, neighbor_y) {
+                                continue;
+                            }
+                            let neighbor_error =
+                                &mut errors[(neighbor_x + neighbor_y * self.width) as usize];
+                            for channel in 0..3 {
+                                neighbor_error[channel] += weight * quantization_error[channel];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Returns the unique non-transparent colors and how often they occur in the order of their first
+/// occurrence
+fn collect_weighted_colors(pixels: &[PixelRGBA]) -> Vec<(Oklab, usize)> {
+    let mut counts: HashMap<PixelRGBA, usize> = HashMap::new();
+    let mut colors_ordered = Vec::new();
+    for pixel in pixels {
+        if pixel.a == 0 {
+            continue;
+        }
+        let color = PixelRGBA::new(pixel.r, pixel.g, pixel.b, 255);
+        let count = counts.entry(color).or_insert(0);
+        if *count == 0 {
+            colors_ordered.push(color);
+        }
+        *count += 1;
+    }
+    colors_ordered
+        .into_iter()
+        .map(|color| (Oklab::from_pixelrgba(color), counts[&color]))
+        .collect()
+}
+
+#[inline]
+fn oklab_axis(color: Oklab, axis: usize) -> f32 {
+    match axis {
+        0 => color.l,
+        1 => color.a,
+        _ => color.b,
+    }
+}
+
+/// Returns the axis with the largest extent and its length
+fn longest_axis(colors: &[(Oklab, usize)]) -> (usize, f32) {
+    (0..3)
+        .map(|axis| {
+            let (min, max) =
+                colors
+                    .iter()
+                    .fold((std::f32::MAX, std::f32::MIN), |(min, max), (color, _)| {
+                        let value = oklab_axis(*color, axis);
+                        (f32::min(min, value), f32::max(max, value))
+                    });
+            (axis, max - min)
+        })
+        .fold((0, -1.0), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+fn weighted_mean(colors: &[(Oklab, usize)]) -> Oklab {
+    let mut sum = Oklab::default();
+    let mut weight_total = 0;
+    for (color, weight) in colors {
+        sum.l += color.l * *weight as f32;
+        sum.a += color.a * *weight as f32;
+        sum.b += color.b * *weight as f32;
+        weight_total += weight;
+    }
+    Oklab::new(
+        sum.l / weight_total as f32,
+        sum.a / weight_total as f32,
+        sum.b / weight_total as f32,
+    )
+}
+
+#[inline]
+fn find_closest_oklab_index(color: Oklab, palette: &[Oklab]) -> usize {
+    let mut closest_index = 0;
+    let mut closest_distance = std::f32::MAX;
+    for (index, candidate) in palette.iter().enumerate() {
+        let distance = Oklab::distance_squared(color, *candidate);
+        if distance < closest_distance {
+            closest_distance = distance;
+            closest_index = index;
+        }
+    }
+    closest_index
+}
+
+/// Returns the normalized thresholds in [-0.5, 0.5[ of a bayer matrix of the given power of two
+/// size
+fn bayer_threshold_matrix(size: usize) -> Vec<f32> {
+    let mut matrix = vec![0usize];
+    let mut current_size = 1;
+    while current_size < size {
+        let next_size = 2 * current_size;
+        let mut next = vec![0; next_size * next_size];
+        for y in 0..current_size {
+            for x in 0..current_size {
+                let value = 4 * matrix[x + y * current_size];
+                next[x + y * next_size] = value;
+                next[(x + current_size) + y * next_size] = value + 2;
+                next[x + (y + current_size) * next_size] = value + 3;
+                next[(x + current_size) + (y + current_size) * next_size] = value + 1;
+            }
+        }
+        matrix = next;
+        current_size = next_size;
+    }
+    let cell_count = (size * size) as f32;
+    matrix
+        .into_iter()
+        .map(|value| (value as f32 + 0.5) / cell_count - 0.5)
+        .collect()
+}
+
+/// Returns the average distance of each palette color to its closest neighbor in RGB space scaled
+/// down to a single channel. This is used as the amplitude for ordered dithering so that it adapts
+/// to the palette density.
+fn ordered_dithering_spread(palette: &[PixelRGBA]) -> f32 {
+    if palette.len() < 2 {
+        return 0.0;
+    }
+    let distance = |x: PixelRGBA, y: PixelRGBA| {
+        let dr = x.r as f32 - y.r as f32;
+        let dg = x.g as f32 - y.g as f32;
+        let db = x.b as f32 - y.b as f32;
+        f32::sqrt(dr * dr + dg * dg + db * db)
+    };
+    let distance_sum: f32 = palette
+        .iter()
+        .enumerate()
+        .map(|(index, &color)| {
+            palette
+                .iter()
+                .enumerate()
+                .filter(|(other_index, _)| *other_index != index)
+                .map(|(_, &other)| distance(color, other))
+                .fold(std::f32::MAX, f32::min)
+        })
+        .sum();
+    distance_sum / (palette.len() as f32 * f32::sqrt(3.0))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_gradient_bitmap() -> Bitmap {
+        let mut bitmap = Bitmap::new(64, 16);
+        for y in 0..bitmap.height {
+            for x in 0..bitmap.width {
+                let value = (x * 4) as u8;
+                bitmap.set(x, y, PixelRGBA::new(value, value, value, 255));
+            }
+        }
+        bitmap
+    }
+
+    #[test]
+    fn palette_extraction() {
+        let mut bitmap = Bitmap::new(8, 8);
+        bitmap.draw_rect_filled(0, 0, 4, 8, PixelRGBA::new(250, 10, 10, 255));
+        bitmap.draw_rect_filled(4, 0, 4, 4, PixelRGBA::new(10, 10, 250, 255));
+        bitmap.set(0, 0, PixelRGBA::new(240, 20, 20, 255));
+
+        let palette = bitmap.extract_palette_median_cut(2);
+        assert_eq!(palette.len(), 2);
+        assert!(palette.contains(&PixelRGBA::new(10, 10, 250, 255)));
+        assert!(palette.iter().all(|color| color.a == 255));
+
+        let mut random = Random::new_from_seed(1234);
+        let palette = bitmap.extract_palette_kmeans(2, 10, &mut random);
+        assert_eq!(palette.len(), 2);
+        assert!(palette.contains(&PixelRGBA::new(10, 10, 250, 255)));
+        assert!(palette
+            .iter()
+            .any(|color| color.r > 240 && color.g < 20 && color.b < 20));
+
+        // NOTE: Asking for more colors than available returns the available colors without the
+        //       transparent ones
+        assert_eq!(bitmap.extract_palette_median_cut(16).len(), 3);
+        assert_eq!(bitmap.extract_palette_kmeans(16, 10, &mut random).len(), 3);
+    }
+
+    #[test]
+    fn palette_mapping_without_dithering() {
+        let palette = [PixelRGBA::black(), PixelRGBA::white(), PixelRGBA::red()];
+        assert_eq!(
+            PixelRGBA::new(200, 30, 40, 255).find_closest_palette_index(&palette),
+            2
+        );
+
+        let mut bitmap = create_gradient_bitmap();
+        bitmap.set(0, 0, PixelRGBA::transparent());
+        let mapped = bitmap.mapped_to_palette(&palette, Dithering::None);
+        assert_eq!(mapped.get(0, 0), PixelRGBA::transparent());
+        assert_eq!(mapped.get(1, 0), PixelRGBA::black());
+        assert_eq!(mapped.get(63, 0), PixelRGBA::white());
+        assert!(mapped
+            .data
+            .iter()
+            .all(|pixel| pixel.a == 0 || palette.contains(pixel)));
+    }
+
+    #[test]
+    fn dithering_preserves_average_brightness() {
+        let palette = [PixelRGBA::black(), PixelRGBA::white()];
+        let bitmap = Bitmap::new_filled(16, 16, PixelRGBA::new(128, 128, 128, 255));
+        for &dithering in &[
+            Dithering::Bayer2x2,
+            Dithering::Bayer4x4,
+            Dithering::Bayer8x8,
+            Dithering::FloydSteinberg,
+            Dithering::Atkinson,
+        ] {
+            let mapped = bitmap.mapped_to_palette(&palette, dithering);
+            let white_count = mapped
+                .data
+                .iter()
+                .filter(|&&pixel| pixel == PixelRGBA::white())
+                .count();
+            let white_ratio = white_count as f32 / mapped.data.len() as f32;
+            assert!(
+                white_ratio > 0.35 && white_ratio < 0.65,
+                "{:?} produced a white ratio of {}",
+                dithering,
+                white_ratio
+            );
+        }
+
+        // NOTE: Without dithering everything maps to the same color
+        let mapped = bitmap.mapped_to_palette(&palette, Dithering::None);
+        assert!(mapped.data.iter().all(|&pixel| pixel == mapped.data[0]));
+    }
+
+    #[test]
+    fn bayer_matrix() {
+        let matrix = bayer_threshold_matrix(4);
+        let mut sorted = matrix.clone();
+        sorted.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        for (index, value) in sorted.iter().enumerate() {
+            assert_eq!(*value, (index as f32 + 0.5) / 16.0 - 0.5);
+        }
+        assert_eq!(matrix[0], sorted[0]);
+        assert_eq!(matrix[10], sorted[1]);
+    }
+}
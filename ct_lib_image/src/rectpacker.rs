@@ -0,0 +1,351 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// MaxRects rectangle packing
+//
+// NOTE: This is based on the paper "A Thousand Ways to Pack the Bin - A Practical Approach to
+//       Two-Dimensional Rectangle Bin Packing" by Jukka Jylänki. We use the best-short-side-fit
+//       heuristic which gives dense results for the kind of sprites we usually pack.
+
+use super::math::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PackedRect {
+    /// The region the rect was packed into. If `rotated` is true its width and height are swapped
+    /// compared to the requested dimensions.
+    pub rect: Recti,
+    /// The rect was rotated by 90 degrees to make it fit better
+    pub rotated: bool,
+}
+
+/// A rectangle packer that can grow in size and can free previously packed rectangles again
+#[derive(Debug, Clone)]
+pub struct MaxRectsPacker {
+    width: i32,
+    height: i32,
+    /// Maximal (possibly overlapping) rectangles that are not occupied by packed rectangles
+    free_rects: Vec<Recti>,
+    used_area: i64,
+}
+
+impl MaxRectsPacker {
+    pub fn new(width: i32, height: i32) -> MaxRectsPacker {
+        assert!(width >= 0 && height >= 0);
+        let free_rects = if width > 0 && height > 0 {
+            vec![Recti::from_width_height(width, height)]
+        } else {
+            Vec::new()
+        };
+        MaxRectsPacker {
+            width,
+            height,
+            free_rects,
+            used_area: 0,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Returns the summed area of all currently packed rectangles
+    #[inline]
+    pub fn used_area(&self) -> i64 {
+        self.used_area
+    }
+
+    /// Returns the percentage of the packing area that is occupied by packed rectangles
+    #[inline]
+    pub fn occupancy(&self) -> f32 {
+        let area_total = self.width as i64 * self.height as i64;
+        if area_total == 0 {
+            0.0
+        } else {
+            self.used_area as f32 / area_total as f32
+        }
+    }
+
+    /// Returns `None` if there is no space left for a rect of the given dimensions. If
+    /// `allow_rotation` is true the rect may be rotated by 90 degrees if that results in a better
+    /// fit.
+    pub fn pack(&mut self, width: i32, height: i32, allow_rotation: bool) -> Option<PackedRect> {
+        assert!(width > 0 && height > 0);
+
+        let mut best: Option<(PackedRect, (i32, i32))> = None;
+        for free_rect in &self.free_rects {
+            let mut candidates = vec![(width, height, false)];
+            if allow_rotation && width != height {
+                candidates.push((height, width, true));
+            }
+            for (candidate_width, candidate_height, rotated) in candidates {
+                if candidate_width > free_rect.width() || candidate_height > free_rect.height() {
+                    continue;
+                }
+                let leftover_horizontal = free_rect.width() - candidate_width;
+                let leftover_vertical = free_rect.height() - candidate_height;
+                let score = (
+                    i32::min(leftover_horizontal, leftover_vertical),
+                    i32::max(leftover_horizontal, leftover_vertical),
+                );
+                let is_better = match best {
+                    Some((_, best_score)) => score < best_score,
+                    None => true,
+                };
+                if is_better {
+                    let rect = Recti::from_xy_width_height(
+                        free_rect.pos.x,
+                        free_rect.pos.y,
+                        candidate_width,
+                        candidate_height,
+                    );
+                    best = Some((PackedRect { rect, rotated }, score));
+                }
+            }
+        }
+
+        let (packed, _score) = best?;
+        self.mark_occupied(packed.rect);
+        Some(packed)
+    }
+
+    /// Makes the region of a previously packed rect available for packing again
+    pub fn free(&mut self, rect: Recti) {
+        assert!(
+            0 <= rect.left()
+                && 0 <= rect.top()
+                && rect.right() <= self.width
+                && rect.bottom() <= self.height,
+            "Freed rect {:?} is not inside the packing area {}x{}",
+            rect,
+            self.width,
+            self.height
+        );
+        self.used_area -= rect.width() as i64 * rect.height() as i64;
+
+        // NOTE: We merge the freed rect with free rects that share a full edge with it. This
+        //       reduces fragmentation when freeing neighbouring rects.
+        let mut merged = rect;
+        loop {
+            let merge_index = self
+                .free_rects
+                .iter()
+                .position(|free_rect| rects_merged(merged, *free_rect).is_some());
+            if let Some(merge_index) = merge_index {
+                let free_rect = self.free_rects.swap_remove(merge_index);
+                merged = rects_merged(merged, free_rect).unwrap();
+            } else {
+                break;
+            }
+        }
+        self.free_rects.push(merged);
+        self.prune_free_rects();
+    }
+
+    /// Grows the packing area. The new area is added to the right and bottom of the current area.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        assert!(
+            width >= self.width && height >= self.height,
+            "Packing area can only grow"
+        );
+
+        let width_previous = self.width;
+        let height_previous = self.height;
+
+        // NOTE: Free rects that touch the previous border can now extend into the new area
+        for free_rect in self.free_rects.iter_mut() {
+            if free_rect.right() == width_previous {
+                free_rect.dim.x += width - width_previous;
+            }
+            if free_rect.bottom() == height_previous {
+                free_rect.dim.y += height - height_previous;
+            }
+        }
+        if width > width_previous {
+            self.free_rects.push(Recti::from_xy_width_height(
+                width_previous,
+                0,
+                width - width_previous,
+                height,
+            ));
+        }
+        if height > height_previous {
+            self.free_rects.push(Recti::from_xy_width_height(
+                0,
+                height_previous,
+                width,
+                height - height_previous,
+            ));
+        }
+
+        self.width = width;
+        self.height = height;
+        self.prune_free_rects();
+    }
+
+    /// Marks the given region as occupied as if a rect was packed there. This is useful for
+    /// restoring the state of a packer.
+    pub fn mark_occupied(&mut self, used: Recti) {
+        self.used_area += used.width() as i64 * used.height() as i64;
+
+        let mut free_rects_new = Vec::with_capacity(self.free_rects.len() + 4);
+        for free_rect in self.free_rects.drain(..) {
+            if !rects_overlap(free_rect, used) {
+                free_rects_new.push(free_rect);
+                continue;
+            }
+
+            // NOTE: We replace the free rect by the (up to four) maximal rects around the used
+            //       rect that are still free
+            if used.left() > free_rect.left() {
+                free_rects_new.push(Recti::from_bounds_left_top_right_bottom(
+                    free_rect.left(),
+                    free_rect.top(),
+                    used.left(),
+                    free_rect.bottom(),
+                ));
+            }
+            if used.right() < free_rect.right() {
+                free_rects_new.push(Recti::from_bounds_left_top_right_bottom(
+                    used.right(),
+                    free_rect.top(),
+                    free_rect.right(),
+                    free_rect.bottom(),
+                ));
+            }
+            if used.top() > free_rect.top() {
+                free_rects_new.push(Recti::from_bounds_left_top_right_bottom(
+                    free_rect.left(),
+                    free_rect.top(),
+                    free_rect.right(),
+                    used.top(),
+                ));
+            }
+            if used.bottom() < free_rect.bottom() {
+                free_rects_new.push(Recti::from_bounds_left_top_right_bottom(
+                    free_rect.left(),
+                    used.bottom(),
+                    free_rect.right(),
+                    free_rect.bottom(),
+                ));
+            }
+        }
+        self.free_rects = free_rects_new;
+        self.prune_free_rects();
+    }
+
+    /// Removes all free rects that are fully contained in other free rects
+    fn prune_free_rects(&mut self) {
+        let mut index = 0;
+        while index < self.free_rects.len() {
+            let rect = self.free_rects[index];
+            let is_redundant = self
+                .free_rects
+                .iter()
+                .enumerate()
+                .any(|(other_index, other)| {
+                    other_index != index
+                        && rect_contains_rect(*other, rect)
+                        // NOTE: For duplicates we only keep the one with the lower index
+                        && (*other != rect || other_index < index)
+                });
+            if is_redundant {
+                self.free_rects.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+#[inline]
+fn rects_overlap(a: Recti, b: Recti) -> bool {
+    a.left() < b.right() && b.left() < a.right() && a.top() < b.bottom() && b.top() < a.bottom()
+}
+
+#[inline]
+fn rect_contains_rect(outer: Recti, inner: Recti) -> bool {
+    outer.left() <= inner.left()
+        && outer.top() <= inner.top()
+        && inner.right() <= outer.right()
+        && inner.bottom() <= outer.bottom()
+}
+
+/// Returns the union of both rects if they share a full edge
+#[inline]
+fn rects_merged(a: Recti, b: Recti) -> Option<Recti> {
+    let share_vertical_edge = a.top() == b.top()
+        && a.bottom() == b.bottom()
+        && (a.right() == b.left() || b.right() == a.left());
+    let share_horizontal_edge = a.left() == b.left()
+        && a.right() == b.right()
+        && (a.bottom() == b.top() || b.bottom() == a.top());
+    if share_vertical_edge || share_horizontal_edge {
+        Some(Recti::smallest_that_contains_both_rects(a, b))
+    } else {
+        None
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_no_overlaps(rects: &[Recti], width: i32, height: i32) {
+        for (index, rect) in rects.iter().enumerate() {
+            assert!(rect_contains_rect(
+                Recti::from_width_height(width, height),
+                *rect
+            ));
+            for other in &rects[index + 1..] {
+                assert!(!rects_overlap(*rect, *other), "{:?} {:?}", rect, other);
+            }
+        }
+    }
+
+    #[test]
+    fn maxrects_packing_fills_area_without_overlaps() {
+        let mut packer = MaxRectsPacker::new(64, 64);
+        let mut rects = Vec::new();
+        for _ in 0..16 {
+            rects.push(packer.pack(16, 16, false).unwrap().rect);
+        }
+        assert!(packer.pack(1, 1, false).is_none());
+        assert_eq!(packer.occupancy(), 1.0);
+        assert_no_overlaps(&rects, 64, 64);
+
+        // NOTE: Freeing two neighbouring rects makes room for a rect that spans both
+        packer.free(rects[0]);
+        packer.free(rects[1]);
+        let packed = packer.pack(32, 16, false).unwrap();
+        assert_eq!(packed.rect.pos, Vec2i::zero());
+    }
+
+    #[test]
+    fn maxrects_packing_with_rotation_and_resize() {
+        let mut packer = MaxRectsPacker::new(32, 8);
+        assert!(packer.pack(8, 32, false).is_none());
+        let packed = packer.pack(8, 32, true).unwrap();
+        assert!(packed.rotated);
+        assert_eq!(packed.rect.dim, Vec2i::new(32, 8));
+
+        packer.resize(64, 64);
+        let mut rects = vec![packed.rect];
+        let sizes = [(30, 20), (10, 50), (20, 20), (32, 8), (5, 7), (12, 3)];
+        for &(width, height) in sizes.iter() {
+            rects.push(packer.pack(width, height, true).unwrap().rect);
+        }
+        assert_no_overlaps(&rects, 64, 64);
+        let area_expected: i64 = rects
+            .iter()
+            .map(|rect| rect.width() as i64 * rect.height() as i64)
+            .sum();
+        assert_eq!(packer.used_area(), area_expected);
+    }
+}
@@ -0,0 +1,453 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Scaling
+//
+// NOTE: The pixel art upscalers (Scale2x/Scale3x and xBR) are meant for images with hard edges
+//       and few colors. The resampling filters work on premultiplied colors internally so that
+//       transparent pixels do not bleed dark fringes into their neighbors. Pixels outside the
+//       bitmap are treated as copies of the nearest border pixel.
+
+use super::bitmap::*;
+
+impl Bitmap {
+    /// Doubles the size using the Scale2x (EPX) algorithm which rounds off diagonal edges
+    /// without introducing new colors. See https://www.scale2x.it/algorithm
+    #[must_use = "This does not change the original bitmap"]
+    pub fn scaled_scale2x(&self) -> Bitmap {
+        let mut result = Bitmap::new(2 * self.width as u32, 2 * self.height as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let b = self.get_clamped(x, y - 1);
+                let d = self.get_clamped(x - 1, y);
+                let e = self.get(x, y);
+                let f = self.get_clamped(x + 1, y);
+                let h = self.get_clamped(x, y + 1);
+
+                let (e0, e1, e2, e3) = if b != h && d != f {
+                    (
+                        if d == b { d } else { e },
+                        if b == f { f } else { e },
+                        if d == h { d } else { e },
+                        if h == f { f } else { e },
+                    )
+                } else {
+                    (e, e, e, e)
+                };
+                result.set(2 * x, 2 * y, e0);
+                result.set(2 * x + 1, 2 * y, e1);
+                result.set(2 * x, 2 * y + 1, e2);
+                result.set(2 * x + 1, 2 * y + 1, e3);
+            }
+        }
+        result
+    }
+
+    /// Triples the size using the Scale3x algorithm which rounds off diagonal edges without
+    /// introducing new colors. See https://www.scale2x.it/algorithm
+    #[must_use = "This does not change the original bitmap"]
+    pub fn scaled_scale3x(&self) -> Bitmap {
+        let mut result = Bitmap::new(3 * self.width as u32, 3 * self.height as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let a = self.get_clamped(x - 1, y - 1);
+                let b = self.get_clamped(x, y - 1);
+                let c = self.get_clamped(x + 1, y - 1);
+                let d = self.get_clamped(x - 1, y);
+                let e = self.get(x, y);
+                let f = self.get_clamped(x + 1, y);
+                let g = self.get_clamped(x - 1, y + 1);
+                let h = self.get_clamped(x, y + 1);
+                let i = self.get_clamped(x + 1, y + 1);
+
+                let block = if b != h && d != f {
+                    [
+                        if d == b { d } else { e },
+                        if (d == b && e != c) || (b == f && e != a) {
+                            b
+                        } else {
+                            e
+                        },
+                        if b == f { f } else { e },
+                        if (d == b && e != g) || (d == h && e != a) {
+                            d
+                        } else {
+                            e
+                        },
+                        e,
+                        if (b == f && e != i) || (h == f && e != c) {
+                            f
+                        } else {
+                            e
+                        },
+                        if d == h { d } else { e },
+                        if (d == h && e != i) || (h == f && e != g) {
+                            h
+                        } else {
+                            e
+                        },
+                        if h == f { f } else { e },
+                    ]
+                } else {
+                    [e; 9]
+                };
+                for (index, &pixel) in block.iter().enumerate() {
+                    result.set(3 * x + index as i32 % 3, 3 * y + index as i32 / 3, pixel);
+                }
+            }
+        }
+        result
+    }
+
+    /// Doubles the size using a first level xBR filter. Compared to Scale2x it detects edges
+    /// by color similarity instead of exact equality and smoothes them by blending.
+    /// See https://forums.libretro.com/t/xbr-algorithm-tutorial/123
+    #[must_use = "This does not change the original bitmap"]
+    pub fn scaled_xbr2x(&self) -> Bitmap {
+        let mut result = Bitmap::new(2 * self.width as u32, 2 * self.height as u32);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let e = self.get(x, y);
+                for &(sx, sy) in &[(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                    // NOTE: The neighbors are named as in the xBR description for the bottom
+                    //       right corner and mirrored for the other corners
+                    let at = |dx: i32, dy: i32| self.get_clamped(x + sx * dx, y + sy * dy);
+                    let b = at(0, -1);
+                    let c = at(1, -1);
+                    let d = at(-1, 0);
+                    let f = at(1, 0);
+                    let g = at(-1, 1);
+                    let h = at(0, 1);
+                    let i = at(1, 1);
+                    let f4 = at(2, 0);
+                    let i4 = at(2, 1);
+                    let h5 = at(0, 2);
+                    let i5 = at(1, 2);
+
+                    let weight_edge = xbr_distance(e, c)
+                        + xbr_distance(e, g)
+                        + xbr_distance(i, h5)
+                        + xbr_distance(i, f4)
+                        + 4.0 * xbr_distance(h, f);
+                    let weight_diagonal = xbr_distance(h, d)
+                        + xbr_distance(h, i5)
+                        + xbr_distance(f, i4)
+                        + xbr_distance(f, b)
+                        + 4.0 * xbr_distance(e, i);
+
+                    let pixel = if weight_edge < weight_diagonal && e != f && e != h {
+                        let closer = if xbr_distance(e, f) <= xbr_distance(e, h) {
+                            f
+                        } else {
+                            h
+                        };
+                        mix_premultiplied(e, closer, 0.5)
+                    } else {
+                        e
+                    };
+                    result.set(
+                        2 * x + if sx > 0 { 1 } else { 0 },
+                        2 * y + if sy > 0 { 1 } else { 0 },
+                        pixel,
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    /// Resamples the bitmap with bilinear interpolation
+    #[must_use = "This does not change the original bitmap"]
+    pub fn scaled_bilinear(&self, new_width: u32, new_height: u32) -> Bitmap {
+        self.resampled(new_width, new_height, 1, |distance| {
+            f32::max(0.0, 1.0 - distance.abs())
+        })
+    }
+
+    /// Resamples the bitmap with bicubic interpolation using the Catmull-Rom kernel
+    #[must_use = "This does not change the original bitmap"]
+    pub fn scaled_bicubic(&self, new_width: u32, new_height: u32) -> Bitmap {
+        self.resampled(new_width, new_height, 2, |distance| {
+            // NOTE: This is the Keys cubic kernel with a = -0.5
+            let x = distance.abs();
+            if x < 1.0 {
+                1.5 * x * x * x - 2.5 * x * x + 1.0
+            } else if x < 2.0 {
+                -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    /// Downscales the bitmap by averaging all source pixels covered by each destination pixel,
+    /// weighted by their coverage. This gives the best quality for downscaling.
+    #[must_use = "This does not change the original bitmap"]
+    pub fn scaled_down_area_average(&self, new_width: u32, new_height: u32) -> Bitmap {
+        assert!(new_width > 0 && new_height > 0);
+        assert!(
+            new_width as i32 <= self.width && new_height as i32 <= self.height,
+            "Area averaging can only downscale"
+        );
+
+        let scale_x = self.width as f32 / new_width as f32;
+        let scale_y = self.height as f32 / new_height as f32;
+        let mut result = Bitmap::new(new_width, new_height);
+        for y in 0..new_height as i32 {
+            let source_top = y as f32 * scale_y;
+            let source_bottom = (y + 1) as f32 * scale_y;
+            for x in 0..new_width as i32 {
+                let source_left = x as f32 * scale_x;
+                let source_right = (x + 1) as f32 * scale_x;
+
+                let mut sum = Color::transparent();
+                let mut weight_sum = 0.0;
+                for source_y in source_top.floor() as i32..source_bottom.ceil() as i32 {
+                    let coverage_y = f32::min(source_bottom, (source_y + 1) as f32)
+                        - f32::max(source_top, source_y as f32);
+                    for source_x in source_left.floor() as i32..source_right.ceil() as i32 {
+                        let coverage_x = f32::min(source_right, (source_x + 1) as f32)
+                            - f32::max(source_left, source_x as f32);
+                        let weight = coverage_x * coverage_y;
+                        sum += weight
+                            * self
+                                .get_clamped(source_x, source_y)
+                                .to_color()
+                                .to_premultiplied_alpha();
+                        weight_sum += weight;
+                    }
+                }
+                result.set(x, y, pixel_from_premultiplied(sum / weight_sum));
+            }
+        }
+        result
+    }
+
+    #[inline]
+    fn get_clamped(&self, x: i32, y: i32) -> PixelRGBA {
+        self.get(
+            i32::clamp(x, 0, self.width - 1),
+            i32::clamp(y, 0, self.height - 1),
+        )
+    }
+
+    /// Separable resampling with the given filter kernel of the given radius. The kernel is
+    /// stretched when downscaling to avoid aliasing.
+    fn resampled<F: Fn(f32) -> f32>(
+        &self,
+        new_width: u32,
+        new_height: u32,
+        kernel_radius: i32,
+        kernel: F,
+    ) -> Bitmap {
+        assert!(new_width > 0 && new_height > 0);
+
+        let premultiplied: Vec<Color> = self
+            .data
+            .iter()
+            .map(|pixel| pixel.to_color().to_premultiplied_alpha())
+            .collect();
+
+        // NOTE: Returns the source indices and normalized weights for each destination pixel
+        let compute_taps = |source_size: i32, dest_size: i32| -> Vec<Vec<(i32, f32)>> {
+            let scale = source_size as f32 / dest_size as f32;
+            let support = f32::max(1.0, scale);
+            (0..dest_size)
+                .map(|dest_index| {
+                    let center = (dest_index as f32 + 0.5) * scale - 0.5;
+                    let radius = kernel_radius as f32 * support;
+                    let mut taps: Vec<(i32, f32)> = ((center - radius).floor() as i32
+                        ..=(center + radius).ceil() as i32)
+                        .map(|source_index| {
+                            (
+                                i32::clamp(source_index, 0, source_size - 1),
+                                kernel((source_index as f32 - center) / support),
+                            )
+                        })
+                        .filter(|(_, weight)| *weight != 0.0)
+                        .collect();
+                    let weight_sum: f32 = taps.iter().map(|(_, weight)| weight).sum();
+                    for tap in taps.iter_mut() {
+                        tap.1 /= weight_sum;
+                    }
+                    taps
+                })
+                .collect()
+        };
+        let taps_x = compute_taps(self.width, new_width as i32);
+        let taps_y = compute_taps(self.height, new_height as i32);
+
+        let mut horizontal = vec![Color::transparent(); new_width as usize * self.height as usize];
+        for y in 0..self.height as usize {
+            for (x, taps) in taps_x.iter().enumerate() {
+                let mut sum = Color::transparent();
+                for &(source_x, weight) in taps {
+                    sum += weight * premultiplied[source_x as usize + y * self.width as usize];
+                }
+                horizontal[x + y * new_width as usize] = sum;
+            }
+        }
+
+        let mut result = Bitmap::new(new_width, new_height);
+        for (y, taps) in taps_y.iter().enumerate() {
+            for x in 0..new_width as usize {
+                let mut sum = Color::transparent();
+                for &(source_y, weight) in taps {
+                    sum += weight * horizontal[x + source_y as usize * new_width as usize];
+                }
+                result.set(x as i32, y as i32, pixel_from_premultiplied(sum));
+            }
+        }
+        result
+    }
+}
+
+/// Converts a premultiplied color back to a non-premultiplied pixel. Clamps overshooting values
+/// which can occur with bicubic filtering.
+#[inline]
+fn pixel_from_premultiplied(color: Color) -> PixelRGBA {
+    let alpha = f32::clamp(color.a, 0.0, 1.0);
+    if alpha == 0.0 {
+        return PixelRGBA::transparent();
+    }
+    let channel = |value: f32| f32::clamp(value / alpha * 255.0 + 0.5, 0.0, 255.0) as u8;
+    PixelRGBA::new(
+        channel(f32::min(color.r, alpha)),
+        channel(f32::min(color.g, alpha)),
+        channel(f32::min(color.b, alpha)),
+        (alpha * 255.0 + 0.5) as u8,
+    )
+}
+
+#[inline]
+fn mix_premultiplied(x: PixelRGBA, y: PixelRGBA, percent: f32) -> PixelRGBA {
+    pixel_from_premultiplied(Color::mix(
+        x.to_color().to_premultiplied_alpha(),
+        y.to_color().to_premultiplied_alpha(),
+        percent,
+    ))
+}
+
+/// Perceptual color difference in YUV space as used by xBR. Also takes alpha into account.
+#[inline]
+fn xbr_distance(x: PixelRGBA, y: PixelRGBA) -> f32 {
+    let dr = x.r as f32 - y.r as f32;
+    let dg = x.g as f32 - y.g as f32;
+    let db = x.b as f32 - y.b as f32;
+    let da = x.a as f32 - y.a as f32;
+
+    let luma = 0.299 * dr + 0.587 * dg + 0.114 * db;
+    let chroma_u = -0.169 * dr - 0.331 * dg + 0.5 * db;
+    let chroma_v = 0.5 * dr - 0.419 * dg - 0.081 * db;
+    48.0 * luma.abs() + 7.0 * chroma_u.abs() + 6.0 * chroma_v.abs() + 48.0 * da.abs()
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a 3x3 image with a diagonal staircase
+    fn create_diagonal_bitmap() -> Bitmap {
+        let mut bitmap = Bitmap::new_filled(3, 3, PixelRGBA::white());
+        bitmap.set(0, 0, PixelRGBA::black());
+        bitmap.set(1, 0, PixelRGBA::black());
+        bitmap.set(0, 1, PixelRGBA::black());
+        bitmap
+    }
+
+    #[test]
+    fn epx_scaling() {
+        let bitmap = create_diagonal_bitmap();
+
+        let scaled = bitmap.scaled_scale2x();
+        assert_eq!((scaled.width, scaled.height), (6, 6));
+        // NOTE: The inner corner of the staircase gets filled
+        assert_eq!(scaled.get(2, 2), PixelRGBA::black());
+        assert_eq!(scaled.get(3, 3), PixelRGBA::white());
+        assert_eq!(scaled.get(5, 5), PixelRGBA::white());
+
+        let scaled = bitmap.scaled_scale3x();
+        assert_eq!((scaled.width, scaled.height), (9, 9));
+        assert_eq!(scaled.get(3, 3), PixelRGBA::black());
+        assert_eq!(scaled.get(4, 4), PixelRGBA::white());
+
+        // NOTE: Flat images stay flat and no new colors get introduced
+        let flat = Bitmap::new_filled(4, 4, PixelRGBA::red());
+        assert_eq!(
+            flat.scaled_scale2x(),
+            Bitmap::new_filled(8, 8, PixelRGBA::red())
+        );
+        assert_eq!(
+            flat.scaled_scale3x(),
+            Bitmap::new_filled(12, 12, PixelRGBA::red())
+        );
+    }
+
+    #[test]
+    fn xbr_scaling() {
+        let bitmap = create_diagonal_bitmap();
+        let scaled = bitmap.scaled_xbr2x();
+        assert_eq!((scaled.width, scaled.height), (6, 6));
+        assert_eq!(scaled.get(0, 0), PixelRGBA::black());
+        assert_eq!(scaled.get(5, 5), PixelRGBA::white());
+
+        // NOTE: The diagonal edge gets smoothed by blending
+        let edge_pixel = scaled.get(2, 2);
+        assert!(edge_pixel.r > 0 && edge_pixel.r < 255);
+
+        let flat = Bitmap::new_filled(4, 4, PixelRGBA::red());
+        assert_eq!(
+            flat.scaled_xbr2x(),
+            Bitmap::new_filled(8, 8, PixelRGBA::red())
+        );
+    }
+
+    #[test]
+    fn resampling() {
+        let mut bitmap = Bitmap::new(2, 1);
+        bitmap.set(0, 0, PixelRGBA::black());
+        bitmap.set(1, 0, PixelRGBA::white());
+
+        let scaled = bitmap.scaled_bilinear(4, 1);
+        assert_eq!(scaled.get(0, 0), PixelRGBA::black());
+        assert_eq!(scaled.get(1, 0), PixelRGBA::greyscale(64));
+        assert_eq!(scaled.get(2, 0), PixelRGBA::greyscale(191));
+        assert_eq!(scaled.get(3, 0), PixelRGBA::white());
+
+        let scaled = bitmap.scaled_bicubic(4, 1);
+        assert!(scaled.get(0, 0).r < scaled.get(1, 0).r);
+        assert!(scaled.get(1, 0).r < scaled.get(2, 0).r);
+        assert!(scaled.get(2, 0).r < scaled.get(3, 0).r);
+
+        // NOTE: Transparent pixels must not darken their neighbors
+        let mut bitmap = Bitmap::new(2, 2);
+        bitmap.set(0, 0, PixelRGBA::red());
+        for scaled in &[bitmap.scaled_bilinear(5, 5), bitmap.scaled_bicubic(5, 5)] {
+            for pixel in &scaled.data {
+                if pixel.a != 0 {
+                    assert_eq!((pixel.r, pixel.g, pixel.b), (255, 0, 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn area_average_downscaling() {
+        let mut bitmap = Bitmap::new_filled(4, 4, PixelRGBA::white());
+        bitmap.draw_rect_filled(0, 0, 2, 2, PixelRGBA::black());
+
+        let scaled = bitmap.scaled_down_area_average(2, 2);
+        assert_eq!(scaled.get(0, 0), PixelRGBA::black());
+        assert_eq!(scaled.get(1, 0), PixelRGBA::white());
+
+        let scaled = bitmap.scaled_down_area_average(1, 1);
+        assert_eq!(scaled.get(0, 0), PixelRGBA::greyscale(191));
+
+        // NOTE: Non integer factors weight pixels by their coverage
+        let scaled = bitmap.scaled_down_area_average(3, 3);
+        assert_eq!(scaled.get(0, 0), PixelRGBA::black());
+        assert_eq!(scaled.get(1, 1), PixelRGBA::greyscale(191));
+        assert_eq!(scaled.get(2, 2), PixelRGBA::white());
+    }
+}
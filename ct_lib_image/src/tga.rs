@@ -0,0 +1,310 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// TGA image format
+//
+// Specification: http://www.dca.fbk.eu/sites/default/files/media/pdf/tga-spec.pdf
+
+use super::bitmap::*;
+
+use super::core::Deserializer;
+
+const TGA_IMAGE_TYPE_COLORMAPPED: u8 = 1;
+const TGA_IMAGE_TYPE_TRUECOLOR: u8 = 2;
+const TGA_IMAGE_TYPE_GRAYSCALE: u8 = 3;
+const TGA_IMAGE_TYPE_FLAG_RLE: u8 = 8;
+
+const TGA_DESCRIPTOR_MASK_ALPHA_BITS: u8 = 0b0000_1111;
+const TGA_DESCRIPTOR_FLAG_RIGHT_TO_LEFT: u8 = 0b0001_0000;
+const TGA_DESCRIPTOR_FLAG_TOP_TO_BOTTOM: u8 = 0b0010_0000;
+
+const TGA_FOOTER_SIGNATURE: &[u8; 18] = b"TRUEVISION-XFILE.\0";
+
+/// Converts a 15, 16, 24 or 32 bit BGR(A) color to RGBA
+fn tga_color_from_bytes(bytes: &[u8], alpha_bits: u8) -> PixelRGBA {
+    match bytes.len() {
+        2 => {
+            let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let expand_5_bit = |channel: u16| {
+                let channel = (channel & 0b1_1111) as u8;
+                (channel << 3) | (channel >> 2)
+            };
+            let alpha = if alpha_bits > 0 && value & 0x8000 == 0 {
+                0
+            } else {
+                255
+            };
+            PixelRGBA::new(
+                expand_5_bit(value >> 10),
+                expand_5_bit(value >> 5),
+                expand_5_bit(value),
+                alpha,
+            )
+        }
+        3 => PixelRGBA::new(bytes[2], bytes[1], bytes[0], 255),
+        4 => PixelRGBA::new(bytes[2], bytes[1], bytes[0], bytes[3]),
+        _ => unreachable!(),
+    }
+}
+
+impl Bitmap {
+    pub fn from_tga_data(tga_data: &[u8]) -> Result<Bitmap, String> {
+        let mut deserializer = Deserializer::new(tga_data);
+        let id_length: u8 = deserializer.deserialize()?;
+        let colormap_type: u8 = deserializer.deserialize()?;
+        let image_type: u8 = deserializer.deserialize()?;
+        let colormap_first_index: u16 = deserializer.deserialize()?;
+        let colormap_length: u16 = deserializer.deserialize()?;
+        let colormap_entry_bits: u8 = deserializer.deserialize()?;
+        let _origin_x: u16 = deserializer.deserialize()?;
+        let _origin_y: u16 = deserializer.deserialize()?;
+        let width: u16 = deserializer.deserialize()?;
+        let height: u16 = deserializer.deserialize()?;
+        let pixel_bits: u8 = deserializer.deserialize()?;
+        let descriptor: u8 = deserializer.deserialize()?;
+        deserializer.skip_bytes(id_length as usize)?;
+
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "Tga data has invalid dimensions {}x{}",
+                width, height
+            ));
+        }
+        let alpha_bits = descriptor & TGA_DESCRIPTOR_MASK_ALPHA_BITS;
+
+        let colormap: Vec<PixelRGBA> = if colormap_type == 1 {
+            let entry_bytes = match colormap_entry_bits {
+                15 | 16 => 2,
+                24 => 3,
+                32 => 4,
+                _ => {
+                    return Err(format!(
+                        "Tga colormap has unsupported entry size {}",
+                        colormap_entry_bits
+                    ))
+                }
+            };
+            let colormap_size_bytes = entry_bytes * colormap_length as usize;
+            let colormap_data = deserializer
+                .get_remaining_data()
+                .get(..colormap_size_bytes)
+                .ok_or_else(|| "Tga colormap is truncated".to_owned())?;
+            let colormap = colormap_data
+                .chunks_exact(entry_bytes)
+                .map(|bytes| tga_color_from_bytes(bytes, alpha_bits))
+                .collect();
+            deserializer.skip_bytes(colormap_size_bytes)?;
+            colormap
+        } else {
+            Vec::new()
+        };
+
+        let is_rle_compressed = image_type & TGA_IMAGE_TYPE_FLAG_RLE != 0;
+        let image_type = image_type & !TGA_IMAGE_TYPE_FLAG_RLE;
+        let pixel_bytes = match (image_type, pixel_bits) {
+            (TGA_IMAGE_TYPE_COLORMAPPED, 8) => 1,
+            (TGA_IMAGE_TYPE_COLORMAPPED, 16) => 2,
+            (TGA_IMAGE_TYPE_TRUECOLOR, 15) | (TGA_IMAGE_TYPE_TRUECOLOR, 16) => 2,
+            (TGA_IMAGE_TYPE_TRUECOLOR, 24) => 3,
+            (TGA_IMAGE_TYPE_TRUECOLOR, 32) => 4,
+            (TGA_IMAGE_TYPE_GRAYSCALE, 8) => 1,
+            (TGA_IMAGE_TYPE_GRAYSCALE, 16) => 2,
+            _ => {
+                return Err(format!(
+                    "Tga image type {} with {} bits per pixel is not supported",
+                    image_type, pixel_bits
+                ))
+            }
+        };
+        if image_type == TGA_IMAGE_TYPE_COLORMAPPED && colormap.is_empty() {
+            return Err("Colormapped tga data has no colormap".to_owned());
+        }
+
+        let decode_pixel = |bytes: &[u8]| -> PixelRGBA {
+            match image_type {
+                TGA_IMAGE_TYPE_COLORMAPPED => {
+                    let index = if bytes.len() == 1 {
+                        bytes[0] as usize
+                    } else {
+                        u16::from_le_bytes([bytes[0], bytes[1]]) as usize
+                    };
+                    index
+                        .checked_sub(colormap_first_index as usize)
+                        .and_then(|index| colormap.get(index))
+                        .cloned()
+                        .unwrap_or(PixelRGBA::transparent())
+                }
+                TGA_IMAGE_TYPE_GRAYSCALE => {
+                    let alpha = if bytes.len() == 2 { bytes[1] } else { 255 };
+                    PixelRGBA::new(bytes[0], bytes[0], bytes[0], alpha)
+                }
+                _ => tga_color_from_bytes(bytes, alpha_bits),
+            }
+        };
+
+        let pixel_count = width as usize * height as usize;
+        let mut buffer = Vec::with_capacity(pixel_count);
+        let pixel_data = deserializer.get_remaining_data();
+        if is_rle_compressed {
+            let mut pos = 0;
+            while buffer.len() < pixel_count {
+                let packet_header = *pixel_data
+                    .get(pos)
+                    .ok_or_else(|| "Tga pixel data is truncated".to_owned())?;
+                pos += 1;
+
+                let packet_length = (packet_header & 0b0111_1111) as usize + 1;
+                let is_run_length_packet = packet_header & 0b1000_0000 != 0;
+                let packet_size_bytes = if is_run_length_packet {
+                    pixel_bytes
+                } else {
+                    pixel_bytes * packet_length
+                };
+                let packet_data = pixel_data
+                    .get(pos..pos + packet_size_bytes)
+                    .ok_or_else(|| "Tga pixel data is truncated".to_owned())?;
+                pos += packet_size_bytes;
+
+                let packet_length = usize::min(packet_length, pixel_count - buffer.len());
+                if is_run_length_packet {
+                    let pixel = decode_pixel(packet_data);
+                    buffer.extend(std::iter::repeat(pixel).take(packet_length));
+                } else {
+                    buffer.extend(
+                        packet_data
+                            .chunks_exact(pixel_bytes)
+                            .take(packet_length)
+                            .map(|bytes| decode_pixel(bytes)),
+                    );
+                }
+            }
+        } else {
+            let pixel_data = pixel_data
+                .get(..pixel_count * pixel_bytes)
+                .ok_or_else(|| "Tga pixel data is truncated".to_owned())?;
+            buffer.extend(
+                pixel_data
+                    .chunks_exact(pixel_bytes)
+                    .map(|bytes| decode_pixel(bytes)),
+            );
+        }
+
+        // NOTE: By default the origin of tga images is the bottom-left corner
+        let width = width as usize;
+        if descriptor & TGA_DESCRIPTOR_FLAG_TOP_TO_BOTTOM == 0 {
+            buffer = buffer
+                .chunks_exact(width)
+                .rev()
+                .flat_map(|row| row.iter().cloned())
+                .collect();
+        }
+        if descriptor & TGA_DESCRIPTOR_FLAG_RIGHT_TO_LEFT != 0 {
+            for row in buffer.chunks_exact_mut(width) {
+                row.reverse();
+            }
+        }
+
+        Ok(Bitmap::new_from_buffer(width as u32, height as u32, buffer))
+    }
+
+    /// Encodes the bitmap as run-length compressed 32-bit tga. Panics if the bitmap is wider or
+    /// taller than 65535 pixels as tga cannot represent such sizes.
+    pub fn encoded_as_tga(&self) -> Vec<u8> {
+        assert!(
+            self.width <= u16::MAX as i32 && self.height <= u16::MAX as i32,
+            "Bitmap of size {}x{} is too big to be encoded as tga",
+            self.width,
+            self.height
+        );
+
+        let mut result = Vec::new();
+        result.push(0); // id length
+        result.push(0); // colormap type
+        result.push(TGA_IMAGE_TYPE_TRUECOLOR | TGA_IMAGE_TYPE_FLAG_RLE);
+        result.extend_from_slice(&[0; 5]); // colormap specification
+        result.extend_from_slice(&0u16.to_le_bytes());
+        result.extend_from_slice(&0u16.to_le_bytes());
+        result.extend_from_slice(&(self.width as u16).to_le_bytes());
+        result.extend_from_slice(&(self.height as u16).to_le_bytes());
+        result.push(32);
+        result.push(8 | TGA_DESCRIPTOR_FLAG_TOP_TO_BOTTOM);
+
+        let push_pixel = |result: &mut Vec<u8>, pixel: PixelRGBA| {
+            result.extend_from_slice(&[pixel.b, pixel.g, pixel.r, pixel.a]);
+        };
+
+        // NOTE: Packets must not cross scanlines
+        for row in self.data.chunks_exact(self.width as usize) {
+            let mut pos = 0;
+            while pos < row.len() {
+                let run_length = row[pos..]
+                    .iter()
+                    .take(128)
+                    .take_while(|&&pixel| pixel == row[pos])
+                    .count();
+                if run_length > 1 {
+                    result.push(0b1000_0000 | (run_length - 1) as u8);
+                    push_pixel(&mut result, row[pos]);
+                    pos += run_length;
+                } else {
+                    // Collect raw pixels until the next run starts
+                    let mut raw_length = 1;
+                    while pos + raw_length < row.len()
+                        && raw_length < 128
+                        && (pos + raw_length + 1 >= row.len()
+                            || row[pos + raw_length] != row[pos + raw_length + 1])
+                    {
+                        raw_length += 1;
+                    }
+                    result.push((raw_length - 1) as u8);
+                    for &pixel in &row[pos..pos + raw_length] {
+                        push_pixel(&mut result, pixel);
+                    }
+                    pos += raw_length;
+                }
+            }
+        }
+
+        // NOTE: Extension and developer area offsets followed by the signature
+        result.extend_from_slice(&[0; 8]);
+        result.extend_from_slice(TGA_FOOTER_SIGNATURE);
+        result
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tga_roundtrip() {
+        let mut bitmap = Bitmap::new(300, 7);
+        for (index, pixel) in bitmap.data.iter_mut().enumerate() {
+            *pixel = if index % 50 < 20 {
+                PixelRGBA::new(1, 2, 3, 4)
+            } else {
+                PixelRGBA::new(index as u8, (index / 3) as u8, 17, 255)
+            };
+        }
+
+        let tga_data = bitmap.encoded_as_tga();
+        let decoded = Bitmap::from_tga_data(&tga_data).unwrap();
+        assert_eq!(decoded, bitmap);
+    }
+
+    #[test]
+    fn tga_decode_uncompressed_bottom_up() {
+        // 2x2 24-bit truecolor with bottom-left origin
+        let mut tga_data = vec![0, 0, TGA_IMAGE_TYPE_TRUECOLOR, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        tga_data.extend_from_slice(&[2, 0, 2, 0, 24, 0]);
+        tga_data.extend_from_slice(&[255, 0, 0, 0, 255, 0]);
+        tga_data.extend_from_slice(&[0, 0, 255, 255, 255, 255]);
+
+        let decoded = Bitmap::from_tga_data(&tga_data).unwrap();
+        assert_eq!(decoded.get(0, 0), PixelRGBA::red());
+        assert_eq!(decoded.get(1, 0), PixelRGBA::white());
+        assert_eq!(decoded.get(0, 1), PixelRGBA::blue());
+        assert_eq!(decoded.get(1, 1), PixelRGBA::green());
+    }
+}
@@ -1,5 +1,7 @@
 pub use ezing::*;
 
+use super::StepPosition;
+
 #[inline]
 pub fn step_middle(percent: f32) -> f32 {
     if percent < 0.5 {
@@ -17,3 +19,91 @@ pub fn step_end(percent: f32) -> f32 {
         1.0
     }
 }
+
+/// Jumps between `count` discrete values like the CSS `steps(count, position)` function. A
+/// `count` of zero is treated as one as it can come from deserialized data.
+///
+/// Based on https://www.w3.org/TR/css-easing-1/#step-easing-functions
+#[inline]
+pub fn steps(percent: f32, count: u32, position: StepPosition) -> f32 {
+    let count = u32::max(count, 1);
+    let percent = f32::clamp(percent, 0.0, 1.0);
+    let step = f32::floor(percent * count as f32);
+    let result = match position {
+        StepPosition::JumpStart => (step + 1.0) / count as f32,
+        StepPosition::JumpEnd => step / count as f32,
+        StepPosition::JumpNone => {
+            if count == 1 {
+                step
+            } else {
+                step / (count - 1) as f32
+            }
+        }
+        StepPosition::JumpBoth => (step + 1.0) / (count + 1) as f32,
+    };
+    f32::min(result, 1.0)
+}
+
+/// Evaluates a cubic Bézier curve from (0, 0) to (1, 1) with the control points (x1, y1) and
+/// (x2, y2) like the CSS `cubic-bezier(x1, y1, x2, y2)` function. The x coordinates of the control
+/// points are clamped to [0, 1] so that the curve is a function of x.
+///
+/// Based on https://www.w3.org/TR/css-easing-1/#cubic-bezier-easing-functions
+pub fn cubic_bezier(percent: f32, x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+    let x1 = f32::clamp(x1, 0.0, 1.0);
+    let x2 = f32::clamp(x2, 0.0, 1.0);
+    let percent = f32::clamp(percent, 0.0, 1.0);
+
+    // NOTE: Coefficients of the polynomial form a*t^3 + b*t^2 + c*t
+    let bezier = |t: f32, p1: f32, p2: f32| {
+        let c = 3.0 * p1;
+        let b = 3.0 * (p2 - p1) - c;
+        let a = 1.0 - c - b;
+        ((a * t + b) * t + c) * t
+    };
+    let bezier_derivative = |t: f32, p1: f32, p2: f32| {
+        let c = 3.0 * p1;
+        let b = 3.0 * (p2 - p1) - c;
+        let a = 1.0 - c - b;
+        (3.0 * a * t + 2.0 * b) * t + c
+    };
+
+    // Find t with x(t) = percent. We try Newton's method first and fall back to bisection which
+    // always works because x(t) is monotonic for x1, x2 in [0, 1].
+    let mut t = percent;
+    let mut found = false;
+    for _ in 0..8 {
+        let error = bezier(t, x1, x2) - percent;
+        if error.abs() < 0.000_01 {
+            found = true;
+            break;
+        }
+        let derivative = bezier_derivative(t, x1, x2);
+        if derivative.abs() < 0.000_001 {
+            break;
+        }
+        t -= error / derivative;
+        if !(0.0..=1.0).contains(&t) {
+            break;
+        }
+    }
+    if !found {
+        let mut t_min = 0.0;
+        let mut t_max = 1.0;
+        t = percent;
+        for _ in 0..32 {
+            let x = bezier(t, x1, x2);
+            if (x - percent).abs() < 0.000_01 {
+                break;
+            }
+            if x < percent {
+                t_min = t;
+            } else {
+                t_max = t;
+            }
+            t = 0.5 * (t_min + t_max);
+        }
+    }
+
+    bezier(t, y1, y2)
+}
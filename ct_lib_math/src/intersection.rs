@@ -185,7 +185,7 @@ impl Recti {
 
 #[inline]
 pub fn intersects_circle_circle(a: Circle, b: Circle) -> bool {
-    squared(a.radius + b.radius) < Vec2::distance_squared(a.center, b.center)
+    Vec2::distance_squared(a.center, b.center) < squared(a.radius + b.radius)
 }
 
 #[inline]
@@ -300,7 +300,7 @@ impl Circle {
     pub fn intersects_rect(self, rect: Rect) -> bool {
         let rect_point_that_is_nearest_to_circle = Point::new(
             f32::max(rect.left(), f32::min(self.center.x, rect.right())),
-            f32::max(rect.bottom(), f32::min(self.center.y, rect.top())),
+            f32::max(rect.top(), f32::min(self.center.y, rect.bottom())),
         );
         rect_point_that_is_nearest_to_circle.intersects_sphere(self)
     }
@@ -429,3 +429,521 @@ pub fn intersection_line_line(a: Line, b: Line) -> Option<Intersection> {
     }
     None
 }
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Collision manifolds
+//
+// Based on the separating axis theorem and the contact clipping of Box2D Lite by Erin Catto
+// https://box2d.org/publications/
+//
+// NOTE: All contact normals point from the first given shape `a` to the second shape `b`. Moving
+//       `a` by `-depth * normal` (or `b` by `depth * normal`) resolves the penetration.
+//
+
+/// Allowed separation for shapes that are still considered touching
+const COLLISION_TOUCHING_TOLERANCE: f32 = 0.0001;
+
+/// A convex polygon with its vertices in clockwise or counterclockwise order
+#[derive(Debug, Clone)]
+pub struct ConvexPolygon {
+    pub vertices: Vec<Vec2>,
+}
+
+impl ConvexPolygon {
+    #[inline]
+    pub fn new(vertices: Vec<Vec2>) -> ConvexPolygon {
+        assert!(
+            vertices.len() >= 3,
+            "Convex polygon needs at least three vertices"
+        );
+        ConvexPolygon { vertices }
+    }
+
+    #[inline]
+    pub fn from_rect(rect: Rect) -> ConvexPolygon {
+        ConvexPolygon::from_quad(Quad::from_rect(rect))
+    }
+
+    /// Useful for oriented rects i.e. created by `Quad::from_rect_transformed`
+    #[inline]
+    pub fn from_quad(quad: Quad) -> ConvexPolygon {
+        ConvexPolygon::new(vec![
+            quad.vert_right_top,
+            quad.vert_right_bottom,
+            quad.vert_left_bottom,
+            quad.vert_left_top,
+        ])
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn translated_by(&self, translation: Vec2) -> ConvexPolygon {
+        ConvexPolygon {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|&vertex| vertex + translation)
+                .collect(),
+        }
+    }
+
+    /// Returns the average of all vertices
+    #[inline]
+    pub fn center(&self) -> Vec2 {
+        self.vertices
+            .iter()
+            .fold(Vec2::zero(), |sum, &vertex| sum + vertex)
+            / self.vertices.len() as f32
+    }
+
+    #[inline]
+    pub fn edge(&self, index: usize) -> Line {
+        Line::new(
+            self.vertices[index],
+            self.vertices[(index + 1) % self.vertices.len()],
+        )
+    }
+
+    /// Returns the normal of the edge with the given index that points away from the polygon
+    #[inline]
+    pub fn edge_normal_outward(&self, index: usize) -> Vec2 {
+        let edge = self.edge(index);
+        let normal = edge.normal();
+        if Vec2::dot(normal, edge.start - self.center()) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    }
+
+    /// Returns the minimum and maximum of all vertices projected onto the given axis
+    #[inline]
+    pub fn projected_onto_axis(&self, axis: Vec2) -> (f32, f32) {
+        self.vertices
+            .iter()
+            .fold((f32::MAX, -f32::MAX), |(min, max), &vertex| {
+                let projection = Vec2::dot(vertex, axis);
+                (f32::min(min, projection), f32::max(max, projection))
+            })
+    }
+
+    #[inline]
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        (0..self.vertices.len()).all(|index| {
+            Vec2::dot(
+                point - self.vertices[index],
+                self.edge_normal_outward(index),
+            ) <= 0.0
+        })
+    }
+
+    /// Returns the point on the boundary of the polygon that is nearest to the given point
+    pub fn closest_point_on_boundary(&self, point: Vec2) -> Vec2 {
+        (0..self.vertices.len())
+            .map(|index| {
+                let edge = self.edge(index);
+                let dir = edge.dir();
+                let t = f32::clamp(
+                    Vec2::dot(point - edge.start, dir) / dir.magnitude_squared(),
+                    0.0,
+                    1.0,
+                );
+                edge.intersection_point(t)
+            })
+            .min_by(|a, b| {
+                compare_floats(
+                    Vec2::distance_squared(*a, point),
+                    Vec2::distance_squared(*b, point),
+                )
+            })
+            .unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContactManifold {
+    /// Points from shape `a` to shape `b`
+    pub normal: Vec2,
+    /// How far the shapes penetrate each other along the normal
+    pub depth: f32,
+    contacts: [Vec2; 2],
+    contact_count: usize,
+}
+
+impl ContactManifold {
+    #[inline]
+    fn new_with_single_contact(normal: Vec2, depth: f32, contact: Vec2) -> ContactManifold {
+        ContactManifold {
+            normal,
+            depth,
+            contacts: [contact, contact],
+            contact_count: 1,
+        }
+    }
+
+    /// Returns one or two points where the shapes touch. Two points are returned if the shapes
+    /// touch along an edge.
+    #[inline]
+    pub fn contacts(&self) -> &[Vec2] {
+        &self.contacts[..self.contact_count]
+    }
+
+    /// Returns the manifold with the roles of shape `a` and `b` swapped
+    #[must_use]
+    #[inline]
+    pub fn flipped(self) -> ContactManifold {
+        ContactManifold {
+            normal: -self.normal,
+            ..self
+        }
+    }
+}
+
+pub fn collide_circle_circle(a: Circle, b: Circle) -> Option<ContactManifold> {
+    let center_diff = b.center - a.center;
+    let distance_squared = center_diff.magnitude_squared();
+    let radius_sum = a.radius + b.radius;
+    if distance_squared >= radius_sum * radius_sum {
+        return None;
+    }
+
+    let distance = distance_squared.sqrt();
+    // NOTE: We choose an arbitrary normal if both centers coincide
+    let normal = center_diff.normalized_or(Vec2::unit_x());
+    Some(ContactManifold::new_with_single_contact(
+        normal,
+        radius_sum - distance,
+        a.center + (a.radius - 0.5 * (radius_sum - distance)) * normal,
+    ))
+}
+
+pub fn collide_polygon_circle(a: &ConvexPolygon, b: Circle) -> Option<ContactManifold> {
+    if a.contains_point(b.center) {
+        // NOTE: The circle center is inside the polygon so we push it out through the nearest edge
+        let (edge_index, distance_to_edge) = (0..a.vertices.len())
+            .map(|index| {
+                let distance =
+                    Vec2::dot(a.vertices[index] - b.center, a.edge_normal_outward(index));
+                (index, distance)
+            })
+            .min_by(|x, y| compare_floats(x.1, y.1))
+            .unwrap();
+        let normal = a.edge_normal_outward(edge_index);
+        return Some(ContactManifold::new_with_single_contact(
+            normal,
+            distance_to_edge + b.radius,
+            b.center + distance_to_edge * normal,
+        ));
+    }
+
+    let closest = a.closest_point_on_boundary(b.center);
+    let distance_squared = Vec2::distance_squared(closest, b.center);
+    if distance_squared >= b.radius * b.radius {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    Some(ContactManifold::new_with_single_contact(
+        (b.center - closest) / distance,
+        b.radius - distance,
+        closest,
+    ))
+}
+
+#[inline]
+pub fn collide_circle_polygon(a: Circle, b: &ConvexPolygon) -> Option<ContactManifold> {
+    collide_polygon_circle(b, a).map(|manifold| manifold.flipped())
+}
+
+pub fn collide_polygon_polygon(a: &ConvexPolygon, b: &ConvexPolygon) -> Option<ContactManifold> {
+    polygon_polygon_manifold(a, b, 0.0)
+}
+
+/// Returns the index of the edge of `a` along which `b` penetrates `a` the least and the signed
+/// separation along that edge normal. A positive separation means that the polygons do not overlap.
+fn polygon_separation_max(a: &ConvexPolygon, b: &ConvexPolygon) -> (usize, f32) {
+    (0..a.vertices.len())
+        .map(|index| {
+            let normal = a.edge_normal_outward(index);
+            let separation = b
+                .vertices
+                .iter()
+                .map(|&vertex| Vec2::dot(vertex - a.vertices[index], normal))
+                .fold(f32::MAX, f32::min);
+            (index, separation)
+        })
+        .max_by(|x, y| compare_floats(x.1, y.1))
+        .unwrap()
+}
+
+fn polygon_polygon_manifold(
+    a: &ConvexPolygon,
+    b: &ConvexPolygon,
+    separation_allowed: f32,
+) -> Option<ContactManifold> {
+    let (edge_a, separation_a) = polygon_separation_max(a, b);
+    if separation_a > separation_allowed {
+        return None;
+    }
+    let (edge_b, separation_b) = polygon_separation_max(b, a);
+    if separation_b > separation_allowed {
+        return None;
+    }
+
+    // NOTE: We prefer `a` as the reference polygon to avoid flip-flopping between frames
+    let a_is_reference = separation_b <= 0.95 * separation_a + 0.5 * COLLISION_TOUCHING_TOLERANCE;
+    let (reference, incident, reference_edge) = if a_is_reference {
+        (a, b, edge_a)
+    } else {
+        (b, a, edge_b)
+    };
+
+    let reference_normal = reference.edge_normal_outward(reference_edge);
+    let reference_line = reference.edge(reference_edge);
+
+    // The incident edge is the edge of the incident polygon that faces the reference edge most
+    let incident_edge = (0..incident.vertices.len())
+        .min_by(|&x, &y| {
+            compare_floats(
+                Vec2::dot(incident.edge_normal_outward(x), reference_normal),
+                Vec2::dot(incident.edge_normal_outward(y), reference_normal),
+            )
+        })
+        .unwrap();
+    let incident_line = incident.edge(incident_edge);
+
+    // Clip the incident edge against the side planes of the reference edge
+    let tangent = reference_line.dir().normalized();
+    let mut points = [incident_line.start, incident_line.end];
+    let bounds = [
+        (-tangent, -Vec2::dot(tangent, reference_line.start)),
+        (tangent, Vec2::dot(tangent, reference_line.end)),
+    ];
+    for &(plane_normal, plane_offset) in &bounds {
+        let distance_0 = Vec2::dot(plane_normal, points[0]) - plane_offset;
+        let distance_1 = Vec2::dot(plane_normal, points[1]) - plane_offset;
+        if distance_0 > 0.0 && distance_1 > 0.0 {
+            // NOTE: This should only happen due to numerical issues
+            return None;
+        }
+        if distance_0 > 0.0 {
+            points[0] += (distance_0 / (distance_0 - distance_1)) * (points[1] - points[0]);
+        } else if distance_1 > 0.0 {
+            points[1] += (distance_1 / (distance_1 - distance_0)) * (points[0] - points[1]);
+        }
+    }
+
+    // Keep only the points that are behind the reference edge
+    let mut manifold = ContactManifold {
+        normal: if a_is_reference {
+            reference_normal
+        } else {
+            -reference_normal
+        },
+        depth: 0.0,
+        contacts: [Vec2::zero(); 2],
+        contact_count: 0,
+    };
+    for &point in &points {
+        let separation = Vec2::dot(point - reference_line.start, reference_normal);
+        if separation <= separation_allowed {
+            manifold.contacts[manifold.contact_count] = point;
+            manifold.contact_count += 1;
+            manifold.depth = f32::max(manifold.depth, -separation);
+        }
+    }
+
+    if manifold.contact_count == 0 {
+        None
+    } else {
+        Some(manifold)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Swept collision (time of impact)
+//
+// NOTE: The movement of each shape is given as its translation over the whole time step. The
+//       resulting `Intersection::time` is in [0, 1] where 0 means that the shapes already overlap
+//       at the start. The normal points from shape `a` to shape `b` at the time of impact.
+//
+
+pub fn sweep_circle_circle(
+    a: Circle,
+    movement_a: Vec2,
+    b: Circle,
+    movement_b: Vec2,
+) -> Option<Intersection> {
+    if let Some(manifold) = collide_circle_circle(a, b) {
+        return Some(Intersection {
+            point: manifold.contacts()[0],
+            normal: manifold.normal,
+            time: 0.0,
+        });
+    }
+
+    // NOTE: We move the center of `a` relative to `b` and check when it hits a circle around `b`
+    //       with the combined radius
+    let movement_relative = movement_a - movement_b;
+    if movement_relative.is_effectively_zero() {
+        return None;
+    }
+    let path = Line::new(a.center, a.center + movement_relative);
+    let (hit, _exit) = intersections_line_circle(path, Circle::new(b.center, a.radius + b.radius));
+    hit.map(|hit| {
+        let center_a = a.center + hit.time * movement_a;
+        let center_b = b.center + hit.time * movement_b;
+        let normal = (center_b - center_a).normalized();
+        Intersection {
+            point: center_a + a.radius * normal,
+            normal,
+            time: hit.time,
+        }
+    })
+}
+
+pub fn sweep_polygon_polygon(
+    a: &ConvexPolygon,
+    movement_a: Vec2,
+    b: &ConvexPolygon,
+    movement_b: Vec2,
+) -> Option<Intersection> {
+    // NOTE: We check on every separating axis during which time interval the projections of both
+    //       polygons overlap. The polygons touch when all of these intervals overlap.
+    let movement_relative = movement_b - movement_a;
+    let mut time_enter = -f32::MAX;
+    let mut time_exit = f32::MAX;
+    let mut normal_enter = Vec2::zero();
+
+    let axes = (0..a.vertices.len())
+        .map(|index| a.edge_normal_outward(index))
+        .chain((0..b.vertices.len()).map(|index| b.edge_normal_outward(index)));
+    for axis in axes {
+        let (min_a, max_a) = a.projected_onto_axis(axis);
+        let (min_b, max_b) = b.projected_onto_axis(axis);
+        let speed = Vec2::dot(movement_relative, axis);
+
+        if is_effectively_zero(speed) {
+            if max_b < min_a || max_a < min_b {
+                return None;
+            }
+            continue;
+        }
+
+        let time_0 = (min_a - max_b) / speed;
+        let time_1 = (max_a - min_b) / speed;
+        let (axis_enter, axis_exit) = if time_0 < time_1 {
+            (time_0, time_1)
+        } else {
+            (time_1, time_0)
+        };
+        if axis_enter > time_enter {
+            time_enter = axis_enter;
+            normal_enter = axis;
+        }
+        time_exit = f32::min(time_exit, axis_exit);
+        if time_enter > time_exit {
+            return None;
+        }
+    }
+
+    if time_enter > 1.0 || time_exit < 0.0 {
+        return None;
+    }
+
+    let time = f32::max(time_enter, 0.0);
+    let a_moved = a.translated_by(time * movement_a);
+    let b_moved = b.translated_by(time * movement_b);
+    let manifold = polygon_polygon_manifold(&a_moved, &b_moved, COLLISION_TOUCHING_TOLERANCE)?;
+    let contacts = manifold.contacts();
+    let point = contacts
+        .iter()
+        .fold(Vec2::zero(), |sum, &contact| sum + contact)
+        / contacts.len() as f32;
+    let normal = if time_enter > 0.0 {
+        if Vec2::dot(normal_enter, b_moved.center() - a_moved.center()) < 0.0 {
+            -normal_enter
+        } else {
+            normal_enter
+        }
+    } else {
+        manifold.normal
+    };
+
+    Some(Intersection {
+        point,
+        normal,
+        time,
+    })
+}
+
+pub fn sweep_polygon_circle(
+    a: &ConvexPolygon,
+    movement_a: Vec2,
+    b: Circle,
+    movement_b: Vec2,
+) -> Option<Intersection> {
+    if let Some(manifold) = collide_polygon_circle(a, b) {
+        return Some(Intersection {
+            point: manifold.contacts()[0],
+            normal: manifold.normal,
+            time: 0.0,
+        });
+    }
+
+    // NOTE: We move the circle center relative to the polygon and check when it hits the polygon
+    //       inflated by the circle radius. The inflated polygon consists of the polygon edges
+    //       pushed outwards and circles around the polygon vertices.
+    let movement_relative = movement_b - movement_a;
+    if movement_relative.is_effectively_zero() {
+        return None;
+    }
+    let path = Line::new(b.center, b.center + movement_relative);
+
+    let mut result: Option<(f32, Vec2)> = None;
+    let mut consider_hit = |time: f32, normal: Vec2| {
+        let is_earlier = match result {
+            Some((time_best, _)) => time < time_best,
+            None => true,
+        };
+        if is_earlier {
+            result = Some((time, normal));
+        }
+    };
+    for index in 0..a.vertices.len() {
+        let normal = a.edge_normal_outward(index);
+        let edge_inflated = a.edge(index).translated_by(b.radius * normal);
+        if let Some(hit) = intersection_line_line(path, edge_inflated) {
+            // NOTE: We only care about the circle entering the edge from the outside
+            if Vec2::dot(movement_relative, normal) < 0.0 {
+                consider_hit(hit.time, normal);
+            }
+        }
+        let (hit, _exit) =
+            intersections_line_circle(path, Circle::new(a.vertices[index], b.radius));
+        if let Some(hit) = hit {
+            consider_hit(hit.time, (hit.point - a.vertices[index]).normalized());
+        }
+    }
+
+    result.map(|(time, normal)| {
+        let center_b = b.center + time * movement_b;
+        Intersection {
+            point: center_b - b.radius * normal,
+            normal,
+            time,
+        }
+    })
+}
+
+#[inline]
+pub fn sweep_circle_polygon(
+    a: Circle,
+    movement_a: Vec2,
+    b: &ConvexPolygon,
+    movement_b: Vec2,
+) -> Option<Intersection> {
+    sweep_polygon_circle(b, movement_b, a, movement_a).map(|intersection| Intersection {
+        normal: -intersection.normal,
+        ..intersection
+    })
+}
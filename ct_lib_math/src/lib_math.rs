@@ -23,10 +23,17 @@ pub mod easing;
 mod random;
 pub use random::*;
 
+mod noise;
+pub use noise::*;
+
+mod spline;
+pub use spline::*;
+
 pub type Point = Vec2;
 pub type Pointi = Vec2i;
 
 use num_traits::Num;
+use serde_derive::{Deserialize, Serialize};
 
 //--------------------------------------------------------------------------------------------------
 // Misc
@@ -585,6 +592,22 @@ impl Line {
     pub fn normal(self) -> Vec2 {
         (self.end - self.start).perpendicular().normalized()
     }
+
+    /// Returns the distance of the given point to the line segment between `start` and `end`
+    #[inline]
+    pub fn distance_to_point(self, point: Point) -> f32 {
+        let dir = self.dir();
+        let length_squared = dir.magnitude_squared();
+        if length_squared == 0.0 {
+            return Vec2::distance(self.start, point);
+        }
+        let t = f32::clamp(
+            Vec2::dot(point - self.start, dir) / length_squared,
+            0.0,
+            1.0,
+        );
+        Vec2::distance(self.intersection_point(t), point)
+    }
 }
 
 #[inline]
@@ -628,6 +651,85 @@ pub fn iterate_line_bresenham<FunctorType: FnMut(i32, i32)>(
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Polyline simplification
+
+/// Removes points of a polyline that deviate less than `tolerance` from the simplified polyline
+/// using the Ramer-Douglas-Peucker algorithm. The first and last point are always kept.
+pub fn polyline_simplified(points: &[Point], tolerance: f32) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let index_last = points.len() - 1;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[index_last] = true;
+
+    let mut ranges = vec![(0, index_last)];
+    while let Some((index_start, index_end)) = ranges.pop() {
+        let segment = Line::new(points[index_start], points[index_end]);
+        let mut distance_max = 0.0;
+        let mut index_max = index_start;
+        for (index, &point) in points
+            .iter()
+            .enumerate()
+            .take(index_end)
+            .skip(index_start + 1)
+        {
+            let distance = segment.distance_to_point(point);
+            if distance > distance_max {
+                distance_max = distance;
+                index_max = index;
+            }
+        }
+        if distance_max > tolerance {
+            keep[index_max] = true;
+            ranges.push((index_start, index_max));
+            ranges.push((index_max, index_end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep.iter())
+        .filter(|(_point, &keep)| keep)
+        .map(|(&point, _keep)| point)
+        .collect()
+}
+
+/// Same as `polyline_simplified` but for closed polygons where the last point connects to the
+/// first point
+pub fn polygon_simplified(points: &[Point], tolerance: f32) -> Vec<Point> {
+    if points.len() < 4 {
+        return points.to_vec();
+    }
+
+    // NOTE: We split the polygon into two polylines at the point farthest away from the first point
+    let index_farthest = (1..points.len())
+        .max_by(|&a, &b| {
+            compare_floats(
+                Vec2::distance_squared(points[0], points[a]),
+                Vec2::distance_squared(points[0], points[b]),
+            )
+        })
+        .unwrap();
+    let mut result = polyline_simplified(&points[..=index_farthest], tolerance);
+    let mut polyline_closing = points[index_farthest..].to_vec();
+    polyline_closing.push(points[0]);
+    let polyline_closing = polyline_simplified(&polyline_closing, tolerance);
+    result.extend_from_slice(&polyline_closing[1..polyline_closing.len() - 1]);
+
+    // NOTE: The first point is always kept by the above so we check it separately
+    if result.len() > 3 {
+        let segment = Line::new(result[result.len() - 1], result[1]);
+        if segment.distance_to_point(result[0]) <= tolerance {
+            result.remove(0);
+        }
+    }
+    result
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Circle
 
@@ -1203,23 +1305,140 @@ impl Interval {
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
-/// Easing
-
-#[derive(Debug, Copy, Clone)]
+// Easing
+
+/// Position of the jumps of `EasingType::Steps` (see CSS `steps()`)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepPosition {
+    /// The first jump happens at the start
+    JumpStart,
+    /// The last jump happens at the end
+    JumpEnd,
+    /// Starts at 0 and ends at 1 without jumping at the start or end
+    JumpNone,
+    /// Jumps at the start and at the end
+    JumpBoth,
+}
+
+/// NOTE: The overshooting easings (`Back*` and `Elastic*`) leave the range [0, 1] in between
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EasingType {
+    #[default]
     Linear,
+    SineIn,
+    SineOut,
+    SineInOut,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
     CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
     StepMiddle,
     StepEnd,
+    Steps {
+        count: u32,
+        position: StepPosition,
+    },
+    /// See CSS `cubic-bezier(x1, y1, x2, y2)`
+    CubicBezier {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+}
+
+impl EasingType {
+    /// Same as CSS `ease`
+    pub const CSS_EASE: EasingType = EasingType::CubicBezier {
+        x1: 0.25,
+        y1: 0.1,
+        x2: 0.25,
+        y2: 1.0,
+    };
+    /// Same as CSS `ease-in`
+    pub const CSS_EASE_IN: EasingType = EasingType::CubicBezier {
+        x1: 0.42,
+        y1: 0.0,
+        x2: 1.0,
+        y2: 1.0,
+    };
+    /// Same as CSS `ease-out`
+    pub const CSS_EASE_OUT: EasingType = EasingType::CubicBezier {
+        x1: 0.0,
+        y1: 0.0,
+        x2: 0.58,
+        y2: 1.0,
+    };
+    /// Same as CSS `ease-in-out`
+    pub const CSS_EASE_IN_OUT: EasingType = EasingType::CubicBezier {
+        x1: 0.42,
+        y1: 0.0,
+        x2: 0.58,
+        y2: 1.0,
+    };
 }
 
 #[inline]
 pub fn ease(percent: f32, easing_type: EasingType) -> f32 {
     match easing_type {
         EasingType::Linear => percent,
+        EasingType::SineIn => easing::sine_in(percent),
+        EasingType::SineOut => easing::sine_out(percent),
+        EasingType::SineInOut => easing::sine_inout(percent),
+        EasingType::QuadIn => easing::quad_in(percent),
+        EasingType::QuadOut => easing::quad_out(percent),
+        EasingType::QuadInOut => easing::quad_inout(percent),
+        EasingType::CubicIn => easing::cubic_in(percent),
+        EasingType::CubicOut => easing::cubic_out(percent),
         EasingType::CubicInOut => easing::cubic_inout(percent),
+        EasingType::QuartIn => easing::quart_in(percent),
+        EasingType::QuartOut => easing::quart_out(percent),
+        EasingType::QuartInOut => easing::quart_inout(percent),
+        EasingType::QuintIn => easing::quint_in(percent),
+        EasingType::QuintOut => easing::quint_out(percent),
+        EasingType::QuintInOut => easing::quint_inout(percent),
+        EasingType::ExpoIn => easing::expo_in(percent),
+        EasingType::ExpoOut => easing::expo_out(percent),
+        EasingType::ExpoInOut => easing::expo_inout(percent),
+        EasingType::CircIn => easing::circ_in(percent),
+        EasingType::CircOut => easing::circ_out(percent),
+        EasingType::CircInOut => easing::circ_inout(percent),
+        EasingType::BackIn => easing::back_in(percent),
+        EasingType::BackOut => easing::back_out(percent),
+        EasingType::BackInOut => easing::back_inout(percent),
+        EasingType::ElasticIn => easing::elastic_in(percent),
+        EasingType::ElasticOut => easing::elastic_out(percent),
+        EasingType::ElasticInOut => easing::elastic_inout(percent),
+        EasingType::BounceIn => easing::bounce_in(percent),
+        EasingType::BounceOut => easing::bounce_out(percent),
+        EasingType::BounceInOut => easing::bounce_inout(percent),
         EasingType::StepMiddle => easing::step_middle(percent),
         EasingType::StepEnd => easing::step_end(percent),
+        EasingType::Steps { count, position } => easing::steps(percent, count, position),
+        EasingType::CubicBezier { x1, y1, x2, y2 } => easing::cubic_bezier(percent, x1, y1, x2, y2),
     }
 }
 
@@ -1399,4 +1618,477 @@ mod tests {
         assert_eq!(floor_to_multiple_of_target_i32(19, 5), 15);
         assert_eq!(floor_to_multiple_of_target_i32(20, 5), 20);
     }
+
+    #[test]
+    fn polyline_and_polygon_simplification() {
+        let polyline = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.1),
+            Vec2::new(2.0, -0.1),
+            Vec2::new(3.0, 5.0),
+            Vec2::new(4.0, 6.0),
+            Vec2::new(5.0, 7.0),
+        ];
+        assert_eq!(
+            polyline_simplified(&polyline, 0.5),
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, -0.1),
+                Vec2::new(3.0, 5.0),
+                Vec2::new(5.0, 7.0),
+            ]
+        );
+        // NOTE: Only the exactly collinear point is removed
+        assert_eq!(
+            polyline_simplified(&polyline, 0.0).len(),
+            polyline.len() - 1
+        );
+
+        // NOTE: A square with extra points on its edges and a starting point in the middle of an
+        //       edge simplifies to its four corners
+        let polygon = [
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(0.0, 2.0),
+            Vec2::new(0.0, 1.0),
+            Vec2::new(0.0, 0.0),
+        ];
+        assert_eq!(
+            polygon_simplified(&polygon, 0.01),
+            vec![
+                Vec2::new(2.0, 0.0),
+                Vec2::new(2.0, 2.0),
+                Vec2::new(0.0, 2.0),
+                Vec2::new(0.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn noise_is_deterministic_bounded_and_continuous() {
+        let noise = Noise::new(&mut Random::new_from_seed(1234));
+        let noise_same = Noise::new(&mut Random::new_from_seed(1234));
+        let noise_other = Noise::new(&mut Random::new_from_seed(4321));
+        let noise_types = [
+            NoiseType::Value,
+            NoiseType::Perlin,
+            NoiseType::OpenSimplex,
+            NoiseType::Worley,
+        ];
+
+        let mut random = Random::new_from_seed(99);
+        for _ in 0..1000 {
+            let x = random.f32_in_range(-50.0, 50.0);
+            let y = random.f32_in_range(-50.0, 50.0);
+            let z = random.f32_in_range(-50.0, 50.0);
+            for &noise_type in &noise_types {
+                let value_min = if noise_type == NoiseType::Worley {
+                    0.0
+                } else {
+                    -1.0
+                };
+                for &value in &[
+                    noise.sample_1d(noise_type, x),
+                    noise.sample_2d(noise_type, x, y),
+                    noise.sample_3d(noise_type, x, y, z),
+                ] {
+                    assert!(value_min <= value && value <= 1.0);
+                }
+                assert_eq!(
+                    noise.sample_3d(noise_type, x, y, z),
+                    noise_same.sample_3d(noise_type, x, y, z)
+                );
+
+                // NOTE: Nearby samples have similar values
+                let step = 0.001;
+                assert!(
+                    (noise.sample_2d(noise_type, x, y) - noise.sample_2d(noise_type, x + step, y))
+                        .abs()
+                        < 0.05
+                );
+                assert!(
+                    (noise.sample_3d(noise_type, x, y, z)
+                        - noise.sample_3d(noise_type, x, y, z + step))
+                    .abs()
+                        < 0.05
+                );
+            }
+
+            let params = FractalNoiseParams::default();
+            assert!(noise.fbm_2d(x, y, &params).abs() <= 1.0);
+            assert!(noise.fbm_domain_warped_3d(x, y, z, 0.5, &params).abs() <= 1.0);
+            let warped = noise.domain_warp_2d(x, y, 0.5, &params);
+            assert!((warped - Vec2::new(x, y)).magnitude() <= 0.5 * std::f32::consts::SQRT_2);
+        }
+
+        assert_eq!(noise.perlin_2d(3.0, -7.0), 0.0);
+        let differing_count = (0..100)
+            .filter(|&index| {
+                let x = 0.37 * index as f32;
+                noise.opensimplex_2d(x, 0.5) != noise_other.opensimplex_2d(x, 0.5)
+            })
+            .count();
+        assert!(differing_count > 90);
+    }
+
+    #[test]
+    fn random_distributions_sampling_and_state() {
+        let mut random = Random::new_from_seed(42);
+        let sample_count = 20_000;
+
+        let mut sum = 0.0;
+        for _ in 0..sample_count {
+            let value = random.f32();
+            assert!((0.0..1.0).contains(&value));
+            let value = random.triangular(1.0, 2.0, 5.0);
+            assert!((1.0..=5.0).contains(&value));
+            let value = random.exponential(2.0);
+            assert!(value >= 0.0);
+            sum += random.gaussian(3.0, 0.5);
+        }
+        assert!((sum / sample_count as f32 - 3.0).abs() < 0.05);
+
+        let mut counts = [0; 3];
+        let table = WeightedTable::new(&[(0, 1.0), (1, 0.0), (2, 3.0)]);
+        for _ in 0..sample_count {
+            counts[table.pick(&mut random)] += 1;
+            counts[random.pick_from_slice_weighted(&[(0, 1.0), (1, 0.0), (2, 3.0)])] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        let ratio = counts[2] as f32 / counts[0] as f32;
+        assert!((ratio - 3.0).abs() < 0.3);
+        let table = WeightedTable::new(&[(0, 0.1), (1, 0.2), (2, 0.0)]);
+        for _ in 0..sample_count {
+            assert_ne!(table.pick(&mut random), 2);
+        }
+
+        let picked = random.reservoir_sample(0..1000, 10);
+        assert_eq!(picked.len(), 10);
+        assert!(picked.iter().all(|&value| value < 1000));
+        assert_eq!(random.reservoir_sample(0..5, 10), vec![0, 1, 2, 3, 4]);
+
+        let rect = Rect::from_xy_width_height(10.0, 20.0, 100.0, 50.0);
+        let points = random.vec2_poisson_disk_samples_in_rect(rect, 5.0);
+        assert!(points.len() > 50);
+        // NOTE: Rects that are not a multiple of the cell size must not produce out of range cells
+        for seed in 0..20 {
+            let rect = Rect::from_xy_width_height(0.1, 0.3, 7.3, 3.7);
+            Random::new_from_seed(seed).vec2_poisson_disk_samples_in_rect(rect, 0.7);
+        }
+        for (index, &a) in points.iter().enumerate() {
+            assert!(a.x >= rect.left() && a.x < rect.right());
+            assert!(a.y >= rect.top() && a.y < rect.bottom());
+            for &b in &points[index + 1..] {
+                assert!(Vec2::distance_squared(a, b) >= 25.0);
+            }
+        }
+
+        // NOTE: Substreams do not depend on the state of their parent
+        let mut master = Random::new_from_seed(7);
+        let mut enemies = master.substream("enemies");
+        master.u32();
+        assert_eq!(master.substream("enemies").u32(), enemies.u32());
+        assert_ne!(
+            master.substream("loot").u32(),
+            master.substream("enemies").u32()
+        );
+
+        // NOTE: A restored generator continues with the exact same sequence
+        let state = enemies.state();
+        let expected: Vec<u32> = (0..10).map(|_| enemies.u32()).collect();
+        let mut restored = Random::new_from_state(state);
+        assert_eq!(
+            (0..10).map(|_| restored.u32()).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn splines_and_path_following() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ];
+
+        // NOTE: Catmull-Rom curves pass through all points
+        let catmull_rom = Spline::new_catmull_rom(&points, false);
+        assert_eq!(catmull_rom.segment_count(), 3);
+        for (index, &point) in points.iter().enumerate() {
+            let parameter = index as f32 / 3.0;
+            assert!(Vec2::distance(catmull_rom.point(parameter), point) < 0.001);
+        }
+
+        // NOTE: A straight Bézier curve has the length of its line and constant speed by distance
+        let line = Spline::new_cubic_bezier(&[
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(9.0, 0.0),
+            Vec2::new(10.0, 0.0),
+        ]);
+        assert!((line.length() - 10.0).abs() < 0.001);
+        assert!(Vec2::distance(line.point_at_distance(2.5), Vec2::new(2.5, 0.0)) < 0.01);
+        assert_eq!(line.tangent(0.3), Vec2::unit_x());
+        let samples = line.sampled_uniformly(5);
+        assert!(Vec2::distance(samples[1], Vec2::new(2.5, 0.0)) < 0.01);
+        assert_eq!(line.sampled_with_spacing(3.0).len(), 4);
+
+        let projection = line.closest_point(Vec2::new(4.0, 3.0));
+        assert!(Vec2::distance(projection.point, Vec2::new(4.0, 0.0)) < 0.01);
+        assert!((projection.distance - 4.0).abs() < 0.01);
+
+        // NOTE: Open B-splines start and end at the end points, closed ones are periodic
+        let bspline = Spline::new_bspline(&points, false);
+        assert!(Vec2::distance(bspline.point(0.0), points[0]) < 0.001);
+        assert!(Vec2::distance(bspline.point(1.0), points[3]) < 0.001);
+        let bspline_closed = Spline::new_bspline(&points, true);
+        assert!(Vec2::distance(bspline_closed.point(0.0), bspline_closed.point(1.0)) < 0.001);
+        assert!(Vec2::dot(bspline_closed.normal(0.25), bspline_closed.tangent(0.25)).abs() < 0.001);
+
+        let mut follower = PathFollower::new(4.0, PathFollowMode::Once);
+        follower.update(&line, 1.0);
+        assert!(Vec2::distance(follower.pos(&line), Vec2::new(4.0, 0.0)) < 0.01);
+        follower.update(&line, 2.0);
+        assert!(follower.is_finished(&line));
+
+        let mut follower = PathFollower::new(4.0, PathFollowMode::PingPong);
+        follower.update(&line, 3.0);
+        assert!((follower.distance - 8.0).abs() < 0.001);
+        assert_eq!(follower.dir(&line), -Vec2::unit_x());
+        follower.update(&line, 4.0);
+        assert!((follower.distance - 8.0).abs() < 0.01);
+        assert_eq!(follower.dir(&line), Vec2::unit_x());
+        // NOTE: Huge steps that overshoot the path many times must not take forever
+        follower.update(&line, 1.0e9);
+        assert!(0.0 <= follower.distance && follower.distance <= line.length());
+
+        let mut follower = PathFollower::new(4.0, PathFollowMode::Loop);
+        follower.update(&line, 3.0);
+        assert!((follower.distance - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn easing_endpoints_monotonicity_steps_and_cubic_bezier() {
+        let monotonic = [
+            EasingType::Linear,
+            EasingType::SineIn,
+            EasingType::SineOut,
+            EasingType::SineInOut,
+            EasingType::QuadIn,
+            EasingType::QuadOut,
+            EasingType::QuadInOut,
+            EasingType::CubicIn,
+            EasingType::CubicOut,
+            EasingType::CubicInOut,
+            EasingType::QuartIn,
+            EasingType::QuartOut,
+            EasingType::QuartInOut,
+            EasingType::QuintIn,
+            EasingType::QuintOut,
+            EasingType::QuintInOut,
+            EasingType::ExpoIn,
+            EasingType::ExpoOut,
+            EasingType::ExpoInOut,
+            EasingType::CircIn,
+            EasingType::CircOut,
+            EasingType::CircInOut,
+            EasingType::StepMiddle,
+            EasingType::StepEnd,
+            EasingType::Steps {
+                count: 4,
+                position: StepPosition::JumpNone,
+            },
+            EasingType::CSS_EASE,
+            EasingType::CSS_EASE_IN,
+            EasingType::CSS_EASE_OUT,
+            EasingType::CSS_EASE_IN_OUT,
+        ];
+        let overshooting = [
+            EasingType::BackIn,
+            EasingType::BackOut,
+            EasingType::BackInOut,
+            EasingType::ElasticIn,
+            EasingType::ElasticOut,
+            EasingType::ElasticInOut,
+            EasingType::BounceIn,
+            EasingType::BounceOut,
+            EasingType::BounceInOut,
+        ];
+
+        for &easing_type in monotonic.iter().chain(overshooting.iter()) {
+            assert!(ease(0.0, easing_type).abs() < 0.001, "{:?}", easing_type);
+            assert!(
+                (ease(1.0, easing_type) - 1.0).abs() < 0.001,
+                "{:?}",
+                easing_type
+            );
+        }
+        for &easing_type in &monotonic {
+            let mut previous = ease(0.0, easing_type);
+            for index in 1..=1000 {
+                let value = ease(index as f32 / 1000.0, easing_type);
+                assert!(value >= previous - 0.000_01, "{:?}", easing_type);
+                assert!((-0.000_01..=1.000_01).contains(&value), "{:?}", easing_type);
+                previous = value;
+            }
+        }
+
+        let steps = |percent, position| easing::steps(percent, 4, position);
+        assert_eq!(steps(0.0, StepPosition::JumpStart), 0.25);
+        assert_eq!(steps(0.3, StepPosition::JumpStart), 0.5);
+        assert_eq!(steps(0.3, StepPosition::JumpEnd), 0.25);
+        assert_eq!(steps(0.3, StepPosition::JumpNone), 1.0 / 3.0);
+        assert_eq!(steps(0.3, StepPosition::JumpBoth), 0.4);
+        assert_eq!(steps(1.0, StepPosition::JumpEnd), 1.0);
+        assert_eq!(easing::steps(0.7, 0, StepPosition::JumpEnd), 0.0);
+        assert_eq!(easing::steps(1.0, 0, StepPosition::JumpEnd), 1.0);
+
+        // NOTE: Reference values from browser implementations of CSS `ease`
+        assert!((ease(0.25, EasingType::CSS_EASE) - 0.4085).abs() < 0.001);
+        assert!((ease(0.5, EasingType::CSS_EASE) - 0.8024).abs() < 0.001);
+        let identity = EasingType::CubicBezier {
+            x1: 0.25,
+            y1: 0.25,
+            x2: 0.75,
+            y2: 0.75,
+        };
+        for index in 0..=10 {
+            let percent = index as f32 / 10.0;
+            assert!((ease(percent, identity) - percent).abs() < 0.001);
+        }
+        // NOTE: Curves with y outside [0, 1] overshoot
+        let overshoot = EasingType::CubicBezier {
+            x1: 0.3,
+            y1: -0.5,
+            x2: 0.7,
+            y2: 1.5,
+        };
+        assert!(ease(0.1, overshoot) < 0.0);
+        assert!(ease(0.9, overshoot) > 1.0);
+    }
+
+    #[test]
+    fn collision_manifolds_and_time_of_impact() {
+        let is_near = |a: Vec2, b: Vec2| Vec2::distance(a, b) < 0.001;
+
+        // Circles
+        let circle_a = Circle::new(Vec2::new(0.0, 0.0), 2.0);
+        let circle_b = Circle::new(Vec2::new(3.0, 0.0), 2.0);
+        let manifold = collide_circle_circle(circle_a, circle_b).unwrap();
+        assert!(is_near(manifold.normal, Vec2::new(1.0, 0.0)));
+        assert!((manifold.depth - 1.0).abs() < 0.001);
+        assert!(is_near(manifold.contacts()[0], Vec2::new(1.5, 0.0)));
+        assert!(collide_circle_circle(circle_a, Circle::new(Vec2::new(5.0, 0.0), 2.0)).is_none());
+        assert!(intersects_circle_circle(circle_a, circle_b));
+
+        // Axis aligned boxes touching along an edge yield two contacts
+        let box_a = ConvexPolygon::from_rect(Rect::from_xy_width_height(0.0, 0.0, 4.0, 4.0));
+        let box_b = ConvexPolygon::from_rect(Rect::from_xy_width_height(3.0, 1.0, 4.0, 4.0));
+        let manifold = collide_polygon_polygon(&box_a, &box_b).unwrap();
+        assert!(is_near(manifold.normal, Vec2::new(1.0, 0.0)));
+        assert!((manifold.depth - 1.0).abs() < 0.001);
+        assert_eq!(manifold.contacts().len(), 2);
+        for &contact in manifold.contacts() {
+            assert!(contact.x >= 3.0 - 0.001 && contact.x <= 4.0 + 0.001);
+            assert!(contact.y >= 1.0 - 0.001 && contact.y <= 4.0 + 0.001);
+        }
+        let flipped = collide_polygon_polygon(&box_b, &box_a).unwrap();
+        assert!(is_near(flipped.normal, Vec2::new(-1.0, 0.0)));
+        assert!(
+            collide_polygon_polygon(&box_a, &box_b.translated_by(Vec2::new(2.0, 0.0))).is_none()
+        );
+
+        // An oriented box touching with its corner yields a single contact
+        let diamond = ConvexPolygon::from_quad(Quad::from_rect_transformed(
+            Vec2::new(2.0, 2.0),
+            Vec2::new(1.0, 1.0),
+            Transform {
+                pos: Vec2::new(5.0, 2.0),
+                scale: Vec2::new(1.0, 1.0),
+                dir_angle: 45.0,
+            },
+        ));
+        let manifold = collide_polygon_polygon(&box_a, &diamond).unwrap();
+        assert_eq!(manifold.contacts().len(), 1);
+        assert!(is_near(manifold.normal, Vec2::new(1.0, 0.0)));
+        assert!((manifold.depth - (std::f32::consts::SQRT_2 - 1.0)).abs() < 0.001);
+
+        // Polygon and circle
+        let manifold =
+            collide_polygon_circle(&box_a, Circle::new(Vec2::new(5.0, 2.0), 1.5)).unwrap();
+        assert!(is_near(manifold.normal, Vec2::new(1.0, 0.0)));
+        assert!((manifold.depth - 0.5).abs() < 0.001);
+        assert!(is_near(manifold.contacts()[0], Vec2::new(4.0, 2.0)));
+        let manifold =
+            collide_circle_polygon(Circle::new(Vec2::new(3.5, 2.0), 1.0), &box_a).unwrap();
+        assert!(is_near(manifold.normal, Vec2::new(-1.0, 0.0)));
+        assert!((manifold.depth - 1.5).abs() < 0.001);
+        assert!(collide_polygon_circle(&box_a, Circle::new(Vec2::new(5.0, 5.0), 1.0)).is_none());
+
+        // Swept circles
+        let hit = sweep_circle_circle(
+            Circle::new(Vec2::new(0.0, 0.0), 1.0),
+            Vec2::new(10.0, 0.0),
+            Circle::new(Vec2::new(10.0, 0.0), 1.0),
+            Vec2::zero(),
+        )
+        .unwrap();
+        assert!((hit.time - 0.8).abs() < 0.001);
+        assert!(is_near(hit.normal, Vec2::new(1.0, 0.0)));
+        assert!(is_near(hit.point, Vec2::new(9.0, 0.0)));
+        assert!(sweep_circle_circle(
+            Circle::new(Vec2::new(0.0, 0.0), 1.0),
+            Vec2::new(5.0, 0.0),
+            Circle::new(Vec2::new(10.0, 0.0), 1.0),
+            Vec2::zero(),
+        )
+        .is_none());
+
+        // Swept polygons moving towards each other
+        let box_far = box_b.translated_by(Vec2::new(7.0, 0.0));
+        let hit =
+            sweep_polygon_polygon(&box_a, Vec2::new(5.0, 0.0), &box_far, Vec2::new(-5.0, 0.0))
+                .unwrap();
+        assert!((hit.time - 0.6).abs() < 0.001);
+        assert!(is_near(hit.normal, Vec2::new(1.0, 0.0)));
+        assert!((hit.point.x - 7.0).abs() < 0.001);
+        assert!(
+            sweep_polygon_polygon(&box_a, Vec2::new(0.0, 5.0), &box_far, Vec2::zero()).is_none()
+        );
+        let hit = sweep_polygon_polygon(&box_a, Vec2::zero(), &box_b, Vec2::zero()).unwrap();
+        assert_eq!(hit.time, 0.0);
+
+        // Swept polygon and circle, including hitting a corner
+        let hit = sweep_circle_polygon(
+            Circle::new(Vec2::new(10.0, 2.0), 1.0),
+            Vec2::new(-10.0, 0.0),
+            &box_a,
+            Vec2::zero(),
+        )
+        .unwrap();
+        assert!((hit.time - 0.5).abs() < 0.001);
+        assert!(is_near(hit.normal, Vec2::new(-1.0, 0.0)));
+        assert!(is_near(hit.point, Vec2::new(4.0, 2.0)));
+        let hit = sweep_polygon_circle(
+            &box_a,
+            Vec2::zero(),
+            Circle::new(Vec2::new(10.0, 10.0), 1.0),
+            Vec2::new(-10.0, -10.0),
+        )
+        .unwrap();
+        let corner_normal = Vec2::new(1.0, 1.0).normalized();
+        assert!(is_near(hit.normal, corner_normal));
+        assert!(is_near(hit.point, Vec2::new(4.0, 4.0)));
+        assert!(sweep_polygon_circle(
+            &box_a,
+            Vec2::zero(),
+            Circle::new(Vec2::new(10.0, 10.0), 1.0),
+            Vec2::new(-10.0, 0.0),
+        )
+        .is_none());
+    }
 }
@@ -0,0 +1,607 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Coherent noise
+//
+// Value, Perlin, OpenSimplex and Worley noise with fractal Brownian motion and domain warping.
+//
+// NOTE: All noise functions return values in [-1, 1] except for Worley noise which returns the
+//       distance to the nearest feature point in [0, 1]. All noise functions are deterministic for
+//       a given seed and sample position.
+//
+
+use super::*;
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoiseType {
+    Value,
+    Perlin,
+    OpenSimplex,
+    Worley,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FractalNoiseParams {
+    pub noise_type: NoiseType,
+    pub octaves: usize,
+    /// Frequency of the first octave
+    pub frequency: f32,
+    /// Frequency multiplier between successive octaves
+    pub lacunarity: f32,
+    /// Amplitude multiplier between successive octaves (also called persistence)
+    pub gain: f32,
+}
+
+impl Default for FractalNoiseParams {
+    fn default() -> Self {
+        FractalNoiseParams {
+            noise_type: NoiseType::Perlin,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Noise {
+    pub seed: u32,
+}
+
+impl Noise {
+    #[inline]
+    pub fn new(random: &mut Random) -> Noise {
+        Noise::new_from_seed(random.u32())
+    }
+
+    #[inline]
+    pub fn new_from_seed(seed: u32) -> Noise {
+        Noise { seed }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Dispatch by noise type
+
+    #[inline]
+    pub fn sample_1d(&self, noise_type: NoiseType, x: f32) -> f32 {
+        match noise_type {
+            NoiseType::Value => self.value_1d(x),
+            NoiseType::Perlin => self.perlin_1d(x),
+            NoiseType::OpenSimplex => self.opensimplex_1d(x),
+            NoiseType::Worley => self.worley_1d(x),
+        }
+    }
+
+    #[inline]
+    pub fn sample_2d(&self, noise_type: NoiseType, x: f32, y: f32) -> f32 {
+        match noise_type {
+            NoiseType::Value => self.value_2d(x, y),
+            NoiseType::Perlin => self.perlin_2d(x, y),
+            NoiseType::OpenSimplex => self.opensimplex_2d(x, y),
+            NoiseType::Worley => self.worley_2d(x, y),
+        }
+    }
+
+    #[inline]
+    pub fn sample_3d(&self, noise_type: NoiseType, x: f32, y: f32, z: f32) -> f32 {
+        match noise_type {
+            NoiseType::Value => self.value_3d(x, y, z),
+            NoiseType::Perlin => self.perlin_3d(x, y, z),
+            NoiseType::OpenSimplex => self.opensimplex_3d(x, y, z),
+            NoiseType::Worley => self.worley_3d(x, y, z),
+        }
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Value noise
+
+    /// Interpolates random values placed at integer coordinates
+    pub fn value_1d(&self, x: f32) -> f32 {
+        let x0 = floori(x);
+        let t = noise_fade(x - x0 as f32);
+        lerp(
+            self.lattice_value(x0, 0, 0),
+            self.lattice_value(x0 + 1, 0, 0),
+            t,
+        )
+    }
+
+    /// Interpolates random values placed at integer coordinates
+    pub fn value_2d(&self, x: f32, y: f32) -> f32 {
+        let x0 = floori(x);
+        let y0 = floori(y);
+        let tx = noise_fade(x - x0 as f32);
+        let ty = noise_fade(y - y0 as f32);
+        lerp(
+            lerp(
+                self.lattice_value(x0, y0, 0),
+                self.lattice_value(x0 + 1, y0, 0),
+                tx,
+            ),
+            lerp(
+                self.lattice_value(x0, y0 + 1, 0),
+                self.lattice_value(x0 + 1, y0 + 1, 0),
+                tx,
+            ),
+            ty,
+        )
+    }
+
+    /// Interpolates random values placed at integer coordinates
+    pub fn value_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        let x0 = floori(x);
+        let y0 = floori(y);
+        let z0 = floori(z);
+        let tx = noise_fade(x - x0 as f32);
+        let ty = noise_fade(y - y0 as f32);
+        let tz = noise_fade(z - z0 as f32);
+        let layer = |z: i32| {
+            lerp(
+                lerp(
+                    self.lattice_value(x0, y0, z),
+                    self.lattice_value(x0 + 1, y0, z),
+                    tx,
+                ),
+                lerp(
+                    self.lattice_value(x0, y0 + 1, z),
+                    self.lattice_value(x0 + 1, y0 + 1, z),
+                    tx,
+                ),
+                ty,
+            )
+        };
+        lerp(layer(z0), layer(z0 + 1), tz)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Perlin noise
+    //
+    // Based on https://mrl.cs.nyu.edu/~perlin/noise/
+
+    /// NOTE: Is always zero at integer coordinates
+    pub fn perlin_1d(&self, x: f32) -> f32 {
+        let x0 = floori(x);
+        let dx = x - x0 as f32;
+        let t = noise_fade(dx);
+        let value = lerp(
+            self.gradient_1d(x0) * dx,
+            self.gradient_1d(x0 + 1) * (dx - 1.0),
+            t,
+        );
+        f32::clamp(2.0 * value, -1.0, 1.0)
+    }
+
+    /// NOTE: Is always zero at integer coordinates
+    pub fn perlin_2d(&self, x: f32, y: f32) -> f32 {
+        let x0 = floori(x);
+        let y0 = floori(y);
+        let dx = x - x0 as f32;
+        let dy = y - y0 as f32;
+        let tx = noise_fade(dx);
+        let ty = noise_fade(dy);
+        let value = lerp(
+            lerp(
+                self.gradient_dot_2d(x0, y0, dx, dy),
+                self.gradient_dot_2d(x0 + 1, y0, dx - 1.0, dy),
+                tx,
+            ),
+            lerp(
+                self.gradient_dot_2d(x0, y0 + 1, dx, dy - 1.0),
+                self.gradient_dot_2d(x0 + 1, y0 + 1, dx - 1.0, dy - 1.0),
+                tx,
+            ),
+            ty,
+        );
+        f32::clamp(std::f32::consts::SQRT_2 * value, -1.0, 1.0)
+    }
+
+    /// NOTE: Is always zero at integer coordinates
+    pub fn perlin_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        let x0 = floori(x);
+        let y0 = floori(y);
+        let z0 = floori(z);
+        let dx = x - x0 as f32;
+        let dy = y - y0 as f32;
+        let dz = z - z0 as f32;
+        let tx = noise_fade(dx);
+        let ty = noise_fade(dy);
+        let layer = |z: i32, dz: f32| {
+            lerp(
+                lerp(
+                    self.gradient_dot_3d(x0, y0, z, dx, dy, dz),
+                    self.gradient_dot_3d(x0 + 1, y0, z, dx - 1.0, dy, dz),
+                    tx,
+                ),
+                lerp(
+                    self.gradient_dot_3d(x0, y0 + 1, z, dx, dy - 1.0, dz),
+                    self.gradient_dot_3d(x0 + 1, y0 + 1, z, dx - 1.0, dy - 1.0, dz),
+                    tx,
+                ),
+                ty,
+            )
+        };
+        let value = lerp(layer(z0, dz), layer(z0 + 1, dz - 1.0), noise_fade(dz));
+        f32::clamp(value, -1.0, 1.0)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // OpenSimplex noise
+    //
+    // Based on the OpenSimplex2 noise by K.jpg https://github.com/KdotJPG/OpenSimplex2
+    // NOTE: Compared to Perlin noise this has less visible axis aligned artifacts
+
+    /// NOTE: This is a slice of the 2D noise
+    #[inline]
+    pub fn opensimplex_1d(&self, x: f32) -> f32 {
+        self.opensimplex_2d(x, 0.0)
+    }
+
+    /// Sums the contributions of the three corners of the simplex (triangle) that contains the
+    /// sample position
+    pub fn opensimplex_2d(&self, x: f32, y: f32) -> f32 {
+        const SKEW: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+        const UNSKEW: f32 = -0.211_324_87; // (1 / sqrt(3) - 1) / 2
+
+        // Transform to the skewed lattice where simplices are half squares
+        let skew = SKEW * (x + y);
+        let x_skewed = x + skew;
+        let y_skewed = y + skew;
+        let x0 = floori(x_skewed);
+        let y0 = floori(y_skewed);
+        let x_inner = x_skewed - x0 as f32;
+        let y_inner = y_skewed - y0 as f32;
+
+        // Offset from the first corner in unskewed space
+        let unskew = UNSKEW * (x_inner + y_inner);
+        let dx0 = x_inner + unskew;
+        let dy0 = y_inner + unskew;
+
+        let mut value = self.opensimplex_contribution_2d(x0, y0, dx0, dy0);
+        value += self.opensimplex_contribution_2d(
+            x0 + 1,
+            y0 + 1,
+            dx0 - (1.0 + 2.0 * UNSKEW),
+            dy0 - (1.0 + 2.0 * UNSKEW),
+        );
+        value += if dy0 > dx0 {
+            self.opensimplex_contribution_2d(x0, y0 + 1, dx0 - UNSKEW, dy0 - (1.0 + UNSKEW))
+        } else {
+            self.opensimplex_contribution_2d(x0 + 1, y0, dx0 - (1.0 + UNSKEW), dy0 - UNSKEW)
+        };
+        f32::clamp(99.0 * value, -1.0, 1.0)
+    }
+
+    /// Sums the contributions of the nearby vertices of a body-centered cubic lattice. The lattice
+    /// consists of two interleaved cubic lattices which are offset by half a cell.
+    pub fn opensimplex_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        // NOTE: This rotation moves the main diagonal of the lattice onto the z-axis which hides
+        //       the lattice structure better in xy-slices
+        let rotation = (2.0 / 3.0) * (x + y + z);
+        let x_rotated = rotation - x;
+        let y_rotated = rotation - y;
+        let z_rotated = rotation - z;
+
+        let mut value = 0.0;
+        for &(offset, seed_offset) in &[(0.0, 0), (0.5, OPENSIMPLEX_SECOND_LATTICE_SEED)] {
+            let xs = x_rotated + offset;
+            let ys = y_rotated + offset;
+            let zs = z_rotated + offset;
+            let x0 = floori(xs);
+            let y0 = floori(ys);
+            let z0 = floori(zs);
+            // NOTE: With our falloff radius only the corners of the enclosing cell can contribute
+            for corner in 0..8 {
+                let corner_x = corner & 1;
+                let corner_y = (corner >> 1) & 1;
+                let corner_z = corner >> 2;
+                let dx = xs - (x0 + corner_x) as f32;
+                let dy = ys - (y0 + corner_y) as f32;
+                let dz = zs - (z0 + corner_z) as f32;
+                let falloff = OPENSIMPLEX_RADIUS_SQUARED_3D - dx * dx - dy * dy - dz * dz;
+                if falloff > 0.0 {
+                    let hash = noise_hash(
+                        self.seed ^ seed_offset,
+                        x0 + corner_x,
+                        y0 + corner_y,
+                        z0 + corner_z,
+                    );
+                    value += squared(squared(falloff)) * gradient_dot_3d(hash, dx, dy, dz);
+                }
+            }
+        }
+        f32::clamp(32.0 * value, -1.0, 1.0)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Worley noise
+
+    /// Returns the distance to the nearest of randomly placed feature points where every integer
+    /// cell contains exactly one feature point
+    pub fn worley_1d(&self, x: f32) -> f32 {
+        let x0 = floori(x);
+        let mut distance_min = f32::MAX;
+        for cell_x in (x0 - 1)..=(x0 + 1) {
+            let hash = noise_hash(self.seed, cell_x, 0, 0);
+            let feature_x = cell_x as f32 + noise_hash_to_unit(hash);
+            distance_min = f32::min(distance_min, (feature_x - x).abs());
+        }
+        f32::clamp(distance_min, 0.0, 1.0)
+    }
+
+    /// Returns the distance to the nearest of randomly placed feature points where every integer
+    /// cell contains exactly one feature point
+    pub fn worley_2d(&self, x: f32, y: f32) -> f32 {
+        let x0 = floori(x);
+        let y0 = floori(y);
+        let mut distance_min_squared = f32::MAX;
+        for cell_y in (y0 - 1)..=(y0 + 1) {
+            for cell_x in (x0 - 1)..=(x0 + 1) {
+                let hash_x = noise_hash(self.seed, cell_x, cell_y, 0);
+                let hash_y = noise_hash_mix(hash_x);
+                let dx = cell_x as f32 + noise_hash_to_unit(hash_x) - x;
+                let dy = cell_y as f32 + noise_hash_to_unit(hash_y) - y;
+                distance_min_squared = f32::min(distance_min_squared, dx * dx + dy * dy);
+            }
+        }
+        f32::clamp(distance_min_squared.sqrt(), 0.0, 1.0)
+    }
+
+    /// Returns the distance to the nearest of randomly placed feature points where every integer
+    /// cell contains exactly one feature point
+    pub fn worley_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        let x0 = floori(x);
+        let y0 = floori(y);
+        let z0 = floori(z);
+        let mut distance_min_squared = f32::MAX;
+        for cell_z in (z0 - 1)..=(z0 + 1) {
+            for cell_y in (y0 - 1)..=(y0 + 1) {
+                for cell_x in (x0 - 1)..=(x0 + 1) {
+                    let hash_x = noise_hash(self.seed, cell_x, cell_y, cell_z);
+                    let hash_y = noise_hash_mix(hash_x);
+                    let hash_z = noise_hash_mix(hash_y);
+                    let dx = cell_x as f32 + noise_hash_to_unit(hash_x) - x;
+                    let dy = cell_y as f32 + noise_hash_to_unit(hash_y) - y;
+                    let dz = cell_z as f32 + noise_hash_to_unit(hash_z) - z;
+                    distance_min_squared =
+                        f32::min(distance_min_squared, dx * dx + dy * dy + dz * dz);
+                }
+            }
+        }
+        f32::clamp(distance_min_squared.sqrt(), 0.0, 1.0)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Fractal Brownian motion
+
+    /// Sums multiple octaves of noise with increasing frequency and decreasing amplitude. The
+    /// result is normalized to the value range of a single octave.
+    pub fn fbm_1d(&self, x: f32, params: &FractalNoiseParams) -> f32 {
+        self.fbm(params, |noise, frequency| {
+            noise.sample_1d(params.noise_type, frequency * x)
+        })
+    }
+
+    /// Sums multiple octaves of noise with increasing frequency and decreasing amplitude. The
+    /// result is normalized to the value range of a single octave.
+    pub fn fbm_2d(&self, x: f32, y: f32, params: &FractalNoiseParams) -> f32 {
+        self.fbm(params, |noise, frequency| {
+            noise.sample_2d(params.noise_type, frequency * x, frequency * y)
+        })
+    }
+
+    /// Sums multiple octaves of noise with increasing frequency and decreasing amplitude. The
+    /// result is normalized to the value range of a single octave.
+    pub fn fbm_3d(&self, x: f32, y: f32, z: f32, params: &FractalNoiseParams) -> f32 {
+        self.fbm(params, |noise, frequency| {
+            noise.sample_3d(
+                params.noise_type,
+                frequency * x,
+                frequency * y,
+                frequency * z,
+            )
+        })
+    }
+
+    fn fbm<SampleFunc>(&self, params: &FractalNoiseParams, sample: SampleFunc) -> f32
+    where
+        SampleFunc: Fn(&Noise, f32) -> f32,
+    {
+        assert!(params.octaves > 0);
+        let mut result = 0.0;
+        let mut amplitude = 1.0;
+        let mut amplitude_sum = 0.0;
+        let mut frequency = params.frequency;
+        for octave in 0..params.octaves {
+            // NOTE: We use a different seed per octave so that the lattices of the octaves do not
+            //       line up at the origin
+            let octave_noise = Noise::new_from_seed(
+                self.seed
+                    .wrapping_add((octave as u32).wrapping_mul(NOISE_OCTAVE_SEED_STEP)),
+            );
+            result += amplitude * sample(&octave_noise, frequency);
+            amplitude_sum += amplitude;
+            amplitude *= params.gain;
+            frequency *= params.lacunarity;
+        }
+        result / amplitude_sum
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Domain warping
+    //
+    // Based on https://iquilezles.org/articles/warp/
+
+    /// Returns the given position displaced by up to `warp_strength` using fractal noise
+    pub fn domain_warp_2d(
+        &self,
+        x: f32,
+        y: f32,
+        warp_strength: f32,
+        params: &FractalNoiseParams,
+    ) -> Vec2 {
+        // NOTE: The arbitrary offsets decorrelate the displacements of the axes
+        Vec2::new(
+            x + warp_strength * self.fbm_2d(x, y, params),
+            y + warp_strength * self.fbm_2d(x + 5.2, y + 1.3, params),
+        )
+    }
+
+    /// Returns the given position displaced by up to `warp_strength` using fractal noise
+    pub fn domain_warp_3d(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+        warp_strength: f32,
+        params: &FractalNoiseParams,
+    ) -> Vec3 {
+        // NOTE: The arbitrary offsets decorrelate the displacements of the axes
+        Vec3::new(
+            x + warp_strength * self.fbm_3d(x, y, z, params),
+            y + warp_strength * self.fbm_3d(x + 5.2, y + 1.3, z + 2.8, params),
+            z + warp_strength * self.fbm_3d(x + 9.7, y + 4.1, z + 7.4, params),
+        )
+    }
+
+    /// Samples fractal noise at a position that was displaced by fractal noise
+    pub fn fbm_domain_warped_2d(
+        &self,
+        x: f32,
+        y: f32,
+        warp_strength: f32,
+        params: &FractalNoiseParams,
+    ) -> f32 {
+        let warped = self.domain_warp_2d(x, y, warp_strength, params);
+        self.fbm_2d(warped.x, warped.y, params)
+    }
+
+    /// Samples fractal noise at a position that was displaced by fractal noise
+    pub fn fbm_domain_warped_3d(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+        warp_strength: f32,
+        params: &FractalNoiseParams,
+    ) -> f32 {
+        let warped = self.domain_warp_3d(x, y, z, warp_strength, params);
+        self.fbm_3d(warped.x, warped.y, warped.z, params)
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Lattice helpers
+
+    /// Returns a random value in [-1, 1]
+    #[inline]
+    fn lattice_value(&self, x: i32, y: i32, z: i32) -> f32 {
+        2.0 * noise_hash_to_unit(noise_hash(self.seed, x, y, z)) - 1.0
+    }
+
+    /// Returns a random gradient in [-1, 1]
+    #[inline]
+    fn gradient_1d(&self, x: i32) -> f32 {
+        2.0 * noise_hash_to_unit(noise_hash(self.seed, x, 0, 0)) - 1.0
+    }
+
+    #[inline]
+    fn gradient_dot_2d(&self, x: i32, y: i32, dx: f32, dy: f32) -> f32 {
+        let (gradient_x, gradient_y) =
+            NOISE_GRADIENTS_2D[(noise_hash(self.seed, x, y, 0) & 7) as usize];
+        gradient_x * dx + gradient_y * dy
+    }
+
+    #[inline]
+    fn gradient_dot_3d(&self, x: i32, y: i32, z: i32, dx: f32, dy: f32, dz: f32) -> f32 {
+        gradient_dot_3d(noise_hash(self.seed, x, y, z), dx, dy, dz)
+    }
+
+    #[inline]
+    fn opensimplex_contribution_2d(&self, x: i32, y: i32, dx: f32, dy: f32) -> f32 {
+        let falloff = OPENSIMPLEX_RADIUS_SQUARED_2D - dx * dx - dy * dy;
+        if falloff > 0.0 {
+            squared(squared(falloff)) * self.gradient_dot_2d(x, y, dx, dy)
+        } else {
+            0.0
+        }
+    }
+}
+
+const OPENSIMPLEX_RADIUS_SQUARED_2D: f32 = 0.5;
+const OPENSIMPLEX_RADIUS_SQUARED_3D: f32 = 0.6;
+const OPENSIMPLEX_SECOND_LATTICE_SEED: u32 = 0x5a4f_2c1b;
+const NOISE_OCTAVE_SEED_STEP: u32 = 0x9e37_79b9;
+
+const NOISE_GRADIENTS_2D: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (
+        std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        -std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        std::f32::consts::FRAC_1_SQRT_2,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ),
+    (
+        -std::f32::consts::FRAC_1_SQRT_2,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ),
+];
+
+/// Dot product with one of the twelve vectors pointing from the center of a cube to its edges
+#[inline]
+fn gradient_dot_3d(hash: u32, dx: f32, dy: f32, dz: f32) -> f32 {
+    match hash % 12 {
+        0 => dx + dy,
+        1 => -dx + dy,
+        2 => dx - dy,
+        3 => -dx - dy,
+        4 => dx + dz,
+        5 => -dx + dz,
+        6 => dx - dz,
+        7 => -dx - dz,
+        8 => dy + dz,
+        9 => -dy + dz,
+        10 => dy - dz,
+        _ => -dy - dz,
+    }
+}
+
+/// Quintic smoothstep with zero first and second derivatives at 0 and 1
+#[inline]
+fn noise_fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn noise_hash(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    noise_hash_mix(
+        seed ^ (x as u32).wrapping_mul(0x8da6_b343)
+            ^ (y as u32).wrapping_mul(0xd816_3841)
+            ^ (z as u32).wrapping_mul(0xcb1a_b31f),
+    )
+}
+
+/// Finalizer of MurmurHash3
+#[inline]
+fn noise_hash_mix(mut hash: u32) -> u32 {
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+    hash
+}
+
+/// Returns a value in [0, 1[
+#[inline]
+fn noise_hash_to_unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
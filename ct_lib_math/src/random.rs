@@ -4,7 +4,12 @@
 
 use super::*;
 
-#[derive(Clone)]
+use serde_derive::{Deserialize, Serialize};
+
+/// NOTE: Serializing a `Random` stores its exact state so that it continues with the same sequence
+///       after deserialization (useful for replays and savegames)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RandomState", into = "RandomState")]
 pub struct Random {
     pub seed: u64,
     generator: oorandom::Rand32,
@@ -30,6 +35,45 @@ impl Random {
             .collect()
     }
 
+    /// Returns a generator that is derived from the seed of this generator and the given name.
+    /// Substreams with different names produce independent sequences and do not depend on how
+    /// many numbers were already drawn from this generator. This makes it possible to i.e.
+    /// change level generation without affecting the loot drops of a given seed.
+    ///
+    /// NOTE: Substreams can be nested: `random.substream("level").substream("enemies")`
+    #[inline]
+    pub fn substream(&self, name: &str) -> Random {
+        // NOTE: We use FNV-1a because it is stable across platforms and Rust versions
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.seed.to_le_bytes().iter().chain(name.as_bytes()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Random::new_from_seed(hash)
+    }
+
+    #[inline]
+    pub fn new_from_state(state: RandomState) -> Random {
+        Random {
+            seed: state.seed,
+            generator: oorandom::Rand32::from_state((
+                state.generator_state,
+                state.generator_increment,
+            )),
+        }
+    }
+
+    /// Returns the exact state of the generator which can be used to continue the sequence later
+    #[inline]
+    pub fn state(&self) -> RandomState {
+        let (generator_state, generator_increment) = self.generator.state();
+        RandomState {
+            seed: self.seed,
+            generator_state,
+            generator_increment,
+        }
+    }
+
     /// Returns a uniformly distributed integer in [std::i32::MIN, std::i32::MAX]
     #[inline]
     pub fn i32(&mut self) -> i32 {
@@ -88,7 +132,7 @@ impl Random {
         // 0x3FFF_FFFF = 0 0111 1111 1111 ... = (-1)^0 * (2^0) * mantissa = 1.0 + [0.0, 1.0[
         // This is a number in [1.0, 2.0[ - therefore we subtract -1
         const AND_PART: u32 = 0b0000_0000_0111_1111_1111_1111_1111_1111;
-        const OR_PART_: u32 = 0b0011_1111_1000_0000_0000_0000_0000_00001;
+        const OR_PART_: u32 = 0b0011_1111_1000_0000_0000_0000_0000_0000;
         let bytes = (random & AND_PART) | OR_PART_;
 
         f32::from_ne_bytes(bytes.to_ne_bytes()) - 1.0
@@ -136,8 +180,8 @@ impl Random {
 
     #[inline]
     pub fn vec2_in_unit_circle(&mut self) -> Vec2 {
-        let angle = self.f32_in_range(-PI, PI);
-        Vec2::from_angle_flipped_y(angle)
+        let angle_deg = self.f32_in_range(-180.0, 180.0);
+        Vec2::from_angle_flipped_y(angle_deg)
     }
 
     /// Picks a random element from given slice
@@ -168,6 +212,292 @@ impl Random {
             last_index -= 1;
         }
     }
+
+    /// Returns the index of a randomly chosen weight where the probability of each index is
+    /// proportional to its weight
+    #[inline]
+    pub fn index_weighted(&mut self, weights: &[f32]) -> usize {
+        assert!(!weights.is_empty());
+        let weights_sum: f32 = weights.iter().sum();
+        assert!(
+            weights_sum > 0.0,
+            "Weights must sum up to a positive number"
+        );
+
+        let mut choice = self.f32() * weights_sum;
+        for (index, &weight) in weights.iter().enumerate() {
+            assert!(weight >= 0.0, "Weights must not be negative");
+            if choice < weight {
+                return index;
+            }
+            choice -= weight;
+        }
+        // NOTE: We can get here due to rounding errors
+        weights.iter().rposition(|&weight| weight > 0.0).unwrap()
+    }
+
+    /// Picks a random element from given slice where the probability of each element is
+    /// proportional to its weight
+    #[inline]
+    pub fn pick_from_slice_weighted<ElemType>(
+        &mut self,
+        elems_and_weights: &[(ElemType, f32)],
+    ) -> ElemType
+    where
+        ElemType: Copy + Clone,
+    {
+        let weights: Vec<f32> = elems_and_weights
+            .iter()
+            .map(|(_elem, weight)| *weight)
+            .collect();
+        elems_and_weights[self.index_weighted(&weights)].0
+    }
+
+    /// Picks `count` random elements from the given elements with equal probability. If there are
+    /// less than `count` elements all of them are returned. This needs only one pass over the
+    /// elements and is therefore useful for iterators of unknown length.
+    ///
+    /// NOTE: The order of the returned elements is not random
+    ///
+    /// Based on https://en.wikipedia.org/wiki/Reservoir_sampling#Simple_algorithm
+    pub fn reservoir_sample<ElemType, IteratorType>(
+        &mut self,
+        elems: IteratorType,
+        count: usize,
+    ) -> Vec<ElemType>
+    where
+        IteratorType: IntoIterator<Item = ElemType>,
+    {
+        let mut reservoir = Vec::with_capacity(count);
+        for (index, elem) in elems.into_iter().enumerate() {
+            if index < count {
+                reservoir.push(elem);
+            } else {
+                assert!(
+                    index < std::u32::MAX as usize,
+                    "Only u32 sized iterators supported"
+                );
+                let replace_index = self.u32_bounded(index as u32) as usize;
+                if replace_index < count {
+                    reservoir[replace_index] = elem;
+                }
+            }
+        }
+        reservoir
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Distributions
+
+    /// Returns a normally distributed number
+    ///
+    /// Based on https://en.wikipedia.org/wiki/Box%E2%80%93Muller_transform
+    #[inline]
+    pub fn gaussian(&mut self, mean: f32, standard_deviation: f32) -> f32 {
+        // NOTE: We need u1 in ]0, 1] because of the logarithm
+        let u1 = 1.0 - self.f32();
+        let u2 = self.f32();
+        let standard_normal = f32::sqrt(-2.0 * f32::ln(u1)) * f32::cos(2.0 * PI * u2);
+        mean + standard_deviation * standard_normal
+    }
+
+    /// Returns an exponentially distributed number in [0, inf[ with a mean of `1 / rate`. This is
+    /// useful for the time between randomly occuring events.
+    #[inline]
+    pub fn exponential(&mut self, rate: f32) -> f32 {
+        assert!(rate > 0.0);
+        -f32::ln(1.0 - self.f32()) / rate
+    }
+
+    /// Returns a number in [min, max] where values near `mode` are most likely
+    ///
+    /// Based on https://en.wikipedia.org/wiki/Triangular_distribution#Generating_triangular-distributed_random_variates
+    #[inline]
+    pub fn triangular(&mut self, min: f32, mode: f32, max: f32) -> f32 {
+        assert!(min <= mode && mode <= max && min < max);
+        let range = max - min;
+        let random = self.f32();
+        if random < (mode - min) / range {
+            min + f32::sqrt(random * range * (mode - min))
+        } else {
+            max - f32::sqrt((1.0 - random) * range * (max - mode))
+        }
+    }
+
+    /// Returns randomly placed points in [rect.left, rect.right[ x [rect.top, rect.bottom[ that are
+    /// at least `distance_min` apart from each other. The points are spread evenly with no large
+    /// gaps which is useful for placing i.e. trees or stars.
+    ///
+    /// Based on "Fast Poisson Disk Sampling in Arbitrary Dimensions" by Robert Bridson
+    pub fn vec2_poisson_disk_samples_in_rect(
+        &mut self,
+        rect: Rect,
+        distance_min: f32,
+    ) -> Vec<Vec2> {
+        assert!(distance_min > 0.0);
+        assert!(rect.width() > 0.0 && rect.height() > 0.0);
+        const CANDIDATE_ATTEMPTS: usize = 30;
+
+        // NOTE: The cell size is chosen so that every cell can contain at most one point
+        let cell_size = distance_min / std::f32::consts::SQRT_2;
+        let cells_width = ceili(rect.width() / cell_size);
+        let cells_height = ceili(rect.height() / cell_size);
+        let mut cells: Vec<Option<usize>> = vec![None; (cells_width * cells_height) as usize];
+        // NOTE: Points very close to the right or bottom border of the rect can round up to a
+        //       cell just outside of the grid, so we clamp the cell coordinates
+        let cell_index = |point: Vec2| -> (i32, i32) {
+            (
+                i32::clamp(
+                    floori((point.x - rect.left()) / cell_size),
+                    0,
+                    cells_width - 1,
+                ),
+                i32::clamp(
+                    floori((point.y - rect.top()) / cell_size),
+                    0,
+                    cells_height - 1,
+                ),
+            )
+        };
+
+        let mut points = Vec::new();
+        let mut active = Vec::new();
+
+        let first = self.vec2_in_rect(rect);
+        let (cell_x, cell_y) = cell_index(first);
+        cells[(cell_x + cell_y * cells_width) as usize] = Some(0);
+        points.push(first);
+        active.push(0);
+
+        while !active.is_empty() {
+            let active_index = self.u32_bounded_exclusive(active.len() as u32) as usize;
+            let point = points[active[active_index]];
+
+            let mut found_candidate = false;
+            for _ in 0..CANDIDATE_ATTEMPTS {
+                let candidate = point
+                    + self.f32_in_range(distance_min, 2.0 * distance_min)
+                        * self.vec2_in_unit_circle();
+                if candidate.x < rect.left()
+                    || candidate.x >= rect.right()
+                    || candidate.y < rect.top()
+                    || candidate.y >= rect.bottom()
+                {
+                    continue;
+                }
+
+                let (cell_x, cell_y) = cell_index(candidate);
+                let mut is_too_close = false;
+                'search: for neighbor_y in
+                    i32::max(0, cell_y - 2)..i32::min(cells_height, cell_y + 3)
+                {
+                    for neighbor_x in i32::max(0, cell_x - 2)..i32::min(cells_width, cell_x + 3) {
+                        if let Some(neighbor) =
+                            cells[(neighbor_x + neighbor_y * cells_width) as usize]
+                        {
+                            if Vec2::distance_squared(candidate, points[neighbor])
+                                < distance_min * distance_min
+                            {
+                                is_too_close = true;
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+                if is_too_close {
+                    continue;
+                }
+
+                cells[(cell_x + cell_y * cells_width) as usize] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                found_candidate = true;
+                break;
+            }
+
+            if !found_candidate {
+                active.swap_remove(active_index);
+            }
+        }
+
+        points
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RandomState {
+    pub seed: u64,
+    pub generator_state: u64,
+    pub generator_increment: u64,
+}
+
+impl From<RandomState> for Random {
+    #[inline]
+    fn from(state: RandomState) -> Random {
+        Random::new_from_state(state)
+    }
+}
+
+impl From<Random> for RandomState {
+    #[inline]
+    fn from(random: Random) -> RandomState {
+        random.state()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Weighted table
+
+/// Picks random elements where the probability of each element is proportional to its weight.
+/// Compared to `Random::pick_from_slice_weighted` this is faster for repeated picks from the same
+/// elements i.e. for loot tables.
+#[derive(Debug, Clone)]
+pub struct WeightedTable<ElemType: Clone> {
+    pub elems: Vec<ElemType>,
+    weights_cumulative: Vec<f32>,
+}
+
+impl<ElemType: Clone> WeightedTable<ElemType> {
+    #[inline]
+    pub fn new(elems_and_weights: &[(ElemType, f32)]) -> WeightedTable<ElemType> {
+        assert!(!elems_and_weights.is_empty());
+        let mut elems = Vec::with_capacity(elems_and_weights.len());
+        let mut weights_cumulative = Vec::with_capacity(elems_and_weights.len());
+        let mut weights_sum = 0.0;
+        for (elem, weight) in elems_and_weights {
+            assert!(*weight >= 0.0, "Weights must not be negative");
+            weights_sum += weight;
+            elems.push(elem.clone());
+            weights_cumulative.push(weights_sum);
+        }
+        assert!(
+            weights_sum > 0.0,
+            "Weights must sum up to a positive number"
+        );
+
+        WeightedTable {
+            elems,
+            weights_cumulative,
+        }
+    }
+
+    #[inline]
+    pub fn pick(&self, random: &mut Random) -> ElemType {
+        let weights_sum = *self.weights_cumulative.last().unwrap();
+        let choice = random.f32() * weights_sum;
+        // NOTE: We search for the first element whose cumulative weight exceeds our choice which
+        //       skips elements with zero weight
+        let index = self
+            .weights_cumulative
+            .partition_point(|&weight_cumulative| weight_cumulative <= choice);
+        // NOTE: If our choice rounded up to the total weight we must not pick any trailing
+        //       elements with zero weight. The first element that reaches the total weight is the
+        //       last one with a positive weight.
+        let index_last_positive = self
+            .weights_cumulative
+            .partition_point(|&weight_cumulative| weight_cumulative < weights_sum);
+        self.elems[usize::min(index, index_last_positive)].clone()
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
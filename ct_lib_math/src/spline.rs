@@ -0,0 +1,441 @@
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Splines
+//
+// Catmull-Rom, cubic Bézier and uniform cubic B-spline curves with arc length parameterization.
+//
+// NOTE: Internally all curve types are converted to a sequence of cubic Bézier segments. A curve
+//       can be evaluated by its parameter `t` in [0, 1] which is not proportional to the
+//       travelled distance or by a distance along the curve in [0, length].
+//
+
+use super::*;
+
+use serde_derive::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Number of samples per segment for approximating the arc length
+const SPLINE_ARC_LENGTH_SAMPLES_PER_SEGMENT: usize = 32;
+
+/// NOTE: Only the segments are serialized. The arc lengths are recomputed on deserialization so
+///       that they always match the segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "SplineState", into = "SplineState")]
+pub struct Spline {
+    /// Control points of the cubic Bézier segments
+    segments: Vec<[Vec2; 4]>,
+    /// Cumulative arc lengths at regularly spaced parameter values
+    arc_lengths: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SplineState {
+    segments: Vec<[Vec2; 4]>,
+}
+
+impl TryFrom<SplineState> for Spline {
+    type Error = String;
+
+    fn try_from(state: SplineState) -> Result<Spline, String> {
+        if state.segments.is_empty() {
+            return Err("Spline needs at least one segment".to_owned());
+        }
+        Ok(Spline::new_from_segments(state.segments))
+    }
+}
+
+impl From<Spline> for SplineState {
+    fn from(spline: Spline) -> SplineState {
+        SplineState {
+            segments: spline.segments,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplineProjection {
+    pub parameter: f32,
+    pub distance: f32,
+    pub point: Vec2,
+}
+
+impl Spline {
+    /// Creates a curve that passes through all given points. If `is_closed` is set the curve
+    /// also connects the last point with the first point.
+    pub fn new_catmull_rom(points: &[Vec2], is_closed: bool) -> Spline {
+        assert!(points.len() >= 2, "Need at least two points");
+        let count = points.len() as i32;
+        let point = |index: i32| {
+            if is_closed {
+                points[index.rem_euclid(count) as usize]
+            } else {
+                points[i32::clamp(index, 0, count - 1) as usize]
+            }
+        };
+
+        let segment_count = if is_closed { count } else { count - 1 };
+        let segments = (0..segment_count)
+            .map(|index| {
+                let p0 = point(index - 1);
+                let p1 = point(index);
+                let p2 = point(index + 1);
+                let p3 = point(index + 2);
+                [p1, p1 + (p2 - p0) / 6.0, p2 - (p3 - p1) / 6.0, p2]
+            })
+            .collect();
+        Spline::new_from_segments(segments)
+    }
+
+    /// Expects points in the form `[start, control, control, end, control, control, end, ..]`
+    /// where each end point is also the start point of the next segment
+    pub fn new_cubic_bezier(points: &[Vec2]) -> Spline {
+        assert!(
+            points.len() >= 4 && points.len() % 3 == 1,
+            "Need 3 * n + 1 points for n segments but got {} points",
+            points.len()
+        );
+        let segments = points
+            .windows(4)
+            .step_by(3)
+            .map(|window| [window[0], window[1], window[2], window[3]])
+            .collect();
+        Spline::new_from_segments(segments)
+    }
+
+    /// Creates a smooth curve that is pulled towards the given points but does not pass through
+    /// them. Open curves start at the first point and end at the last point.
+    pub fn new_bspline(points: &[Vec2], is_closed: bool) -> Spline {
+        assert!(points.len() >= 2, "Need at least two points");
+        let count = points.len() as i32;
+        let point = |index: i32| {
+            if is_closed {
+                points[index.rem_euclid(count) as usize]
+            } else {
+                points[i32::clamp(index, 0, count - 1) as usize]
+            }
+        };
+
+        // NOTE: For open curves we repeat the end points so that the curve starts and ends there
+        let (index_first, segment_count) = if is_closed {
+            (-1, count)
+        } else {
+            (-2, count + 1)
+        };
+        let segments = (index_first..index_first + segment_count)
+            .map(|index| {
+                let p0 = point(index);
+                let p1 = point(index + 1);
+                let p2 = point(index + 2);
+                let p3 = point(index + 3);
+                [
+                    (p0 + 4.0 * p1 + p2) / 6.0,
+                    (2.0 * p1 + p2) / 3.0,
+                    (p1 + 2.0 * p2) / 3.0,
+                    (p1 + 4.0 * p2 + p3) / 6.0,
+                ]
+            })
+            .collect();
+        Spline::new_from_segments(segments)
+    }
+
+    fn new_from_segments(segments: Vec<[Vec2; 4]>) -> Spline {
+        let mut spline = Spline {
+            segments,
+            arc_lengths: Vec::new(),
+        };
+
+        let sample_count = spline.segments.len() * SPLINE_ARC_LENGTH_SAMPLES_PER_SEGMENT;
+        let mut arc_lengths = Vec::with_capacity(sample_count + 1);
+        let mut length = 0.0;
+        let mut point_previous = spline.point(0.0);
+        arc_lengths.push(0.0);
+        for sample_index in 1..=sample_count {
+            let point = spline.point(sample_index as f32 / sample_count as f32);
+            length += Vec2::distance(point_previous, point);
+            arc_lengths.push(length);
+            point_previous = point;
+        }
+        spline.arc_lengths = arc_lengths;
+        spline
+    }
+
+    #[inline]
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    #[inline]
+    pub fn length(&self) -> f32 {
+        *self.arc_lengths.last().unwrap()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Evaluation by parameter
+
+    /// Returns the point at the given parameter in [0, 1]
+    pub fn point(&self, parameter: f32) -> Vec2 {
+        let ([p0, p1, p2, p3], t) = self.segment_and_local_parameter(parameter);
+        let s = 1.0 - t;
+        (s * s * s) * p0 + (3.0 * s * s * t) * p1 + (3.0 * s * t * t) * p2 + (t * t * t) * p3
+    }
+
+    /// Returns the derivative with respect to the given parameter in [0, 1]
+    pub fn derivative(&self, parameter: f32) -> Vec2 {
+        let ([p0, p1, p2, p3], t) = self.segment_and_local_parameter(parameter);
+        let s = 1.0 - t;
+        let derivative_local =
+            (3.0 * s * s) * (p1 - p0) + (6.0 * s * t) * (p2 - p1) + (3.0 * t * t) * (p3 - p2);
+        self.segments.len() as f32 * derivative_local
+    }
+
+    /// Returns the normalized direction of the curve at the given parameter in [0, 1]
+    #[inline]
+    pub fn tangent(&self, parameter: f32) -> Vec2 {
+        let derivative = self.derivative(parameter);
+        if derivative.is_effectively_zero() {
+            // NOTE: This can happen at control points that coincide. We use a finite difference
+            //       instead.
+            let (parameter_start, parameter_end) = if parameter < 0.5 {
+                (parameter, parameter + 0.001)
+            } else {
+                (parameter - 0.001, parameter)
+            };
+            (self.point(parameter_end) - self.point(parameter_start)).normalized_or(Vec2::unit_x())
+        } else {
+            derivative.normalized()
+        }
+    }
+
+    /// Returns the tangent rotated by 90 degrees (see `Vec2::perpendicular`)
+    #[inline]
+    pub fn normal(&self, parameter: f32) -> Vec2 {
+        self.tangent(parameter).perpendicular()
+    }
+
+    #[inline]
+    fn segment_and_local_parameter(&self, parameter: f32) -> ([Vec2; 4], f32) {
+        let parameter_scaled = f32::clamp(parameter, 0.0, 1.0) * self.segments.len() as f32;
+        let segment_index = usize::min(parameter_scaled as usize, self.segments.len() - 1);
+        (
+            self.segments[segment_index],
+            parameter_scaled - segment_index as f32,
+        )
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Evaluation by distance
+
+    /// Returns the parameter in [0, 1] at the given distance along the curve
+    pub fn parameter_at_distance(&self, distance: f32) -> f32 {
+        let distance = f32::clamp(distance, 0.0, self.length());
+        let sample_count = self.arc_lengths.len() - 1;
+
+        // NOTE: We search the first sample that is not shorter than our distance and interpolate
+        //       linearly from its predecessor
+        let index = self
+            .arc_lengths
+            .partition_point(|&length| length < distance);
+        if index == 0 {
+            return 0.0;
+        }
+        let length_before = self.arc_lengths[index - 1];
+        let length_after = self.arc_lengths[index];
+        let percent = if length_after > length_before {
+            (distance - length_before) / (length_after - length_before)
+        } else {
+            0.0
+        };
+        ((index - 1) as f32 + percent) / sample_count as f32
+    }
+
+    /// Returns the approximate distance along the curve at the given parameter in [0, 1]
+    pub fn distance_at_parameter(&self, parameter: f32) -> f32 {
+        let sample_count = self.arc_lengths.len() - 1;
+        let sample = f32::clamp(parameter, 0.0, 1.0) * sample_count as f32;
+        let index = usize::min(sample as usize, sample_count - 1);
+        lerp(
+            self.arc_lengths[index],
+            self.arc_lengths[index + 1],
+            sample - index as f32,
+        )
+    }
+
+    #[inline]
+    pub fn point_at_distance(&self, distance: f32) -> Vec2 {
+        self.point(self.parameter_at_distance(distance))
+    }
+
+    #[inline]
+    pub fn tangent_at_distance(&self, distance: f32) -> Vec2 {
+        self.tangent(self.parameter_at_distance(distance))
+    }
+
+    #[inline]
+    pub fn normal_at_distance(&self, distance: f32) -> Vec2 {
+        self.normal(self.parameter_at_distance(distance))
+    }
+
+    /// Returns `count` points that are evenly spaced along the curve including its start and end
+    pub fn sampled_uniformly(&self, count: usize) -> Vec<Vec2> {
+        assert!(count >= 2);
+        let length = self.length();
+        (0..count)
+            .map(|index| self.point_at_distance(length * index as f32 / (count - 1) as f32))
+            .collect()
+    }
+
+    /// Returns points that are `spacing` apart along the curve starting at its start. The end of the
+    /// curve is only included if the length of the curve is a multiple of `spacing`.
+    pub fn sampled_with_spacing(&self, spacing: f32) -> Vec<Vec2> {
+        assert!(spacing > 0.0);
+        let count = floori(self.length() / spacing) as usize + 1;
+        (0..count)
+            .map(|index| self.point_at_distance(spacing * index as f32))
+            .collect()
+    }
+
+    //----------------------------------------------------------------------------------------------
+    // Projection
+
+    /// Returns the point on the curve that is closest to the given point
+    pub fn closest_point(&self, point: Vec2) -> SplineProjection {
+        // NOTE: We first find the closest of our arc length samples and then refine the result
+        //       between its neighboring samples using ternary search
+        let sample_count = self.arc_lengths.len() - 1;
+        let distance_squared_at =
+            |parameter: f32| Vec2::distance_squared(self.point(parameter), point);
+        let sample_closest = (0..=sample_count)
+            .map(|index| {
+                (
+                    index,
+                    distance_squared_at(index as f32 / sample_count as f32),
+                )
+            })
+            .min_by(|a, b| compare_floats(a.1, b.1))
+            .unwrap()
+            .0;
+
+        let mut parameter_min = sample_closest.saturating_sub(1) as f32 / sample_count as f32;
+        let mut parameter_max =
+            usize::min(sample_closest + 1, sample_count) as f32 / sample_count as f32;
+        for _ in 0..32 {
+            let third = (parameter_max - parameter_min) / 3.0;
+            if distance_squared_at(parameter_min + third)
+                < distance_squared_at(parameter_max - third)
+            {
+                parameter_max -= third;
+            } else {
+                parameter_min += third;
+            }
+        }
+
+        let parameter = 0.5 * (parameter_min + parameter_max);
+        SplineProjection {
+            parameter,
+            distance: self.distance_at_parameter(parameter),
+            point: self.point(parameter),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Path following
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathFollowMode {
+    /// Stops at the end of the path
+    Once,
+    /// Jumps back to the start after reaching the end. Useful for closed paths.
+    Loop,
+    /// Turns around at both ends of the path
+    PingPong,
+}
+
+/// Moves along a spline with constant speed. The follower does not own the spline so that
+/// multiple followers can share the same path.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PathFollower {
+    /// Distance travelled along the path in [0, path length]
+    pub distance: f32,
+    pub speed: f32,
+    pub mode: PathFollowMode,
+    pub is_moving_backwards: bool,
+}
+
+impl PathFollower {
+    pub fn new(speed: f32, mode: PathFollowMode) -> PathFollower {
+        PathFollower {
+            distance: 0.0,
+            speed,
+            mode,
+            is_moving_backwards: false,
+        }
+    }
+
+    /// Advances the follower and returns its new position
+    pub fn update(&mut self, path: &Spline, deltatime: f32) -> Vec2 {
+        let length = path.length();
+        let step = self.speed * deltatime;
+        let direction = if self.is_moving_backwards { -1.0 } else { 1.0 };
+        let distance = self.distance + direction * step;
+
+        self.distance = match self.mode {
+            PathFollowMode::Once => f32::clamp(distance, 0.0, length),
+            PathFollowMode::Loop => {
+                if length > 0.0 {
+                    wrap_value_in_range(distance, length)
+                } else {
+                    0.0
+                }
+            }
+            PathFollowMode::PingPong => {
+                if length > 0.0 {
+                    // NOTE: We unfold the back and forth movement into a loop of twice the path
+                    //       length where the second half is the way back. This way a single step
+                    //       can overshoot the path any number of times.
+                    let distance_unfolded = if self.is_moving_backwards {
+                        2.0 * length - self.distance
+                    } else {
+                        self.distance
+                    };
+                    let distance_unfolded = (distance_unfolded + step).rem_euclid(2.0 * length);
+                    self.is_moving_backwards = distance_unfolded > length;
+                    if self.is_moving_backwards {
+                        f32::clamp(2.0 * length - distance_unfolded, 0.0, length)
+                    } else {
+                        distance_unfolded
+                    }
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        self.pos(path)
+    }
+
+    #[inline]
+    pub fn pos(&self, path: &Spline) -> Vec2 {
+        path.point_at_distance(self.distance)
+    }
+
+    /// Returns the normalized direction the follower is currently moving in
+    #[inline]
+    pub fn dir(&self, path: &Spline) -> Vec2 {
+        let tangent = path.tangent_at_distance(self.distance);
+        if self.is_moving_backwards {
+            -tangent
+        } else {
+            tangent
+        }
+    }
+
+    #[inline]
+    pub fn is_finished(&self, path: &Spline) -> bool {
+        self.mode == PathFollowMode::Once
+            && if self.is_moving_backwards {
+                self.distance <= 0.0
+            } else {
+                self.distance >= path.length()
+            }
+    }
+}
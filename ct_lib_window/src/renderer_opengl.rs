@@ -941,6 +941,110 @@ void main()
 }
 "#;
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Shader for multi channel signed distance fields
+
+// NOTE: This uses the same vertex layout as the default shader so that both can share the same
+//       vertex data
+const VERTEX_SHADER_SOURCE_DISTANCEFIELD: &str = VERTEX_SHADER_SOURCE_DEFAULT;
+
+// NOTE: The texture is expected to contain a multi channel signed distance field in its rgb
+//       channels and a true signed distance field in its alpha channel. As our atlas textures use
+//       premultiplied alpha and nearest neighbour filtering we need to un-premultiply and
+//       interpolate the texels manually. All distances are given in texels.
+const FRAGMENT_SHADER_SOURCE_DISTANCEFIELD: &str = r#"
+#ifdef GL_ES
+#extension GL_OES_standard_derivatives : enable
+#endif
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+varying vec4 v_color;
+varying vec2 v_uv;
+varying float v_additivity;
+
+uniform vec2 u_texture_size;
+uniform float u_spread;
+uniform vec4 u_outline_color;
+uniform float u_outline_thickness;
+uniform vec4 u_glow_color;
+uniform float u_glow_radius;
+
+uniform sampler2D u_texture;
+
+vec4 texel_unpremultiplied(vec2 texel_pos)
+{
+    vec4 texel = texture2D(u_texture, (texel_pos + 0.5) / u_texture_size);
+    if (texel.a == 0.0) {
+        return vec4(0.0);
+    }
+    return vec4(texel.rgb / texel.a, texel.a);
+}
+
+float median(float r, float g, float b)
+{
+    return max(min(r, g), min(max(r, g), b));
+}
+
+vec4 blend_over(vec4 src, vec4 dst)
+{
+    return src + dst * (1.0 - src.a);
+}
+
+void main()
+{
+    vec2 texel_pos = v_uv * u_texture_size - 0.5;
+    vec2 texel_pos_base = floor(texel_pos);
+    vec2 texel_fraction = texel_pos - texel_pos_base;
+    vec4 texel = mix(
+        mix(texel_unpremultiplied(texel_pos_base),
+            texel_unpremultiplied(texel_pos_base + vec2(1.0, 0.0)),
+            texel_fraction.x),
+        mix(texel_unpremultiplied(texel_pos_base + vec2(0.0, 1.0)),
+            texel_unpremultiplied(texel_pos_base + vec2(1.0, 1.0)),
+            texel_fraction.x),
+        texel_fraction.y);
+
+    float distance_sharp = (median(texel.r, texel.g, texel.b) - 0.5) * 2.0 * u_spread;
+    float distance_true = (texel.a - 0.5) * 2.0 * u_spread;
+
+#if defined(GL_ES) && !defined(GL_OES_standard_derivatives)
+    float texels_per_pixel = 1.0;
+#else
+    float texels_per_pixel = max(0.5 * (fwidth(texel_pos.x) + fwidth(texel_pos.y)), 0.0001);
+#endif
+
+    float fill_coverage = clamp(distance_sharp / texels_per_pixel + 0.5, 0.0, 1.0);
+    float outline_coverage = 0.0;
+    if (u_outline_thickness > 0.0) {
+        outline_coverage =
+            clamp((distance_sharp + u_outline_thickness) / texels_per_pixel + 0.5, 0.0, 1.0);
+    }
+    float glow_intensity = 0.0;
+    if (u_glow_radius > 0.0) {
+        glow_intensity = clamp(1.0 + distance_true / u_glow_radius, 0.0, 1.0);
+        glow_intensity = glow_intensity * glow_intensity;
+    }
+
+    // NOTE: The fill is treated like a white texel in the default shader that is modulated by the
+    //       vertex color
+    vec4 color = u_glow_color * glow_intensity;
+    color = blend_over(u_outline_color * outline_coverage, color);
+    color = blend_over(v_color * fill_coverage, color);
+    color.a = color.a * (1.0 - v_additivity);
+
+    if (dot(color, color) == 0.0) {
+        discard;
+    }
+
+    gl_FragColor = color;
+}
+"#;
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Shader for blitting
 
@@ -1020,16 +1124,28 @@ impl Renderer {
         )
         .expect("Could not compile blit shader");
 
+        let shader_distancefield = Shader::new(
+            gl.clone(),
+            "distancefield".to_owned(),
+            VERTEX_SHADER_SOURCE_DISTANCEFIELD,
+            FRAGMENT_SHADER_SOURCE_DISTANCEFIELD,
+        )
+        .expect("Could not compile distancefield shader");
+
         let drawobject_default = DrawObject::new_from_shader(gl.clone(), &shader_default);
         let drawobject_blit = DrawObject::new_from_shader(gl.clone(), &shader_blit);
+        let drawobject_distancefield =
+            DrawObject::new_from_shader(gl.clone(), &shader_distancefield);
 
         let mut drawobjects = HashMap::new();
         drawobjects.insert("default".to_owned(), drawobject_default);
         drawobjects.insert("blit".to_owned(), drawobject_blit);
+        drawobjects.insert("distancefield".to_owned(), drawobject_distancefield);
 
         let mut shaders = HashMap::new();
         shaders.insert("default".to_owned(), shader_default);
         shaders.insert("blit".to_owned(), shader_blit);
+        shaders.insert("distancefield".to_owned(), shader_distancefield);
 
         gl_check_state_ok(&gl).expect("Something went wrong while creating renderer");
 
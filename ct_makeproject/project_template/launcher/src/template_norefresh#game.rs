@@ -1765,7 +1765,7 @@ impl SceneSprites3dSpatialSound {
             Drawparams::without_additivity(DEPTH_DRAW, Color::white(), Drawspace::Canvas),
         );
         if input.keyboard.recently_pressed(Scancode::B) {
-            let screen_shake = ModulatorScreenShake::new(&mut globals.random, 4.0, 1.0, 60.0);
+            let screen_shake = ModulatorScreenShake::new(&mut globals.random, 4.0, 1.0, 15.0);
             globals.camera.add_shake(screen_shake);
         }
 